@@ -0,0 +1,63 @@
+//! Inspects and compacts path databases, see `scionnet::path_db`.
+//!
+//! ```text
+//! cargo run --example path_db -- dump paths.db
+//! cargo run --example path_db -- check paths.db
+//! cargo run --example path_db -- compact paths.db [compacted.db]
+//! ```
+//!
+//! `compact` drops expired and duplicate paths, rewriting the database in
+//! place unless an output file is given.
+
+use scionnet::path_db::{self, PathDb};
+use std::process::exit;
+use std::time::SystemTime;
+
+fn usage() -> ! {
+    eprintln!("usage: path_db (dump | check | compact) <file> [<output>]");
+    exit(2)
+}
+
+fn open(file: &str) -> PathDb {
+    PathDb::open(file).unwrap_or_else(|e| {
+        eprintln!("{}: {}", file, e);
+        exit(1)
+    })
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (command, file) = match &args[..] {
+        [command, file] | [command, file, _] => (command.as_str(), file.as_str()),
+        _ => usage(),
+    };
+    match command {
+        "dump" => {
+            let now = SystemTime::now();
+            let db = open(file);
+            for path in db.iter() {
+                let hops: Vec<String> =
+                    path.interfaces().map(|i| format!("{}#{}", i.ia, i.id)).collect();
+                let expired = if path.is_expired(now) { " expired" } else { "" };
+                let (src, dst, mtu) = (path.src(), path.dst(), path.mtu());
+                println!("{} -> {} [{}] mtu={}{}", src, dst, hops.join(" "), mtu, expired);
+            }
+        }
+        "check" => {
+            let db = open(file);
+            println!("{}: {} paths to {} IAs", file, db.len(), db.ias().count());
+        }
+        "compact" => {
+            let output = args.get(2).map_or(file, String::as_str);
+            let before = open(file).len();
+            match path_db::compact(file, output, SystemTime::now()) {
+                Ok(kept) => println!("{}: kept {} of {} paths", output, kept, before),
+                Err(e) => {
+                    eprintln!("{}: {}", file, e);
+                    exit(1)
+                }
+            }
+        }
+        _ => usage(),
+    }
+}
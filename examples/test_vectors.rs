@@ -0,0 +1,169 @@
+//! Emits canonical parse/format/wire-encoding test vectors as JSON, so that
+//! other SCION implementations (Go, Python, ...) can check that they agree
+//! with this crate.
+//!
+//! ```text
+//! cargo run --example test_vectors > scionnet-vectors.json
+//! cargo run --example test_vectors -- scionnet-vectors.json
+//! ```
+//!
+//! Every vector holds the `input`, and either the parsed result (canonical
+//! `display` form, numeric fields and `wire` encoding as hex) or the `error`
+//! reported for it. The wire encoding is the one of the SCION address header:
+//! the 8 byte ISD-AS followed by the 4 or 16 byte host address, plus the 2 byte
+//! port for socket addresses. Service addresses take 4 bytes, the 2 byte
+//! service number padded with zeros. All integers are big-endian.
+
+use scionnet::{HostAddr, IpAddr, IsdAsn, ScionAddr, SocketAddr, SocketAddrScion};
+use serde_json::{json, Value};
+use std::str::FromStr;
+
+const ISD_ASN: &[&str] = &[
+    "0-0",
+    "1-ff00:0:110",
+    "19-ffaa:1:1067",
+    "1-64512",
+    "1-65551",
+    "1-0:1:f",
+    "1-4294967295",
+    "1-1:0:0",
+    "65535-ffff:ffff:ffff",
+    "19-ffaa:1",
+    "19-ffaa:1:1067:0",
+    "70000-1",
+    "1-10000:0:0",
+    "1-",
+    "-1",
+    "",
+];
+
+const SCION_ADDR: &[&str] = &[
+    "19-ffaa:1:1067,127.0.0.1",
+    "1-150,10.150.0.30",
+    "1-ff00:0:110,::1",
+    "1-ff00:0:110,[::1]",
+    "1-ff00:0:110,fe80::1:2",
+    "1-ff00:0:110,::ffff:10.0.0.1",
+    "19-ffaa:1:1067,CS",
+    "1-ff00:0:110,DS_M",
+    "1-ff00:0:110,cs",
+    "1-ff00:0:110,10.0.0.256",
+    "1-ff00:0:110",
+    "1-ff00:0:110,",
+];
+
+const SOCKET_ADDR: &[&str] = &[
+    "19-ffaa:1:1067,127.0.0.1:53",
+    "19-ffaa:1:1067,[127.0.0.1]:53",
+    "1-ff00:0:110,[::1]:443",
+    "1-ff00:0:110,[fe80::1:2]:0",
+    "1-150,10.150.0.30:65535",
+    "1-ff00:0:110,CS:30252",
+    "10.0.0.1:80",
+    "[::1]:443",
+    "[::ffff:10.0.0.1]:80",
+    "1-ff00:0:110,10.0.0.1:65536",
+    "1-ff00:0:110,10.0.0.1",
+    "1-ff00:0:110,::1:443",
+];
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn host_wire(host: &HostAddr) -> Vec<u8> {
+    match host {
+        HostAddr::Ip(IpAddr::V4(ip)) => ip.octets().to_vec(),
+        HostAddr::Ip(IpAddr::V6(ip)) => ip.octets().to_vec(),
+        HostAddr::Svc(svc) => [svc.to_u16().to_be_bytes(), [0, 0]].concat(),
+    }
+}
+
+fn scion_wire(ia: u64, host: &HostAddr) -> Vec<u8> {
+    let mut wire = ia.to_be_bytes().to_vec();
+    wire.extend_from_slice(&host_wire(host));
+    wire
+}
+
+fn vectors<T, E, F>(inputs: &[&str], to_json: F) -> Value
+where
+    T: FromStr<Err = E>,
+    E: ToString,
+    F: Fn(&T) -> Value,
+{
+    inputs
+        .iter()
+        .map(|input| match T::from_str(input) {
+            Ok(v) => {
+                let mut vector = to_json(&v);
+                vector["input"] = json!(input);
+                vector
+            }
+            Err(e) => json!({ "input": input, "error": e.to_string() }),
+        })
+        .collect()
+}
+
+fn isd_asn(ia: &IsdAsn) -> Value {
+    json!({
+        "display": ia.to_string(),
+        "isd": ia.isd().to_u16(),
+        "asn": ia.asn().to_u64(),
+        "ia": ia.to_u64(),
+        "wire": hex(&ia.to_u64().to_be_bytes()),
+    })
+}
+
+fn scion_addr(a: &ScionAddr) -> Value {
+    json!({
+        "display": a.to_string(),
+        "ia": a.get_ia(),
+        "host": a.get_host().to_string(),
+        "wire": hex(&scion_wire(a.get_ia(), a.get_host())),
+    })
+}
+
+fn socket_addr(a: &SocketAddr) -> Value {
+    match a {
+        SocketAddr::SCION(s) => {
+            let mut wire = scion_wire(s.ia(), s.host());
+            wire.extend_from_slice(&s.port().to_be_bytes());
+            json!({
+                "display": a.to_string(),
+                "family": a.family().as_str(),
+                "ia": s.ia(),
+                "host": s.host().to_string(),
+                "port": s.port(),
+                "wire": hex(&wire),
+            })
+        }
+        _ => {
+            let mut wire = host_wire(&a.host());
+            wire.extend_from_slice(&a.port().to_be_bytes());
+            json!({
+                "display": a.to_string(),
+                "family": a.family().as_str(),
+                "host": a.host().to_string(),
+                "port": a.port(),
+                "wire": hex(&wire),
+            })
+        }
+    }
+}
+
+fn main() {
+    let doc = json!({
+        "generator": concat!("scionnet ", env!("CARGO_PKG_VERSION")),
+        "isd_asn": vectors::<IsdAsn, _, _>(ISD_ASN, isd_asn),
+        "scion_addr": vectors::<ScionAddr, _, _>(SCION_ADDR, scion_addr),
+        "socket_addr_scion": vectors::<SocketAddrScion, _, _>(SOCKET_ADDR, |a| {
+            socket_addr(&SocketAddr::SCION(*a))
+        }),
+        "socket_addr": vectors::<SocketAddr, _, _>(SOCKET_ADDR, socket_addr),
+    });
+    let out = serde_json::to_string_pretty(&doc).unwrap() + "\n";
+    match std::env::args().nth(1) {
+        Some(path) => std::fs::write(&path, out).unwrap_or_else(|e| panic!("{}: {}", path, e)),
+        None => print!("{}", out),
+    }
+}
@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use scionnet::ScionAddr;
+
+fuzz_target!(|data: &[u8]| {
+    // must never panic, whatever the input
+    let _ = ScionAddr::parse_ascii(data);
+    let _ = ScionAddr::parse_ascii_prefix(data);
+});
@@ -0,0 +1,36 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use scionnet::{AddrParseError, IpAddr, IsdAsn, ScionAddr, SocketAddr, SocketAddrScion};
+use std::fmt::{Debug, Display};
+use std::str::FromStr;
+
+/// Checks that an address parsed from `s` is written in a form that parses
+/// back to the same address and is written the same again, and that parsing
+/// only a prefix of `s` consumes it whole.
+fn roundtrip<T>(s: &str, parse_prefix: fn(&[u8]) -> Result<(T, usize), AddrParseError>)
+where
+    T: FromStr + Display + Debug + PartialEq,
+    T::Err: Debug,
+{
+    let Ok(addr) = s.parse::<T>() else {
+        return;
+    };
+    let text = addr.to_string();
+    let again = text.parse::<T>().unwrap_or_else(|e| panic!("{:?} wrote {:?}: {:?}", s, text, e));
+    assert_eq!(again, addr, "{:?} wrote {:?}", s, text);
+    assert_eq!(again.to_string(), text);
+    let (prefix, len) = parse_prefix(s.as_bytes()).expect("a prefix of a valid address");
+    assert_eq!((prefix, len), (addr, s.len()), "{:?}", s);
+}
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(s) = std::str::from_utf8(data) else {
+        return;
+    };
+    roundtrip::<IpAddr>(s, IpAddr::parse_ascii_prefix);
+    roundtrip::<IsdAsn>(s, IsdAsn::parse_ascii_prefix);
+    roundtrip::<ScionAddr>(s, ScionAddr::parse_ascii_prefix);
+    roundtrip::<SocketAddrScion>(s, SocketAddrScion::parse_ascii_prefix);
+    roundtrip::<SocketAddr>(s, SocketAddr::parse_ascii_prefix);
+});
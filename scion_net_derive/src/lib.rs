@@ -0,0 +1,113 @@
+//! Derive macros for the `scionnet` crate.
+//!
+//! Use them through `scionnet` with the `derive` feature enabled
+//! rather than depending on this crate directly.
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, LitStr, Type};
+
+/// Derives `scionnet::AddrConfig` for a struct with named fields.
+///
+/// Every field is parsed with its `FromStr` implementation from the value
+/// stored under the field name. Fields of type `Option<T>` may be absent.
+///
+/// Field attributes:
+///
+/// * `#[addr_config(rename = "key")]` looks the value up under `key`.
+/// * `#[addr_config(default = "value")]` uses `value` if the key is absent.
+#[proc_macro_derive(AddrConfig, attributes(addr_config))]
+pub fn derive_addr_config(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+struct FieldAttrs {
+    rename: Option<LitStr>,
+    default: Option<LitStr>,
+}
+
+fn field_attrs(field: &syn::Field) -> syn::Result<FieldAttrs> {
+    let mut attrs = FieldAttrs { rename: None, default: None };
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("addr_config")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                attrs.rename = Some(meta.value()?.parse()?);
+                Ok(())
+            } else if meta.path.is_ident("default") {
+                attrs.default = Some(meta.value()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `rename` or `default`"))
+            }
+        })?;
+    }
+    Ok(attrs)
+}
+
+fn is_option(ty: &Type) -> bool {
+    match ty {
+        Type::Path(p) => p
+            .path
+            .segments
+            .last()
+            .is_some_and(|seg| seg.ident == "Option"),
+        _ => false,
+    }
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    name,
+                    "AddrConfig can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                name,
+                "AddrConfig can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut inits = Vec::new();
+    for field in fields {
+        let ident = field.ident.as_ref().unwrap();
+        let attrs = field_attrs(field)?;
+        let key = attrs
+            .rename
+            .unwrap_or_else(|| LitStr::new(&ident.to_string(), ident.span()));
+        let default = match attrs.default {
+            Some(d) => quote!(::std::option::Option::Some(#d)),
+            None => quote!(::std::option::Option::None),
+        };
+        let parse = if is_option(&field.ty) {
+            quote!(::scionnet::__private::parse_optional(lookup, #key, #default)?)
+        } else {
+            quote!(::scionnet::__private::parse_required(lookup, #key, #default)?)
+        };
+        inits.push(quote!(#ident: #parse));
+    }
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::scionnet::AddrConfig for #name #ty_generics #where_clause {
+            fn from_lookup(
+                lookup: &mut dyn ::std::ops::FnMut(&str) -> ::std::option::Option<::std::string::String>,
+            ) -> ::std::result::Result<Self, ::scionnet::AddrConfigError> {
+                ::std::result::Result::Ok(#name {
+                    #(#inits,)*
+                })
+            }
+        }
+    })
+}
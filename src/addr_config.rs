@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// The error returned when loading an [`AddrConfig`] fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AddrConfigError {
+    /// A required key was not set.
+    Missing { key: String },
+    /// The value of a key could not be parsed.
+    Invalid { key: String, value: String, reason: String },
+}
+
+impl fmt::Display for AddrConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AddrConfigError::Missing { key } => write!(f, "missing configuration key '{}'", key),
+            AddrConfigError::Invalid { key, value, reason } => {
+                write!(f, "invalid value '{}' for configuration key '{}': {}", value, key, reason)
+            }
+        }
+    }
+}
+
+impl Error for AddrConfigError {}
+
+/// A configuration struct whose fields are loaded from string key-value pairs,
+/// i.e. listen and peer endpoints of a SCION service.
+///
+/// Implement it with `#[derive(AddrConfig)]` (feature `derive`):
+///
+/// ```ignore
+/// #[derive(AddrConfig)]
+/// struct ServiceConfig {
+///     listen: SocketAddrScion,
+///     #[addr_config(rename = "peer_addr")]
+///     peer: Option<SocketAddr>,
+///     #[addr_config(default = "1-ff00:0:110")]
+///     local_ia: IsdAsn,
+/// }
+///
+/// // reads SVC_LISTEN, SVC_PEER_ADDR and SVC_LOCAL_IA
+/// let config = ServiceConfig::from_env("SVC_")?;
+/// ```
+pub trait AddrConfig: Sized {
+    /// Loads the configuration, calling `lookup` for the value of each key.
+    fn from_lookup(lookup: &mut dyn FnMut(&str) -> Option<String>) -> Result<Self, AddrConfigError>;
+
+    /// Loads the configuration from a map of keys to values.
+    fn from_map(map: &HashMap<String, String>) -> Result<Self, AddrConfigError> {
+        Self::from_lookup(&mut |key| map.get(key).cloned())
+    }
+
+    /// Loads the configuration from environment variables named
+    /// `prefix` followed by the upper-cased key.
+    fn from_env(prefix: &str) -> Result<Self, AddrConfigError> {
+        Self::from_lookup(&mut |key| std::env::var(format!("{}{}", prefix, key.to_uppercase())).ok())
+    }
+}
+
+fn parse_value<T>(key: &str, value: &str) -> Result<T, AddrConfigError>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    value.trim().parse().map_err(|e: T::Err| AddrConfigError::Invalid {
+        key: key.to_string(),
+        value: value.to_string(),
+        reason: e.to_string(),
+    })
+}
+
+#[doc(hidden)]
+pub fn parse_required<T>(
+    lookup: &mut dyn FnMut(&str) -> Option<String>,
+    key: &str,
+    default: Option<&str>,
+) -> Result<T, AddrConfigError>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    match lookup(key) {
+        Some(value) => parse_value(key, &value),
+        None => match default {
+            Some(value) => parse_value(key, value),
+            None => Err(AddrConfigError::Missing { key: key.to_string() }),
+        },
+    }
+}
+
+#[doc(hidden)]
+pub fn parse_optional<T>(
+    lookup: &mut dyn FnMut(&str) -> Option<String>,
+    key: &str,
+    default: Option<&str>,
+) -> Result<Option<T>, AddrConfigError>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    match lookup(key).as_deref().or(default) {
+        Some(value) => parse_value(key, value).map(Some),
+        None => Ok(None),
+    }
+}
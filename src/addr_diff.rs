@@ -0,0 +1,163 @@
+use crate::SocketAddr;
+#[cfg(feature = "topology")]
+use crate::{IsdAsn, Topology};
+use std::collections::BTreeSet;
+
+/// The changes between two sets of socket addresses,
+/// as computed by [`diff_addr_sets`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AddrSetDiff {
+    /// Addresses only present in the new set.
+    pub added: Vec<SocketAddr>,
+    /// Addresses only present in the old set.
+    pub removed: Vec<SocketAddr>,
+    /// SCION endpoints that kept their host and port but moved to a
+    /// different IA, as `(old, new)` pairs. They are not repeated in
+    /// `added`/`removed`.
+    pub ia_changed: Vec<(SocketAddr, SocketAddr)>,
+}
+
+impl AddrSetDiff {
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.ia_changed.is_empty()
+    }
+}
+
+/// Compares two sets of socket addresses.
+///
+/// Addresses are compared in their [canonical](SocketAddr::to_canonical) form
+/// and duplicates are ignored. All lists of the result are sorted.
+pub fn diff_addr_sets(old: &[SocketAddr], new: &[SocketAddr]) -> AddrSetDiff {
+    let old: BTreeSet<SocketAddr> = old.iter().map(SocketAddr::to_canonical).collect();
+    let new: BTreeSet<SocketAddr> = new.iter().map(SocketAddr::to_canonical).collect();
+
+    let mut removed: Vec<SocketAddr> = old.difference(&new).cloned().collect();
+    let mut added: Vec<SocketAddr> = new.difference(&old).cloned().collect();
+    let mut ia_changed = Vec::new();
+
+    removed.retain(|old_addr| {
+        let moved = match *old_addr {
+            SocketAddr::SCION(ref o) => added.iter().position(|new_addr| match *new_addr {
+                SocketAddr::SCION(ref n) => n.host() == o.host() && n.port() == o.port(),
+                _ => false,
+            }),
+            _ => None,
+        };
+        match moved {
+            Some(i) => {
+                ia_changed.push((*old_addr, added.remove(i)));
+                false
+            }
+            None => true,
+        }
+    });
+
+    AddrSetDiff { added, removed, ia_changed }
+}
+
+/// A changed underlay address of a border router, as reported by
+/// [`diff_topologies`].
+#[cfg(feature = "topology")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnderlayChange {
+    /// The name of the border router.
+    pub router: String,
+    /// The interface whose local underlay address changed, or `None` for
+    /// the internal address of the router.
+    pub interface: Option<u64>,
+    pub old: std::net::SocketAddr,
+    pub new: std::net::SocketAddr,
+}
+
+/// The changes between two topologies, as computed by [`diff_topologies`].
+#[cfg(feature = "topology")]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TopologyDiff {
+    /// The old and new ISD-AS, if the local IA changed.
+    pub ia_changed: Option<(IsdAsn, IsdAsn)>,
+    /// Names of the border routers only present in the new topology.
+    pub added_routers: Vec<String>,
+    /// Names of the border routers only present in the old topology.
+    pub removed_routers: Vec<String>,
+    /// Interfaces only present in the new topology, as `(router, ifid)` pairs.
+    pub added_interfaces: Vec<(String, u64)>,
+    /// Interfaces only present in the old topology, as `(router, ifid)` pairs.
+    pub removed_interfaces: Vec<(String, u64)>,
+    /// Underlay addresses that changed for routers and interfaces present in both.
+    pub underlay_changed: Vec<UnderlayChange>,
+    /// Interfaces that lead to a different neighbor AS, as `(ifid, old, new)`.
+    pub neighbor_changed: Vec<(u64, IsdAsn, IsdAsn)>,
+}
+
+#[cfg(feature = "topology")]
+impl TopologyDiff {
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        *self == TopologyDiff::default()
+    }
+}
+
+/// Compares two topologies of an AS, i.e. before and after reloading its
+/// `topology.json`. All lists of the result are sorted.
+#[cfg(feature = "topology")]
+pub fn diff_topologies(old: &Topology, new: &Topology) -> TopologyDiff {
+    let mut diff = TopologyDiff::default();
+    if old.ia != new.ia {
+        diff.ia_changed = Some((old.ia, new.ia));
+    }
+    diff.removed_routers = old
+        .border_routers
+        .keys()
+        .filter(|name| !new.border_routers.contains_key(*name))
+        .cloned()
+        .collect();
+    diff.added_routers = new
+        .border_routers
+        .keys()
+        .filter(|name| !old.border_routers.contains_key(*name))
+        .cloned()
+        .collect();
+
+    for (name, old_br) in &old.border_routers {
+        let new_br = match new.border_routers.get(name) {
+            Some(br) => br,
+            None => continue,
+        };
+        if old_br.internal_addr != new_br.internal_addr {
+            diff.underlay_changed.push(UnderlayChange {
+                router: name.clone(),
+                interface: None,
+                old: old_br.internal_addr,
+                new: new_br.internal_addr,
+            });
+        }
+        for (&ifid, old_intf) in &old_br.interfaces {
+            let new_intf = match new_br.interfaces.get(&ifid) {
+                Some(intf) => intf,
+                None => {
+                    diff.removed_interfaces.push((name.clone(), ifid));
+                    continue;
+                }
+            };
+            if old_intf.local != new_intf.local {
+                diff.underlay_changed.push(UnderlayChange {
+                    router: name.clone(),
+                    interface: Some(ifid),
+                    old: old_intf.local,
+                    new: new_intf.local,
+                });
+            }
+            if old_intf.neighbor != new_intf.neighbor {
+                diff.neighbor_changed.push((ifid, old_intf.neighbor, new_intf.neighbor));
+            }
+        }
+        for &ifid in new_br.interfaces.keys() {
+            if !old_br.interfaces.contains_key(&ifid) {
+                diff.added_interfaces.push((name.clone(), ifid));
+            }
+        }
+    }
+    diff.neighbor_changed.sort_by_key(|&(ifid, ..)| ifid);
+    diff
+}
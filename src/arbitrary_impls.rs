@@ -0,0 +1,164 @@
+//! `Arbitrary` implementations, enabled by the `arbitrary` feature, for
+//! fuzzing protocols that embed the address types.
+//!
+//! Every generated value is one the constructors could have built: AS
+//! numbers stay within their 48 bits, so they cannot spill into the ISD of
+//! an IA. The enums pick their variant first, from one byte of the input.
+
+use crate::{
+    Asn, HostAddr, IpAddr, Ipv4Addr, Ipv6Addr, Isd, IsdAsn, ScionAddr, SocketAddr, SocketAddrScion,
+    SocketAddrV4, SocketAddrV6, Svc,
+};
+use arbitrary::{size_hint, Arbitrary, Result, Unstructured};
+use std::convert::TryFrom;
+
+impl<'a> Arbitrary<'a> for Ipv4Addr {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Ipv4Addr::from(<[u8; 4]>::arbitrary(u)?))
+    }
+
+    fn size_hint(_depth: usize) -> (usize, Option<usize>) {
+        (4, Some(4))
+    }
+}
+
+impl<'a> Arbitrary<'a> for Ipv6Addr {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Ipv6Addr::from_octets(<[u8; 16]>::arbitrary(u)?))
+    }
+
+    fn size_hint(_depth: usize) -> (usize, Option<usize>) {
+        (16, Some(16))
+    }
+}
+
+impl<'a> Arbitrary<'a> for IpAddr {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(match u.int_in_range(0..=1u8)? {
+            0 => IpAddr::V4(u.arbitrary()?),
+            _ => IpAddr::V6(u.arbitrary()?),
+        })
+    }
+
+    fn size_hint(depth: usize) -> (usize, Option<usize>) {
+        size_hint::and(
+            (1, Some(1)),
+            size_hint::or(Ipv4Addr::size_hint(depth), Ipv6Addr::size_hint(depth)),
+        )
+    }
+}
+
+impl<'a> Arbitrary<'a> for Svc {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Svc::new(u.arbitrary()?))
+    }
+
+    fn size_hint(_depth: usize) -> (usize, Option<usize>) {
+        (2, Some(2))
+    }
+}
+
+impl<'a> Arbitrary<'a> for HostAddr {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(match u.int_in_range(0..=1u8)? {
+            0 => HostAddr::Ip(u.arbitrary()?),
+            _ => HostAddr::Svc(u.arbitrary()?),
+        })
+    }
+
+    fn size_hint(depth: usize) -> (usize, Option<usize>) {
+        size_hint::and((1, Some(1)), size_hint::or(IpAddr::size_hint(depth), Svc::size_hint(depth)))
+    }
+}
+
+impl<'a> Arbitrary<'a> for Isd {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Isd::new(u.arbitrary()?))
+    }
+
+    fn size_hint(_depth: usize) -> (usize, Option<usize>) {
+        (2, Some(2))
+    }
+}
+
+impl<'a> Arbitrary<'a> for Asn {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let asn = u.int_in_range(0..=Asn::MAX.to_u64())?;
+        Ok(Asn::try_from(asn).expect("an AS number within 48 bits"))
+    }
+
+    fn size_hint(_depth: usize) -> (usize, Option<usize>) {
+        (0, Some(6))
+    }
+}
+
+impl<'a> Arbitrary<'a> for IsdAsn {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(IsdAsn::new(u.arbitrary()?, u.arbitrary()?))
+    }
+
+    fn size_hint(depth: usize) -> (usize, Option<usize>) {
+        size_hint::and(Isd::size_hint(depth), Asn::size_hint(depth))
+    }
+}
+
+impl<'a> Arbitrary<'a> for ScionAddr {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(ScionAddr::from_ia_host(u.arbitrary()?, u.arbitrary()?))
+    }
+
+    fn size_hint(depth: usize) -> (usize, Option<usize>) {
+        size_hint::and(IsdAsn::size_hint(depth), HostAddr::size_hint(depth))
+    }
+}
+
+impl<'a> Arbitrary<'a> for SocketAddrV4 {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(SocketAddrV4::new(u.arbitrary()?, u.arbitrary()?))
+    }
+
+    fn size_hint(_depth: usize) -> (usize, Option<usize>) {
+        (6, Some(6))
+    }
+}
+
+impl<'a> Arbitrary<'a> for SocketAddrV6 {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(SocketAddrV6::new(u.arbitrary()?, u.arbitrary()?, u.arbitrary()?, u.arbitrary()?))
+    }
+
+    fn size_hint(_depth: usize) -> (usize, Option<usize>) {
+        (26, Some(26))
+    }
+}
+
+impl<'a> Arbitrary<'a> for SocketAddrScion {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(SocketAddrScion::from_scion_addr(u.arbitrary()?, u.arbitrary()?))
+    }
+
+    fn size_hint(depth: usize) -> (usize, Option<usize>) {
+        size_hint::and(ScionAddr::size_hint(depth), (2, Some(2)))
+    }
+}
+
+impl<'a> Arbitrary<'a> for SocketAddr {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(match u.int_in_range(0..=2u8)? {
+            0 => SocketAddr::V4(u.arbitrary()?),
+            1 => SocketAddr::V6(u.arbitrary()?),
+            _ => SocketAddr::SCION(u.arbitrary()?),
+        })
+    }
+
+    fn size_hint(depth: usize) -> (usize, Option<usize>) {
+        size_hint::and(
+            (1, Some(1)),
+            size_hint::or_all(&[
+                SocketAddrV4::size_hint(depth),
+                SocketAddrV6::size_hint(depth),
+                SocketAddrScion::size_hint(depth),
+            ]),
+        )
+    }
+}
@@ -0,0 +1,202 @@
+use crate::path_db::Fnv1a;
+use crate::{IsdAsn, SocketAddrScion};
+use std::hash::Hasher;
+
+/// Which backends a [`WeightedBalancer`] prefers by their ISD-AS.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum IaAffinity {
+    /// All healthy backends are candidates.
+    #[default]
+    None,
+    /// Backends in the given IA are picked while any of them is healthy,
+    /// the others only after.
+    Prefer(IsdAsn),
+    /// Only backends in the given IA are picked.
+    Require(IsdAsn),
+}
+
+#[derive(Clone, Debug)]
+struct Backend {
+    addr: SocketAddrScion,
+    weight: u32,
+    healthy: bool,
+}
+
+/// Spreads flows over weighted backends.
+///
+/// [`pick`](Self::pick) maps a flow key to a backend by weighted rendezvous
+/// hashing: every flow keeps its backend as long as that backend stays up,
+/// each backend gets a share of the flows proportional to its weight, and
+/// adding or removing a backend only moves the flows it gains or loses.
+/// Backends with weight 0 are drained, they get no flows.
+///
+/// Health is tracked with [`mark_down`](Self::mark_down) and
+/// [`mark_up`](Self::mark_up), or by probing every backend with
+/// [`check_health`](Self::check_health).
+///
+/// The balancer does not synchronize itself; wrap it in a `RwLock` to share
+/// it between threads.
+///
+/// ```
+/// use scionnet::{SocketAddrScion, WeightedBalancer};
+///
+/// let a: SocketAddrScion = "1-ff00:0:110,10.0.0.1:443".parse().unwrap();
+/// let b: SocketAddrScion = "1-ff00:0:111,10.0.0.2:443".parse().unwrap();
+/// let mut balancer = WeightedBalancer::new();
+/// balancer.add(a, 1);
+/// balancer.add(b, 3);
+/// let backend = balancer.pick("client-42").unwrap();
+/// assert_eq!(balancer.pick("client-42"), Some(backend));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct WeightedBalancer {
+    backends: Vec<Backend>,
+    affinity: IaAffinity,
+}
+
+/// Returns the rendezvous score of `backend` for the flow with hash `flow`.
+fn score(flow: u64, backend: &Backend) -> f64 {
+    // explicit big-endian bytes, as derived hashes differ between platforms
+    let mut hasher = Fnv1a::default();
+    hasher.write(&flow.to_be_bytes());
+    hasher.write(&backend.addr.to_key_bytes());
+    // FNV-1a barely spreads the last bytes into the upper bits, which the
+    // score is taken from, so they are mixed with the MurmurHash3 finalizer
+    let mut h = hasher.finish();
+    h = (h ^ (h >> 33)).wrapping_mul(0xff51_afd7_ed55_8ccd);
+    h = (h ^ (h >> 33)).wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    h ^= h >> 33;
+    // a uniform value in (0, 1)
+    let u = ((h >> 11) as f64 + 0.5) / (1u64 << 53) as f64;
+    -f64::from(backend.weight) / u.ln()
+}
+
+impl WeightedBalancer {
+    #[must_use]
+    pub fn new() -> WeightedBalancer {
+        WeightedBalancer::default()
+    }
+
+    /// Sets the IA affinity of [`pick`](Self::pick).
+    #[must_use]
+    pub fn with_affinity(mut self, affinity: IaAffinity) -> WeightedBalancer {
+        self.affinity = affinity;
+        self
+    }
+
+    pub fn set_affinity(&mut self, affinity: IaAffinity) {
+        self.affinity = affinity;
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn affinity(&self) -> IaAffinity {
+        self.affinity
+    }
+
+    /// Adds the healthy backend `addr`, or sets its weight if it was added before.
+    pub fn add(&mut self, addr: SocketAddrScion, weight: u32) {
+        match self.backends.iter_mut().find(|b| b.addr == addr) {
+            Some(b) => b.weight = weight,
+            None => self.backends.push(Backend { addr, weight, healthy: true }),
+        }
+    }
+
+    /// Removes the backend `addr`, returning `true` if it was present.
+    pub fn remove(&mut self, addr: &SocketAddrScion) -> bool {
+        let before = self.backends.len();
+        self.backends.retain(|b| b.addr != *addr);
+        self.backends.len() != before
+    }
+
+    /// Returns the weight of `addr`, or `None` if it is no backend.
+    #[must_use]
+    pub fn weight(&self, addr: &SocketAddrScion) -> Option<u32> {
+        self.backend(addr).map(|b| b.weight)
+    }
+
+    fn backend(&self, addr: &SocketAddrScion) -> Option<&Backend> {
+        self.backends.iter().find(|b| b.addr == *addr)
+    }
+
+    fn set_healthy(&mut self, addr: &SocketAddrScion, healthy: bool) -> bool {
+        match self.backends.iter_mut().find(|b| b.addr == *addr) {
+            Some(b) => {
+                b.healthy = healthy;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Takes `addr` out of rotation until it is marked up again, returning
+    /// `false` if it is no backend.
+    pub fn mark_down(&mut self, addr: &SocketAddrScion) -> bool {
+        self.set_healthy(addr, false)
+    }
+
+    /// Puts `addr` back into rotation, returning `false` if it is no backend.
+    pub fn mark_up(&mut self, addr: &SocketAddrScion) -> bool {
+        self.set_healthy(addr, true)
+    }
+
+    /// Returns `true` if `addr` is a backend that is not marked down.
+    #[must_use]
+    pub fn is_healthy(&self, addr: &SocketAddrScion) -> bool {
+        self.backend(addr).is_some_and(|b| b.healthy)
+    }
+
+    /// Probes every backend with `probe`, i.e. an SCMP echo or a connection
+    /// attempt, marking it up or down by the result. Returns the number of
+    /// healthy backends.
+    pub fn check_health<F: FnMut(&SocketAddrScion) -> bool>(&mut self, mut probe: F) -> usize {
+        for b in &mut self.backends {
+            b.healthy = probe(&b.addr);
+        }
+        self.backends.iter().filter(|b| b.healthy).count()
+    }
+
+    /// Returns all backends, in the order they were added.
+    pub fn backends(&self) -> impl Iterator<Item = &SocketAddrScion> + '_ {
+        self.backends.iter().map(|b| &b.addr)
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.backends.len()
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.backends.is_empty()
+    }
+
+    /// Returns the backend of the flow `flow_key`, i.e. the
+    /// [key bytes](SocketAddrScion::to_key_bytes) of a client address or a
+    /// session id, or `None` if no healthy backend with a weight is left.
+    ///
+    /// Only the bytes of the key are hashed, so a flow gets the same backend
+    /// on every platform.
+    #[must_use]
+    pub fn pick<K: AsRef<[u8]> + ?Sized>(&self, flow_key: &K) -> Option<SocketAddrScion> {
+        let mut hasher = Fnv1a::default();
+        hasher.write(flow_key.as_ref());
+        let flow = hasher.finish();
+        let best = |in_ia: Option<IsdAsn>| {
+            self.backends
+                .iter()
+                .filter(|b| b.healthy && b.weight > 0)
+                .filter(|b| in_ia.map_or(true, |ia| b.addr.ia() == ia.to_u64()))
+                .map(|b| (score(flow, b), b.addr))
+                .max_by(|x, y| x.0.total_cmp(&y.0))
+                .map(|(_, addr)| addr)
+        };
+        match self.affinity {
+            IaAffinity::None => best(None),
+            IaAffinity::Prefer(ia) => best(Some(ia)).or_else(|| best(None)),
+            IaAffinity::Require(ia) => best(Some(ia)),
+        }
+    }
+}
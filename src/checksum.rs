@@ -0,0 +1,67 @@
+use crate::udp_socket::encode_host;
+use crate::ScionAddr;
+
+/// A ones' complement sum of 16-bit big endian words, fed in pieces that
+/// may have odd lengths.
+#[derive(Default)]
+struct Sum {
+    sum: u64,
+    odd: Option<u8>,
+}
+
+impl Sum {
+    fn add(&mut self, mut data: &[u8]) {
+        if let (Some(hi), Some((&lo, rest))) = (self.odd, data.split_first()) {
+            self.sum += u64::from(u16::from_be_bytes([hi, lo]));
+            self.odd = None;
+            data = rest;
+        }
+        let mut words = data.chunks_exact(2);
+        for word in &mut words {
+            self.sum += u64::from(u16::from_be_bytes([word[0], word[1]]));
+        }
+        if let [last] = words.remainder() {
+            self.odd = Some(*last);
+        }
+    }
+
+    fn finish(mut self) -> u16 {
+        if let Some(hi) = self.odd {
+            self.sum += u64::from(hi) << 8;
+        }
+        while self.sum > 0xffff {
+            self.sum = (self.sum & 0xffff) + (self.sum >> 16);
+        }
+        !(self.sum as u16)
+    }
+}
+
+/// Computes the checksum of an upper-layer packet (SCMP, UDP, ...) with
+/// protocol number `proto`, covering the SCION pseudo header of `src` and `dst`.
+///
+/// The checksum field within `upper` must be zero.
+pub(crate) fn scion_checksum(src: &ScionAddr, dst: &ScionAddr, proto: u8, upper: &[u8]) -> u16 {
+    scion_checksum_vectored(src, dst, proto, &[upper])
+}
+
+/// Computes the checksum of an upper-layer packet given as the concatenation
+/// of `parts`, see [`scion_checksum`].
+pub(crate) fn scion_checksum_vectored(
+    src: &ScionAddr,
+    dst: &ScionAddr,
+    proto: u8,
+    parts: &[&[u8]],
+) -> u16 {
+    let len: usize = parts.iter().map(|p| p.len()).sum();
+    let mut sum = Sum::default();
+    sum.add(&dst.get_ia().to_be_bytes());
+    sum.add(&src.get_ia().to_be_bytes());
+    sum.add(&encode_host(dst.get_host()).1);
+    sum.add(&encode_host(src.get_host()).1);
+    sum.add(&(len as u32).to_be_bytes());
+    sum.add(&[0, 0, 0, proto]);
+    for part in parts {
+        sum.add(part);
+    }
+    sum.finish()
+}
@@ -0,0 +1,265 @@
+use crate::SocketAddr;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// The 5-tuple identifying a flow: source and destination socket address
+/// and the L4 protocol number.
+///
+/// Addresses are stored in their [`SocketAddr::to_canonical`] form, so that
+/// IPv4-mapped IPv6 and plain IPv4 endpoints name the same flow.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FlowId {
+    src: SocketAddr,
+    dst: SocketAddr,
+    proto: u8,
+}
+
+impl FlowId {
+    pub fn new(src: SocketAddr, dst: SocketAddr, proto: u8) -> FlowId {
+        FlowId { src: src.to_canonical(), dst: dst.to_canonical(), proto }
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn src(&self) -> &SocketAddr {
+        &self.src
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn dst(&self) -> &SocketAddr {
+        &self.dst
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn proto(&self) -> u8 {
+        self.proto
+    }
+
+    /// Returns the flow of the packets sent in the opposite direction.
+    #[must_use]
+    pub fn reversed(&self) -> FlowId {
+        FlowId { src: self.dst, dst: self.src, proto: self.proto }
+    }
+}
+
+impl fmt::Display for FlowId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} -> {} ({})", self.src, self.dst, self.proto)
+    }
+}
+
+impl fmt::Debug for FlowId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+/// The lifecycle state of a tracked connection.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ConnState {
+    /// Packets were seen in one direction only.
+    New,
+    /// Packets were seen in both directions.
+    Established,
+    /// The connection is being torn down.
+    Closing,
+}
+
+/// How long a connection in each state may stay idle before it expires.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ConnTimeouts {
+    pub new: Duration,
+    pub established: Duration,
+    pub closing: Duration,
+}
+
+impl ConnTimeouts {
+    #[must_use]
+    pub fn for_state(&self, state: ConnState) -> Duration {
+        match state {
+            ConnState::New => self.new,
+            ConnState::Established => self.established,
+            ConnState::Closing => self.closing,
+        }
+    }
+}
+
+impl Default for ConnTimeouts {
+    fn default() -> Self {
+        ConnTimeouts {
+            new: Duration::from_secs(30),
+            established: Duration::from_secs(300),
+            closing: Duration::from_secs(10),
+        }
+    }
+}
+
+/// A tracked connection.
+#[derive(Clone, Debug)]
+pub struct ConnEntry<T> {
+    pub state: ConnState,
+    pub data: T,
+    pub last_seen: Instant,
+}
+
+type Shard<T> = Mutex<HashMap<FlowId, ConnEntry<T>>>;
+type ExpiryCallback<T> = Box<dyn Fn(&FlowId, ConnEntry<T>) + Send + Sync>;
+
+/// A concurrent connection-tracking table keyed by [`FlowId`], i.e. for
+/// SCION-aware NATs and gateways.
+///
+/// Every connection carries user data `T` and expires once it has been idle
+/// for longer than the [timeout](ConnTimeouts) of its state. Expired entries
+/// are removed by [`expire`](Self::expire), which hands them to the callback
+/// set with [`on_expire`](Self::on_expire).
+pub struct ConnTrack<T> {
+    timeouts: ConnTimeouts,
+    shards: Box<[Shard<T>]>,
+    on_expire: Option<ExpiryCallback<T>>,
+}
+
+impl<T> ConnTrack<T> {
+    pub fn new(timeouts: ConnTimeouts) -> ConnTrack<T> {
+        let shards = std::thread::available_parallelism().map_or(4, |n| n.get() * 4);
+        ConnTrack {
+            timeouts,
+            shards: (0..shards).map(|_| Mutex::new(HashMap::new())).collect(),
+            on_expire: None,
+        }
+    }
+
+    /// Sets the callback receiving every entry removed by [`expire`](Self::expire).
+    pub fn on_expire<F>(&mut self, callback: F)
+    where
+        F: Fn(&FlowId, ConnEntry<T>) + Send + Sync + 'static,
+    {
+        self.on_expire = Some(Box::new(callback));
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn timeouts(&self) -> ConnTimeouts {
+        self.timeouts
+    }
+
+    fn shard(&self, flow: &FlowId) -> &Shard<T> {
+        let mut hasher = DefaultHasher::new();
+        flow.hash(&mut hasher);
+        &self.shards[hasher.finish() as usize % self.shards.len()]
+    }
+
+    /// Tracks a new connection, replacing and returning any previous entry for `flow`.
+    pub fn insert(&self, flow: FlowId, data: T) -> Option<ConnEntry<T>> {
+        let entry = ConnEntry { state: ConnState::New, data, last_seen: Instant::now() };
+        self.shard(&flow).lock().unwrap().insert(flow, entry)
+    }
+
+    /// Marks `flow` as seen just now. Returns `false` if it is not tracked.
+    pub fn touch(&self, flow: &FlowId) -> bool {
+        self.update(flow, |e| e.last_seen = Instant::now()).is_some()
+    }
+
+    /// Moves `flow` to `state` and marks it as seen just now.
+    /// Returns `false` if it is not tracked.
+    pub fn set_state(&self, flow: &FlowId, state: ConnState) -> bool {
+        self.update(flow, |e| {
+            e.state = state;
+            e.last_seen = Instant::now();
+        })
+        .is_some()
+    }
+
+    /// Calls `f` with the entry of `flow` while holding its lock.
+    pub fn update<R, F: FnOnce(&mut ConnEntry<T>) -> R>(&self, flow: &FlowId, f: F) -> Option<R> {
+        self.shard(flow).lock().unwrap().get_mut(flow).map(f)
+    }
+
+    /// Stops tracking `flow`, returning its entry.
+    pub fn remove(&self, flow: &FlowId) -> Option<ConnEntry<T>> {
+        self.shard(flow).lock().unwrap().remove(flow)
+    }
+
+    #[must_use]
+    pub fn contains(&self, flow: &FlowId) -> bool {
+        self.shard(flow).lock().unwrap().contains_key(flow)
+    }
+
+    /// Removes all connections that are expired now.
+    ///
+    /// Returns the number of removed connections.
+    pub fn expire(&self) -> usize {
+        self.expire_at(Instant::now())
+    }
+
+    /// Removes all connections that are expired as of `now`.
+    ///
+    /// The expiry callback is called without holding any lock, so it may
+    /// access the table.
+    pub fn expire_at(&self, now: Instant) -> usize {
+        let mut count = 0;
+        for shard in self.shards.iter() {
+            let expired: Vec<_> = {
+                let mut map = shard.lock().unwrap();
+                let flows: Vec<FlowId> = map
+                    .iter()
+                    .filter(|(_, e)| {
+                        now.saturating_duration_since(e.last_seen) >= self.timeouts.for_state(e.state)
+                    })
+                    .map(|(flow, _)| *flow)
+                    .collect();
+                flows.into_iter().filter_map(|f| map.remove_entry(&f)).collect()
+            };
+            count += expired.len();
+            if let Some(ref callback) = self.on_expire {
+                for (flow, entry) in expired {
+                    callback(&flow, entry);
+                }
+            }
+        }
+        count
+    }
+
+    /// Returns the number of tracked connections.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|s| s.lock().unwrap().len()).sum()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T: Clone> ConnTrack<T> {
+    /// Returns a copy of the entry of `flow`.
+    #[must_use]
+    pub fn get(&self, flow: &FlowId) -> Option<ConnEntry<T>> {
+        self.shard(flow).lock().unwrap().get(flow).cloned()
+    }
+
+    /// Returns a copy of all tracked connections.
+    ///
+    /// Shards are copied one after another, so the snapshot is not atomic
+    /// with respect to concurrent updates.
+    #[must_use]
+    pub fn snapshot(&self) -> Vec<(FlowId, ConnEntry<T>)> {
+        let mut all = Vec::new();
+        for shard in self.shards.iter() {
+            all.extend(shard.lock().unwrap().iter().map(|(f, e)| (*f, e.clone())));
+        }
+        all
+    }
+}
+
+impl<T> Default for ConnTrack<T> {
+    fn default() -> Self {
+        ConnTrack::new(ConnTimeouts::default())
+    }
+}
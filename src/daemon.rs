@@ -0,0 +1,412 @@
+//! A client for the gRPC API of the SCION daemon (`sciond`), enabled by the
+//! `daemon` feature.
+//!
+//! The daemon knows the local topology and fetches paths from the control
+//! service on behalf of the applications of a host:
+//!
+//! ```ignore
+//! let mut daemon = DaemonClient::connect(DEFAULT_DAEMON_ADDRESS).await?;
+//! let local = daemon.as_info(None).await?.ia;
+//! let paths = daemon.paths(local, "2-ff00:0:220".parse()?).await?;
+//! socket.set_route(paths[0].dst, paths[0].route().unwrap());
+//! ```
+
+use crate::{AddrKind, AddrParseError, IsdAsn, PathInterface, ScionPath, PATH_TYPE_SCION};
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+use tonic::codegen::http::uri::PathAndQuery;
+use tonic::transport::{Channel, Endpoint};
+
+/// The address the SCION daemon listens on by default.
+pub const DEFAULT_DAEMON_ADDRESS: &str = "127.0.0.1:30255";
+
+/// Where the SCION daemon listens: a TCP socket or a Unix domain socket.
+///
+/// The textual form is either a socket address or a host name and port,
+/// optionally prefixed with `tcp://`, i.e. `tcp://sciond:30255`, or a socket
+/// path prefixed with `unix://`, i.e. `unix:///run/scion/sciond.sock`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum DaemonAddr {
+    Tcp(std::net::SocketAddr),
+    /// A host name and port, resolved when connecting, i.e. the name of the
+    /// daemon's container.
+    Host(String, u16),
+    Unix(PathBuf),
+}
+
+impl Default for DaemonAddr {
+    /// Returns [`DEFAULT_DAEMON_ADDRESS`].
+    fn default() -> Self {
+        DaemonAddr::Tcp(std::net::SocketAddr::from(([127, 0, 0, 1], 30255)))
+    }
+}
+
+impl FromStr for DaemonAddr {
+    type Err = AddrParseError;
+
+    fn from_str(s: &str) -> Result<DaemonAddr, AddrParseError> {
+        if let Some(path) = s.strip_prefix("unix://") {
+            if path.is_empty() {
+                return Err(AddrParseError::new(AddrKind::Socket));
+            }
+            return Ok(DaemonAddr::Unix(PathBuf::from(path)));
+        }
+        let addr = s.strip_prefix("tcp://").unwrap_or(s);
+        if let Ok(addr) = std::net::SocketAddr::from_str(addr) {
+            return Ok(DaemonAddr::Tcp(addr));
+        }
+        let err = || AddrParseError::new(AddrKind::Socket);
+        let (host, port) = addr.rsplit_once(':').ok_or_else(err)?;
+        let port = u16::from_str(port).map_err(|_| err())?;
+        let is_name = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '.';
+        if host.is_empty() || !host.chars().all(is_name) {
+            return Err(err());
+        }
+        Ok(DaemonAddr::Host(host.to_string(), port))
+    }
+}
+
+impl fmt::Display for DaemonAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DaemonAddr::Tcp(addr) => write!(f, "{}", addr),
+            DaemonAddr::Host(host, port) => write!(f, "{}:{}", host, port),
+            DaemonAddr::Unix(path) => write!(f, "unix://{}", path.display()),
+        }
+    }
+}
+
+impl From<std::net::SocketAddr> for DaemonAddr {
+    fn from(addr: std::net::SocketAddr) -> DaemonAddr {
+        DaemonAddr::Tcp(addr)
+    }
+}
+
+impl From<PathBuf> for DaemonAddr {
+    fn from(path: PathBuf) -> DaemonAddr {
+        DaemonAddr::Unix(path)
+    }
+}
+
+/// Connects the channel of a [`DaemonAddr::Unix`] to its socket, whatever
+/// the URI of the endpoint.
+#[cfg(unix)]
+#[derive(Clone)]
+struct UnixConnector(std::sync::Arc<PathBuf>);
+
+#[cfg(unix)]
+impl tower_service::Service<tonic::transport::Uri> for UnixConnector {
+    type Response = hyper_util::rt::TokioIo<tokio::net::UnixStream>;
+    type Error = io::Error;
+    type Future = std::pin::Pin<
+        Box<dyn std::future::Future<Output = io::Result<Self::Response>> + Send>,
+    >;
+
+    fn poll_ready(&mut self, _: &mut std::task::Context<'_>) -> std::task::Poll<io::Result<()>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _: tonic::transport::Uri) -> Self::Future {
+        let path = self.0.clone();
+        Box::pin(async move {
+            tokio::net::UnixStream::connect(&*path)
+                .await
+                .map(hyper_util::rt::TokioIo::new)
+        })
+    }
+}
+
+/// The messages of `proto/daemon/v1/daemon.proto`, restricted to the
+/// fields used by this client.
+pub(crate) mod proto {
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct PathsRequest {
+        #[prost(uint64, tag = "1")]
+        pub source_isd_as: u64,
+        #[prost(uint64, tag = "2")]
+        pub destination_isd_as: u64,
+        #[prost(bool, tag = "3")]
+        pub refresh: bool,
+        #[prost(bool, tag = "4")]
+        pub hidden: bool,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct PathsResponse {
+        #[prost(message, repeated, tag = "1")]
+        pub paths: Vec<Path>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Path {
+        #[prost(bytes = "vec", tag = "1")]
+        pub raw: Vec<u8>,
+        #[prost(message, optional, tag = "2")]
+        pub interface: Option<Interface>,
+        #[prost(message, repeated, tag = "3")]
+        pub interfaces: Vec<PathInterface>,
+        #[prost(uint32, tag = "4")]
+        pub mtu: u32,
+        #[prost(message, optional, tag = "5")]
+        pub expiration: Option<Timestamp>,
+        #[prost(message, repeated, tag = "6")]
+        pub latency: Vec<Duration>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct PathInterface {
+        #[prost(uint64, tag = "1")]
+        pub isd_as: u64,
+        #[prost(uint64, tag = "2")]
+        pub id: u64,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Interface {
+        #[prost(message, optional, tag = "1")]
+        pub address: Option<Underlay>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Underlay {
+        #[prost(string, tag = "1")]
+        pub address: String,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct AsRequest {
+        #[prost(uint64, tag = "1")]
+        pub isd_as: u64,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct AsResponse {
+        #[prost(uint64, tag = "1")]
+        pub isd_as: u64,
+        #[prost(bool, tag = "2")]
+        pub core: bool,
+        #[prost(uint32, tag = "3")]
+        pub mtu: u32,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct InterfacesRequest {}
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct InterfacesResponse {
+        #[prost(map = "uint64, message", tag = "1")]
+        pub interfaces: ::std::collections::HashMap<u64, Interface>,
+    }
+
+    /// `google.protobuf.Timestamp`
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Timestamp {
+        #[prost(int64, tag = "1")]
+        pub seconds: i64,
+        #[prost(int32, tag = "2")]
+        pub nanos: i32,
+    }
+
+    /// `google.protobuf.Duration`
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Duration {
+        #[prost(int64, tag = "1")]
+        pub seconds: i64,
+        #[prost(int32, tag = "2")]
+        pub nanos: i32,
+    }
+}
+
+/// Information about an AS, as returned by [`DaemonClient::as_info`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct AsInfo {
+    pub ia: IsdAsn,
+    /// Whether the AS is a core AS of its ISD.
+    pub core: bool,
+    /// The MTU within the AS.
+    pub mtu: u16,
+}
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn status_to_io(status: tonic::Status) -> io::Error {
+    let kind = match status.code() {
+        tonic::Code::NotFound => io::ErrorKind::NotFound,
+        tonic::Code::InvalidArgument => io::ErrorKind::InvalidInput,
+        tonic::Code::DeadlineExceeded => io::ErrorKind::TimedOut,
+        tonic::Code::Unavailable => io::ErrorKind::ConnectionRefused,
+        tonic::Code::PermissionDenied | tonic::Code::Unauthenticated => {
+            io::ErrorKind::PermissionDenied
+        }
+        _ => io::ErrorKind::Other,
+    };
+    io::Error::new(kind, status.message().to_string())
+}
+
+fn underlay_addr(intf: &Option<proto::Interface>) -> io::Result<Option<std::net::SocketAddr>> {
+    match intf.as_ref().and_then(|i| i.address.as_ref()) {
+        Some(u) if !u.address.is_empty() => u
+            .address
+            .parse()
+            .map(Some)
+            .map_err(|_| invalid_data(format!("invalid underlay address '{}'", u.address))),
+        _ => Ok(None),
+    }
+}
+
+fn to_duration(d: &proto::Duration) -> Option<Duration> {
+    if d.seconds < 0 || d.nanos < 0 {
+        // negative latencies mark unknown values
+        return None;
+    }
+    Some(Duration::new(d.seconds as u64, d.nanos as u32))
+}
+
+impl ScionPath {
+    pub(crate) fn from_proto(src: IsdAsn, dst: IsdAsn, path: proto::Path) -> io::Result<ScionPath> {
+        let expiration = match path.expiration {
+            Some(ref t) if t.seconds >= 0 => {
+                SystemTime::UNIX_EPOCH + Duration::new(t.seconds as u64, t.nanos.max(0) as u32)
+            }
+            _ => SystemTime::UNIX_EPOCH,
+        };
+        Ok(ScionPath {
+            src,
+            dst,
+            path_type: if path.raw.is_empty() { 0 } else { PATH_TYPE_SCION },
+            next_hop: underlay_addr(&path.interface)?,
+            interfaces: path
+                .interfaces
+                .iter()
+                .map(|i| PathInterface { ia: IsdAsn::from(i.isd_as), id: i.id })
+                .collect(),
+            mtu: u16::try_from(path.mtu).unwrap_or(u16::MAX),
+            expiration,
+            latency: path.latency.iter().map(to_duration).collect(),
+            raw: path.raw,
+        })
+    }
+}
+
+/// A connection to the SCION daemon.
+#[derive(Clone)]
+pub struct DaemonClient {
+    grpc: tonic::client::Grpc<Channel>,
+}
+
+impl DaemonClient {
+    /// Connects to the daemon at `addr` in the textual form of a
+    /// [`DaemonAddr`], i.e. [`DEFAULT_DAEMON_ADDRESS`] or
+    /// `unix:///run/scion/sciond.sock`.
+    pub async fn connect(addr: &str) -> io::Result<DaemonClient> {
+        let addr = DaemonAddr::from_str(addr)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        Self::connect_to(&addr).await
+    }
+
+    /// Connects to the daemon at `addr`, trying all addresses of a host name.
+    ///
+    /// Unix domain sockets are only supported on Unix.
+    pub async fn connect_to(addr: &DaemonAddr) -> io::Result<DaemonClient> {
+        let refused = |e| io::Error::new(io::ErrorKind::ConnectionRefused, e);
+        let channel = match addr {
+            DaemonAddr::Tcp(_) | DaemonAddr::Host(..) => {
+                let endpoint = Endpoint::from_shared(format!("http://{}", addr))
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+                endpoint.connect().await.map_err(refused)?
+            }
+            #[cfg(unix)]
+            DaemonAddr::Unix(path) => {
+                // the URI is only used for the HTTP/2 authority
+                Endpoint::from_static("http://localhost")
+                    .connect_with_connector(UnixConnector(std::sync::Arc::new(path.clone())))
+                    .await
+                    .map_err(refused)?
+            }
+            #[cfg(not(unix))]
+            DaemonAddr::Unix(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "Unix domain sockets are not supported on this platform",
+                ))
+            }
+        };
+        Ok(DaemonClient { grpc: tonic::client::Grpc::new(channel) })
+    }
+
+    async fn unary<Req, Resp>(&mut self, method: &'static str, request: Req) -> io::Result<Resp>
+    where
+        Req: prost::Message + Send + Sync + 'static,
+        Resp: prost::Message + Default + Send + Sync + 'static,
+    {
+        self.grpc
+            .ready()
+            .await
+            .map_err(|e| io::Error::new(io::ErrorKind::ConnectionAborted, e))?;
+        let codec = tonic::codec::ProstCodec::default();
+        let path = PathAndQuery::from_static(method);
+        self.grpc
+            .unary(tonic::Request::new(request), path, codec)
+            .await
+            .map(tonic::Response::into_inner)
+            .map_err(status_to_io)
+    }
+
+    /// Returns the paths from `src` to `dst` known to the daemon.
+    pub async fn paths(&mut self, src: IsdAsn, dst: IsdAsn) -> io::Result<Vec<ScionPath>> {
+        self.paths_with(src, dst, false, false).await
+    }
+
+    /// Returns the paths from `src` to `dst`, asking the daemon to fetch fresh
+    /// paths if `refresh` is set and to include hidden paths if `hidden` is set.
+    pub async fn paths_with(
+        &mut self,
+        src: IsdAsn,
+        dst: IsdAsn,
+        refresh: bool,
+        hidden: bool,
+    ) -> io::Result<Vec<ScionPath>> {
+        let request = proto::PathsRequest {
+            source_isd_as: src.to_u64(),
+            destination_isd_as: dst.to_u64(),
+            refresh,
+            hidden,
+        };
+        let response: proto::PathsResponse =
+            self.unary("/proto.daemon.v1.DaemonService/Paths", request).await?;
+        response.paths.into_iter().map(|p| ScionPath::from_proto(src, dst, p)).collect()
+    }
+
+    /// Returns information about the AS `ia`, or about the local AS for `None`.
+    pub async fn as_info(&mut self, ia: Option<IsdAsn>) -> io::Result<AsInfo> {
+        let request = proto::AsRequest { isd_as: ia.map_or(0, IsdAsn::to_u64) };
+        let response: proto::AsResponse =
+            self.unary("/proto.daemon.v1.DaemonService/AS", request).await?;
+        Ok(AsInfo {
+            ia: IsdAsn::from(response.isd_as),
+            core: response.core,
+            mtu: u16::try_from(response.mtu).unwrap_or(u16::MAX),
+        })
+    }
+
+    /// Returns the underlay addresses of the border routers of the local AS,
+    /// keyed by interface id.
+    pub async fn interfaces(&mut self) -> io::Result<HashMap<u64, std::net::SocketAddr>> {
+        let response: proto::InterfacesResponse = self
+            .unary("/proto.daemon.v1.DaemonService/Interfaces", proto::InterfacesRequest {})
+            .await?;
+        let mut interfaces = HashMap::with_capacity(response.interfaces.len());
+        for (id, intf) in response.interfaces {
+            if let Some(addr) = underlay_addr(&Some(intf))? {
+                interfaces.insert(id, addr);
+            }
+        }
+        Ok(interfaces)
+    }
+}
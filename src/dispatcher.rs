@@ -0,0 +1,261 @@
+//! Sockets registered with the SCION dispatcher, for hosts still running one.
+//!
+//! Applications talk to the dispatcher over a Unix stream socket. Every
+//! message on it is framed like this, the address and port being absent
+//! for the address type none:
+//!
+//! ```text
+//! +--------------------+---------+-----------+-----------+------+-----------+
+//! | cookie 0xde00ad01  | addr    | payload   | address   | port | payload   |
+//! | be02ef03 (8 bytes) | type (1)| len (4)   | (0/4/16)  | (0/2)|           |
+//! +--------------------+---------+-----------+-----------+------+-----------+
+//! ```
+//!
+//! The first message registers the socket and is answered with the port
+//! assigned to it. After that, the payloads are SCION packets: outgoing ones
+//! carry the underlay next hop as their address, incoming ones the underlay
+//! address they came from.
+
+use crate::udp_socket::{first_addr, SocketState, MAX_DATAGRAM};
+use crate::{IsdAsn, ScionRoute, SocketAddr, SocketAddrScion, Svc, ToScionSocketAddrs};
+use std::io::{self, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::time::Duration;
+
+/// Where the dispatcher listens by default.
+pub const DEFAULT_DISPATCHER_SOCKET: &str = "/run/shm/dispatcher/default.sock";
+
+const COOKIE: u64 = 0xde00_ad01_be02_ef03;
+const FRAME_HEADER_LEN: usize = 13;
+const ADDR_TYPE_NONE: u8 = 0;
+const ADDR_TYPE_IPV4: u8 = 1;
+const ADDR_TYPE_IPV6: u8 = 2;
+
+const CMD_ALWAYS_ON: u8 = 0x01;
+const CMD_ENABLE_SCMP: u8 = 0x02;
+const CMD_BIND_ADDRESS: u8 = 0x04;
+
+fn invalid_frame(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn push_addr(buf: &mut Vec<u8>, addr: std::net::SocketAddr) {
+    match addr.ip() {
+        std::net::IpAddr::V4(ip) => buf.extend_from_slice(&ip.octets()),
+        std::net::IpAddr::V6(ip) => buf.extend_from_slice(&ip.octets()),
+    }
+}
+
+fn addr_type(addr: std::net::SocketAddr) -> u8 {
+    if addr.is_ipv4() {
+        ADDR_TYPE_IPV4
+    } else {
+        ADDR_TYPE_IPV6
+    }
+}
+
+/// Frames `payload` for the dispatcher, with the underlay address `addr`.
+///
+/// Fails if `payload` is longer than the 32-bit length field allows.
+pub fn encode_frame(addr: Option<std::net::SocketAddr>, payload: &[u8]) -> io::Result<Vec<u8>> {
+    let len = u32::try_from(payload.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "dispatcher frame too large"))?;
+    let mut frame = Vec::with_capacity(FRAME_HEADER_LEN + 18 + payload.len());
+    frame.extend_from_slice(&COOKIE.to_be_bytes());
+    frame.push(addr.map_or(ADDR_TYPE_NONE, addr_type));
+    frame.extend_from_slice(&len.to_be_bytes());
+    if let Some(addr) = addr {
+        push_addr(&mut frame, addr);
+        frame.extend_from_slice(&addr.port().to_be_bytes());
+    }
+    frame.extend_from_slice(payload);
+    Ok(frame)
+}
+
+/// Reads one frame from `r`, returning its address and payload.
+///
+/// Fails on payloads longer than a datagram can be, before reading them.
+pub fn read_frame<R: Read>(mut r: R) -> io::Result<(Option<std::net::SocketAddr>, Vec<u8>)> {
+    let mut header = [0u8; FRAME_HEADER_LEN];
+    r.read_exact(&mut header)?;
+    if header[..8] != COOKIE.to_be_bytes() {
+        return Err(invalid_frame("dispatcher frame with a bad cookie"));
+    }
+    let len = u32::from_be_bytes([header[9], header[10], header[11], header[12]]) as usize;
+    if len > MAX_DATAGRAM {
+        return Err(invalid_frame("dispatcher frame longer than a datagram"));
+    }
+    let addr = match header[8] {
+        ADDR_TYPE_NONE => None,
+        ADDR_TYPE_IPV4 => {
+            let mut raw = [0u8; 6];
+            r.read_exact(&mut raw)?;
+            let ip = std::net::Ipv4Addr::new(raw[0], raw[1], raw[2], raw[3]);
+            Some(std::net::SocketAddr::new(ip.into(), u16::from_be_bytes([raw[4], raw[5]])))
+        }
+        ADDR_TYPE_IPV6 => {
+            let mut raw = [0u8; 18];
+            r.read_exact(&mut raw)?;
+            let mut octets = [0u8; 16];
+            octets.copy_from_slice(&raw[..16]);
+            let ip = std::net::Ipv6Addr::from(octets);
+            Some(std::net::SocketAddr::new(ip.into(), u16::from_be_bytes([raw[16], raw[17]])))
+        }
+        _ => return Err(invalid_frame("dispatcher frame with an unknown address type")),
+    };
+    let mut payload = vec![0u8; len];
+    r.read_exact(&mut payload)?;
+    Ok((addr, payload))
+}
+
+/// The registration of a socket with the dispatcher.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Registration {
+    pub ia: IsdAsn,
+    /// The address packets to the socket are sent to; port 0 asks the
+    /// dispatcher for an ephemeral port.
+    pub public: std::net::SocketAddr,
+    /// The address the socket is bound to, if not `public`, i.e. behind NAT.
+    pub bind: Option<std::net::SocketAddr>,
+    /// The service address to receive anycast packets of, i.e. 0x0002 for the
+    /// control service.
+    pub svc: Option<u16>,
+}
+
+impl Registration {
+    /// Returns the registration of the SCION address `addr`. A service
+    /// address is registered as `svc`, with an unspecified public address.
+    #[must_use]
+    pub fn new(addr: SocketAddrScion) -> Registration {
+        let ip = addr.ip().map_or(std::net::Ipv4Addr::UNSPECIFIED.into(), Into::into);
+        Registration {
+            ia: IsdAsn::from(addr.ia()),
+            public: std::net::SocketAddr::new(ip, addr.port()),
+            bind: None,
+            svc: addr.host().svc().map(Svc::to_u16),
+        }
+    }
+
+    /// Returns the encoded registration, the payload of the first frame.
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut command = CMD_ALWAYS_ON | CMD_ENABLE_SCMP;
+        if self.bind.is_some() {
+            command |= CMD_BIND_ADDRESS;
+        }
+        let mut buf = vec![command, crate::wire::PROTO_UDP];
+        buf.extend_from_slice(&self.ia.to_u64().to_be_bytes());
+        for addr in std::iter::once(self.public).chain(self.bind) {
+            buf.extend_from_slice(&addr.port().to_be_bytes());
+            buf.push(addr_type(addr));
+            push_addr(&mut buf, addr);
+        }
+        if let Some(svc) = self.svc {
+            buf.extend_from_slice(&svc.to_be_bytes());
+        }
+        buf
+    }
+}
+
+/// A SCION/UDP socket registered with the dispatcher, which sends and
+/// receives the packets for it.
+///
+/// It is used like a [`ScionUdpSocket`](crate::ScionUdpSocket): packets to
+/// other ASes need a route set with [`set_route`](Self::set_route).
+pub struct DispatcherSocket {
+    stream: UnixStream,
+    state: SocketState,
+}
+
+impl DispatcherSocket {
+    /// Registers `addr` with the dispatcher at [`DEFAULT_DISPATCHER_SOCKET`].
+    pub fn bind<A: ToScionSocketAddrs>(addr: A) -> io::Result<DispatcherSocket> {
+        Self::bind_with(DEFAULT_DISPATCHER_SOCKET, addr)
+    }
+
+    /// Registers `addr` with the dispatcher listening at `dispatcher`. Port 0
+    /// asks for an ephemeral port.
+    pub fn bind_with<P: AsRef<Path>, A: ToScionSocketAddrs>(
+        dispatcher: P,
+        addr: A,
+    ) -> io::Result<DispatcherSocket> {
+        Self::register(dispatcher, Registration::new(first_addr(addr)?))
+    }
+
+    /// Registers the socket described by `registration`.
+    pub fn register<P: AsRef<Path>>(
+        dispatcher: P,
+        registration: Registration,
+    ) -> io::Result<DispatcherSocket> {
+        let mut stream = UnixStream::connect(dispatcher)?;
+        stream.write_all(&encode_frame(None, &registration.to_bytes())?)?;
+        let (_, reply) = read_frame(&mut stream)?;
+        let port = match reply[..] {
+            [hi, lo] => u16::from_be_bytes([hi, lo]),
+            _ => return Err(invalid_frame("malformed dispatcher registration reply")),
+        };
+        if registration.public.port() != 0 && port != registration.public.port() {
+            return Err(io::Error::new(io::ErrorKind::AddrInUse, "dispatcher assigned another port"));
+        }
+        let local = SocketAddrScion::from_ia(
+            registration.ia,
+            registration.public.ip(),
+            port,
+        );
+        Ok(DispatcherSocket { stream, state: SocketState::new(local) })
+    }
+
+    /// Returns the SCION address this socket is registered for.
+    pub fn local_addr(&self) -> io::Result<SocketAddrScion> {
+        Ok(self.state.local())
+    }
+
+    /// Sets the route used for packets to hosts in `ia`.
+    pub fn set_route(&self, ia: IsdAsn, route: ScionRoute) {
+        self.state.set_route(ia, route)
+    }
+
+    /// Removes the route for `ia` and returns it.
+    pub fn remove_route(&self, ia: IsdAsn) -> Option<ScionRoute> {
+        self.state.remove_route(ia)
+    }
+
+    /// Returns the route currently used for packets to `ia`.
+    pub fn route(&self, ia: IsdAsn) -> Option<ScionRoute> {
+        self.state.route(ia)
+    }
+
+    /// Sends `buf` to the first SCION address `addr` resolves to, returning
+    /// the number of payload bytes sent.
+    pub fn send_to<A: ToScionSocketAddrs>(&self, buf: &[u8], addr: A) -> io::Result<usize> {
+        let (packet, next_hop) = self.state.encode(buf, &first_addr(addr)?)?;
+        (&self.stream).write_all(&encode_frame(Some(next_hop), &packet)?)?;
+        Ok(buf.len())
+    }
+
+    /// Receives a single SCION/UDP datagram, skipping anything else.
+    ///
+    /// Returns the number of payload bytes read and the sender's address.
+    /// Payload bytes that do not fit into `buf` are discarded.
+    pub fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        loop {
+            let (_, packet) = read_frame(&self.stream)?;
+            if let Some((src, payload)) = crate::udp_socket::decode_packet(&packet) {
+                let n = payload.len().min(buf.len());
+                buf[..n].copy_from_slice(&payload[..n]);
+                return Ok((n, SocketAddr::SCION(src)));
+            }
+        }
+    }
+
+    /// Sets the read timeout. A timeout in the middle of a frame leaves
+    /// the stream out of step, so the socket must not be used after that.
+    pub fn set_read_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        self.stream.set_read_timeout(dur)
+    }
+
+    pub fn set_write_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        self.stream.set_write_timeout(dur)
+    }
+}
@@ -1,83 +1,148 @@
-use std::fmt::Write;
-
-use std::fmt::{Result,Error};
+use std::fmt;
 use std::mem::MaybeUninit;
+use std::ops::Deref;
 use std::str;
 
-
-
-/// Used for slow path in `Display` implementations when alignment is required.
+/// A string of at most `SIZE` bytes on the stack, to format addresses and
+/// paths without allocating.
+///
+/// The `Display` implementations of this crate use it when padding, and the
+/// address types return one from `to_display_buffer`:
+///
+/// ```
+/// use scionnet::DisplayBuffer;
+/// use std::fmt::Write;
+///
+/// let mut buf = DisplayBuffer::<16>::new();
+/// write!(buf, "{}:{}", "10.0.0.1", 53).unwrap();
+/// assert_eq!(&*buf, "10.0.0.1:53");
+/// assert_eq!((buf.len(), buf.capacity()), (11, 16));
+/// // writes that do not fit fail and leave the buffer as it was
+/// assert!(buf.write_str("-too-long").is_err());
+/// assert_eq!(&*buf, "10.0.0.1:53");
+/// ```
 pub struct DisplayBuffer<const SIZE: usize> {
     buf: [MaybeUninit<u8>; SIZE],
     len: usize,
 }
 
-
 impl<const SIZE: usize> DisplayBuffer<SIZE> {
-    
     #[inline]
     pub const fn new() -> Self {
-        Self { buf: [MaybeUninit::uninit();SIZE], len: 0 }
+        Self { buf: [MaybeUninit::uninit(); SIZE], len: 0 }
     }
 
+    /// Returns what was written to the buffer so far.
     #[inline]
     pub fn as_str(&self) -> &str {
-        // SAFETY: `buf` is only written to by the `fmt::Write::write_str` implementation
-        // which writes a valid UTF-8 string to `buf` and correctly sets `len`.
-       /* unsafe {
-            let s = MaybeUninit::slice_assume_init_ref(&self.buf[..self.len]);
-            str::from_utf8_unchecked(s)
-        }*/
-
-        let initialized_array: [u8; SIZE] = unsafe {
-            let mut result: [u8; SIZE] = MaybeUninit::uninit().assume_init();
-            for i in 0..SIZE {
-                result[i] = self.buf[i].assume_init();
-            }
-            result
-        };
-    
-        // Use transmute to get a stable reference to the initialized slice
-        let initialized_slice: &[u8] = unsafe {
-            std::slice::from_raw_parts(
-                initialized_array.as_ptr(),
-                SIZE,
-            )
-        };
-
-        unsafe{
-        str::from_utf8_unchecked(initialized_slice)
-        }
+        // SAFETY: the bytes are only written to by the `fmt::Write::write_str`
+        // implementation, which writes whole UTF-8 strings.
+        unsafe { str::from_utf8_unchecked(self.as_bytes()) }
+    }
+
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        let init = &self.buf[..self.len];
+        // SAFETY: the first `len` bytes of `buf` are initialized, and
+        // `MaybeUninit<u8>` has the layout of `u8`.
+        unsafe { &*(init as *const [MaybeUninit<u8>] as *const [u8]) }
+    }
+
+    /// Returns the number of bytes written to the buffer.
+    #[inline]
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of bytes the buffer can hold, `SIZE`.
+    #[inline]
+    pub const fn capacity(&self) -> usize {
+        SIZE
+    }
+
+    /// Empties the buffer, keeping its capacity.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.len = 0;
     }
 }
 
-impl<const SIZE: usize> std::fmt::Write for DisplayBuffer<SIZE> {
-   /* fn write_str(&mut self, s: &str) -> fmt::Result {
-        let bytes = s.as_bytes();
+impl<const SIZE: usize> Default for DisplayBuffer<SIZE> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-        if let Some(buf) = self.buf.get_mut(self.len..(self.len + bytes.len())) {
-            MaybeUninit::write_slice(buf, bytes);
-            self.len += bytes.len();
-            Ok(())
-        } else {
-            Err(fmt::Error)
+impl<const SIZE: usize> fmt::Write for DisplayBuffer<SIZE> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let bytes = s.as_bytes();
+        // a string that does not fit is not written at all, so the buffer
+        // never ends in the middle of a character
+        let end = self.len.checked_add(bytes.len()).ok_or(fmt::Error)?;
+        let buf = self.buf.get_mut(self.len..end).ok_or(fmt::Error)?;
+        for (dest, &src) in buf.iter_mut().zip(bytes) {
+            dest.write(src);
         }
-    } */
+        self.len = end;
+        Ok(())
+    }
+}
 
-    fn write_str(&mut self, s: &str) -> std::fmt::Result {
-        let bytes = s.as_bytes();
+impl<const SIZE: usize> TryFrom<&str> for DisplayBuffer<SIZE> {
+    type Error = fmt::Error;
 
-        if let Some(buf) = self.buf.get_mut(self.len..(self.len + bytes.len())) {
-            for (dest, &src) in buf.iter_mut().zip(bytes.iter()) {
-                unsafe {
-                    // Write each byte manually
-                    dest.write(src);
-                }
-            }
-            self.len += bytes.len();
-            Ok(())
-        } else {
-            Err(std::fmt::Error)
-        }
+    /// Fails if `s` is longer than `SIZE` bytes.
+    fn try_from(s: &str) -> Result<Self, fmt::Error> {
+        let mut buf = Self::new();
+        fmt::Write::write_str(&mut buf, s)?;
+        Ok(buf)
+    }
+}
+
+impl<const SIZE: usize> Deref for DisplayBuffer<SIZE> {
+    type Target = str;
+
+    #[inline]
+    fn deref(&self) -> &str {
+        self.as_str()
     }
-}
\ No newline at end of file
+}
+
+impl<const SIZE: usize> AsRef<str> for DisplayBuffer<SIZE> {
+    #[inline]
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<const SIZE: usize> Clone for DisplayBuffer<SIZE> {
+    fn clone(&self) -> Self {
+        // the written prefix is all that is initialized
+        Self::try_from(self.as_str()).unwrap()
+    }
+}
+
+impl<const SIZE: usize> PartialEq for DisplayBuffer<SIZE> {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl<const SIZE: usize> Eq for DisplayBuffer<SIZE> {}
+
+impl<const SIZE: usize> fmt::Display for DisplayBuffer<SIZE> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+impl<const SIZE: usize> fmt::Debug for DisplayBuffer<SIZE> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
@@ -0,0 +1,157 @@
+use crate::SocketAddr;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Counters describing how an [`EndpointPool`] has been used so far.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct PoolMetrics {
+    /// `take` calls that returned a pooled connection.
+    pub hits: u64,
+    /// `take` calls that found no usable connection.
+    pub misses: u64,
+    /// Connections dropped because they were idle for too long.
+    pub expired: u64,
+    /// Connections handed back by `put` because the endpoint was full.
+    pub rejected: u64,
+}
+
+struct IdleEntry<T> {
+    conn: T,
+    since: Instant,
+}
+
+/// A pool of reusable connections (or flows) keyed by endpoint.
+///
+/// Endpoints are keyed by their [`SocketAddr::to_canonical`] form, so
+/// `[::ffff:10.0.0.1]:80` and `10.0.0.1:80` share the same idle connections.
+/// Connections that stayed idle for longer than the idle timeout are never
+/// handed out again, and at most `max_idle_per_endpoint` connections are kept
+/// for any single endpoint.
+///
+/// The pool does not synchronize itself; wrap it in a `Mutex` to share it
+/// between threads.
+pub struct EndpointPool<T> {
+    idle: HashMap<SocketAddr, Vec<IdleEntry<T>>>,
+    idle_timeout: Duration,
+    max_idle_per_endpoint: usize,
+    metrics: PoolMetrics,
+}
+
+impl<T> EndpointPool<T> {
+    pub fn new(idle_timeout: Duration, max_idle_per_endpoint: usize) -> EndpointPool<T> {
+        EndpointPool {
+            idle: HashMap::new(),
+            idle_timeout,
+            max_idle_per_endpoint,
+            metrics: PoolMetrics::default(),
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn idle_timeout(&self) -> Duration {
+        self.idle_timeout
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn max_idle_per_endpoint(&self) -> usize {
+        self.max_idle_per_endpoint
+    }
+
+    /// Takes the most recently returned idle connection to `endpoint`.
+    ///
+    /// Expired connections encountered on the way are dropped.
+    pub fn take(&mut self, endpoint: &SocketAddr) -> Option<T> {
+        let key = endpoint.to_canonical();
+        let timeout = self.idle_timeout;
+        let mut expired = 0;
+        let mut found = None;
+
+        if let Some(entries) = self.idle.get_mut(&key) {
+            while let Some(entry) = entries.pop() {
+                if entry.since.elapsed() < timeout {
+                    found = Some(entry.conn);
+                    break;
+                }
+                expired += 1;
+            }
+            if entries.is_empty() {
+                self.idle.remove(&key);
+            }
+        }
+
+        self.metrics.expired += expired;
+        if found.is_some() {
+            self.metrics.hits += 1;
+        } else {
+            self.metrics.misses += 1;
+        }
+        found
+    }
+
+    /// Returns `conn` to the pool as an idle connection to `endpoint`.
+    ///
+    /// If the endpoint already holds `max_idle_per_endpoint` idle connections
+    /// the connection is not pooled but handed back to the caller.
+    pub fn put(&mut self, endpoint: SocketAddr, conn: T) -> Result<(), T> {
+        let key = endpoint.to_canonical();
+        if self.idle_count(&key) >= self.max_idle_per_endpoint {
+            self.metrics.rejected += 1;
+            return Err(conn);
+        }
+        self.idle
+            .entry(key)
+            .or_default()
+            .push(IdleEntry { conn, since: Instant::now() });
+        Ok(())
+    }
+
+    /// Drops all connections that exceeded the idle timeout and returns
+    /// how many were removed.
+    pub fn purge_expired(&mut self) -> usize {
+        let timeout = self.idle_timeout;
+        let mut removed = 0;
+        self.idle.retain(|_, entries| {
+            let before = entries.len();
+            entries.retain(|e| e.since.elapsed() < timeout);
+            removed += before - entries.len();
+            !entries.is_empty()
+        });
+        self.metrics.expired += removed as u64;
+        removed
+    }
+
+    /// Removes all idle connections to `endpoint`.
+    pub fn remove_endpoint(&mut self, endpoint: &SocketAddr) -> Vec<T> {
+        self.idle
+            .remove(&endpoint.to_canonical())
+            .map(|entries| entries.into_iter().map(|e| e.conn).collect())
+            .unwrap_or_default()
+    }
+
+    /// The number of idle connections kept for `endpoint`.
+    #[must_use]
+    pub fn idle_count(&self, endpoint: &SocketAddr) -> usize {
+        self.idle
+            .get(&endpoint.to_canonical())
+            .map_or(0, |entries| entries.len())
+    }
+
+    /// The total number of idle connections in the pool.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.idle.values().map(|entries| entries.len()).sum()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.idle.is_empty()
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn metrics(&self) -> PoolMetrics {
+        self.metrics
+    }
+}
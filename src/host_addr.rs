@@ -0,0 +1,244 @@
+use crate::{AddrKind, AddrParseError, IpAddr, Ipv4Addr, Ipv6Addr};
+use std::fmt;
+use std::str::FromStr;
+
+/// A SCION service address, the anycast or multicast address of all
+/// instances of a service within an AS, i.e. `CS` for the control service.
+///
+/// The textual form is the name of the service, followed by `_M` for the
+/// multicast address. Services without a name are written as four hex
+/// digits, i.e. `0x0003`.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Svc(u16);
+
+impl Svc {
+    /// The discovery service.
+    pub const DS: Svc = Svc(0x0001);
+    /// The control service, which serves path segments and certificates.
+    pub const CS: Svc = Svc(0x0002);
+    /// Any service instance, used to bootstrap hosts.
+    pub const WILDCARD: Svc = Svc(0x0010);
+
+    const MULTICAST_BIT: u16 = 0x8000;
+
+    const NAMES: &'static [(&'static str, Svc)] =
+        &[("DS", Svc::DS), ("CS", Svc::CS), ("Wildcard", Svc::WILDCARD)];
+
+    #[must_use]
+    #[inline]
+    pub const fn new(value: u16) -> Svc {
+        Svc(value)
+    }
+
+    /// Returns the value carried in the SCION address header.
+    #[must_use]
+    #[inline]
+    pub const fn to_u16(self) -> u16 {
+        self.0
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn is_multicast(self) -> bool {
+        self.0 & Self::MULTICAST_BIT != 0
+    }
+
+    /// Returns the multicast address of the service.
+    #[must_use]
+    #[inline]
+    pub const fn multicast(self) -> Svc {
+        Svc(self.0 | Self::MULTICAST_BIT)
+    }
+
+    /// Returns the anycast address of the service.
+    #[must_use]
+    #[inline]
+    pub const fn anycast(self) -> Svc {
+        Svc(self.0 & !Self::MULTICAST_BIT)
+    }
+}
+
+impl Svc {
+    fn write_to<W: fmt::Write>(&self, w: &mut W) -> fmt::Result {
+        let base = self.anycast();
+        match Self::NAMES.iter().find(|(_, svc)| *svc == base) {
+            Some((name, _)) => w.write_str(name)?,
+            None => write!(w, "{:#06x}", base.0)?,
+        }
+        if self.is_multicast() {
+            w.write_str("_M")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Svc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.precision().is_none() && f.width().is_none() {
+            return self.write_to(f);
+        }
+        let mut s = String::new();
+        self.write_to(&mut s)?;
+        f.pad(&s)
+    }
+}
+
+impl fmt::Debug for Svc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl FromStr for Svc {
+    type Err = AddrParseError;
+    fn from_str(s: &str) -> Result<Svc, AddrParseError> {
+        let err = || AddrParseError::new(AddrKind::Svc);
+        let (base, multicast) = match s.strip_suffix("_M") {
+            Some(base) => (base, true),
+            None => (s, false),
+        };
+        let svc = match Self::NAMES.iter().find(|(name, _)| *name == base) {
+            Some(&(_, svc)) => svc,
+            None => {
+                let hex = base.strip_prefix("0x").filter(|h| h.len() == 4).ok_or_else(err)?;
+                let value = u16::from_str_radix(hex, 16).map_err(|_| err())?;
+                if value & Self::MULTICAST_BIT != 0 {
+                    return Err(err());
+                }
+                Svc(value)
+            }
+        };
+        Ok(if multicast { svc.multicast() } else { svc })
+    }
+}
+
+/// The host part of a SCION address: an IP address or a service address.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum HostAddr {
+    Ip(IpAddr),
+    Svc(Svc),
+}
+
+impl Default for HostAddr {
+    fn default() -> Self {
+        HostAddr::Ip(IpAddr::default())
+    }
+}
+
+impl HostAddr {
+    /// Returns the IP address, or `None` for a service address.
+    #[must_use]
+    #[inline]
+    pub const fn ip(&self) -> Option<IpAddr> {
+        match *self {
+            HostAddr::Ip(ip) => Some(ip),
+            HostAddr::Svc(_) => None,
+        }
+    }
+
+    /// Returns the service address, or `None` for an IP address.
+    #[must_use]
+    #[inline]
+    pub const fn svc(&self) -> Option<Svc> {
+        match *self {
+            HostAddr::Ip(_) => None,
+            HostAddr::Svc(svc) => Some(svc),
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn is_ip(&self) -> bool {
+        matches!(self, HostAddr::Ip(_))
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn is_svc(&self) -> bool {
+        matches!(self, HostAddr::Svc(_))
+    }
+
+    /// Returns `true` for the unspecified IP addresses `0.0.0.0` and `::`.
+    #[must_use]
+    #[inline]
+    pub const fn is_unspecified(&self) -> bool {
+        match *self {
+            HostAddr::Ip(ip) => ip.is_unspecified(),
+            HostAddr::Svc(_) => false,
+        }
+    }
+
+    /// Converts an IPv4-mapped IPv6 address to IPv4, see
+    /// [`IpAddr::to_canonical`].
+    #[must_use]
+    #[inline]
+    pub const fn to_canonical(&self) -> HostAddr {
+        match *self {
+            HostAddr::Ip(ip) => HostAddr::Ip(ip.to_canonical()),
+            HostAddr::Svc(svc) => HostAddr::Svc(svc),
+        }
+    }
+}
+
+impl fmt::Display for HostAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HostAddr::Ip(ip) => fmt::Display::fmt(ip, f),
+            HostAddr::Svc(svc) => fmt::Display::fmt(svc, f),
+        }
+    }
+}
+
+impl fmt::Debug for HostAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl FromStr for HostAddr {
+    type Err = AddrParseError;
+    fn from_str(s: &str) -> Result<HostAddr, AddrParseError> {
+        match IpAddr::from_str(s) {
+            Ok(ip) => Ok(HostAddr::Ip(ip)),
+            Err(_) => Svc::from_str(s)
+                .map(HostAddr::Svc)
+                .map_err(|_| AddrParseError::new(AddrKind::Host)),
+        }
+    }
+}
+
+impl From<IpAddr> for HostAddr {
+    fn from(ip: IpAddr) -> HostAddr {
+        HostAddr::Ip(ip)
+    }
+}
+
+impl From<Ipv4Addr> for HostAddr {
+    fn from(ip: Ipv4Addr) -> HostAddr {
+        HostAddr::Ip(IpAddr::V4(ip))
+    }
+}
+
+impl From<Ipv6Addr> for HostAddr {
+    fn from(ip: Ipv6Addr) -> HostAddr {
+        HostAddr::Ip(IpAddr::V6(ip))
+    }
+}
+
+impl From<std::net::IpAddr> for HostAddr {
+    fn from(ip: std::net::IpAddr) -> HostAddr {
+        HostAddr::Ip(ip.into())
+    }
+}
+
+impl From<Svc> for HostAddr {
+    fn from(svc: Svc) -> HostAddr {
+        HostAddr::Svc(svc)
+    }
+}
+
+impl PartialEq<IpAddr> for HostAddr {
+    fn eq(&self, other: &IpAddr) -> bool {
+        matches!(self, HostAddr::Ip(ip) if ip == other)
+    }
+}
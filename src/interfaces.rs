@@ -0,0 +1,105 @@
+//! Enumeration of the local network interfaces with `getifaddrs`.
+
+use crate::{IpAddr, SocketAddrV4, SocketAddrV6};
+use std::ffi::CStr;
+use std::{io, ptr};
+
+/// An IP address assigned to an [`Interface`].
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct InterfaceAddr {
+    pub ip: IpAddr,
+    /// The netmask of the subnet of the address, if the OS reports one.
+    pub netmask: Option<IpAddr>,
+    /// The scope id of an IPv6 address, i.e. to reach link-local peers
+    /// through this interface. Always 0 for IPv4.
+    pub scope_id: u32,
+}
+
+/// A network interface of the host, with the addresses assigned to it.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Interface {
+    pub name: String,
+    /// The index of the interface, 0 if the OS does not know it.
+    pub index: u32,
+    pub is_up: bool,
+    pub is_loopback: bool,
+    pub addrs: Vec<InterfaceAddr>,
+}
+
+/// Returns the network interfaces of the host in the order the OS lists
+/// them, i.e. to pick the local underlay address to put into a
+/// [`ScionAddr`](crate::ScionAddr).
+///
+/// ```
+/// let interfaces = scionnet::interfaces().unwrap();
+/// let underlay = interfaces
+///     .iter()
+///     .filter(|i| i.is_up && !i.is_loopback)
+///     .flat_map(|i| &i.addrs)
+///     .find(|a| a.ip.is_ipv4());
+/// # let _ = underlay;
+/// ```
+pub fn interfaces() -> io::Result<Vec<Interface>> {
+    let mut head: *mut libc::ifaddrs = ptr::null_mut();
+    // SAFETY: `head` is a valid place for the list to be stored in
+    if unsafe { libc::getifaddrs(&mut head) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let mut interfaces: Vec<Interface> = Vec::new();
+    let mut next = head;
+    while !next.is_null() {
+        // SAFETY: the entries of the list stay valid until `freeifaddrs`
+        let ifa = unsafe { &*next };
+        next = ifa.ifa_next;
+        // SAFETY: the name of an entry is a valid C string
+        let name = unsafe { CStr::from_ptr(ifa.ifa_name) };
+        // every address of an interface comes in an entry of its own
+        let i = match interfaces.iter().position(|i| i.name.as_bytes() == name.to_bytes()) {
+            Some(i) => i,
+            None => {
+                interfaces.push(Interface {
+                    name: name.to_string_lossy().into_owned(),
+                    // SAFETY: `name` is a valid C string
+                    index: unsafe { libc::if_nametoindex(name.as_ptr()) },
+                    is_up: ifa.ifa_flags & libc::IFF_UP as libc::c_uint != 0,
+                    is_loopback: ifa.ifa_flags & libc::IFF_LOOPBACK as libc::c_uint != 0,
+                    addrs: Vec::new(),
+                });
+                interfaces.len() - 1
+            }
+        };
+        // SAFETY: the addresses of an entry are null or valid socket addresses
+        if let Some((ip, scope_id)) = unsafe { decode(ifa.ifa_addr) } {
+            let netmask = unsafe { decode(ifa.ifa_netmask) }.map(|(netmask, _)| netmask);
+            interfaces[i].addrs.push(InterfaceAddr { ip, netmask, scope_id });
+        }
+    }
+    // SAFETY: `head` came from `getifaddrs` and none of the entries is used
+    // past this point
+    unsafe { libc::freeifaddrs(head) };
+    Ok(interfaces)
+}
+
+/// Returns the IP address and scope id held by `addr`, or `None` if it is
+/// null or not an IP socket address.
+///
+/// # Safety
+///
+/// `addr` must be null or point to a socket address as large as its family
+/// says.
+unsafe fn decode(addr: *const libc::sockaddr) -> Option<(IpAddr, u32)> {
+    if addr.is_null() {
+        return None;
+    }
+    match libc::c_int::from((*addr).sa_family) {
+        libc::AF_INET => {
+            let sin = SocketAddrV4::from_sockaddr_in(&*(addr as *const libc::sockaddr_in));
+            Some((IpAddr::V4(*sin.ip()), 0))
+        }
+        libc::AF_INET6 => {
+            let sin6 = SocketAddrV6::from_sockaddr_in6(&*(addr as *const libc::sockaddr_in6));
+            Some((IpAddr::V6(*sin6.ip()), sin6.scope_id()))
+        }
+        _ => None,
+    }
+}
@@ -2,39 +2,42 @@ use std::cmp::Ordering;
 use crate::{Ipv6Addr, Ipv4Addr};
 use std::fmt::{self, Write};
 use std::iter;
-use std::str::FromStr;
-use std::mem::transmute;
 
 
 use super::display_buffer::DisplayBuffer;
 
 
-impl From<std::net::IpAddr> for IpAddr{
-    fn from(ip: std::net::IpAddr) -> IpAddr
-    {
-        IpAddr::from_str(&ip.to_string() ).unwrap()
+impl From<std::net::IpAddr> for IpAddr {
+    #[inline]
+    fn from(ip: std::net::IpAddr) -> IpAddr {
+        match ip {
+            std::net::IpAddr::V4(ip) => IpAddr::V4(ip.into()),
+            std::net::IpAddr::V6(ip) => IpAddr::V6(ip.into()),
+        }
     }
 }
 
-impl From<std::net::Ipv4Addr> for IpAddr{
-    fn from(ip: std::net::Ipv4Addr) -> IpAddr
-    {
-        IpAddr::from_str(&ip.to_string() ).unwrap()
+impl From<std::net::Ipv4Addr> for IpAddr {
+    #[inline]
+    fn from(ip: std::net::Ipv4Addr) -> IpAddr {
+        IpAddr::V4(ip.into())
     }
 }
 
-impl From<std::net::Ipv6Addr> for IpAddr{
-    fn from(ip: std::net::Ipv6Addr) -> IpAddr
-    {
-        IpAddr::from_str(&ip.to_string() ).unwrap()
+impl From<std::net::Ipv6Addr> for IpAddr {
+    #[inline]
+    fn from(ip: std::net::Ipv6Addr) -> IpAddr {
+        IpAddr::V6(ip.into())
     }
 }
 
-impl Into<std::net::IpAddr> for IpAddr
-{
-    fn into(self) -> std::net::IpAddr
-    {
-        std::net::IpAddr::from_str( &self.to_string() ).unwrap()
+impl From<IpAddr> for std::net::IpAddr {
+    #[inline]
+    fn from(ip: IpAddr) -> std::net::IpAddr {
+        match ip {
+            IpAddr::V4(ip) => std::net::IpAddr::V4(ip.into()),
+            IpAddr::V6(ip) => std::net::IpAddr::V6(ip.into()),
+        }
     }
 }
 
@@ -123,6 +126,70 @@ impl IpAddr {
         }
     }
 
+    /// Returns `true` for the private IPv4 ranges and the IPv6 unique local
+    /// addresses `fc00::/7`.
+    #[must_use]
+    #[inline]
+    pub const fn is_private(&self) -> bool {
+        match self {
+            IpAddr::V4(ip) => ip.is_private(),
+            IpAddr::V6(ip) => ip.is_unique_local(),
+        }
+    }
+
+    /// Returns `true` for `169.254.0.0/16` and `fe80::/10`.
+    #[must_use]
+    #[inline]
+    pub const fn is_link_local(&self) -> bool {
+        match self {
+            IpAddr::V4(ip) => ip.is_link_local(),
+            IpAddr::V6(ip) => ip.is_unicast_link_local(),
+        }
+    }
+
+    /// Returns `true` for the IPv4 shared address space `100.64.0.0/10`,
+    /// always `false` for IPv6.
+    #[must_use]
+    #[inline]
+    pub const fn is_shared(&self) -> bool {
+        match self {
+            IpAddr::V4(ip) => ip.is_shared(),
+            IpAddr::V6(_) => false,
+        }
+    }
+
+    /// Returns `true` for the IPv6 link-local unicast addresses `fe80::/10`,
+    /// always `false` for IPv4.
+    #[must_use]
+    #[inline]
+    pub const fn is_unicast_link_local(&self) -> bool {
+        match self {
+            IpAddr::V4(_) => false,
+            IpAddr::V6(ip) => ip.is_unicast_link_local(),
+        }
+    }
+
+    /// Returns `true` for the IPv6 unique local addresses `fc00::/7`,
+    /// always `false` for IPv4.
+    #[must_use]
+    #[inline]
+    pub const fn is_unique_local(&self) -> bool {
+        match self {
+            IpAddr::V4(_) => false,
+            IpAddr::V6(ip) => ip.is_unique_local(),
+        }
+    }
+
+    /// Returns `true` for globally reachable unicast addresses.
+    #[must_use]
+    #[inline]
+    pub const fn is_unicast_global(&self) -> bool {
+        match self {
+            IpAddr::V4(ip) => ip.is_global() && !ip.is_multicast(),
+            IpAddr::V6(ip) => ip.is_unicast_global(),
+        }
+    }
+
     
  
     #[must_use]
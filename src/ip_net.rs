@@ -0,0 +1,359 @@
+use crate::{DisplayBuffer, IpAddr, Ipv4Addr, Ipv6Addr, IsdAsn, ScionAddr};
+use std::error::Error;
+use std::fmt::{self, Write};
+
+/// An error returned when building a network with a prefix longer than its
+/// address, i.e. a `/33` IPv4 network.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrefixLenError {
+    len: u8,
+    max: u8,
+}
+
+impl PrefixLenError {
+    /// Returns the prefix length that was asked for.
+    #[must_use]
+    pub const fn prefix_len(&self) -> u8 {
+        self.len
+    }
+
+    /// Returns the number of bits of the address, the longest valid prefix.
+    #[must_use]
+    pub const fn max(&self) -> u8 {
+        self.max
+    }
+}
+
+impl fmt::Display for PrefixLenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "prefix length {} is longer than {} bits", self.len, self.max)
+    }
+}
+
+impl Error for PrefixLenError {}
+
+/// An IPv4 network, i.e. `10.0.0.0/8`: the addresses sharing the first
+/// `prefix_len` bits of the network address.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Ord, PartialOrd, Debug, Default)]
+pub struct Ipv4Net {
+    network: Ipv4Addr,
+    len: u8,
+}
+
+impl Ipv4Net {
+    /// Returns the network of the first `len` bits of `addr`, dropping the
+    /// others. Fails if `len` is greater than 32.
+    pub fn new(addr: Ipv4Addr, len: u8) -> Result<Ipv4Net, PrefixLenError> {
+        if len > 32 {
+            return Err(PrefixLenError { len, max: 32 });
+        }
+        let network = Ipv4Addr::from_bits(addr.to_bits() & Self::mask(len));
+        Ok(Ipv4Net { network, len })
+    }
+
+    const fn mask(len: u8) -> u32 {
+        match u32::MAX.checked_shl(32 - len as u32) {
+            Some(mask) => mask,
+            None => 0,
+        }
+    }
+
+    /// Returns the lowest address of the network.
+    #[must_use]
+    #[inline]
+    pub const fn network(&self) -> Ipv4Addr {
+        self.network
+    }
+
+    /// Returns the highest address of the network, with all host bits set.
+    #[must_use]
+    #[inline]
+    pub const fn broadcast(&self) -> Ipv4Addr {
+        Ipv4Addr::from_bits(self.network.to_bits() | !Self::mask(self.len))
+    }
+
+    /// Returns the mask of the prefix, i.e. `255.255.0.0` for a `/16`.
+    #[must_use]
+    #[inline]
+    pub const fn netmask(&self) -> Ipv4Addr {
+        Ipv4Addr::from_bits(Self::mask(self.len))
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn prefix_len(&self) -> u8 {
+        self.len
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn contains(&self, addr: Ipv4Addr) -> bool {
+        addr.to_bits() & Self::mask(self.len) == self.network.to_bits()
+    }
+}
+
+impl From<Ipv4Addr> for Ipv4Net {
+    /// Returns the `/32` network holding only `addr`.
+    fn from(addr: Ipv4Addr) -> Ipv4Net {
+        Ipv4Net { network: addr, len: 32 }
+    }
+}
+
+impl fmt::Display for Ipv4Net {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.precision().is_none() && f.width().is_none() {
+            write!(f, "{}/{}", self.network, self.len)
+        } else {
+            const LONGEST_IPV4_NET: &str = "255.255.255.255/32";
+
+            let mut buf = DisplayBuffer::<{ LONGEST_IPV4_NET.len() }>::new();
+            // the buffer is long enough for the longest IPv4 network
+            write!(buf, "{}/{}", self.network, self.len).unwrap();
+            f.pad(buf.as_str())
+        }
+    }
+}
+
+/// An IPv6 network, i.e. `2001:db8::/32`: the addresses sharing the first
+/// `prefix_len` bits of the network address.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Ord, PartialOrd, Debug, Default)]
+pub struct Ipv6Net {
+    network: Ipv6Addr,
+    len: u8,
+}
+
+impl Ipv6Net {
+    /// Returns the network of the first `len` bits of `addr`, dropping the
+    /// others. Fails if `len` is greater than 128.
+    pub fn new(addr: Ipv6Addr, len: u8) -> Result<Ipv6Net, PrefixLenError> {
+        if len > 128 {
+            return Err(PrefixLenError { len, max: 128 });
+        }
+        let network = Ipv6Addr::from_bits(addr.to_bits() & Self::mask(len));
+        Ok(Ipv6Net { network, len })
+    }
+
+    const fn mask(len: u8) -> u128 {
+        match u128::MAX.checked_shl(128 - len as u32) {
+            Some(mask) => mask,
+            None => 0,
+        }
+    }
+
+    /// Returns the lowest address of the network.
+    #[must_use]
+    #[inline]
+    pub const fn network(&self) -> Ipv6Addr {
+        self.network
+    }
+
+    /// Returns the highest address of the network, with all host bits set.
+    /// IPv6 has no broadcast, the name is the one of [`Ipv4Net::broadcast`].
+    #[must_use]
+    #[inline]
+    pub const fn broadcast(&self) -> Ipv6Addr {
+        Ipv6Addr::from_bits(self.network.to_bits() | !Self::mask(self.len))
+    }
+
+    /// Returns the mask of the prefix, i.e. `ffff:ffff::` for a `/32`.
+    #[must_use]
+    #[inline]
+    pub const fn netmask(&self) -> Ipv6Addr {
+        Ipv6Addr::from_bits(Self::mask(self.len))
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn prefix_len(&self) -> u8 {
+        self.len
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn contains(&self, addr: Ipv6Addr) -> bool {
+        addr.to_bits() & Self::mask(self.len) == self.network.to_bits()
+    }
+}
+
+impl From<Ipv6Addr> for Ipv6Net {
+    /// Returns the `/128` network holding only `addr`.
+    fn from(addr: Ipv6Addr) -> Ipv6Net {
+        Ipv6Net { network: addr, len: 128 }
+    }
+}
+
+impl fmt::Display for Ipv6Net {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.precision().is_none() && f.width().is_none() {
+            write!(f, "{}/{}", self.network, self.len)
+        } else {
+            const LONGEST_IPV6_NET: &str = "ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff/128";
+
+            let mut buf = DisplayBuffer::<{ LONGEST_IPV6_NET.len() }>::new();
+            // the buffer is long enough for the longest IPv6 network
+            write!(buf, "{}/{}", self.network, self.len).unwrap();
+            f.pad(buf.as_str())
+        }
+    }
+}
+
+/// An IP network, either IPv4 or IPv6.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Ord, PartialOrd, Debug)]
+pub enum IpNet {
+    V4(Ipv4Net),
+    V6(Ipv6Net),
+}
+
+impl IpNet {
+    /// Returns the network of the first `len` bits of `addr`. Fails if `len`
+    /// is greater than the number of bits of the address.
+    pub fn new(addr: IpAddr, len: u8) -> Result<IpNet, PrefixLenError> {
+        match addr {
+            IpAddr::V4(addr) => Ipv4Net::new(addr, len).map(IpNet::V4),
+            IpAddr::V6(addr) => Ipv6Net::new(addr, len).map(IpNet::V6),
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn network(&self) -> IpAddr {
+        match self {
+            IpNet::V4(net) => IpAddr::V4(net.network()),
+            IpNet::V6(net) => IpAddr::V6(net.network()),
+        }
+    }
+
+    /// Returns the highest address of the network, with all host bits set.
+    #[must_use]
+    #[inline]
+    pub const fn broadcast(&self) -> IpAddr {
+        match self {
+            IpNet::V4(net) => IpAddr::V4(net.broadcast()),
+            IpNet::V6(net) => IpAddr::V6(net.broadcast()),
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn netmask(&self) -> IpAddr {
+        match self {
+            IpNet::V4(net) => IpAddr::V4(net.netmask()),
+            IpNet::V6(net) => IpAddr::V6(net.netmask()),
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn prefix_len(&self) -> u8 {
+        match self {
+            IpNet::V4(net) => net.prefix_len(),
+            IpNet::V6(net) => net.prefix_len(),
+        }
+    }
+
+    /// Returns `true` if `addr` is in the network. Addresses of the other
+    /// family never are, IPv4-mapped IPv6 addresses included.
+    #[must_use]
+    #[inline]
+    pub const fn contains(&self, addr: IpAddr) -> bool {
+        match (self, addr) {
+            (IpNet::V4(net), IpAddr::V4(addr)) => net.contains(addr),
+            (IpNet::V6(net), IpAddr::V6(addr)) => net.contains(addr),
+            _ => false,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn is_ipv4(&self) -> bool {
+        matches!(self, IpNet::V4(_))
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn is_ipv6(&self) -> bool {
+        matches!(self, IpNet::V6(_))
+    }
+}
+
+impl From<Ipv4Net> for IpNet {
+    fn from(net: Ipv4Net) -> IpNet {
+        IpNet::V4(net)
+    }
+}
+
+impl From<Ipv6Net> for IpNet {
+    fn from(net: Ipv6Net) -> IpNet {
+        IpNet::V6(net)
+    }
+}
+
+impl From<IpAddr> for IpNet {
+    /// Returns the network holding only `addr`.
+    fn from(addr: IpAddr) -> IpNet {
+        match addr {
+            IpAddr::V4(addr) => IpNet::V4(addr.into()),
+            IpAddr::V6(addr) => IpNet::V6(addr.into()),
+        }
+    }
+}
+
+impl fmt::Display for IpNet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IpNet::V4(net) => net.fmt(f),
+            IpNet::V6(net) => net.fmt(f),
+        }
+    }
+}
+
+/// An IP network within an AS, i.e. `1-ff00:0:110,10.0.0.0/8`, as SIGs
+/// announce them.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Ord, PartialOrd, Debug)]
+pub struct ScionNet {
+    ia: IsdAsn,
+    net: IpNet,
+}
+
+impl ScionNet {
+    #[must_use]
+    #[inline]
+    pub const fn new(ia: IsdAsn, net: IpNet) -> ScionNet {
+        ScionNet { ia, net }
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn isd_asn(&self) -> IsdAsn {
+        self.ia
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn net(&self) -> IpNet {
+        self.net
+    }
+
+    /// Returns `true` if `addr` is in the AS and its host in the network.
+    /// Service addresses never are.
+    #[must_use]
+    pub fn contains(&self, addr: &ScionAddr) -> bool {
+        addr.isd_asn() == self.ia && addr.ip().is_some_and(|ip| self.net.contains(ip))
+    }
+}
+
+impl fmt::Display for ScionNet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.precision().is_none() && f.width().is_none() {
+            write!(f, "{},{}", self.ia, self.net)
+        } else {
+            const LONGEST_SCION_NET: &str =
+                "65535-ffff:ffff:ffff,ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff/128";
+
+            let mut buf = DisplayBuffer::<{ LONGEST_SCION_NET.len() }>::new();
+            // the buffer is long enough for the longest SCION network
+            write!(buf, "{},{}", self.ia, self.net).unwrap();
+            f.pad(buf.as_str())
+        }
+    }
+}
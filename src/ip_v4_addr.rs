@@ -2,10 +2,10 @@ use std::cmp::Ordering;
 use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, Not};
 use std::fmt::{self, Write};
 use crate::{Ipv6Addr, IpAddr, DisplayBuffer, bitop_impls};
-use std::str::FromStr;
 
+/// An IPv4 address, laid out like its four octets.
 #[derive(Copy, Clone, PartialEq, Eq, Hash)]
-
+#[repr(transparent)]
 pub struct Ipv4Addr {
     octets: [u8; 4],
 }
@@ -16,18 +16,17 @@ impl Default for Ipv4Addr{
     }
 }
 
-impl From<std::net::Ipv4Addr> for Ipv4Addr{
-    fn from(ip: std::net::Ipv4Addr) -> Ipv4Addr
-    {
-        Ipv4Addr::from_str(&ip.to_string() ).unwrap()
+impl From<std::net::Ipv4Addr> for Ipv4Addr {
+    #[inline]
+    fn from(ip: std::net::Ipv4Addr) -> Ipv4Addr {
+        Ipv4Addr { octets: ip.octets() }
     }
 }
 
-impl Into<std::net::Ipv4Addr> for Ipv4Addr
-{
-    fn into(self) -> std::net::Ipv4Addr
-    {
-        std::net::Ipv4Addr::from_str( &self.to_string() ).unwrap()
+impl From<Ipv4Addr> for std::net::Ipv4Addr {
+    #[inline]
+    fn from(ip: Ipv4Addr) -> std::net::Ipv4Addr {
+        std::net::Ipv4Addr::from(ip.octets)
     }
 }
 impl PartialOrd<IpAddr> for Ipv4Addr {
@@ -1,12 +1,11 @@
 use std::cmp::Ordering;
-use std::mem::transmute;
 use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, Not};
 use std::fmt::{self, Write};
-use std::str::FromStr;
 use crate::{IpAddr,DisplayBuffer, Ipv4Addr,bitop_impls};
 
+/// An IPv6 address, laid out like its sixteen octets.
 #[derive(Copy, Clone, PartialEq, Eq, Hash)]
-
+#[repr(transparent)]
 pub struct Ipv6Addr {
   pub(crate)  octets: [u8; 16],
 }
@@ -18,18 +17,17 @@ impl Default for Ipv6Addr{
     }
 }
 
-impl From<std::net::Ipv6Addr> for Ipv6Addr{
-    fn from(ip: std::net::Ipv6Addr) -> Ipv6Addr
-    {
-        Ipv6Addr::from_str(&ip.to_string() ).unwrap()
+impl From<std::net::Ipv6Addr> for Ipv6Addr {
+    #[inline]
+    fn from(ip: std::net::Ipv6Addr) -> Ipv6Addr {
+        Ipv6Addr { octets: ip.octets() }
     }
 }
 
-impl Into<std::net::Ipv6Addr> for Ipv6Addr
-{
-    fn into(self) -> std::net::Ipv6Addr
-    {
-        std::net::Ipv6Addr::from_str( &self.to_string() ).unwrap()
+impl From<Ipv6Addr> for std::net::Ipv6Addr {
+    #[inline]
+    fn from(ip: Ipv6Addr) -> std::net::Ipv6Addr {
+        std::net::Ipv6Addr::from(ip.octets)
     }
 }
 
@@ -71,21 +69,15 @@ impl Ipv6Addr {
     #[must_use]
     #[inline]
     pub const fn new(a: u16, b: u16, c: u16, d: u16, e: u16, f: u16, g: u16, h: u16) -> Ipv6Addr {
-        let addr16 = [
-            a.to_be(),
-            b.to_be(),
-            c.to_be(),
-            d.to_be(),
-            e.to_be(),
-            f.to_be(),
-            g.to_be(),
-            h.to_be(),
-        ];
-        Ipv6Addr {
-            // All elements in `addr16` are big endian.
-            // SAFETY: `[u16; 8]` is always safe to transmute to `[u8; 16]`.
-            octets: unsafe { transmute::<_, [u8; 16]>(addr16) },
-        }
+        let [a0, a1] = a.to_be_bytes();
+        let [b0, b1] = b.to_be_bytes();
+        let [c0, c1] = c.to_be_bytes();
+        let [d0, d1] = d.to_be_bytes();
+        let [e0, e1] = e.to_be_bytes();
+        let [f0, f1] = f.to_be_bytes();
+        let [g0, g1] = g.to_be_bytes();
+        let [h0, h1] = h.to_be_bytes();
+        Ipv6Addr { octets: [a0, a1, b0, b1, c0, c1, d0, d1, e0, e1, f0, f1, g0, g1, h0, h1] }
     }
 
     
@@ -107,6 +99,23 @@ impl Ipv6Addr {
         Ipv6Addr { octets: bits.to_be_bytes() }
     }
 
+    /// Creates an IPv6 address from eight 16-bit segments, see
+    /// [`Ipv6Addr::segments`].
+    #[must_use]
+    #[inline]
+    pub const fn from_segments(segments: [u16; 8]) -> Ipv6Addr {
+        let [a, b, c, d, e, f, g, h] = segments;
+        Ipv6Addr::new(a, b, c, d, e, f, g, h)
+    }
+
+    /// Creates an IPv6 address from sixteen 8-bit integers, see
+    /// [`Ipv6Addr::octets`].
+    #[must_use]
+    #[inline]
+    pub const fn from_octets(octets: [u8; 16]) -> Ipv6Addr {
+        Ipv6Addr { octets }
+    }
+
 
     
     #[doc(alias = "IN6ADDR_LOOPBACK_INIT")]
@@ -126,19 +135,16 @@ impl Ipv6Addr {
     #[must_use]
     #[inline]
     pub const fn segments(&self) -> [u16; 8] {
-        // All elements in `self.octets` must be big endian.
-        // SAFETY: `[u8; 16]` is always safe to transmute to `[u16; 8]`.
-        let [a, b, c, d, e, f, g, h] = unsafe { std::mem::transmute::<_, [u16; 8]>(self.octets) };
-        // We want native endian u16
+        let o = self.octets;
         [
-            u16::from_be(a),
-            u16::from_be(b),
-            u16::from_be(c),
-            u16::from_be(d),
-            u16::from_be(e),
-            u16::from_be(f),
-            u16::from_be(g),
-            u16::from_be(h),
+            u16::from_be_bytes([o[0], o[1]]),
+            u16::from_be_bytes([o[2], o[3]]),
+            u16::from_be_bytes([o[4], o[5]]),
+            u16::from_be_bytes([o[6], o[7]]),
+            u16::from_be_bytes([o[8], o[9]]),
+            u16::from_be_bytes([o[10], o[11]]),
+            u16::from_be_bytes([o[12], o[13]]),
+            u16::from_be_bytes([o[14], o[15]]),
         ]
     }
 
@@ -275,6 +281,14 @@ impl Ipv6Addr {
     }
 
 
+    /// Returns `true` for the IPv4-mapped addresses `::ffff:0:0/96`, see
+    /// [`Ipv6Addr::to_ipv4_mapped`].
+    #[must_use]
+    #[inline]
+    pub const fn is_ipv4_mapped(&self) -> bool {
+        matches!(self.segments(), [0, 0, 0, 0, 0, 0xffff, _, _])
+    }
+
     #[inline]
     #[must_use = "this returns the result of the operation, \
                   without modifying the original"]
@@ -332,6 +346,14 @@ impl Ipv6Addr {
     pub const fn octets(&self) -> [u8; 16] {
         self.octets
     }
+
+    /// Returns the sixteen eight-bit integers the IPv6 address consists of,
+    /// without copying them.
+    #[must_use]
+    #[inline]
+    pub const fn as_octets(&self) -> &[u8; 16] {
+        &self.octets
+    }
 }
 
 /// Write an Ipv6Addr, conforming to the canonical style described by
@@ -479,7 +501,7 @@ impl From<[u8; 16]> for Ipv6Addr {
  
     #[inline]
     fn from(octets: [u8; 16]) -> Ipv6Addr {
-        Ipv6Addr { octets }
+        Ipv6Addr::from_octets(octets)
     }
 }
 
@@ -488,8 +510,7 @@ impl From<[u16; 8]> for Ipv6Addr {
  
     #[inline]
     fn from(segments: [u16; 8]) -> Ipv6Addr {
-        let [a, b, c, d, e, f, g, h] = segments;
-        Ipv6Addr::new(a, b, c, d, e, f, g, h)
+        Ipv6Addr::from_segments(segments)
     }
 }
 
@@ -0,0 +1,426 @@
+use crate::scion_addr::format_AS;
+use crate::scion_parse_utils::as_to_dotted_hex;
+use crate::{AddrKind, AddrParseError};
+use std::convert::TryFrom;
+use std::fmt;
+
+/// A 16-bit SCION isolation domain (ISD) number.
+///
+/// ISD 0 is the wildcard ISD and stands for "any ISD".
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Ord, PartialOrd, Debug, Default)]
+pub struct Isd(u16);
+
+/// A 48-bit SCION AS number.
+///
+/// The value is guaranteed to fit into the lower 48 bits of an IA,
+/// so it can never overflow into the ISD bits.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Ord, PartialOrd, Debug, Default)]
+pub struct Asn(u64);
+
+/// Reads the digits `b[start..end]` as a number in `radix` at compile time.
+/// Fails on other characters, no digits or a number greater than `max`.
+const fn const_number(b: &[u8], start: usize, end: usize, radix: u64, max: u64) -> Option<u64> {
+    if start >= end {
+        return None;
+    }
+    let mut value = 0;
+    let mut i = start;
+    while i < end {
+        let digit = match b[i] {
+            c @ b'0'..=b'9' => c - b'0',
+            c @ b'a'..=b'f' => c - b'a' + 10,
+            c @ b'A'..=b'F' => c - b'A' + 10,
+            _ => return None,
+        } as u64;
+        // `value` is at most `max` here, so this cannot overflow
+        value = value * radix + digit;
+        if digit >= radix || value > max {
+            return None;
+        }
+        i += 1;
+    }
+    Some(value)
+}
+
+/// Reads an AS number from `b[start..end]` at compile time, in the forms
+/// the parser accepts: three groups of hex digits or a 32-bit decimal number.
+const fn const_asn(b: &[u8], start: usize, end: usize) -> Option<u64> {
+    let mut colons = [0; 2];
+    let mut count = 0;
+    let mut i = start;
+    while i < end {
+        if b[i] == b':' {
+            if count == 2 {
+                return None;
+            }
+            colons[count] = i;
+            count += 1;
+        }
+        i += 1;
+    }
+    if count == 0 {
+        // like the parser, decimal numbers have no leading zeros
+        if end - start > 1 && b[start] == b'0' {
+            return None;
+        }
+        return const_number(b, start, end, 10, u32::MAX as u64);
+    }
+    if count != 2 {
+        return None;
+    }
+    let bounds = [(start, colons[0]), (colons[0] + 1, colons[1]), (colons[1] + 1, end)];
+    let mut asn = 0;
+    let mut g = 0;
+    while g < 3 {
+        let (from, to) = bounds[g];
+        if to - from > 4 {
+            return None;
+        }
+        asn = match const_number(b, from, to, 16, 0xffff) {
+            Some(group) => (asn << 16) | group,
+            None => return None,
+        };
+        g += 1;
+    }
+    Some(asn)
+}
+
+impl Isd {
+    /// The wildcard ISD, standing for "any ISD".
+    pub const WILDCARD: Isd = Isd(0);
+    pub const MAX: Isd = Isd(u16::MAX);
+
+    #[must_use]
+    #[inline]
+    pub const fn new(isd: u16) -> Isd {
+        Isd(isd)
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn to_u16(self) -> u16 {
+        self.0
+    }
+
+    /// Parses an ISD number in constants, i.e. `Isd::from_str_const("19")`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `s` is not an ISD number, which fails the build when used
+    /// in a constant.
+    #[must_use]
+    pub const fn from_str_const(s: &str) -> Isd {
+        match const_number(s.as_bytes(), 0, s.len(), 10, u16::MAX as u64) {
+            Some(isd) if s.len() <= 6 => Isd(isd as u16),
+            _ => panic!("invalid ISD number"),
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn is_wildcard(self) -> bool {
+        self.0 == Isd::WILDCARD.0
+    }
+}
+
+impl Asn {
+    /// The number of bits an AS number occupies inside an IA.
+    pub const BITS: u32 = 48;
+
+    /// The wildcard AS, standing for "any AS".
+    pub const WILDCARD: Asn = Asn(0);
+    pub const MAX: Asn = Asn((1 << Asn::BITS) - 1);
+
+    #[must_use]
+    #[inline]
+    pub const fn to_u64(self) -> u64 {
+        self.0
+    }
+
+    /// Parses an AS number in constants, i.e.
+    /// `Asn::from_str_const("ffaa:1:1067")`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `s` is not an AS number, which fails the build when used
+    /// in a constant.
+    #[must_use]
+    pub const fn from_str_const(s: &str) -> Asn {
+        match const_asn(s.as_bytes(), 0, s.len()) {
+            Some(asn) => Asn(asn),
+            None => panic!("invalid AS number"),
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn is_wildcard(self) -> bool {
+        self.0 == Asn::WILDCARD.0
+    }
+
+    /// Returns `true` for the numbers 1 to 2^32 - 1, which are dedicated to
+    /// BGP AS numbers and printed in decimal.
+    #[must_use]
+    #[inline]
+    pub const fn is_bgp(self) -> bool {
+        self.0 != Asn::WILDCARD.0 && self.0 <= u32::MAX as u64
+    }
+
+    /// Returns `true` for the `2:0:0/16` range allocated to public SCION-only
+    /// ASes, those that are not existing BGP ASes.
+    #[must_use]
+    #[inline]
+    pub const fn is_scion_only(self) -> bool {
+        self.0 >> 32 == 2
+    }
+}
+
+impl From<u16> for Isd {
+    #[inline]
+    fn from(isd: u16) -> Isd {
+        Isd(isd)
+    }
+}
+
+impl TryFrom<u32> for Isd {
+    type Error = AddrParseError;
+
+    /// Fails if `isd` does not fit into 16 bits.
+    fn try_from(isd: u32) -> Result<Isd, AddrParseError> {
+        u16::try_from(isd)
+            .map(Isd)
+            .map_err(|_| AddrParseError::new(AddrKind::Isd))
+    }
+}
+
+impl From<Isd> for u16 {
+    #[inline]
+    fn from(isd: Isd) -> u16 {
+        isd.0
+    }
+}
+
+impl TryFrom<u64> for Asn {
+    type Error = AddrParseError;
+
+    /// Fails if `asn` does not fit into 48 bits.
+    fn try_from(asn: u64) -> Result<Asn, AddrParseError> {
+        if asn <= Asn::MAX.0 {
+            Ok(Asn(asn))
+        } else {
+            Err(AddrParseError::new(AddrKind::Asn))
+        }
+    }
+}
+
+impl From<u32> for Asn {
+    /// Every BGP AS number is a valid SCION AS number.
+    #[inline]
+    fn from(asn: u32) -> Asn {
+        Asn(asn as u64)
+    }
+}
+
+impl From<Asn> for u64 {
+    #[inline]
+    fn from(asn: Asn) -> u64 {
+        asn.0
+    }
+}
+
+impl fmt::Display for Isd {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl fmt::Display for Asn {
+    /// BGP AS numbers are printed in decimal, all others in the
+    /// colon-separated hex notation, i.e. `ffaa:1:1067`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.precision().is_some() || f.width().is_some() {
+            return f.pad(&format_AS(self.0));
+        }
+        if self.0 <= u64::from(u32::MAX) {
+            write!(f, "{}", self.0)
+        } else {
+            let groups = (self.0 >> 32, (self.0 >> 16) & 0xffff, self.0 & 0xffff);
+            write!(f, "{:x}:{:x}:{:x}", groups.0, groups.1, groups.2)
+        }
+    }
+}
+
+/// A SCION ISD-AS pair (IA), i.e. `19-ffaa:1:1067`.
+///
+/// The ISD occupies the upper 16 bits and the AS number the lower 48 bits
+/// of the packed `u64` representation used throughout the crate.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Ord, PartialOrd, Debug, Default)]
+pub struct IsdAsn(u64);
+
+impl IsdAsn {
+    /// The IA whose ISD and AS are both wildcards.
+    pub const WILDCARD: IsdAsn = IsdAsn::new(Isd::WILDCARD, Asn::WILDCARD);
+
+    #[must_use]
+    #[inline]
+    pub const fn new(isd: Isd, asn: Asn) -> IsdAsn {
+        IsdAsn(((isd.0 as u64) << Asn::BITS) | asn.0)
+    }
+
+    /// Parses an ISD-AS pair in constants, so well-known IAs can be declared
+    /// in their textual form:
+    ///
+    /// ```
+    /// use scionnet::IsdAsn;
+    ///
+    /// const IA: IsdAsn = IsdAsn::from_str_const("19-ffaa:1:1067");
+    /// assert_eq!(IA, "19-ffaa:1:1067".parse().unwrap());
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `s` is not an ISD-AS pair, which fails the build when used
+    /// in a constant.
+    #[must_use]
+    pub const fn from_str_const(s: &str) -> IsdAsn {
+        let b = s.as_bytes();
+        let mut dash = 0;
+        while dash < b.len() && b[dash] != b'-' {
+            dash += 1;
+        }
+        let isd = match const_number(b, 0, dash, 10, u16::MAX as u64) {
+            Some(isd) if dash <= 6 => isd,
+            _ => panic!("invalid ISD number"),
+        };
+        if dash == b.len() {
+            panic!("missing '-' in the ISD-AS pair");
+        }
+        match const_asn(b, dash + 1, b.len()) {
+            Some(asn) => IsdAsn((isd << Asn::BITS) | asn),
+            None => panic!("invalid AS number"),
+        }
+    }
+
+    /// Returns the IA of the raw ISD and AS numbers, failing if `asn` does not
+    /// fit into 48 bits.
+    pub fn try_new(isd: u16, asn: u64) -> Result<IsdAsn, AddrParseError> {
+        Ok(IsdAsn::new(Isd(isd), Asn::try_from(asn)?))
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn isd(self) -> Isd {
+        Isd((self.0 >> Asn::BITS) as u16)
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn asn(self) -> Asn {
+        Asn(self.0 & Asn::MAX.0)
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn to_u64(self) -> u64 {
+        self.0
+    }
+
+    /// Returns `true` if the ISD or the AS is a wildcard.
+    #[must_use]
+    #[inline]
+    pub const fn is_wildcard(self) -> bool {
+        self.isd().is_wildcard() || self.asn().is_wildcard()
+    }
+}
+
+impl From<u64> for IsdAsn {
+    /// Every 64-bit value is a valid packed IA.
+    #[inline]
+    fn from(ia: u64) -> IsdAsn {
+        IsdAsn(ia)
+    }
+}
+
+impl From<IsdAsn> for u64 {
+    #[inline]
+    fn from(ia: IsdAsn) -> u64 {
+        ia.0
+    }
+}
+
+impl fmt::Display for IsdAsn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.precision().is_none() && f.width().is_none() {
+            write!(f, "{}-{}", self.isd(), self.asn())
+        } else {
+            f.pad(&format!("{}-{}", self.isd(), self.asn()))
+        }
+    }
+}
+
+/// A range of AS numbers sharing their upper bits, i.e. `2:0:0/16` for the
+/// public SCION-only ASes. The length counts the leading bits of the 48-bit
+/// AS number that are fixed.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Ord, PartialOrd, Debug, Default)]
+pub struct AsnPrefix {
+    network: Asn,
+    len: u8,
+}
+
+impl AsnPrefix {
+    /// Returns the prefix of the first `len` bits of `asn`, dropping the
+    /// others. Fails if `len` is greater than 48.
+    pub fn new(asn: Asn, len: u8) -> Result<AsnPrefix, AddrParseError> {
+        if u32::from(len) > Asn::BITS {
+            return Err(AddrParseError::new(AddrKind::AsnPrefix));
+        }
+        let network = Asn(asn.0 & Self::mask(len));
+        Ok(AsnPrefix { network, len })
+    }
+
+    const fn mask(len: u8) -> u64 {
+        match len {
+            0 => 0,
+            len => Asn::MAX.0 & !(Asn::MAX.0 >> len),
+        }
+    }
+
+    /// Returns the lowest AS number of the range.
+    #[must_use]
+    #[inline]
+    pub const fn network(&self) -> Asn {
+        self.network
+    }
+
+    /// Returns the highest AS number of the range.
+    #[must_use]
+    #[inline]
+    pub const fn last(&self) -> Asn {
+        Asn(self.network.0 | (!Self::mask(self.len) & Asn::MAX.0))
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn prefix_len(&self) -> u8 {
+        self.len
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn contains(&self, asn: Asn) -> bool {
+        asn.0 & Self::mask(self.len) == self.network.0
+    }
+
+    /// Returns the AS numbers of the range in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = Asn> {
+        (self.network.0..=self.last().0).map(Asn)
+    }
+}
+
+impl fmt::Display for AsnPrefix {
+    /// The network is always printed in the colon-separated hex notation, as
+    /// the prefix length counts its bits, i.e. `0:1:0/32` instead of `65536/32`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(&format!("{}/{}", as_to_dotted_hex(self.network.0), self.len))
+    }
+}
@@ -0,0 +1,220 @@
+use crate::{AddrKind, AddrParseError, Isd, ScionPath};
+use std::fmt;
+
+/// An inclusive range of ISD numbers, i.e. `16-63`.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Ord, PartialOrd, Debug)]
+pub struct IsdRange {
+    start: Isd,
+    end: Isd,
+}
+
+impl IsdRange {
+    /// Returns the range from `start` to `end`, both included. Fails if
+    /// `start` is greater than `end`.
+    pub fn new(start: Isd, end: Isd) -> Result<IsdRange, AddrParseError> {
+        if start > end {
+            return Err(AddrParseError::new(AddrKind::IsdRange));
+        }
+        Ok(IsdRange { start, end })
+    }
+
+    /// Returns the range holding only `isd`.
+    #[must_use]
+    #[inline]
+    pub const fn single(isd: Isd) -> IsdRange {
+        IsdRange { start: isd, end: isd }
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn start(&self) -> Isd {
+        self.start
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn end(&self) -> Isd {
+        self.end
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn contains(&self, isd: Isd) -> bool {
+        self.start.to_u16() <= isd.to_u16() && isd.to_u16() <= self.end.to_u16()
+    }
+
+    /// Returns the ISD numbers of the range in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = Isd> {
+        (self.start.to_u16()..=self.end.to_u16()).map(Isd::new)
+    }
+
+    fn bounds(&self) -> (u32, u32) {
+        (self.start.to_u16().into(), self.end.to_u16().into())
+    }
+
+    fn from_bounds(start: u32, end: u32) -> IsdRange {
+        // the callers only pass bounds of existing ranges, or ones in between
+        IsdRange { start: Isd::new(start as u16), end: Isd::new(end as u16) }
+    }
+}
+
+impl From<Isd> for IsdRange {
+    fn from(isd: Isd) -> IsdRange {
+        IsdRange::single(isd)
+    }
+}
+
+impl fmt::Display for IsdRange {
+    /// A range of a single ISD is printed as that ISD, i.e. `5`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.start == self.end {
+            f.pad(&self.start.to_string())
+        } else {
+            f.pad(&format!("{}-{}", self.start, self.end))
+        }
+    }
+}
+
+/// A set of ISD numbers, i.e. `1-15,64-4094`, to filter paths by the ISDs
+/// they cross.
+///
+/// The set is kept as sorted ranges that neither overlap nor touch, so sets
+/// holding the same ISDs compare equal and print the same.
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct IsdSet {
+    ranges: Vec<IsdRange>,
+}
+
+impl IsdSet {
+    #[must_use]
+    pub fn new() -> IsdSet {
+        IsdSet::default()
+    }
+
+    /// Returns the set of every ISD, the wildcard ISD 0 included.
+    #[must_use]
+    pub fn all() -> IsdSet {
+        IsdSet { ranges: vec![IsdRange { start: Isd::WILDCARD, end: Isd::MAX }] }
+    }
+
+    /// Returns the ranges of the set in ascending order.
+    #[must_use]
+    pub fn ranges(&self) -> &[IsdRange] {
+        &self.ranges
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    #[must_use]
+    pub fn contains(&self, isd: Isd) -> bool {
+        // the first range ending at or after `isd` is the only candidate
+        let i = self.ranges.partition_point(|r| r.end < isd);
+        self.ranges.get(i).is_some_and(|r| r.contains(isd))
+    }
+
+    /// Adds the ISDs of `range` to the set.
+    pub fn insert<R: Into<IsdRange>>(&mut self, range: R) {
+        let (mut start, mut end) = range.into().bounds();
+        // the ranges overlapping or touching the new one are merged into it
+        let first = self.ranges.partition_point(|r| r.bounds().1 + 1 < start);
+        let last = self.ranges.partition_point(|r| r.bounds().0 <= end + 1);
+        if first < last {
+            start = start.min(self.ranges[first].bounds().0);
+            end = end.max(self.ranges[last - 1].bounds().1);
+        }
+        self.ranges.splice(first..last, [IsdRange::from_bounds(start, end)]);
+    }
+
+    #[must_use]
+    pub fn union(&self, other: &IsdSet) -> IsdSet {
+        let mut set = self.clone();
+        other.ranges.iter().for_each(|&r| set.insert(r));
+        set
+    }
+
+    #[must_use]
+    pub fn intersection(&self, other: &IsdSet) -> IsdSet {
+        let mut ranges = Vec::new();
+        let (mut a, mut b) = (self.ranges.iter().peekable(), other.ranges.iter().peekable());
+        while let (Some(x), Some(y)) = (a.peek(), b.peek()) {
+            let (start, end) = (x.start.max(y.start), x.end.min(y.end));
+            if start <= end {
+                ranges.push(IsdRange { start, end });
+            }
+            // the range ending first cannot overlap any later one
+            if x.end < y.end {
+                a.next();
+            } else {
+                b.next();
+            }
+        }
+        IsdSet { ranges }
+    }
+
+    /// Returns the ISDs not in the set.
+    #[must_use]
+    pub fn complement(&self) -> IsdSet {
+        let mut ranges = Vec::new();
+        let mut next = 0;
+        for r in &self.ranges {
+            let (start, end) = r.bounds();
+            if next < start {
+                ranges.push(IsdRange::from_bounds(next, start - 1));
+            }
+            next = end + 1;
+        }
+        if next <= u32::from(u16::MAX) {
+            ranges.push(IsdRange::from_bounds(next, u16::MAX.into()));
+        }
+        IsdSet { ranges }
+    }
+
+    /// Returns the ISDs of the set that are not in `other`.
+    #[must_use]
+    pub fn difference(&self, other: &IsdSet) -> IsdSet {
+        self.intersection(&other.complement())
+    }
+
+    /// Returns `true` if `path` only crosses ISDs of the set.
+    #[must_use]
+    pub fn contains_path(&self, path: &ScionPath) -> bool {
+        self.contains(path.src.isd())
+            && self.contains(path.dst.isd())
+            && path.interfaces.iter().all(|i| self.contains(i.ia.isd()))
+    }
+
+    /// Returns the ISDs of the set in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = Isd> + '_ {
+        self.ranges.iter().flat_map(IsdRange::iter)
+    }
+}
+
+impl From<IsdRange> for IsdSet {
+    fn from(range: IsdRange) -> IsdSet {
+        IsdSet { ranges: vec![range] }
+    }
+}
+
+impl<R: Into<IsdRange>> FromIterator<R> for IsdSet {
+    fn from_iter<I: IntoIterator<Item = R>>(iter: I) -> IsdSet {
+        let mut set = IsdSet::new();
+        iter.into_iter().for_each(|r| set.insert(r));
+        set
+    }
+}
+
+impl<R: Into<IsdRange>> Extend<R> for IsdSet {
+    fn extend<I: IntoIterator<Item = R>>(&mut self, iter: I) {
+        iter.into_iter().for_each(|r| self.insert(r));
+    }
+}
+
+impl fmt::Display for IsdSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ranges: Vec<String> = self.ranges.iter().map(IsdRange::to_string).collect();
+        f.pad(&ranges.join(","))
+    }
+}
@@ -4,24 +4,83 @@
 
 // #![feature(maybe_uninit_uninit_array)]
 extern crate libc;
+// lets the derive macros refer to `::scionnet` from within this crate as well
+extern crate self as scionnet;
 pub use self::ip_addr::IpAddr as IpAddr;
 pub use self::ip_v4_addr::Ipv4Addr as Ipv4Addr;
 pub use self::ip_v6_addr::Ipv6Addr;
 pub use self::ip_v6_addr::Ipv6MulticastScope as Ipv6MulticastScope;
+pub use self::ip_net::{IpNet, Ipv4Net, Ipv6Net, PrefixLenError, ScionNet};
+pub use self::parse_any::{parse_any, Parsed};
+pub use self::prefix_map::PrefixMap;
 pub use self::scion_addr::ScionAddr as ScionAddr;
+pub use self::host_addr::{HostAddr, Svc};
+pub use self::isd_asn::{Asn, AsnPrefix, Isd, IsdAsn};
 
 pub use self::socket_addr::AddrParseError as AddrParseError;
 
 pub use self::socket_addr::SocketAddr as SocketAddr;
 pub use self::socket_addr::AddrKind as AddrKind;
+pub use self::socket_addr::{AddrFamily, AddrFamilyError, L3Addr, L3AddrVisitor, SocketAddrVisitor};
 pub use self::sock_addr_scion::SocketAddrScion as SocketAddrScion;
+pub use self::sock_addr_scion::SocketAddrScionBuilder;
 
 pub use self::sock_addr_v6::SocketAddrV6 as SocketAddrV6;
 pub use self::sock_addr_v4::SocketAddrV4 as SocketAddrV4;
 pub use self::scion_parse_utils::*;
 pub use self::parser::*;
+pub use self::services::{register_service, service_port};
 pub use self::display_buffer::*;
 
+pub use self::endpoint_pool::{EndpointPool, PoolMetrics};
+pub use self::balancer::{IaAffinity, WeightedBalancer};
+pub use self::isd_set::{IsdRange, IsdSet};
+pub use self::negotiate::{pick_compatible, FamilySet, SupportsFamilies};
+pub use self::remote_endpoint::{RemoteEndpoint, DEFAULT_RETRY_AFTER};
+pub use self::addr_diff::{diff_addr_sets, AddrSetDiff};
+#[cfg(feature = "topology")]
+pub use self::addr_diff::{diff_topologies, TopologyDiff, UnderlayChange};
+#[cfg(feature = "topology")]
+pub use self::topology::{BorderRouter, BrInterface, LinkType, Topology, TopologyWatcher};
+pub use self::resolve::{
+    rank_addrs, AsyncResolve, DnsTxtResolver, HostsFileResolver, ResolveFuture, ResolverChain,
+    SystemResolver,
+};
+
+pub use self::addr_config::{AddrConfig, AddrConfigError};
+#[cfg(unix)]
+pub use self::sockaddr::SockaddrError;
+#[cfg(unix)]
+pub use self::interfaces::{interfaces, Interface, InterfaceAddr};
+pub use self::udp_socket::{ScionRoute, ScionUdpSocket};
+pub use self::rate_limit::{RateDecision, RateLimitKey, RateLimiter, DEFAULT_MAX_BUCKETS};
+pub use self::conntrack::{ConnEntry, ConnState, ConnTimeouts, ConnTrack, FlowId};
+pub use self::scion_path::{PathInterface, ScionPath, PATH_TYPE_SCION};
+pub use self::path_selector::{FirstPath, LowestLatency, MtuAware, PathSelector, ShortestPath};
+pub use self::policy::{Acl, AclEntry, HopPredicate, Policy, PolicyError, Sequence};
+pub use self::parse_observer::{
+    set_parse_observer, with_parse_observer, ParseObserver, ParseRejection, Redaction,
+    SampledObserver,
+};
+#[cfg(feature = "tokio")]
+pub use self::tokio_socket::AsyncScionUdpSocket;
+#[cfg(feature = "daemon")]
+pub use self::scion_endpoint::{
+    ScionEndpoint, DAEMON_ADDRESS_ENV, DEFAULT_PATH_CACHE_CAPACITY, DEFAULT_PATH_TTL,
+    LOCAL_ADDR_ENV,
+};
+pub use self::tcp::{
+    set_default_transport, Incoming, IpTcpTransport, ScionTcpListener, ScionTcpStream,
+    StreamTransport, TransportListener, TransportStream, DEFAULT_PREAMBLE_TIMEOUT,
+};
+#[cfg(feature = "derive")]
+pub use scion_net_derive::AddrConfig;
+
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::addr_config::{parse_optional, parse_required};
+}
+
 pub use self::bitop_impl::*;
 pub use self::sock_addr_traits::*;
 
@@ -30,14 +89,69 @@ mod ip_addr;
 mod scion_parse_utils;
 mod ip_v4_addr;
 mod ip_v6_addr;
+mod ip_net;
+mod prefix_map;
+mod parse_any;
 mod scion_addr;
+mod host_addr;
+mod isd_asn;
+mod isd_set;
 mod sock_addr_v4;
 mod sock_addr_v6;
 mod socket_addr;
 mod bitop_impl;
 mod parser;
+mod services;
 mod sock_addr_scion;
 mod sock_addr_traits;
+mod endpoint_pool;
+mod balancer;
+mod negotiate;
+mod remote_endpoint;
+mod addr_diff;
+mod resolve;
+mod addr_config;
+mod udp_socket;
+mod tcp;
+mod rate_limit;
+mod conntrack;
+mod scion_path;
+pub mod path;
+pub mod path_db;
+pub mod registry;
+pub mod wire;
+pub mod underlay;
+#[cfg(unix)]
+pub mod dispatcher;
+#[cfg(unix)]
+mod sockaddr;
+#[cfg(unix)]
+mod interfaces;
+mod policy;
+mod path_selector;
+mod parse_observer;
+mod checksum;
+pub mod scmp;
+#[cfg(feature = "os-zone")]
+pub mod zone;
+#[cfg(feature = "serde")]
+mod serde_impls;
+#[cfg(feature = "arbitrary")]
+mod arbitrary_impls;
+#[cfg(any(test, feature = "proptest", feature = "test-vectors"))]
+pub mod testing;
+#[cfg(feature = "socket2")]
+mod socket2_impls;
+#[cfg(feature = "simd")]
+mod simd;
+#[cfg(feature = "tokio")]
+mod tokio_socket;
+#[cfg(feature = "daemon")]
+pub mod daemon;
+#[cfg(feature = "daemon")]
+mod scion_endpoint;
+#[cfg(feature = "topology")]
+mod topology;
 // rust/library/core/src/net/mod.rs
 
 
@@ -46,8 +160,36 @@ mod tests {
     
     use crate::{as_from_dotted_hex, as_to_dotted_hex};
 
-    use super::{SocketAddr, Ipv4Addr,SocketAddrScion,IpAddr,ScionAddr,make_ia};
+    use super::{SocketAddr, Ipv4Addr,SocketAddrScion,IpAddr,ScionAddr,make_ia,Asn,Isd,IsdAsn,EndpointPool,diff_addr_sets,
+                AsyncResolve,ResolveFuture,ResolverChain,ScionRoute,ScionUdpSocket,
+                PathInterface,ScionPath,PATH_TYPE_SCION};
+    use std::convert::TryFrom;
     use std::str::FromStr;
+    use std::time::Duration;
+    use std::future::Future;
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Wake, Waker};
+    use std::thread::{self, Thread};
+
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    fn block_on<F: Future>(fut: F) -> F::Output {
+        let mut fut = Box::pin(fut);
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            match fut.as_mut().poll(&mut cx) {
+                Poll::Ready(out) => return out,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
 
     #[test]
     fn it_works() {
@@ -63,11 +205,13 @@ mod tests {
         assert_eq!(b, 281105609592935);
 
         assert_eq!(as_to_dotted_hex(b),"ffaa:1:1067");
+        assert_eq!(as_to_dotted_hex(0x1_0000_0000),"1:0:0");
+        assert_eq!(as_to_dotted_hex(0xffaa_0000_0000),"ffaa:0:0");
 
 
         let a = SocketAddr::from_str("19-ffaa:1:1067,127.0.0.1:53").unwrap();
-        let ia = if let SocketAddr::SCION(SocketAddrScion{ addr, port:_}) =a {addr.get_ia()}else{0};
-        let port = if let SocketAddr::SCION(SocketAddrScion{ addr:_, port:p}) =a {p}else{0};
+        let ia = a.as_scion().map_or(0, |s| s.addr().get_ia());
+        let port = a.as_scion().map_or(0, |s| s.port());
         assert_eq!(port,53);
 /*
 ia: 5629130167095399 isd: 19 as: 281105609592935
@@ -76,23 +220,3317 @@ ia: 5629130167095399 isd: 19 as: 281105609592935
         assert_eq!(make_ia(19,b),5629130167095399);
         assert_eq!(ia,make_ia(19,b));
 
-        assert_eq!( if let SocketAddr::SCION(SocketAddrScion{ addr, port:_}) =a {addr.get_isd()}else{0}, 19);
-        assert_eq!( if let SocketAddr::SCION(SocketAddrScion{ addr, port:_}) =a {addr.get_as()}else{0}, 281105609592935);
+        assert_eq!(a.as_scion().map_or(0, |s| s.addr().get_isd()), 19);
+        assert_eq!(a.as_scion().map_or(0, |s| s.addr().get_as()), 281105609592935);
 
         assert_eq!(a.to_string(), "19-ffaa:1:1067,127.0.0.1:53");
 
-        let expected = SocketAddr::SCION( SocketAddrScion::new1(ScionAddr::new1(19, b
-                                                                , IpAddr::V4( Ipv4Addr::new(127,0,0,1) ) 
+        let expected = SocketAddr::SCION( SocketAddrScion::from_scion_addr(ScionAddr::from_ia(IsdAsn::from(make_ia(19, b
+                                                                )), IpAddr::V4( Ipv4Addr::new(127,0,0,1) ) 
                                                             ) ,53) );
         assert_eq!(a, expected);
 
 
         let s = ScionAddr::from_str("1-150,10.150.0.30");
         assert!(s.is_ok());
-        assert_eq!(*s.as_ref().unwrap(), ScionAddr::new1(1, 150, IpAddr::V4(Ipv4Addr::new(10,150,0,30) ) ) );
+        assert_eq!(*s.as_ref().unwrap(), ScionAddr::from_ia(IsdAsn::from(make_ia(1, 150)), IpAddr::V4(Ipv4Addr::new(10,150,0,30) ) ) );
 
         let so: SocketAddr = (s.unwrap(),53).into();
         assert_eq!(so, SocketAddr::from_str("1-150,10.150.0.30:53").unwrap() );
         
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn asn_isd_newtypes()
+    {
+        assert_eq!(Asn::try_from(0xffff_ffff_ffff_u64).unwrap(), Asn::MAX);
+        assert!(Asn::try_from(0x1_0000_0000_0000_u64).is_err());
+        assert_eq!(Isd::try_from(19_u32).unwrap(), Isd::new(19));
+        assert!(Isd::try_from(0x1_0000_u32).is_err());
+
+        assert_eq!(Asn::try_from(0xffaa_0001_1067_u64).unwrap().to_string(), "ffaa:1:1067");
+        assert_eq!(Asn::from(65551_u32).to_string(), "65551");
+
+        let asn = Asn::try_from(0xffaa_0001_1067_u64).unwrap();
+        let a = ScionAddr::from_parts(Isd::new(19), asn, IpAddr::V4(Ipv4Addr::new(127,0,0,1)));
+        assert_eq!(a, ScionAddr::from_str("19-ffaa:1:1067,127.0.0.1").unwrap());
+        assert_eq!(a.isd(), Isd::new(19));
+        assert_eq!(a.asn(), asn);
+    }
+
+    #[test]
+    fn endpoint_pool_canonical_keys()
+    {
+        let mut pool = EndpointPool::new(Duration::from_secs(60), 2);
+        let v4 = SocketAddr::from_str("10.0.0.1:80").unwrap();
+        let mapped = SocketAddr::from_str("[::ffff:10.0.0.1]:80").unwrap();
+
+        assert!(pool.put(mapped, 1).is_ok());
+        assert!(pool.put(v4, 2).is_ok());
+        assert_eq!(pool.put(v4, 3), Err(3));
+        assert_eq!(pool.idle_count(&mapped), 2);
+
+        assert_eq!(pool.take(&v4), Some(2));
+        assert_eq!(pool.take(&mapped), Some(1));
+        assert_eq!(pool.take(&v4), None);
+        assert!(pool.is_empty());
+
+        let m = pool.metrics();
+        assert_eq!((m.hits, m.misses, m.rejected), (2, 1, 1));
+
+        let mut expiring = EndpointPool::new(Duration::from_secs(0), 4);
+        assert!(expiring.put(v4, ()).is_ok());
+        assert_eq!(expiring.take(&v4), None);
+        assert_eq!(expiring.metrics().expired, 1);
+    }
+
+    #[test]
+    fn parse_isd_asn()
+    {
+        let ia = IsdAsn::from_str("19-ffaa:1:1067").unwrap();
+        assert_eq!(ia.isd(), Isd::new(19));
+        assert_eq!(ia.asn(), Asn::from_str("ffaa:1:1067").unwrap());
+        assert_eq!(ia.to_u64(), make_ia(19, 0xffaa_0001_1067));
+        assert_eq!(ia.to_string(), "19-ffaa:1:1067");
+
+        // BGP AS numbers are written in decimal
+        assert_eq!(IsdAsn::from_str("1-65551").unwrap().asn(), Asn::from(65551_u32));
+        assert_eq!(IsdAsn::from_str("1-0:1:f").unwrap().to_string(), "1-65551");
+        assert_eq!(ScionAddr::from_str("1-65551,10.0.0.1").unwrap().get_as(), 65551);
+
+        assert!(IsdAsn::from_str("19-ffaa:1").is_err());
+        assert!(IsdAsn::from_str("19-ffaa:1:1067,127.0.0.1").is_err());
+        assert!(IsdAsn::from_str("70000-1").is_err());
+        assert!(Isd::from_str("19").is_ok());
+    }
+
+    #[test]
+    fn diff_socket_addr_sets()
+    {
+        let parse = |s: &str| SocketAddr::from_str(s).unwrap();
+        let old = [parse("10.0.0.1:80"), parse("1-ff00:0:110,10.0.0.2:53"), parse("[::1]:443")];
+        let new = [parse("[::ffff:10.0.0.1]:80"), parse("1-ff00:0:111,10.0.0.2:53"), parse("10.0.0.3:80")];
+
+        let diff = diff_addr_sets(&old, &new);
+        assert_eq!(diff.added, vec![parse("10.0.0.3:80")]);
+        assert_eq!(diff.removed, vec![parse("[::1]:443")]);
+        assert_eq!(diff.ia_changed, vec![(old[1], new[1])]);
+
+        assert!(diff_addr_sets(&old, &old).is_empty());
+    }
+
+    #[cfg(feature = "topology")]
+    #[test]
+    fn topology_diff() {
+        use crate::{
+            diff_topologies, BorderRouter, BrInterface, LinkType, Topology, TopologyWatcher,
+        };
+        use std::collections::BTreeMap;
+
+        let intf = |neighbor: &str, local: &str| BrInterface {
+            neighbor: neighbor.parse().unwrap(),
+            link_to: LinkType::Child,
+            mtu: 1280,
+            local: local.parse().unwrap(),
+            remote: None,
+        };
+        let br = BorderRouter {
+            internal_addr: "127.0.0.9:31002".parse().unwrap(),
+            interfaces: vec![(1, intf("1-ff00:0:111", "127.0.0.4:50000"))].into_iter().collect(),
+        };
+        let topo = Topology {
+            ia: "1-ff00:0:110".parse().unwrap(),
+            core: true,
+            mtu: 1472,
+            dispatched_ports: None,
+            border_routers: vec![("br1".to_string(), br)].into_iter().collect(),
+            control_services: BTreeMap::new(),
+            extra: Default::default(),
+        };
+        assert_eq!(topo.next_hop(1), Some("127.0.0.9:31002".parse().unwrap()));
+        assert_eq!(topo.next_hop(2), None);
+        assert!(diff_topologies(&topo, &topo).is_empty());
+
+        let mut moved = topo.clone();
+        let br = moved.border_routers.get_mut("br1").unwrap();
+        br.interfaces.insert(1, intf("1-ff00:0:112", "127.0.0.4:50001"));
+        br.interfaces.insert(2, intf("1-ff00:0:113", "127.0.0.4:50002"));
+        moved.border_routers.insert("br2".to_string(), moved.border_routers["br1"].clone());
+        let diff = diff_topologies(&topo, &moved);
+        assert!(diff.ia_changed.is_none() && diff.removed_routers.is_empty());
+        assert_eq!(diff.added_routers, vec!["br2".to_string()]);
+        assert_eq!(diff.added_interfaces, vec![("br1".to_string(), 2)]);
+        assert_eq!(diff.underlay_changed.len(), 1);
+        assert_eq!(diff.underlay_changed[0].interface, Some(1));
+        let (old, new) = ("1-ff00:0:111".parse().unwrap(), "1-ff00:0:112".parse().unwrap());
+        assert_eq!(diff.neighbor_changed, vec![(1, old, new)]);
+
+        let mut reloaded = topo.clone();
+        assert_eq!(reloaded.reload(moved.clone()), diff);
+        assert_eq!(reloaded, moved);
+
+        let path = std::env::temp_dir().join(format!("scionnet-watch-{}.json", std::process::id()));
+        topo.to_file(&path).unwrap();
+        let mut watcher = TopologyWatcher::new(&path).unwrap();
+        assert_eq!(watcher.topology(), &topo);
+        assert_eq!(watcher.poll().unwrap(), None);
+        moved.to_file(&path).unwrap();
+        assert_eq!(watcher.poll().unwrap(), Some(diff));
+        assert_eq!(watcher.topology(), &moved);
+        assert_eq!(watcher.poll().unwrap(), None);
+        std::fs::write(&path, "{").unwrap();
+        assert!(watcher.poll().is_err());
+        assert_eq!(watcher.topology(), &moved);
+        std::fs::remove_file(&path).unwrap();
+        assert!(reloaded.reload_from_file(&path).is_err());
+        assert_eq!(reloaded, moved);
+    }
+
+    #[test]
+    fn resolve_hosts_and_dns_txt()
+    {
+        let hosts = "127.0.0.1 localhost\n\
+                     19-ffaa:1:1067,[10.0.0.1] server server.local # comment\n\
+                     ::1 server\n";
+        assert_eq!(crate::resolve::lookup_hosts(hosts, "server", 80),
+                   vec![SocketAddr::from_str("19-ffaa:1:1067,10.0.0.1:80").unwrap(),
+                        SocketAddr::from_str("[::1]:80").unwrap()]);
+        assert!(crate::resolve::lookup_hosts(hosts, "comment", 80).is_empty());
+
+        // answer the query with a single TXT record split into two strings
+        let mut msg = crate::resolve::build_txt_query(0x1234, "server.example").unwrap();
+        msg[2] |= 0x80;
+        msg[7] = 1;
+        let txt: &[u8] = b"scion=19-ffaa:1:1067,10.0.0.1";
+        msg.extend_from_slice(&[0xc0, 12, 0, 16, 0, 1, 0, 0, 0, 60, 0, txt.len() as u8 + 2]);
+        msg.push(6);
+        msg.extend_from_slice(&txt[..6]);
+        msg.push(txt.len() as u8 - 6);
+        msg.extend_from_slice(&txt[6..]);
+        assert_eq!(crate::resolve::parse_txt_response(0x1234, &msg).unwrap(),
+                   vec!["scion=19-ffaa:1:1067,10.0.0.1".to_string()]);
+        assert!(crate::resolve::parse_txt_response(0x4321, &msg).is_err());
+        // truncated replies are malformed, not a panic
+        for len in 0..msg.len() {
+            let err = crate::resolve::parse_txt_response(0x1234, &msg[..len]).unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::InvalidData, "{} bytes", len);
+        }
+    }
+
+    #[test]
+    fn resolver_chain_order_and_ranking()
+    {
+        struct Fixed(Vec<SocketAddr>);
+        impl AsyncResolve for Fixed {
+            fn resolve<'a>(&'a self, _host: &'a str, _port: u16) -> ResolveFuture<'a> {
+                let addrs = self.0.clone();
+                Box::pin(async move { Ok(addrs) })
+            }
+        }
+        struct Never;
+        impl AsyncResolve for Never {
+            fn resolve<'a>(&'a self, _host: &'a str, _port: u16) -> ResolveFuture<'a> {
+                Box::pin(std::future::pending())
+            }
+        }
+
+        let v4 = SocketAddr::from_str("10.0.0.1:443").unwrap();
+        let scion = SocketAddr::from_str("1-ff00:0:110,10.0.0.1:443").unwrap();
+        let mut chain = ResolverChain::new();
+        chain.push_with_timeout(Never, Duration::from_millis(10))
+             .push(Fixed(vec![]))
+             .push(Fixed(vec![v4, scion, v4]))
+             .push(Fixed(vec![v4]));
+        assert_eq!(block_on(chain.resolve("example", 443)).unwrap(), vec![scion, v4]);
+
+        // literal addresses bypass the resolvers
+        assert_eq!(block_on(ResolverChain::new().resolve("10.0.0.1", 443)).unwrap(), vec![v4]);
+        assert!(block_on(ResolverChain::new().resolve("example", 443)).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_textual_form()
+    {
+        let a = SocketAddr::from_str("19-ffaa:1:1067,127.0.0.1:53").unwrap();
+        let json = serde_json::to_string(&a).unwrap();
+        assert_eq!(json, "\"19-ffaa:1:1067,127.0.0.1:53\"");
+        assert_eq!(serde_json::from_str::<SocketAddr>(&json).unwrap(), a);
+
+        let ia: IsdAsn = serde_json::from_str("\"1-ff00:0:110\"").unwrap();
+        assert_eq!(ia.to_string(), "1-ff00:0:110");
+        let hosts: Vec<IpAddr> = serde_json::from_str("[\"10.0.0.1\", \"::1\"]").unwrap();
+        assert_eq!(hosts.len(), 2);
+        assert!(serde_json::from_str::<ScionAddr>("\"19-ffaa:1\"").is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_compact_form()
+    {
+        fn cbor_round_trip<T>(v: &T) -> T
+        where T: serde::Serialize + serde::de::DeserializeOwned {
+            let mut buf = Vec::new();
+            ciborium::into_writer(v, &mut buf).unwrap();
+            ciborium::from_reader(&buf[..]).unwrap()
+        }
+
+        let addrs: Vec<SocketAddr> = ["19-ffaa:1:1067,127.0.0.1:53", "1-ff00:0:110,[fe80::1]:443",
+                                      "10.0.0.1:80", "[fe80::1%3]:443", "1-ff00:0:110,CS:30252"]
+            .iter().map(|s| SocketAddr::from_str(s).unwrap()).collect();
+
+        for a in &addrs {
+            let bytes = bincode::serialize(a).unwrap();
+            assert_eq!(&bincode::deserialize::<SocketAddr>(&bytes).unwrap(), a);
+            assert_eq!(&cbor_round_trip(a), a);
+        }
+
+        // raw octets and integers, no strings: 4 byte tag + 4 octets + 2 byte port
+        assert_eq!(bincode::serialize(&addrs[2]).unwrap().len(), 10);
+        // a service host is tagged like an IP host, after the IPv4 and IPv6 ones
+        let host = bincode::serialize(&addrs[4]).unwrap();
+        assert_eq!(&host[12..18], &[2, 0, 0, 0, 2, 0]);
+        let ia = IsdAsn::from_str("19-ffaa:1:1067").unwrap();
+        assert_eq!(bincode::serialize(&ia).unwrap(), ia.to_u64().to_le_bytes());
+        assert_eq!(cbor_round_trip(&ia), ia);
+
+        // out of range AS numbers are rejected
+        assert!(bincode::deserialize::<Asn>(&u64::MAX.to_le_bytes()).is_err());
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_addr_config()
+    {
+        use crate::{AddrConfig, AddrConfigError};
+        use std::collections::HashMap;
+
+        #[derive(AddrConfig)]
+        struct ServiceConfig {
+            listen: SocketAddrScion,
+            #[addr_config(rename = "peer_addr")]
+            peer: Option<SocketAddr>,
+            #[addr_config(default = "1-ff00:0:110")]
+            local_ia: IsdAsn,
+        }
+
+        let mut map = HashMap::new();
+        map.insert("listen".to_string(), "19-ffaa:1:1067,127.0.0.1:53".to_string());
+        let config = ServiceConfig::from_map(&map).unwrap();
+        assert!(config.listen == SocketAddrScion::from_str("19-ffaa:1:1067,127.0.0.1:53").unwrap());
+        assert_eq!(config.peer, None);
+        assert_eq!(config.local_ia.to_string(), "1-ff00:0:110");
+
+        map.insert("peer_addr".to_string(), "10.0.0.1".to_string());
+        match ServiceConfig::from_map(&map) {
+            Err(AddrConfigError::Invalid { key, .. }) => assert_eq!(key, "peer_addr"),
+            _ => panic!("expected an invalid peer_addr"),
+        }
+
+        match ServiceConfig::from_map(&HashMap::new()) {
+            Err(e) => assert_eq!(e, AddrConfigError::Missing { key: "listen".to_string() }),
+            Ok(_) => panic!("expected a missing key"),
+        }
+    }
+
+    #[cfg(feature = "os-zone")]
+    #[test]
+    fn ipv6_zone_names()
+    {
+        use crate::SocketAddrV6;
+        use crate::zone::if_name_to_index;
+
+        // the loopback interface is the only one we can rely on
+        let name = ["lo", "lo0"].iter().find(|n| if_name_to_index(n).is_some());
+        if let Some(name) = name {
+            let s = format!("[fe80::1%{}]:443", name);
+            let a = SocketAddrV6::from_str(&s).unwrap();
+            assert_eq!(Some(a.scope_id()), if_name_to_index(name));
+            assert_eq!(a.to_string(), s);
+            assert_eq!(SocketAddrV6::from_str(&format!("[fe80::1%{}]:443", a.scope_id())).unwrap(), a);
+        }
+        assert!(SocketAddrV6::from_str("[fe80::1%no-such-if0]:443").is_err());
+    }
+
+    #[test]
+    fn scion_udp_socket_loopback()
+    {
+        let ia = IsdAsn::from_str("1-ff00:0:110").unwrap();
+        let local = |port| SocketAddrScion::from_ia(ia, IpAddr::V4(Ipv4Addr::LOCALHOST), port);
+        let a = ScionUdpSocket::bind(local(0)).unwrap();
+        let b = ScionUdpSocket::bind(local(0)).unwrap();
+        a.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        b.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let b_addr = b.local_addr().unwrap();
+        assert_ne!(b_addr.port(), 0);
+
+        assert_eq!(a.send_to(b"ping", SocketAddr::SCION(b_addr)).unwrap(), 4);
+        let mut buf = [0u8; 16];
+        let (n, from) = b.recv_from(&mut buf).unwrap();
+        assert_eq!(&buf[..n], b"ping");
+        assert_eq!(from, SocketAddr::SCION(a.local_addr().unwrap()));
+
+        b.send_to(b"pong", from).unwrap();
+        let (n, _) = a.recv_from(&mut buf).unwrap();
+        assert_eq!(&buf[..n], b"pong");
+
+        // other ASes need a route, and plain IP destinations are rejected
+        let remote = SocketAddrScion::from_str("2-ff00:0:220,127.0.0.1:9").unwrap();
+        assert!(a.send_to(b"x", SocketAddr::SCION(remote)).is_err());
+        let route = ScionRoute {
+            next_hop: b.underlay().local_addr().unwrap(),
+            path_type: 0,
+            raw_path: Vec::new(),
+        };
+        a.set_route(IsdAsn::from(remote.ia()), route.clone());
+        assert_eq!(a.route(IsdAsn::from(remote.ia())), Some(route));
+        a.send_to(b"via br", SocketAddr::SCION(remote)).unwrap();
+        let (n, _) = b.recv_from(&mut buf).unwrap();
+        assert_eq!(&buf[..n], b"via br");
+        assert!(a.send_to(b"x", "127.0.0.1:9").is_err());
+    }
+
+    #[test]
+    fn scion_tcp_stream_over_ip()
+    {
+        use crate::{IpTcpTransport, ScionTcpListener, ScionTcpStream};
+        use std::io::{Read, Write};
+
+        let server_ia = IsdAsn::from_str("1-ff00:0:110").unwrap();
+        let client_ia = IsdAsn::from_str("2-ff00:0:220").unwrap();
+        let server = IpTcpTransport::new(server_ia);
+        let listener = ScionTcpListener::bind_with(&server, "1-ff00:0:110,127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        assert_eq!(addr.ia(), server_ia.to_u64());
+
+        let handle = thread::spawn(move || {
+            let client = IpTcpTransport::new(client_ia);
+            let mut stream = ScionTcpStream::connect_with(&client, SocketAddr::SCION(addr)).unwrap();
+            assert_eq!(stream.peer_addr().unwrap(), addr);
+            stream.write_all(b"hello").unwrap();
+            let mut buf = [0u8; 5];
+            stream.read_exact(&mut buf).unwrap();
+            assert_eq!(&buf, b"world");
+            stream.local_addr().unwrap()
+        });
+
+        let (mut stream, peer) = listener.accept().unwrap();
+        let mut buf = [0u8; 5];
+        stream.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"hello");
+        stream.write_all(b"world").unwrap();
+        let client_addr = handle.join().unwrap();
+        assert_eq!(peer, SocketAddr::SCION(client_addr));
+        assert_eq!(client_addr.ia(), client_ia.to_u64());
+
+        // plain IP addresses cannot be connected to
+        let client = IpTcpTransport::new(client_ia);
+        assert!(ScionTcpStream::connect_with(&client, "127.0.0.1:1").is_err());
+
+        // a client without a preamble only holds up the listener until the timeout
+        let server = IpTcpTransport::new(server_ia).preamble_timeout(Duration::from_millis(50));
+        let listener = ScionTcpListener::bind_with(&server, "1-ff00:0:110,127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let _silent = std::net::TcpStream::connect(("127.0.0.1", addr.port())).unwrap();
+        assert!(listener.accept().is_err());
+        let stream = ScionTcpStream::connect_with(&client, SocketAddr::SCION(addr)).unwrap();
+        let (accepted, _) = listener.accept().unwrap();
+        assert_eq!(accepted.peer_addr().unwrap(), stream.local_addr().unwrap());
+    }
+
+    #[test]
+    fn to_scion_socket_addrs()
+    {
+        use crate::ToScionSocketAddrs;
+        use crate::Ipv6Addr;
+
+        fn all<A: ToScionSocketAddrs>(addr: A) -> Vec<SocketAddrScion> {
+            addr.to_scion_socket_addrs().unwrap().collect()
+        }
+
+        let a = SocketAddrScion::from_str("1-ff00:0:110,10.0.0.1:80").unwrap();
+        let ia = IsdAsn::from_str("1-ff00:0:110").unwrap();
+        assert_eq!(all(a), vec![a]);
+        assert_eq!(all(SocketAddr::SCION(a)), vec![a]);
+        assert_eq!(all((ia, Ipv4Addr::new(10, 0, 0, 1), 80)), vec![a]);
+        assert_eq!(all((ScionAddr::from_str("1-ff00:0:110,10.0.0.1").unwrap(), 80)), vec![a]);
+        assert_eq!(all("1-ff00:0:110,10.0.0.1:80"), vec![a]);
+        assert_eq!(all(("1-ff00:0:110,10.0.0.1", 80)), vec![a]);
+
+        let b = SocketAddrScion::from_ia(ia, IpAddr::V6(Ipv6Addr::LOCALHOST), 443);
+        let list = vec![a, b];
+        assert_eq!(all(&list), list);
+        assert_eq!(all(list.as_slice()), list);
+
+        // IP endpoints have no SCION address
+        assert!(SocketAddr::from_str("10.0.0.1:80").unwrap().to_scion_socket_addrs().is_err());
+        assert!("10.0.0.1:80".to_scion_socket_addrs().is_err());
+        assert!("1-ff00:0:110,10.0.0.1".to_scion_socket_addrs().is_err());
+    }
+
+    #[test]
+    fn socket_addr_scion_from_url_encoded()
+    {
+        let a = SocketAddrScion::from_str("19-ffaa:1:1067,127.0.0.1:53").unwrap();
+        assert_eq!(SocketAddrScion::from_url_encoded("19-ffaa%3A1%3A1067%2C%5B127.0.0.1%5D%3A53"), Ok(a));
+        assert_eq!(SocketAddrScion::from_url_encoded("19-ffaa:1:1067%2c127.0.0.1:53"), Ok(a));
+        assert_eq!(SocketAddrScion::from_url_encoded("19-ffaa:1:1067,127.0.0.1:53"), Ok(a));
+        assert_eq!(SocketAddrScion::from_url_encoded("1-ff00:0:110%2C%5B%3A%3A1%5D%3A443").unwrap().port(), 443);
+
+        // malformed escapes, control characters, spaces and overlong inputs
+        assert!(SocketAddrScion::from_url_encoded("19-ffaa:1:1067%2").is_err());
+        assert!(SocketAddrScion::from_url_encoded("19-ffaa:1:1067%zz127.0.0.1:53").is_err());
+        assert!(SocketAddrScion::from_url_encoded("19-ffaa:1:1067,127.0.0.1:53%00").is_err());
+        assert!(SocketAddrScion::from_url_encoded("19-ffaa:1:1067,+127.0.0.1:53").is_err());
+        assert!(SocketAddrScion::from_url_encoded("19-ffaa:1:1067%252C127.0.0.1:53").is_err());
+        assert!(SocketAddrScion::from_url_encoded(&"%30".repeat(200)).is_err());
+        assert!(SocketAddrScion::from_url_encoded(&"0".repeat(1000)).is_err());
+    }
+
+    #[test]
+    fn rate_limiter_buckets()
+    {
+        use crate::{RateDecision, RateLimitKey, RateLimiter};
+        use std::time::Instant;
+
+        let parse = |s: &str| SocketAddrScion::from_str(s).unwrap();
+        let a = parse("1-ff00:0:110,10.0.0.1:4000");
+        let b = parse("1-ff00:0:110,10.0.0.2:4000");
+        let c = parse("1-ff00:0:111,10.0.0.1:4000");
+        let d = parse("2-ff00:0:210,10.0.0.1:4000");
+        let now = Instant::now();
+
+        let limiter = RateLimiter::with_shards(RateLimitKey::Ia, 10, 2, 4);
+        assert!(limiter.check_at(&a, now).is_allowed());
+        assert!(limiter.check_at(&b, now).is_allowed());
+        assert_eq!(limiter.check_at(&a, now),
+                   RateDecision::Deny { retry_after: Duration::from_millis(100) });
+        assert!(limiter.check_at(&c, now).is_allowed());
+        assert!(limiter.check_at(&a, now + Duration::from_millis(100)).is_allowed());
+        assert_eq!(limiter.len(), 2);
+
+        let limiter = RateLimiter::new(RateLimitKey::Isd, 1, 1);
+        assert!(limiter.check_at(&a, now).is_allowed());
+        assert!(!limiter.check_at(&c, now).is_allowed());
+        assert!(limiter.check_at(&d, now).is_allowed());
+
+        let limiter = RateLimiter::new(RateLimitKey::HostPrefix { v4: 24, v6: 64 }, 1, 1);
+        assert!(limiter.check_at(&a, now).is_allowed());
+        assert!(!limiter.check_at(&b, now).is_allowed());
+        assert!(limiter.check_at(&c, now).is_allowed());
+
+        let limiter = RateLimiter::new(RateLimitKey::Addr, 1, 1);
+        assert!(limiter.check_at(&a, now).is_allowed());
+        assert!(limiter.check_at(&b, now).is_allowed());
+        assert!(!limiter.check_at(&a, now).is_allowed());
+
+        // a full limiter drops the refilled buckets, then the least recently used ones
+        let limiter = RateLimiter::with_shards(RateLimitKey::Addr, 1, 2, 1).with_max_buckets(2);
+        assert!(limiter.check_at(&a, now).is_allowed());
+        assert!(limiter.check_at(&b, now + Duration::from_millis(10)).is_allowed());
+        assert!(limiter.check_at(&c, now + Duration::from_millis(20)).is_allowed());
+        assert_eq!(limiter.len(), 2);
+        // the bucket of `a` is gone, so it is full again
+        assert!(limiter.check_at(&a, now + Duration::from_millis(30)).is_allowed());
+        assert!(limiter.check_at(&a, now + Duration::from_millis(30)).is_allowed());
+        assert_eq!(limiter.len(), 2);
+        for port in 0..100 {
+            let src = SocketAddrScion::from_scion_addr(*a.addr(), port);
+            limiter.check_at(&src, now + Duration::from_secs(10));
+        }
+        assert_eq!(limiter.len(), 2);
+
+        // a sweep frees an eighth of the shard, the least recently used first
+        let limiter = RateLimiter::with_shards(RateLimitKey::Addr, 1, 1, 1).with_max_buckets(16);
+        let src = |port| SocketAddrScion::from_scion_addr(*a.addr(), port);
+        let at = |port| now + Duration::from_millis(u64::from(port));
+        for port in 0..17 {
+            assert!(limiter.check_at(&src(port), at(port)).is_allowed());
+        }
+        assert_eq!(limiter.len(), 15);
+        assert!(!limiter.check_at(&src(2), at(17)).is_allowed());
+        assert!(limiter.check_at(&src(0), at(17)).is_allowed());
+        assert!(limiter.check_at(&src(1), at(17)).is_allowed());
+        assert_eq!(limiter.len(), 15);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn async_scion_udp_socket()
+    {
+        use crate::AsyncScionUdpSocket;
+
+        let rt = tokio::runtime::Builder::new_current_thread().enable_io().build().unwrap();
+        rt.block_on(async {
+            let local = "1-ff00:0:110,127.0.0.1:0";
+            let a = AsyncScionUdpSocket::bind(local).await.unwrap();
+            let b = AsyncScionUdpSocket::from_std(ScionUdpSocket::bind(local).unwrap()).unwrap();
+
+            a.send_to(b"ping", b.local_addr().unwrap()).await.unwrap();
+            let mut buf = [0u8; 16];
+            let (n, from) = b.recv_from(&mut buf).await.unwrap();
+            assert_eq!(&buf[..n], b"ping");
+            assert_eq!(from, SocketAddr::SCION(a.local_addr().unwrap()));
+
+            b.send_to(b"pong", from).await.unwrap();
+            let (n, _) = a.recv_from(&mut buf).await.unwrap();
+            assert_eq!(&buf[..n], b"pong");
+        });
+    }
+
+    #[test]
+    fn conntrack_timeouts_and_expiry()
+    {
+        use crate::{ConnState, ConnTimeouts, ConnTrack, FlowId};
+        use std::sync::Mutex;
+        use std::time::Instant;
+
+        let client = SocketAddr::from_str("1-ff00:0:110,10.0.0.1:4000").unwrap();
+        let server = SocketAddr::from_str("2-ff00:0:220,10.0.0.2:443").unwrap();
+        let a = FlowId::new(client, server, 17);
+        let b = FlowId::new(SocketAddr::from_str("[::ffff:10.0.0.3]:53").unwrap(),
+                            SocketAddr::from_str("10.0.0.4:53").unwrap(), 17);
+        assert_eq!(b, FlowId::new(SocketAddr::from_str("10.0.0.3:53").unwrap(),
+                                  SocketAddr::from_str("10.0.0.4:53").unwrap(), 17));
+        assert_eq!(a.reversed().src(), &server);
+
+        let timeouts = ConnTimeouts {
+            new: Duration::from_secs(5),
+            established: Duration::from_secs(60),
+            closing: Duration::from_secs(1),
+        };
+        let expired = Arc::new(Mutex::new(Vec::new()));
+        let mut table = ConnTrack::new(timeouts);
+        let sink = expired.clone();
+        table.on_expire(move |flow, entry| sink.lock().unwrap().push((*flow, entry.data)));
+
+        assert!(table.insert(a, "a").is_none());
+        table.insert(b, "b");
+        assert!(table.set_state(&a, ConnState::Established));
+        assert!(!table.touch(&a.reversed()));
+        assert_eq!(table.get(&a).unwrap().state, ConnState::Established);
+        assert_eq!(table.snapshot().len(), 2);
+
+        let now = Instant::now();
+        assert_eq!(table.expire_at(now), 0);
+        assert_eq!(table.expire_at(now + Duration::from_secs(10)), 1);
+        assert_eq!(*expired.lock().unwrap(), vec![(b, "b")]);
+        assert!(table.contains(&a));
+
+        table.set_state(&a, ConnState::Closing);
+        assert_eq!(table.expire_at(Instant::now() + Duration::from_secs(2)), 1);
+        assert!(table.is_empty());
+    }
+
+    #[test]
+    fn scion_path_route() {
+        let src = IsdAsn::from(0x0001_ff00_0000_0110);
+        let dst = IsdAsn::from(0x0002_ff00_0000_0220);
+        let empty = ScionPath::empty(src);
+        assert!(empty.is_empty() && !empty.is_expired(std::time::SystemTime::now()));
+        assert_eq!(empty.hop_count(), 1);
+        assert_eq!(empty.to_string(), "[empty]");
+        assert!(empty.route().is_none());
+
+        let path = ScionPath {
+            src,
+            dst,
+            path_type: PATH_TYPE_SCION,
+            raw: vec![0; 36],
+            next_hop: Some("10.0.0.1:31002".parse().unwrap()),
+            interfaces: vec![PathInterface { ia: src, id: 1 }, PathInterface { ia: dst, id: 5 }],
+            mtu: 1472,
+            expiration: std::time::SystemTime::UNIX_EPOCH,
+            latency: vec![None],
+        };
+        assert_eq!(path.hop_count(), 2);
+        assert!(path.is_expired(std::time::SystemTime::now()));
+        assert_eq!(path.to_string(), "1-ff00:0:110#1 2-ff00:0:220#5");
+        let route = path.route().unwrap();
+        assert_eq!(route.path_type, PATH_TYPE_SCION);
+        assert_eq!(route.raw_path.len(), 36);
+    }
+
+    #[cfg(feature = "daemon")]
+    #[test]
+    fn daemon_path_from_proto() {
+        use crate::daemon::proto;
+        use prost::Message;
+
+        let src = IsdAsn::from(0x0001_ff00_0000_0110);
+        let dst = IsdAsn::from(0x0001_ff00_0000_0111);
+        let msg = proto::Path {
+            raw: vec![1, 2, 3, 4],
+            interface: Some(proto::Interface {
+                address: Some(proto::Underlay { address: "127.0.0.1:31000".into() }),
+            }),
+            interfaces: vec![
+                proto::PathInterface { isd_as: src.to_u64(), id: 2 },
+                proto::PathInterface { isd_as: dst.to_u64(), id: 3 },
+            ],
+            mtu: 1400,
+            expiration: Some(proto::Timestamp { seconds: 4_000_000_000, nanos: 0 }),
+            latency: vec![proto::Duration { seconds: 0, nanos: 5_000_000 }, proto::Duration { seconds: -1, nanos: 0 }],
+        };
+        let bytes = msg.encode_to_vec();
+        let path = ScionPath::from_proto(src, dst, proto::Path::decode(&bytes[..]).unwrap()).unwrap();
+        assert_eq!(path.path_type, PATH_TYPE_SCION);
+        assert_eq!(path.next_hop, Some("127.0.0.1:31000".parse().unwrap()));
+        assert_eq!(path.interfaces[1], PathInterface { ia: dst, id: 3 });
+        assert_eq!(path.mtu, 1400);
+        assert!(!path.is_expired(std::time::SystemTime::now()));
+        assert_eq!(path.latency, vec![Some(Duration::from_millis(5)), None]);
+
+        let bad = proto::Path {
+            interface: Some(proto::Interface { address: Some(proto::Underlay { address: "nope".into() }) }),
+            ..msg
+        };
+        assert!(ScionPath::from_proto(src, dst, bad).is_err());
+    }
+
+    #[test]
+    fn scion_path_base64_hex() {
+        let src = IsdAsn::from(0x0001_ff00_0000_0110);
+        let dst = IsdAsn::from(0x0001_ff00_0000_0111);
+        // one segment with two hop fields: meta header, info field, 2 hop fields
+        let mut raw = vec![0x00, 0x00, 0x20, 0x00];
+        raw.extend((0..8 + 2 * 12).map(|i| i as u8));
+        let path = ScionPath { raw: raw.clone(), path_type: PATH_TYPE_SCION, ..ScionPath::empty(src) };
+
+        let b64 = path.to_base64();
+        assert_eq!(&b64[..8], "AAAgAAAB");
+        let decoded = ScionPath::from_base64(src, dst, &b64).unwrap();
+        assert_eq!(decoded.raw, raw);
+        assert_eq!(decoded.path_type, PATH_TYPE_SCION);
+        assert_eq!(decoded.dst, dst);
+        assert_eq!(ScionPath::from_hex(src, dst, &path.to_hex().to_uppercase()).unwrap().raw, raw);
+        assert_eq!(&path.to_hex()[..10], "0000200000");
+
+        assert!(ScionPath::from_base64(src, dst, "").unwrap().is_empty());
+        // truncated path, bad alphabet, non-canonical padding bits, odd hex
+        assert!(ScionPath::from_hex(src, dst, &path.to_hex()[..60]).is_err());
+        assert!(ScionPath::from_base64(src, dst, "AAA*").is_err());
+        assert!(ScionPath::from_base64(src, dst, "AB==").is_err());
+        assert!(ScionPath::from_hex(src, dst, "000").is_err());
+        // current hop field past the end of the path
+        raw[0] = 0x02;
+        let bad = ScionPath { raw, ..path };
+        assert!(ScionPath::from_base64(src, dst, &bad.to_base64()).is_err());
+    }
+
+    #[cfg(feature = "topology")]
+    #[test]
+    fn topology_json() {
+        use crate::{diff_topologies, LinkType, Topology};
+
+        let json = r#"{
+            "attributes": ["core"],
+            "isd_as": "1-ff00:0:110",
+            "mtu": 1472,
+            "dispatched_ports": "31000-32767",
+            "control_service": {
+                "cs1-ff00_0_110-1": { "addr": "127.0.0.11:31000" }
+            },
+            "border_routers": {
+                "br1-ff00_0_110-1": {
+                    "internal_addr": "127.0.0.9:31002",
+                    "interfaces": {
+                        "1": {
+                            "underlay": { "local": "127.0.0.4:50000", "remote": "127.0.0.5:50000" },
+                            "isd_as": "1-ff00:0:111",
+                            "link_to": "child",
+                            "mtu": 1280
+                        }
+                    }
+                },
+                "br1-ff00_0_110-2": {
+                    "internal_addr": "[::1]:31004",
+                    "interfaces": {
+                        "2": {
+                            "underlay": { "public": "[::1]:50002" },
+                            "isd_as": "2-ff00:0:210",
+                            "link_to": "CORE",
+                            "mtu": 1472
+                        }
+                    }
+                }
+            }
+        }"#;
+        let topo: Topology = json.parse().unwrap();
+        assert_eq!(topo.ia.to_string(), "1-ff00:0:110");
+        assert!(topo.core);
+        assert_eq!(topo.mtu, 1472);
+        assert_eq!(topo.dispatched_ports, Some((31000, 32767)));
+        assert_eq!(topo.control_services.len(), 1);
+        assert_eq!(topo.next_hop(1), Some("127.0.0.9:31002".parse().unwrap()));
+        assert_eq!(topo.next_hop(2), Some("[::1]:31004".parse().unwrap()));
+        assert_eq!(topo.next_hop(3), None);
+        let (_, intf) = topo.interface(2).unwrap();
+        assert_eq!(intf.link_to, LinkType::Core);
+        assert_eq!(intf.remote, None);
+        let local = topo.scion_addr("10.0.0.1".parse().unwrap());
+        assert_eq!(local.to_string(), "1-ff00:0:110,10.0.0.1");
+        assert_eq!(topo.local_addr().unwrap().get_ia(), topo.ia.to_u64());
+
+        assert!(Topology::from_json(&json.replace("\"child\"", "\"sibling\"")).is_err());
+        assert!(Topology::from_json(&json.replace("\"mtu\": 1472,\n            \"dispatched", "\"dispatched")).is_err());
+
+        let moved = Topology::from_json(&json.replace("127.0.0.9:31002", "127.0.0.10:31002").replace(
+            "\"2\": {",
+            "\"3\": {",
+        ))
+        .unwrap();
+        let diff = diff_topologies(&topo, &moved);
+        assert!(diff.ia_changed.is_none() && diff.added_routers.is_empty());
+        assert_eq!(diff.underlay_changed.len(), 1);
+        assert_eq!(diff.underlay_changed[0].interface, None);
+        assert_eq!(diff.removed_interfaces, vec![("br1-ff00_0_110-2".to_string(), 2)]);
+        assert_eq!(diff.added_interfaces, vec![("br1-ff00_0_110-2".to_string(), 3)]);
+        assert!(diff_topologies(&topo, &topo).is_empty());
+    }
+
+    #[test]
+    fn dataplane_path_encoding() {
+        use crate::path::{HopField, InfoField, Path, HOP_FIELD_LEN};
+
+        let hop = |ingress, egress| HopField {
+            exp_time: 63,
+            cons_ingress: ingress,
+            cons_egress: egress,
+            mac: [ingress as u8; 6],
+            ..HopField::default()
+        };
+        // up segment against construction direction, then a down segment
+        let path = Path {
+            curr_inf: 0,
+            curr_hf: 1,
+            seg_len: [2, 3, 0],
+            info_fields: vec![
+                InfoField { cons_dir: false, peer: false, seg_id: 0x1234, timestamp: 1_700_000_000 },
+                InfoField { cons_dir: true, peer: false, seg_id: 0xbeef, timestamp: 1_700_000_100 },
+            ],
+            hop_fields: vec![hop(1, 0), hop(0, 2), hop(0, 5), hop(6, 7), hop(8, 0)],
+        };
+        let bytes = path.to_bytes();
+        assert_eq!(bytes.len(), 4 + 2 * 8 + 5 * 12);
+        assert_eq!(bytes[..4], [0x01, 0x00, 0x20, 0xc0]);
+        assert_eq!(bytes[4..12], [0x00, 0x00, 0x12, 0x34, 0x65, 0x53, 0xf1, 0x00]);
+
+        // trailing payload is left alone
+        let mut packet = bytes.clone();
+        packet.extend_from_slice(b"payload");
+        let decoded = Path::decode_from(&packet).unwrap();
+        assert_eq!(decoded, path);
+        assert_eq!(decoded.encoded_len(), bytes.len());
+        assert!(Path::decode_from(&bytes[..bytes.len() - 1]).is_err());
+
+        let reply = path.reversed().unwrap();
+        assert_eq!(reply.seg_len, [3, 2, 0]);
+        assert_eq!((reply.curr_inf, reply.curr_hf), (1, 3));
+        assert!(!reply.info_fields[0].cons_dir && reply.info_fields[1].cons_dir);
+        assert_eq!(reply.info_fields[0].seg_id, 0xbeef);
+        assert_eq!(reply.hop_fields[0], hop(8, 0));
+        assert_eq!(reply.reversed().unwrap(), path);
+
+        let mut buf = [0u8; 16];
+        assert!(path.encode_to(&mut buf).is_err());
+        let broken = Path { seg_len: [2, 0, 3], ..path.clone() };
+        assert!(broken.to_bytes().is_empty());
+
+        // CurrHF indexes at most 64 hop fields
+        let long = Path {
+            seg_len: [22, 22, 21],
+            info_fields: vec![InfoField::default(); 3],
+            hop_fields: vec![hop(1, 2); 65],
+            ..Path::default()
+        };
+        assert!(long.to_bytes().is_empty());
+        assert!(long.reversed().is_err());
+        let longest = Path { seg_len: [22, 21, 21], hop_fields: vec![hop(1, 2); 64], ..long };
+        let mut raw = longest.to_bytes();
+        assert_eq!(Path::decode_from(&raw).unwrap(), longest);
+        raw.extend_from_slice(&[0; HOP_FIELD_LEN]);
+        raw[3] += 1;
+        assert!(Path::decode_from(&raw).is_err());
+
+        let scion_path = ScionPath { raw: bytes, path_type: PATH_TYPE_SCION, ..ScionPath::empty(IsdAsn::from(1)) };
+        assert_eq!(scion_path.decode().unwrap(), path);
+    }
+
+    #[test]
+    fn ipv6_const_segments() {
+        use crate::Ipv6Addr;
+
+        const ADDR: Ipv6Addr = Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0xffff, 0xc00a, 0x2ff);
+        const SEGMENTS: [u16; 8] = ADDR.segments();
+        const _: () = assert!(SEGMENTS[1] == 0xdb8 && SEGMENTS[7] == 0x2ff);
+        const _: () = assert!(Ipv6Addr::LOCALHOST.segments()[7] == 1);
+
+        assert_eq!(SEGMENTS, [0x2001, 0xdb8, 0, 0, 0, 0xffff, 0xc00a, 0x2ff]);
+        assert_eq!(ADDR.octets()[..4], [0x20, 0x01, 0x0d, 0xb8]);
+        assert_eq!(ADDR.octets()[14..], [0x02, 0xff]);
+        assert_eq!(ADDR.to_string(), "2001:db8::ffff:c00a:2ff");
+        assert_eq!(Ipv6Addr::from_bits(ADDR.to_bits()).segments(), SEGMENTS);
+    }
+
+    #[test]
+    fn path_policy() {
+        use crate::{Acl, HopPredicate, Policy, PolicyError, Sequence};
+
+        let ia = |s: &str| IsdAsn::from_str(s).unwrap();
+        let path = |hops: &[(&str, u64)]| ScionPath {
+            interfaces: hops.iter().map(|&(a, id)| PathInterface { ia: ia(a), id }).collect(),
+            ..ScionPath::empty(ia(hops[0].0))
+        };
+        // 110 -> 120 -> 130 and 110 -> 130 directly
+        let long = path(&[("1-ff00:0:110", 1), ("1-ff00:0:120", 2), ("1-ff00:0:120", 3), ("1-ff00:0:130", 4)]);
+        let short = path(&[("1-ff00:0:110", 5), ("1-ff00:0:130", 6)]);
+
+        let pred: HopPredicate = "1-ff00:0:120#2,3".parse().unwrap();
+        assert!(pred.matches(ia("1-ff00:0:120"), 2, 3) && !pred.matches(ia("1-ff00:0:120"), 3, 2));
+        assert_eq!(pred.to_string(), "1-ff00:0:120#2,3");
+        assert_eq!("1".parse::<HopPredicate>().unwrap().to_string(), "1-0");
+        assert!("0-ff00:0:1".parse::<HopPredicate>().is_err());
+        assert!("1-0#4".parse::<HopPredicate>().is_err());
+
+        let acl = Acl::parse(["- 1-ff00:0:120#2", "+"]).unwrap();
+        assert!(!acl.allows(&long) && acl.allows(&short));
+        assert_eq!(acl.entries()[0].to_string(), "- 1-ff00:0:120#2");
+        assert_eq!(Acl::parse(["+ 1"]), Err(PolicyError::MissingDefault));
+
+        let via_120: Sequence = "1-ff00:0:110#1 1-ff00:0:120#2,3 1-ff00:0:130".parse().unwrap();
+        assert!(via_120.matches(&long) && !via_120.matches(&short));
+        let any: Sequence = "1-ff00:0:110 0* 1-ff00:0:130#0".parse().unwrap();
+        assert!(any.matches(&long) && any.matches(&short));
+        let alt: Sequence = "0 (1-ff00:0:120 | 1-ff00:0:121)? 0".parse().unwrap();
+        assert!(alt.matches(&long) && alt.matches(&short));
+        assert!(!"0 1-ff00:0:121+ 0".parse::<Sequence>().unwrap().matches(&long));
+        assert!("0 (1-ff00:0:120".parse::<Sequence>().is_err());
+        assert!("* 0".parse::<Sequence>().is_err());
+
+        let policy = Policy { acl: None, sequence: Some(any) };
+        let ordered = policy.filter(vec![long.clone(), short.clone()]);
+        assert_eq!(ordered, vec![short.clone(), long.clone()]);
+        let strict = Policy { acl: Some(acl), sequence: None };
+        assert_eq!(strict.filter(vec![long, short.clone()]), vec![short]);
+    }
+
+    #[test]
+    fn scion_udp_socket_vectored() {
+        use std::io::{IoSlice, IoSliceMut};
+
+        let ia = IsdAsn::from_str("1-ff00:0:110").unwrap();
+        let local = |port| SocketAddrScion::from_ia(ia, IpAddr::V4(Ipv4Addr::LOCALHOST), port);
+        let a = ScionUdpSocket::bind(local(0)).unwrap();
+        let b = ScionUdpSocket::bind(local(0)).unwrap();
+        b.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let b_addr = b.local_addr().unwrap();
+
+        let sent = a.send_vectored_to(&[IoSlice::new(b"hdr:"), IoSlice::new(b"payload")], b_addr).unwrap();
+        assert_eq!(sent, 11);
+        let mut buf = [0u8; 16];
+        let (n, from) = b.recv_from(&mut buf).unwrap();
+        assert_eq!(&buf[..n], b"hdr:payload");
+        assert_eq!(from, SocketAddr::SCION(a.local_addr().unwrap()));
+
+        // plain UDP noise is skipped, the payload is split over the buffers
+        // and what does not fit is dropped
+        a.underlay().send_to(b"noise", b.underlay().local_addr().unwrap()).unwrap();
+        a.send_to(b"0123456789", b_addr).unwrap();
+        let (mut head, mut tail) = ([0u8; 4], [0u8; 4]);
+        let (n, from) = b
+            .recv_vectored_from(&mut [IoSliceMut::new(&mut head), IoSliceMut::new(&mut tail)])
+            .unwrap();
+        assert_eq!(n, 8);
+        assert_eq!((&head, &tail), (b"0123", b"4567"));
+        assert_eq!(from, SocketAddr::SCION(a.local_addr().unwrap()));
+    }
+
+    #[test]
+    fn parse_observer_sampling() {
+        use crate::{set_parse_observer, with_parse_observer, AddrKind, ParseRejection, Redaction, SampledObserver};
+        use std::sync::Mutex;
+
+        type Seen = Arc<Mutex<Vec<(String, AddrKind, usize, u64)>>>;
+        let seen: Seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = seen.clone();
+        let record = move |r: &ParseRejection<'_>| {
+            sink.lock().unwrap().push((r.input_lossy().into_owned(), r.kind.clone(), r.offset, r.suppressed))
+        };
+
+        with_parse_observer(Arc::new(record.clone()), || {
+            assert!(SocketAddrScion::from_str("1-ff00:0:110,10.0.0.1:99999").is_err());
+            assert!(IsdAsn::from_str("1-ff00:0:11x").is_err());
+            assert!(Ipv4Addr::from_str("10.0.0.1").is_ok());
+            assert!(Ipv4Addr::from_str("255.255.255.255.1").is_err());
+            assert!(SocketAddrScion::from_url_encoded("1-ff00%3G").is_err());
+        });
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                ("1-ff00:0:110,10.0.0.1:99999".to_string(), AddrKind::SocketScion, 22, 0),
+                ("1-ff00:0:11x".to_string(), AddrKind::Ia, 11, 0),
+                ("255.255.255.255.1".to_string(), AddrKind::Ipv4, 15, 0),
+                ("1-ff00%3G".to_string(), AddrKind::SocketScion, 6, 0),
+            ]
+        );
+
+        // the scoped observer is gone again
+        seen.lock().unwrap().clear();
+        assert!(IsdAsn::from_str("x").is_err());
+        assert!(seen.lock().unwrap().is_empty());
+
+        let sampled = SampledObserver::new(record, 2, Duration::from_secs(3600)).redact(Redaction::Truncate(2));
+        let sampled = Arc::new(sampled);
+        with_parse_observer(sampled.clone(), || {
+            for bad in ["aa", "bbb", "cccc", "ddddd"] {
+                assert!(IsdAsn::from_str(bad).is_err());
+            }
+        });
+        let seen_now: Vec<_> = seen.lock().unwrap().iter().map(|s| s.0.clone()).collect();
+        assert_eq!(seen_now, vec!["aa", "bb"]);
+
+        // the global observer sees failures on all threads
+        let global = Arc::new(Mutex::new(0));
+        let counter = global.clone();
+        let previous = set_parse_observer(Some(Arc::new(move |r: &ParseRejection<'_>| {
+            if r.input == b"global-marker" {
+                *counter.lock().unwrap() += 1;
+            }
+        })));
+        assert!(previous.is_none());
+        thread::spawn(|| assert!(IsdAsn::from_str("global-marker").is_err())).join().unwrap();
+        assert!(set_parse_observer(None).is_some());
+        assert!(IsdAsn::from_str("global-marker").is_err());
+        assert_eq!(*global.lock().unwrap(), 1);
+    }
+
+    #[test]
+    fn path_selectors() {
+        use crate::{FirstPath, LowestLatency, MtuAware, PathSelector, ShortestPath};
+        use std::time::SystemTime;
+
+        let src = IsdAsn::from_str("1-ff00:0:110").unwrap();
+        let dst = IsdAsn::from_str("1-ff00:0:130").unwrap();
+        let path = |hops: usize, mtu: u16, latency: Vec<Option<u64>>| ScionPath {
+            src,
+            dst,
+            path_type: PATH_TYPE_SCION,
+            raw: vec![0; 4],
+            next_hop: Some("127.0.0.1:31002".parse().unwrap()),
+            interfaces: (0..2 * (hops - 1) as u64).map(|id| PathInterface { ia: src, id }).collect(),
+            mtu,
+            expiration: SystemTime::now() + Duration::from_secs(3600),
+            latency: latency.into_iter().map(|l| l.map(Duration::from_millis)).collect(),
+        };
+        let mut expired = path(2, 1500, vec![Some(1)]);
+        expired.expiration = SystemTime::UNIX_EPOCH;
+        let paths = vec![
+            expired,
+            path(4, 1400, vec![Some(5), Some(5), Some(5)]),
+            path(3, 1280, vec![Some(1), None]),
+            path(3, 1472, vec![Some(20), Some(20)]),
+        ];
+
+        assert_eq!(FirstPath.select(&paths), Some(1));
+        assert_eq!(ShortestPath.select(&paths), Some(2));
+        assert_eq!(LowestLatency.select(&paths), Some(1));
+        assert_eq!(MtuAware::new(1350).select(&paths), Some(3));
+        assert_eq!(MtuAware::new(9000).select(&paths), Some(3));
+        assert_eq!(ShortestPath.select(&paths[..1]), None);
+        let last = |paths: &[ScionPath]| paths.len().checked_sub(1);
+        assert_eq!(last.select(&paths), Some(3));
+
+        let socket = ScionUdpSocket::bind(SocketAddrScion::from_ia(src, IpAddr::V4(Ipv4Addr::LOCALHOST), 0)).unwrap();
+        let chosen = socket.select_path(&paths, &ShortestPath).unwrap();
+        assert_eq!(chosen, &paths[2]);
+        assert_eq!(socket.route(dst), paths[2].route());
+        assert!(socket.select_path(&paths[..1], &FirstPath).is_none());
+    }
+
+    #[test]
+    fn scmp_messages() {
+        use crate::scmp::{self, ScmpMessage};
+        let src = ScionAddr::from_ia(IsdAsn::from(make_ia(1, 0xff00_0000_0110)), IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)));
+        let dst = ScionAddr::from_ia(IsdAsn::from(make_ia(2, 0xff00_0000_0220)), IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)));
+
+        let echo = ScmpMessage::EchoRequest { id: 7, seq: 1, data: b"ping".to_vec() };
+        let wire = echo.encode(&src, &dst);
+        assert_eq!(&wire[..2], &[scmp::TYPE_ECHO_REQUEST, 0]);
+        assert!(scmp::verify_checksum(&wire, &src, &dst));
+        let other = ScionAddr::from_ia(dst.isd_asn(), IpAddr::V4(Ipv4Addr::new(10, 0, 0, 3)));
+        assert!(!scmp::verify_checksum(&wire, &src, &other));
+        let decoded = ScmpMessage::decode_from(&wire).unwrap();
+        assert_eq!(decoded, echo);
+        assert!(!decoded.is_error());
+        assert_eq!(
+            decoded.echo_reply(),
+            Some(ScmpMessage::EchoReply { id: 7, seq: 1, data: b"ping".to_vec() })
+        );
+
+        // quote a SCION/UDP packet sent from `src` to `dst`
+        let mut quote = vec![0u8; 12];
+        quote.extend_from_slice(&dst.get_ia().to_be_bytes());
+        quote.extend_from_slice(&src.get_ia().to_be_bytes());
+        quote.extend_from_slice(&[10, 0, 0, 2, 10, 0, 0, 1]);
+        let msgs = [
+            ScmpMessage::DestinationUnreachable { code: scmp::unreachable::PORT_UNREACHABLE, quote: quote.clone() },
+            ScmpMessage::PacketTooBig { mtu: 1280, quote: quote.clone() },
+            ScmpMessage::ParameterProblem { code: 0, pointer: 9, quote: quote.clone() },
+            ScmpMessage::ExternalInterfaceDown { ia: src.isd_asn(), interface: 3, quote: quote.clone() },
+            ScmpMessage::InternalConnectivityDown { ia: src.isd_asn(), ingress: 1, egress: 2, quote: quote.clone() },
+            ScmpMessage::Unknown { kind: 99, code: 1, body: vec![1, 2, 3] },
+        ];
+        for msg in &msgs {
+            let wire = msg.encode(&src, &dst);
+            assert_eq!(&ScmpMessage::decode_from(&wire).unwrap(), msg);
+        }
+        assert!(msgs[0].is_error());
+        assert_eq!(msgs[1].quoted_addrs(), Some((src, dst)));
+        assert_eq!(msgs[5].quoted_addrs(), None);
+        assert!(ScmpMessage::decode_from(&[scmp::TYPE_PACKET_TOO_BIG, 0, 0, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn scion_addr_slug() {
+        for (text, slug) in [
+            ("1-ff00:0:110,10.0.0.1", "1-ff00_0_110_10-0-0-1"),
+            ("19-ffaa:1:1067,fd00::1", "19-ffaa_1_1067_fd00--1"),
+            ("1-65551,::ffff:1.2.3.4", "1-65551_--ffff-1.2.3.4"),
+        ] {
+            let addr = ScionAddr::from_str(text).unwrap();
+            assert_eq!(addr.to_slug(), slug);
+            assert_eq!(ScionAddr::from_slug(slug), Ok(addr));
+        }
+        assert!(ScionAddr::from_slug("1-ff00:0:110,10.0.0.1").is_err());
+        assert!(ScionAddr::from_slug("1-ff00_0_110").is_err());
+        assert!(ScionAddr::from_slug("1-ff00_0_110_10-0-0").is_err());
+    }
+
+    #[test]
+    fn remote_endpoint_failover()
+    {
+        use crate::{IpTcpTransport, RemoteEndpoint, ScionTcpListener, ScionTcpStream};
+
+        let scion = SocketAddr::from_str("1-ff00:0:110,10.0.0.1:443").unwrap();
+        let ip = SocketAddr::from_str("192.0.2.1:443").unwrap();
+        let v6 = SocketAddr::from_str("[2001:db8::1]:443").unwrap();
+        let mut remote = RemoteEndpoint::new([scion, ip, scion, v6]);
+        assert_eq!(remote.candidates().copied().collect::<Vec<_>>(), vec![scion, ip, v6]);
+        assert_eq!(remote.current(), Some(scion));
+
+        remote.mark_failed();
+        assert_eq!(remote.current(), Some(ip));
+        assert!(!remote.is_healthy(&scion));
+        assert_eq!(remote.failures(&scion), 1);
+        remote.mark_failed();
+        assert_eq!(remote.current(), Some(v6));
+        // with every other candidate down, keep going round
+        remote.mark_failed();
+        assert_eq!(remote.current(), Some(scion));
+        remote.mark_ok();
+        assert!(remote.is_healthy(&scion));
+        remote.rotate();
+        assert_eq!(remote.current(), Some(ip));
+
+        // failed candidates are retried once the retry time has passed
+        let mut remote = RemoteEndpoint::new([scion, ip]).with_retry_after(Duration::ZERO);
+        remote.mark_failed();
+        assert!(remote.is_healthy(&scion));
+        assert!(RemoteEndpoint::new([]).current().is_none());
+
+        // connecting skips IP candidates and candidates that refuse
+        let server_ia = IsdAsn::from_str("1-ff00:0:110").unwrap();
+        let server = IpTcpTransport::new(server_ia);
+        let listener = ScionTcpListener::bind_with(&server, "1-ff00:0:110,127.0.0.1:0").unwrap();
+        let up = SocketAddr::SCION(listener.local_addr().unwrap());
+        let closed = {
+            let l = ScionTcpListener::bind_with(&server, "1-ff00:0:110,127.0.0.1:0").unwrap();
+            SocketAddr::SCION(l.local_addr().unwrap())
+        };
+        let mut remote = RemoteEndpoint::new([SocketAddr::from_str("127.0.0.1:1").unwrap(), closed, up]);
+        let client = IpTcpTransport::new(IsdAsn::from_str("2-ff00:0:220").unwrap());
+        let stream = ScionTcpStream::connect_endpoint_with(&client, &mut remote).unwrap();
+        assert_eq!(SocketAddr::SCION(stream.peer_addr().unwrap()), up);
+        assert_eq!(remote.current(), Some(up));
+        assert_eq!(remote.failures(&closed), 1);
+        assert_eq!(remote.failures(&SocketAddr::from_str("127.0.0.1:1").unwrap()), 0);
+    }
+
+    #[test]
+    fn parse_service_names_and_default_ports()
+    {
+        use crate::{register_service, service_port, SocketAddrV4, SocketAddrV6};
+
+        let a = SocketAddrScion::from_str("19-ffaa:1:1067,127.0.0.1:https").unwrap();
+        assert_eq!(a, SocketAddrScion::from_str("19-ffaa:1:1067,127.0.0.1:443").unwrap());
+        assert_eq!(SocketAddr::from_str("1-ff00:0:110,10.0.0.1:HTTP").unwrap().port(), 80);
+        assert!(SocketAddrScion::from_str("1-ff00:0:110,10.0.0.1:no-such-service").is_err());
+        assert!(SocketAddrScion::from_str("1-ff00:0:110,10.0.0.1:-https").is_err());
+        // the parsers of the std types stay with port numbers
+        assert!(SocketAddr::from_str("10.0.0.1:http").is_err());
+        assert!(SocketAddr::from_str("[::1]:ssh").is_err());
+        assert!(SocketAddrV4::from_str("1.2.3.4:https").is_err());
+        assert!(SocketAddrV6::from_str("[::1]:https").is_err());
+
+        assert_eq!(service_port("scion-daemon"), Some(30255));
+        assert_eq!(register_service("synth-test-svc", 7000).unwrap(), None);
+        assert_eq!(register_service("Synth-Test-Svc", 7001).unwrap(), Some(7000));
+        let addr = SocketAddrScion::from_str("1-ff00:0:110,10.0.0.1:synth-test-svc").unwrap();
+        assert_eq!(addr.port(), 7001);
+        for bad in ["", "8080", "1st", "a:b", "a]", "a,b", "-a", "a_b"] {
+            assert!(register_service(bad, 1).is_err(), "{}", bad);
+        }
+        assert_eq!(service_port("8080"), None);
+
+        assert_eq!(SocketAddrScion::parse_with_default_port("19-ffaa:1:1067,127.0.0.1", 443), Ok(a));
+        assert_eq!(SocketAddrScion::parse_with_default_port("19-ffaa:1:1067,127.0.0.1:https", 80), Ok(a));
+        assert_eq!(SocketAddrScion::parse_with_default_port("19-ffaa:1:1067,[::1]:8", 80).unwrap().port(), 8);
+        assert_eq!(SocketAddr::parse_with_default_port("19-ffaa:1:1067,127.0.0.1", 443), Ok(SocketAddr::SCION(a)));
+        assert_eq!(SocketAddr::parse_with_default_port("10.0.0.1", 53), SocketAddr::from_str("10.0.0.1:53"));
+        assert_eq!(SocketAddr::parse_with_default_port("10.0.0.1:54", 53), SocketAddr::from_str("10.0.0.1:54"));
+        assert_eq!(SocketAddr::parse_with_default_port("fe80::1", 53), SocketAddr::from_str("[fe80::1]:53"));
+        assert_eq!(SocketAddr::parse_with_default_port("[fe80::1]", 53), SocketAddr::from_str("[fe80::1]:53"));
+        assert_eq!(SocketAddr::parse_with_default_port("[fe80::1]:dns", 1), SocketAddr::from_str("[fe80::1]:53"));
+        assert!(SocketAddr::parse_with_default_port("10.0.0.1:", 53).is_err());
+        assert!(SocketAddr::parse_with_default_port("[fe80::1", 53).is_err());
+    }
+
+    #[test]
+    fn scmp_traceroute() {
+        use crate::path::{HopField, InfoField, Path};
+        use crate::scmp::{self, ScmpMessage, TracerouteHop};
+        use crate::udp_socket::{decode_scion_header, encode_scion_header};
+
+        let src_ia = IsdAsn::from_str("1-ff00:0:111").unwrap();
+        let core_ia = IsdAsn::from_str("1-ff00:0:110").unwrap();
+        let dst_ia = IsdAsn::from_str("1-ff00:0:112").unwrap();
+        let hop = |ingress, egress| HopField { exp_time: 63, cons_ingress: ingress, cons_egress: egress, ..HopField::default() };
+        // up segment 111 -> 110 against construction direction, down segment 110 -> 112
+        let raw = Path {
+            curr_inf: 0,
+            curr_hf: 0,
+            seg_len: [2, 2, 0],
+            info_fields: vec![
+                InfoField { cons_dir: false, ..InfoField::default() },
+                InfoField { cons_dir: true, ..InfoField::default() },
+            ],
+            hop_fields: vec![hop(11, 0), hop(0, 1), hop(0, 2), hop(21, 0)],
+        };
+
+        // a border router answering probes for interfaces 11 and 1, ignoring the others
+        let router = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut path = ScionPath::empty(src_ia);
+        path.dst = dst_ia;
+        path.path_type = PATH_TYPE_SCION;
+        path.raw = raw.to_bytes();
+        path.next_hop = Some(router.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            let mut buf = [0u8; 2048];
+            for _ in 0..4 {
+                let (len, from) = router.recv_from(&mut buf).unwrap();
+                let (src, next_hdr, hdr_len, l4_len) = decode_scion_header(&buf[..len]).unwrap();
+                assert_eq!(next_hdr, scmp::PROTO_SCMP);
+                let path = Path::decode_from(&buf[12 + 16 + 8..hdr_len]).unwrap();
+                let Ok(ScmpMessage::TracerouteRequest { id, seq }) =
+                    ScmpMessage::decode_from(&buf[hdr_len..hdr_len + l4_len])
+                else {
+                    panic!("not a traceroute request");
+                };
+                let alerted: Vec<_> = path
+                    .hop_fields
+                    .iter()
+                    .flat_map(|h| [(h.ingress_router_alert, h.cons_ingress), (h.egress_router_alert, h.cons_egress)])
+                    .filter(|&(alert, _)| alert)
+                    .map(|(_, ifid)| ifid)
+                    .collect();
+                assert_eq!(alerted.len(), 1);
+                let (ia, ifid) = match alerted[0] {
+                    11 => (src_ia, 11),
+                    1 => (core_ia, 1),
+                    _ => continue,
+                };
+                let me = ScionAddr::from_ia(ia, IpAddr::V4(Ipv4Addr::LOCALHOST));
+                let reply = ScmpMessage::TracerouteReply { id, seq, ia, interface: ifid }.encode(&me, &src);
+                let mut packet = encode_scion_header(&me, &src, scmp::PROTO_SCMP, 0, &[], reply.len()).unwrap();
+                packet.extend_from_slice(&reply);
+                router.send_to(&packet, from).unwrap();
+            }
+        });
+
+        let socket = ScionUdpSocket::bind(SocketAddrScion::from_ia(src_ia, IpAddr::V4(Ipv4Addr::LOCALHOST), 0)).unwrap();
+        let dst = ScionAddr::from_ia(dst_ia, IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)));
+        let hops = scmp::traceroute(&socket, &dst, &path, Duration::from_millis(300)).unwrap();
+        handle.join().unwrap();
+        let hops: Vec<_> = hops.into_iter().map(|h| h.map(|TracerouteHop { ia, interface, .. }| (ia, interface))).collect();
+        assert_eq!(hops, vec![Some((src_ia, 11)), Some((core_ia, 1)), None, None]);
+        assert_eq!(socket.underlay().read_timeout().unwrap(), None);
+        assert!(scmp::traceroute(&socket, &dst, &ScionPath::empty(src_ia), Duration::from_millis(1)).unwrap().is_empty());
+
+        let reply = ScmpMessage::TracerouteReply { id: 1, seq: 2, ia: core_ia, interface: 3 };
+        assert_eq!(ScmpMessage::decode_from(&reply.to_bytes()).unwrap(), reply);
+    }
+
+    #[test]
+    fn addr_family_visitors()
+    {
+        use crate::{AddrFamily, L3Addr, L3AddrVisitor, SocketAddrVisitor};
+
+        struct Port;
+        impl SocketAddrVisitor for Port {
+            type Output = (AddrFamily, u16);
+            fn visit_other(self, family: AddrFamily, addr: &SocketAddr) -> (AddrFamily, u16) {
+                (family, addr.port())
+            }
+            fn visit_scion(self, addr: &SocketAddrScion) -> (AddrFamily, u16) {
+                (AddrFamily::Scion, addr.port() + 1)
+            }
+        }
+
+        let v4 = SocketAddr::from_str("10.0.0.1:80").unwrap();
+        let v6 = SocketAddr::from_str("[::1]:443").unwrap();
+        let scion = SocketAddr::from_str("1-ff00:0:110,10.0.0.1:53").unwrap();
+        assert_eq!(v4.family(), AddrFamily::Ipv4);
+        assert_eq!(v6.family().to_string(), "ipv6");
+        assert_eq!(scion.family().as_str(), "scion");
+        assert_eq!(v4.visit(Port), (AddrFamily::Ipv4, 80));
+        assert_eq!(v6.visit(Port), (AddrFamily::Ipv6, 443));
+        assert_eq!(scion.visit(Port), (AddrFamily::Scion, 54));
+
+        struct IsScion;
+        impl L3AddrVisitor for IsScion {
+            type Output = bool;
+            fn visit_other(self, _: AddrFamily, _: &L3Addr) -> bool {
+                false
+            }
+            fn visit_scion(self, _: &ScionAddr) -> bool {
+                true
+            }
+        }
+        let host = L3Addr::IP(IpAddr::V6(crate::Ipv6Addr::LOCALHOST));
+        assert_eq!(host.family(), AddrFamily::Ipv6);
+        assert!(!host.visit(IsScion));
+        assert!(L3Addr::SCION(ScionAddr::from_str("1-ff00:0:110,10.0.0.1").unwrap()).visit(IsScion));
+    }
+
+    #[test]
+    fn wire_common_header() {
+        use crate::wire::{self, CommonHeader};
+
+        let hdr = CommonHeader {
+            version: 0,
+            traffic_class: 0xb8,
+            flow_id: 0xabcde,
+            next_header: 17,
+            header_len: 12 + 16 + 4 + 16 + 36,
+            payload_len: 1200,
+            path_type: PATH_TYPE_SCION,
+            dst_addr_type: wire::ADDR_TYPE_IPV4,
+            src_addr_type: wire::ADDR_TYPE_IPV6,
+        };
+        let bytes = hdr.to_bytes();
+        assert_eq!(bytes, [0x0b, 0x8a, 0xbc, 0xde, 17, 21, 0x04, 0xb0, 1, 0x03, 0, 0]);
+        assert_eq!(CommonHeader::decode_from(&bytes).unwrap(), hdr);
+        assert_eq!(hdr.address_header_len(), 36);
+        assert_eq!(hdr.path_len(), 36);
+
+        assert!(CommonHeader::decode_from(&bytes[..11]).is_err());
+        let mut v1 = bytes.clone();
+        v1[0] |= 0x10;
+        assert!(CommonHeader::decode_from(&v1).is_err());
+        assert!(CommonHeader { flow_id: 1 << 20, ..hdr }.encode_to(&mut [0u8; 12]).is_err());
+        assert!(CommonHeader { header_len: 62, ..hdr }.encode_to(&mut [0u8; 12]).is_err());
+        assert!(CommonHeader { header_len: 1024, ..hdr }.encode_to(&mut [0u8; 12]).is_err());
+        assert!(hdr.encode_to(&mut [0u8; 11]).is_err());
+    }
+
+    #[cfg(feature = "daemon")]
+    #[test]
+    fn daemon_addr() {
+        use crate::daemon::{DaemonAddr, DaemonClient, DEFAULT_DAEMON_ADDRESS};
+        use std::path::PathBuf;
+
+        let tcp = DaemonAddr::from_str(DEFAULT_DAEMON_ADDRESS).unwrap();
+        assert_eq!(tcp, DaemonAddr::default());
+        assert_eq!(DaemonAddr::from_str("tcp://127.0.0.1:30255"), Ok(tcp.clone()));
+        assert_eq!(tcp.to_string(), DEFAULT_DAEMON_ADDRESS);
+        let unix = DaemonAddr::from_str("unix:///run/scion/sciond.sock").unwrap();
+        assert_eq!(unix, DaemonAddr::Unix(PathBuf::from("/run/scion/sciond.sock")));
+        assert_eq!(unix.to_string(), "unix:///run/scion/sciond.sock");
+        assert!(DaemonAddr::from_str("unix://").is_err());
+        assert!(DaemonAddr::from_str("localhost").is_err());
+        let host = DaemonAddr::from_str("tcp://sciond:30255").unwrap();
+        assert_eq!(host, DaemonAddr::Host("sciond".to_string(), 30255));
+        assert_eq!(host.to_string(), "sciond:30255");
+        let localhost = DaemonAddr::Host("localhost".to_string(), 30255);
+        assert_eq!(DaemonAddr::from_str("localhost:30255"), Ok(localhost));
+        for bad in ["localhost:", ":30255", "localhost:65536", "tcp://a/b:30255", "[::1:30255"] {
+            assert!(DaemonAddr::from_str(bad).is_err(), "{}", bad);
+        }
+
+        let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        rt.block_on(async {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let port = listener.local_addr().unwrap().port();
+            assert!(DaemonClient::connect(&format!("localhost:{}", port)).await.is_ok());
+        });
+
+        #[cfg(unix)]
+        {
+            let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+            let dir = std::env::temp_dir().join(format!("scionnet-daemon-{}", std::process::id()));
+            std::fs::create_dir_all(&dir).unwrap();
+            let path = dir.join("sciond.sock");
+            rt.block_on(async {
+                let missing = DaemonAddr::Unix(dir.join("missing.sock"));
+                assert!(DaemonClient::connect_to(&missing).await.is_err());
+                let _listener = tokio::net::UnixListener::bind(&path).unwrap();
+                let addr = format!("unix://{}", path.display());
+                assert!(DaemonClient::connect(&addr).await.is_ok());
+            });
+            std::fs::remove_dir_all(&dir).unwrap();
+        }
+    }
+
+    #[test]
+    fn wire_udp_header_checksum() {
+        use crate::wire::{CommonHeader, UdpHeader, UDP_HEADER_LEN};
+
+        let src = ScionAddr::from_str("1-ff00:0:110,127.0.0.1").unwrap();
+        let dst = ScionAddr::from_str("1-ff00:0:110,fd00::1").unwrap();
+        let mut hdr = UdpHeader::new(40000, 53, 5).unwrap();
+        assert_eq!(hdr.length, 13);
+        hdr.set_checksum(&src, &dst, b"hello");
+        assert_ne!(hdr.checksum, 0);
+        assert!(hdr.verify_checksum(&src, &dst, b"hello"));
+        assert!(!hdr.verify_checksum(&src, &dst, b"hellO"));
+        assert_eq!(hdr.compute_checksum_vectored(&src, &dst, &[b"h", b"ell", b"o"]), hdr.checksum);
+        assert_eq!(UdpHeader::decode(&hdr.encode()).unwrap(), hdr);
+        assert!(UdpHeader::decode(&hdr.encode()[..7]).is_err());
+        assert!(UdpHeader::decode(&[0, 1, 0, 2, 0, 7, 0, 0]).is_err());
+        assert!(UdpHeader::new(1, 2, 65535).is_err());
+
+        // the sockets fill in the checksum
+        let socket = ScionUdpSocket::bind(SocketAddrScion::from_scion_addr(src, 0)).unwrap();
+        let raw = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let to = SocketAddrScion::from_ia(src.isd_asn(), IpAddr::V4(Ipv4Addr::LOCALHOST), raw.local_addr().unwrap().port());
+        for sent in [
+            socket.send_to(b"payload", to).unwrap(),
+            socket.send_vectored_to(&[std::io::IoSlice::new(b"pay"), std::io::IoSlice::new(b"load")], to).unwrap(),
+        ] {
+            assert_eq!(sent, 7);
+            let mut buf = [0u8; 256];
+            let (len, _) = raw.recv_from(&mut buf).unwrap();
+            let common = CommonHeader::decode_from(&buf).unwrap();
+            let udp = UdpHeader::decode(&buf[common.header_len..]).unwrap();
+            let payload = &buf[common.header_len + UDP_HEADER_LEN..len];
+            assert_eq!(payload, b"payload");
+            assert!(udp.verify_checksum(&ScionAddr::from(socket.local_addr().unwrap()), &ScionAddr::from(to), payload));
+        }
+    }
+
+    #[cfg(feature = "daemon")]
+    #[test]
+    fn scion_endpoint_path_cache() {
+        use crate::scion_endpoint::PathCache;
+        use std::time::{Instant, SystemTime};
+
+        let dst = IsdAsn::from(make_ia(2, 0xff00_0000_0220));
+        let mut path = ScionPath::empty(dst);
+        path.raw = vec![0; 8];
+        path.expiration = SystemTime::now() + Duration::from_secs(3600);
+
+        let mut cache = PathCache::new(Duration::from_secs(60), 2);
+        let now = Instant::now();
+        assert!(cache.get(dst, now).is_none());
+        cache.insert(dst, vec![path.clone()], now);
+        assert_eq!(cache.get(dst, now).unwrap().len(), 1);
+        assert!(cache.get(dst, now + Duration::from_secs(60)).is_none());
+
+        path.expiration = SystemTime::UNIX_EPOCH + Duration::from_secs(1);
+        cache.insert(dst, vec![path.clone()], now);
+        assert!(cache.get(dst, now).is_none());
+        cache.insert(dst, Vec::new(), now);
+        assert!(cache.get(dst, now).is_none());
+        cache.remove(dst);
+        assert!(cache.get(dst, now).is_none());
+
+        // the least recently used destination makes room for a new one
+        path.expiration = SystemTime::now() + Duration::from_secs(3600);
+        let ias: Vec<IsdAsn> = (1..=3).map(|asn| IsdAsn::from(make_ia(2, asn))).collect();
+        cache.insert(ias[0], vec![path.clone()], now);
+        cache.insert(ias[1], vec![path.clone()], now + Duration::from_secs(1));
+        assert!(cache.get(ias[0], now + Duration::from_secs(2)).is_some());
+        cache.insert(ias[2], vec![path.clone()], now + Duration::from_secs(3));
+        assert!(cache.get(ias[1], now + Duration::from_secs(3)).is_none());
+        assert_eq!(cache.paths().count(), 2);
+
+        // only destinations used since they were fetched are refreshed
+        let later = now + Duration::from_secs(50);
+        assert_eq!(cache.due(later, Duration::from_secs(5)), vec![]);
+        assert_eq!(cache.due(later, Duration::from_secs(10)), vec![ias[0]]);
+        path.expiration = SystemTime::now() + Duration::from_secs(30);
+        cache.insert(ias[0], vec![path.clone()], later);
+        assert!(cache.get(ias[0], later + Duration::from_secs(1)).is_some());
+        assert_eq!(cache.due(later, Duration::from_secs(5)), vec![]);
+        assert_eq!(cache.due(later, Duration::from_secs(30)), vec![ias[0]]);
+        // the other one goes once stale, as it is unused
+        assert_eq!(cache.due(now + Duration::from_secs(70), Duration::ZERO), vec![]);
+        assert_eq!(cache.paths().count(), 1);
+        cache.set_capacity(0);
+        assert_eq!(cache.paths().count(), 0);
+    }
+
+    #[test]
+    fn wire_packet_builder() {
+        use crate::path::{HopField, InfoField, Path};
+        use crate::udp_socket::decode_scion_header;
+        use crate::wire::{CommonHeader, PacketBuilder, UdpHeader, PATH_TYPE_EMPTY};
+
+        let src = SocketAddrScion::from_str("1-ff00:0:110,10.0.0.1:5000").unwrap();
+        let dst = SocketAddrScion::from_str("1-ff00:0:111,[2001:db8::2]:6000").unwrap();
+        let path = Path {
+            seg_len: [2, 0, 0],
+            info_fields: vec![InfoField { cons_dir: true, peer: false, seg_id: 7, timestamp: 1 }],
+            hop_fields: vec![
+                HopField { cons_egress: 1, ..HopField::default() },
+                HopField { cons_ingress: 2, ..HopField::default() },
+            ],
+            ..Path::default()
+        };
+        let builder = PacketBuilder::new(src, dst, path.clone()).traffic_class(0xb8).flow_id(0x12345);
+        let packet = builder.build(b"hello").unwrap();
+        assert_eq!(packet.len(), builder.packet_len(5));
+
+        let common = CommonHeader::decode_from(&packet).unwrap();
+        assert_eq!((common.traffic_class, common.flow_id), (0xb8, 0x12345));
+        assert_eq!(common.path_type, PATH_TYPE_SCION);
+        assert_eq!(common.header_len, 12 + 16 + 16 + 4 + path.encoded_len());
+        assert_eq!(Path::decode_from(&packet[common.header_len - path.encoded_len()..]).unwrap(), path);
+        let (from, next_header, header_len, l4_len) = decode_scion_header(&packet).unwrap();
+        assert_eq!((from, next_header), (ScionAddr::from(src), 17));
+        assert_eq!(header_len + l4_len, packet.len());
+        let udp = UdpHeader::decode(&packet[header_len..]).unwrap();
+        assert_eq!((udp.src_port, udp.dst_port), (5000, 6000));
+        assert!(udp.verify_checksum(&src.into(), &dst.into(), b"hello"));
+        assert_eq!(&packet[header_len + 8..], b"hello");
+
+        let local = PacketBuilder::empty_path(src, src).build(b"").unwrap();
+        assert_eq!(CommonHeader::decode_from(&local).unwrap().path_type, PATH_TYPE_EMPTY);
+        assert_eq!(local.len(), 12 + 16 + 8 + 8);
+        assert!(PacketBuilder::empty_path(src, dst).flow_id(1 << 20).build(b"").is_err());
+        let mut short = [0u8; 16];
+        assert!(builder.build_into(b"hello", &mut short).is_err());
+    }
+
+    #[cfg(feature = "topology")]
+    #[test]
+    fn topology_canonical_json() {
+        use crate::Topology;
+
+        // the topology of ff00:0:110 in the "tiny" test topology of scionproto,
+        // as generated by its topology generator
+        let json = r#"{
+          "attributes": ["authoritative", "core", "issuing", "voting"],
+          "isd_as": "1-ff00:0:110",
+          "mtu": 1472,
+          "test_dispatcher": true,
+          "dispatched_ports": "31000-32767",
+          "control_service": {
+            "cs1-ff00_0_110-1": { "addr": "127.0.0.11:31000" }
+          },
+          "discovery_service": {
+            "cs1-ff00_0_110-1": { "addr": "127.0.0.11:31000" }
+          },
+          "border_routers": {
+            "br1-ff00_0_110-1": {
+              "internal_addr": "127.0.0.9:31002",
+              "interfaces": {
+                "1": {
+                  "underlay": { "local": "127.0.0.4:50000", "remote": "127.0.0.5:50000" },
+                  "isd_as": "1-ff00:0:111",
+                  "link_to": "child",
+                  "mtu": 1280,
+                  "bfd": { "disable": true }
+                },
+                "2": {
+                  "underlay": { "public": "[fd00:f00d:cafe::7f00:4]:50000" },
+                  "isd_as": "1-ff00:0:112",
+                  "link_to": "child",
+                  "mtu": 1472
+                }
+              }
+            }
+          }
+        }"#;
+        let topo = Topology::from_json(json).unwrap();
+        let canonical = topo.to_json();
+        assert_eq!(Topology::from_json(&canonical).unwrap(), topo);
+        assert_eq!(Topology::from_json(&canonical).unwrap().to_json(), canonical);
+        // only the `public` underlay address changes
+        let written: serde_json::Value = serde_json::from_str(&canonical).unwrap();
+        let json_local = json.replace("\"public\"", "\"local\"");
+        let read: serde_json::Value = serde_json::from_str(&json_local).unwrap();
+        assert_eq!(written, read);
+        let top = ["attributes", "border_routers", "control_service", "discovery_service",
+                   "dispatched_ports", "isd_as", "mtu", "test_dispatcher"];
+        let keys: Vec<usize> = top
+            .iter()
+            .map(|k| canonical.find(&format!("\n  \"{}\"", k)).unwrap())
+            .collect();
+        assert!(keys.windows(2).all(|w| w[0] < w[1]));
+
+        // the unknown members of removed entries go with them
+        let mut moved = topo.clone();
+        let router = moved.border_routers.remove("br1-ff00_0_110-1").unwrap();
+        moved.border_routers.insert("br1-ff00_0_110-2".to_string(), router);
+        let moved_json = moved.to_json();
+        assert!(!moved_json.contains("br1-ff00_0_110-1") && !moved_json.contains("bfd"));
+        assert!(moved_json.contains("discovery_service"));
+
+        let mut edge = topo.clone();
+        edge.core = false;
+        edge.dispatched_ports = None;
+        edge.control_services.clear();
+        let edge_json = edge.to_json();
+        assert!(!edge_json.contains("\"core\"") && !edge_json.contains("control_service"));
+        assert!(edge_json.contains("\"voting\""));
+        assert_eq!(Topology::from_json(&edge_json).unwrap(), edge);
+
+        let path = std::env::temp_dir().join(format!("scionnet-topology-{}.json", std::process::id()));
+        topo.to_file(&path).unwrap();
+        assert_eq!(Topology::from_file(&path).unwrap(), topo);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn wire_parsed_packet() {
+        use crate::path::{HopField, InfoField, Path};
+        use crate::wire::{PacketBuilder, ParsedPacket};
+
+        let src = SocketAddrScion::from_str("1-ff00:0:110,10.0.0.1:5000").unwrap();
+        let dst = SocketAddrScion::from_str("2-ff00:0:220,[2001:db8::2]:6000").unwrap();
+        let path = Path {
+            seg_len: [1, 1, 0],
+            info_fields: vec![
+                InfoField { cons_dir: false, peer: false, seg_id: 1, timestamp: 2 },
+                InfoField { cons_dir: true, peer: false, seg_id: 3, timestamp: 4 },
+            ],
+            hop_fields: vec![
+                HopField { cons_egress: 1, ..HopField::default() },
+                HopField { cons_ingress: 2, ..HopField::default() },
+            ],
+            ..Path::default()
+        };
+        let mut packet = PacketBuilder::new(src, dst, path.clone()).flow_id(9).build(b"hello").unwrap();
+        let len = packet.len();
+        packet.extend_from_slice(&[0; 4]);
+
+        let parsed = ParsedPacket::parse(&packet).unwrap();
+        assert_eq!((parsed.src, parsed.dst), (src, dst));
+        assert_eq!(parsed.payload, b"hello");
+        assert_eq!(parsed.packet_len(), len);
+        assert!(parsed.verify_checksum());
+        assert_eq!(parsed.path().unwrap(), Some(path.clone()));
+
+        let reply = parsed.reply().unwrap().build(b"hi").unwrap();
+        let reply = ParsedPacket::parse(&reply).unwrap();
+        assert_eq!((reply.src, reply.dst, reply.common.flow_id), (dst, src, 9));
+        assert_eq!(reply.path().unwrap(), Some(path.reversed().unwrap()));
+
+        let local = PacketBuilder::empty_path(src, src).build(b"").unwrap();
+        let local = ParsedPacket::parse(&local).unwrap();
+        assert!(local.payload.is_empty());
+        assert_eq!(local.path().unwrap(), None);
+
+        let err = |p: &[u8]| ParsedPacket::parse(p).unwrap_err().to_string();
+        assert!(err(&packet[..len - 1]).contains("payload length"));
+        assert!(err(&packet[..20]).contains("header longer"));
+        let mut bad = packet.clone();
+        bad[4] = 1;
+        assert!(err(&bad).contains("does not carry UDP"));
+        let mut bad = packet.clone();
+        bad[9] = 0x10;
+        assert!(ParsedPacket::parse(&bad).is_err());
+        let mut bad = packet[..len].to_vec();
+        bad[len - 1] ^= 1;
+        assert!(!ParsedPacket::parse(&bad).unwrap().verify_checksum());
+    }
+
+    #[test]
+    fn underlay_endhost_port() {
+        use crate::scmp::ScmpMessage;
+        use crate::underlay::{dst_port, underlay_port, ShimDispatcher, ENDHOST_PORT};
+        use crate::wire::PacketBuilder;
+
+        assert_eq!(underlay_port(31000, Some((31000, 32767))), 31000);
+        assert_eq!(underlay_port(8080, Some((31000, 32767))), ENDHOST_PORT);
+        assert_eq!(underlay_port(8080, None), 8080);
+
+        let a = SocketAddrScion::from_str("1-ff00:0:110,127.0.0.1:40001").unwrap();
+        let b = SocketAddrScion::from_str("1-ff00:0:110,127.0.0.1:40002").unwrap();
+        let udp = PacketBuilder::empty_path(a, b).build(b"x").unwrap();
+        assert_eq!(dst_port(&udp), Some(40002));
+        let (src, dst) = (ScionAddr::from(a), ScionAddr::from(b));
+        let scmp = |msg: ScmpMessage| {
+            let body = msg.encode(&src, &dst);
+            let mut packet =
+                crate::udp_socket::encode_scion_header(&src, &dst, 202, 0, &[], body.len()).unwrap();
+            packet.extend_from_slice(&body);
+            packet
+        };
+        assert_eq!(dst_port(&scmp(ScmpMessage::EchoRequest { id: 7, seq: 1, data: vec![] })), Some(7));
+        // an error about a packet b sent to a is meant for b's port
+        let quote = PacketBuilder::empty_path(b, a).build(b"x").unwrap();
+        let error = scmp(ScmpMessage::DestinationUnreachable { code: 4, quote });
+        assert_eq!(dst_port(&error), Some(40002));
+        assert_eq!(dst_port(&udp[..20]), None);
+
+        let shim = ShimDispatcher::bind_addr("127.0.0.1:0".parse().unwrap()).unwrap();
+        let shim_port = shim.local_addr().unwrap().port();
+        let receiver = ScionUdpSocket::bind("1-ff00:0:110,127.0.0.1:0").unwrap();
+        receiver.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let port = receiver.local_addr().unwrap().port();
+        let sender = ScionUdpSocket::bind("1-ff00:0:110,127.0.0.1:0").unwrap();
+        // the shim does not listen on the real end host port, so have the
+        // sender hand the packet to it directly
+        let to = SocketAddrScion::from_ia(IsdAsn::from(make_ia(1, 0xff00_0000_0110)), IpAddr::V4(Ipv4Addr::LOCALHOST), port);
+        let packet =
+            PacketBuilder::empty_path(sender.local_addr().unwrap(), to).build(b"via shim").unwrap();
+        sender.underlay().send_to(&packet, ("127.0.0.1", shim_port)).unwrap();
+        assert_eq!(shim.forward_one().unwrap(), Some(port));
+        let mut buf = [0u8; 16];
+        let (n, from) = receiver.recv_from(&mut buf).unwrap();
+        assert_eq!(&buf[..n], b"via shim");
+        assert_eq!(from, SocketAddr::SCION(sender.local_addr().unwrap()));
+        // a packet for the shim's own port is dropped instead of looping
+        let to_shim = SocketAddrScion::from_ia(IsdAsn::from(to.ia()), to.ip().unwrap(), shim_port);
+        let packet = PacketBuilder::empty_path(sender.local_addr().unwrap(), to_shim);
+        let packet = packet.build(b"loop").unwrap();
+        sender.underlay().send_to(&packet, ("127.0.0.1", shim_port)).unwrap();
+        assert_eq!(shim.forward_one().unwrap(), None);
+        assert_eq!(shim.send_errors(), 0);
+
+        sender.set_dispatched_ports(Some((port, port)));
+        assert_eq!(sender.dispatched_ports(), Some((port, port)));
+        sender.send_to(b"direct", to).unwrap();
+        let (n, _) = receiver.recv_from(&mut buf).unwrap();
+        assert_eq!(&buf[..n], b"direct");
+    }
+
+    #[test]
+    fn scmp_responder() {
+        use crate::path::{HopField, InfoField, Path};
+        use crate::scmp::{ScmpMessage, ScmpResponder, PROTO_SCMP};
+        use crate::udp_socket::{decode_scion_header, encode_scion_header};
+        use crate::underlay::ShimDispatcher;
+        use crate::wire::{CommonHeader, PacketBuilder};
+        use crate::{RateLimitKey, RateLimiter};
+
+        let pinger = ScionAddr::from_str("1-ff00:0:110,10.0.0.1").unwrap();
+        let host = ScionAddr::from_str("1-ff00:0:111,10.0.0.2").unwrap();
+        let path = Path {
+            seg_len: [2, 0, 0],
+            curr_hf: 1,
+            info_fields: vec![InfoField { cons_dir: true, peer: false, seg_id: 1, timestamp: 2 }],
+            hop_fields: vec![
+                HopField { cons_egress: 1, ..HopField::default() },
+                HopField { cons_ingress: 2, ..HopField::default() },
+            ],
+            ..Path::default()
+        };
+        let request = |msg: ScmpMessage, path_type: u8, raw: &[u8]| {
+            let body = msg.encode(&pinger, &host);
+            let mut packet =
+                encode_scion_header(&pinger, &host, PROTO_SCMP, path_type, raw, body.len()).unwrap();
+            packet.extend_from_slice(&body);
+            packet
+        };
+        let ping = ScmpMessage::EchoRequest { id: 9, seq: 1, data: b"ping".to_vec() };
+        let echo = request(ping, PATH_TYPE_SCION, &path.to_bytes());
+
+        let responder = ScmpResponder::new();
+        let reply = responder.reply(&echo).unwrap();
+        let (from, next_hdr, hdr_len, l4_len) = decode_scion_header(&reply).unwrap();
+        assert_eq!((from, next_hdr), (host, PROTO_SCMP));
+        let common = CommonHeader::decode_from(&reply).unwrap();
+        let raw = &reply[hdr_len - common.path_len()..hdr_len];
+        assert_eq!(Path::decode_from(raw).unwrap(), path.reversed().unwrap());
+        let body = &reply[hdr_len..hdr_len + l4_len];
+        assert!(crate::scmp::verify_checksum(body, &host, &pinger));
+        assert_eq!(
+            ScmpMessage::decode_from(body).unwrap(),
+            ScmpMessage::EchoReply { id: 9, seq: 1, data: b"ping".to_vec() }
+        );
+
+        let trace = request(ScmpMessage::TracerouteRequest { id: 9, seq: 2 }, 0, &[]);
+        let reply = responder.reply(&trace).unwrap();
+        let (_, _, hdr_len, _) = decode_scion_header(&reply).unwrap();
+        assert!(matches!(
+            ScmpMessage::decode_from(&reply[hdr_len..]).unwrap(),
+            ScmpMessage::TracerouteReply { id: 9, seq: 2, interface: 0, .. }
+        ));
+        assert!(ScmpResponder::new().with_traceroute(false).reply(&trace).is_none());
+
+        // replies, bad checksums and UDP are not answered
+        let mut corrupt = echo.clone();
+        *corrupt.last_mut().unwrap() ^= 1;
+        assert!(responder.reply(&corrupt).is_none());
+        let pong = request(ScmpMessage::EchoReply { id: 9, seq: 1, data: vec![] }, 0, &[]);
+        assert!(responder.reply(&pong).is_none());
+        let a = SocketAddrScion::from_scion_addr(pinger, 1);
+        assert!(responder.reply(&PacketBuilder::empty_path(a, a).build(b"x").unwrap()).is_none());
+
+        let limited = ScmpResponder::new().with_rate_limit(RateLimiter::new(RateLimitKey::Addr, 1, 1));
+        assert!(limited.reply(&echo).is_some());
+        assert!(limited.reply(&echo).is_none());
+
+        let shim = ShimDispatcher::bind_addr("127.0.0.1:0".parse().unwrap())
+            .unwrap()
+            .with_responder(ScmpResponder::new());
+        let client = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        client.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        client.send_to(&echo, shim.local_addr().unwrap()).unwrap();
+        assert_eq!(shim.forward_one().unwrap(), None);
+        let mut buf = [0u8; 256];
+        let (n, _) = client.recv_from(&mut buf).unwrap();
+        assert_eq!(buf[..n], responder.reply(&echo).unwrap()[..]);
+    }
+
+    #[test]
+    fn weighted_balancer() {
+        use crate::{IaAffinity, WeightedBalancer};
+
+        let a = SocketAddrScion::from_str("1-ff00:0:110,10.0.0.1:443").unwrap();
+        let b = SocketAddrScion::from_str("1-ff00:0:111,10.0.0.2:443").unwrap();
+        let c = SocketAddrScion::from_str("1-ff00:0:111,10.0.0.3:443").unwrap();
+        let mut balancer = WeightedBalancer::new();
+        assert_eq!(balancer.pick(&1u32.to_be_bytes()), None);
+        balancer.add(a, 1);
+        balancer.add(b, 3);
+        balancer.add(c, 0);
+        assert_eq!(balancer.len(), 3);
+
+        let mut counts = std::collections::HashMap::new();
+        for flow in 0..4000u32 {
+            *counts.entry(balancer.pick(&flow.to_be_bytes()).unwrap()).or_insert(0) += 1;
+        }
+        assert!(!counts.contains_key(&c));
+        assert!((2700..3300).contains(&counts[&b]), "{:?}", counts);
+
+        // flows of a backend that goes down move, the others stay
+        let flows = |balancer: &WeightedBalancer| -> Vec<_> {
+            (0..100u32).map(|f| balancer.pick(&f.to_be_bytes()).unwrap()).collect()
+        };
+        let before = flows(&balancer);
+        assert!(balancer.mark_down(&a));
+        assert!(!balancer.is_healthy(&a));
+        for (f, was) in before.iter().enumerate() {
+            let now = balancer.pick(&(f as u32).to_be_bytes()).unwrap();
+            assert!(now == b && (*was == a || *was == b));
+        }
+        assert!(balancer.mark_up(&a));
+        assert_eq!(flows(&balancer), before);
+
+        assert_eq!(balancer.check_health(|addr| *addr != b), 2);
+        assert_eq!(balancer.pick("x"), Some(a));
+        balancer.add(c, 1);
+        assert_eq!(balancer.weight(&c), Some(1));
+
+        let mut affine = balancer.clone().with_affinity(IaAffinity::Prefer(IsdAsn::from(b.ia())));
+        assert_eq!(affine.pick("x"), Some(c));
+        affine.mark_down(&c);
+        assert_eq!(affine.pick("x"), Some(a));
+        affine.set_affinity(IaAffinity::Require(IsdAsn::from(b.ia())));
+        assert_eq!(affine.pick("x"), None);
+        assert!(affine.remove(&c) && !affine.remove(&c));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn dispatcher_registration() {
+        use crate::dispatcher::{encode_frame, read_frame, DispatcherSocket, Registration};
+        use crate::wire::PacketBuilder;
+        use std::io::Write;
+        use std::os::unix::net::UnixListener;
+
+        let public: std::net::SocketAddr = "10.0.0.1:40000".parse().unwrap();
+        let ia = IsdAsn::from(make_ia(1, 0xff00_0000_0110));
+        let reg = Registration { ia, public, bind: None, svc: None };
+        let mut expected = vec![0x03, 17, 0, 1, 0xff, 0, 0, 0, 1, 0x10, 0x9c, 0x40, 1, 10, 0, 0, 1];
+        assert_eq!(reg.to_bytes(), expected);
+        let bound = Registration { bind: Some("192.168.0.1:40001".parse().unwrap()), svc: Some(2), ..reg };
+        expected[0] |= 0x04;
+        expected.extend_from_slice(&[0x9c, 0x41, 1, 192, 168, 0, 1, 0, 2]);
+        assert_eq!(bound.to_bytes(), expected);
+
+        let frame = encode_frame(Some("[::1]:30041".parse().unwrap()), b"abc").unwrap();
+        assert_eq!(frame.len(), 13 + 18 + 3);
+        let (addr, payload) = read_frame(&frame[..]).unwrap();
+        assert_eq!((addr, &payload[..]), (Some("[::1]:30041".parse().unwrap()), &b"abc"[..]));
+        let mut bad = frame.clone();
+        bad[0] = 0;
+        assert!(read_frame(&bad[..]).is_err());
+        // a length of 4 GiB fails without waiting for the payload
+        let mut huge = frame.clone();
+        huge[9..13].copy_from_slice(&[0xff; 4]);
+        let err = read_frame(&huge[..]).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+        let dir = std::env::temp_dir().join(format!("scionnet-dispatcher-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("default.sock");
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        let peer = SocketAddrScion::from_str("1-ff00:0:110,10.0.0.2:5000").unwrap();
+        let dispatcher = thread::spawn(move || {
+            let (mut conn, _) = listener.accept().unwrap();
+            let (addr, registration) = read_frame(&mut conn).unwrap();
+            assert_eq!(addr, None);
+            assert_eq!(&registration[10..12], &[0, 0]);
+            conn.write_all(&encode_frame(None, &[0x9c, 0x40]).unwrap()).unwrap();
+            let local = SocketAddrScion::from_str("1-ff00:0:110,10.0.0.1:40000").unwrap();
+            let packet = PacketBuilder::empty_path(peer, local).build(b"hi").unwrap();
+            let from = "10.0.0.2:5000".parse().unwrap();
+            conn.write_all(&encode_frame(Some(from), &packet).unwrap()).unwrap();
+            read_frame(&mut conn).unwrap()
+        });
+
+        let socket = DispatcherSocket::bind_with(&path, "1-ff00:0:110,10.0.0.1:0").unwrap();
+        socket.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        assert_eq!(socket.local_addr().unwrap().port(), 40000);
+        let mut buf = [0u8; 8];
+        assert_eq!(socket.recv_from(&mut buf).unwrap(), (2, SocketAddr::SCION(peer)));
+        assert_eq!(&buf[..2], b"hi");
+        socket.send_to(b"hey", peer).unwrap();
+        let (next_hop, packet) = dispatcher.join().unwrap();
+        assert_eq!(next_hop, Some("10.0.0.2:5000".parse().unwrap()));
+        let parsed = crate::wire::ParsedPacket::parse(&packet).unwrap();
+        assert_eq!((parsed.dst, parsed.payload), (peer, &b"hey"[..]));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature = "topology")]
+    #[test]
+    fn underlay_next_hop() {
+        use crate::path::{HopField, InfoField, Path};
+        use crate::Topology;
+
+        let topo = Topology::from_json(r#"{
+            "isd_as": "1-ff00:0:110",
+            "mtu": 1472,
+            "dispatched_ports": "31000-32767",
+            "border_routers": {
+                "br1": {
+                    "internal_addr": "127.0.0.9:31002",
+                    "interfaces": {
+                        "1": { "underlay": { "local": "127.0.0.4:50000" }, "isd_as": "1-ff00:0:111",
+                               "link_to": "child", "mtu": 1280 },
+                        "2": { "underlay": { "local": "127.0.0.4:50002" }, "isd_as": "1-ff00:0:112",
+                               "link_to": "child", "mtu": 1280 }
+                    }
+                },
+                "br2": {
+                    "internal_addr": "127.0.0.10:31004",
+                    "interfaces": {
+                        "3": { "underlay": { "local": "127.0.0.5:50000" }, "isd_as": "2-ff00:0:210",
+                               "link_to": "core", "mtu": 1472 }
+                    }
+                }
+            }
+        }"#).unwrap();
+        let mut path = Path {
+            seg_len: [2, 0, 0],
+            info_fields: vec![InfoField { cons_dir: true, peer: false, seg_id: 0, timestamp: 0 }],
+            hop_fields: vec![
+                HopField { cons_egress: 3, ..HopField::default() },
+                HopField { cons_ingress: 7, ..HopField::default() },
+            ],
+            ..Path::default()
+        };
+        let next_hop = |dst: &str, path: &Path| {
+            let dst = SocketAddrScion::from_str(dst).unwrap();
+            dst.underlay_next_hop(&topo, path).map(|hop| hop.to_string())
+        };
+        let remote = "2-ff00:0:210,10.0.0.2:8080";
+        assert_eq!(next_hop(remote, &path).unwrap(), "127.0.0.10:31004");
+        path.info_fields[0].cons_dir = false;
+        path.hop_fields[0] = HopField { cons_ingress: 1, ..HopField::default() };
+        assert_eq!(next_hop(remote, &path).unwrap(), "127.0.0.9:31002");
+        path.hop_fields[0].cons_ingress = 9;
+        assert_eq!(next_hop(remote, &path).unwrap_err().kind(), std::io::ErrorKind::NotFound);
+        assert!(next_hop(remote, &Path::default()).is_err());
+
+        assert_eq!(next_hop("1-ff00:0:110,10.0.0.3:31005", &Path::default()).unwrap(), "10.0.0.3:31005");
+        assert_eq!(next_hop("1-ff00:0:110,10.0.0.3:8080", &Path::default()).unwrap(), "10.0.0.3:30041");
+    }
+
+    #[test]
+    fn parse_any() {
+        use crate::{parse_any, AddrKind, IpNet, Parsed, ScionNet};
+
+        type Check = fn(&Parsed) -> bool;
+        let cases: [(&str, Check); 14] = [
+            ("1-ff00:0:110", |p| matches!(p, Parsed::Ia(_))),
+            ("10.0.0.1", |p| matches!(p, Parsed::Ip(IpAddr::V4(_)))),
+            ("::1", |p| matches!(p, Parsed::Ip(IpAddr::V6(_)))),
+            ("10.0.0.0/8", |p| matches!(p, Parsed::IpNet(IpNet::V4(_)))),
+            ("2001:db8::/32", |p| matches!(p, Parsed::IpNet(IpNet::V6(_)))),
+            ("1-ff00:0:110,10.0.0.1", |p| matches!(p, Parsed::Scion(_))),
+            ("1-ff00:0:110,[::1]", |p| matches!(p, Parsed::Scion(_))),
+            ("1-ff00:0:110,::1:80", |p| matches!(p, Parsed::Scion(_))),
+            ("1-ff00:0:110,CS", |p| matches!(p, Parsed::Scion(_))),
+            ("1-ff00:0:110,10.0.0.0/8", |p| matches!(p, Parsed::ScionNet(_))),
+            ("10.0.0.1:53", |p| matches!(p, Parsed::Socket(SocketAddr::V4(_)))),
+            ("[::1]:53", |p| matches!(p, Parsed::Socket(SocketAddr::V6(_)))),
+            ("1-ff00:0:110,[::1]:53", |p| matches!(p, Parsed::Socket(SocketAddr::SCION(_)))),
+            ("[1-ff00:0:110,CS]:53", |p| matches!(p, Parsed::Socket(SocketAddr::SCION(_)))),
+        ];
+        for (s, is) in cases {
+            let parsed = parse_any(s).unwrap();
+            assert!(is(&parsed), "{} parsed as {:?}", s, parsed);
+            assert_eq!(parsed.to_string().parse::<Parsed>().unwrap(), parsed, "{}", s);
+        }
+
+        let net = ScionNet::from_str("1-ff00:0:110,2001:db8::/32").unwrap();
+        assert_eq!(net.to_string(), "1-ff00:0:110,2001:db8::/32");
+        assert_eq!(format!("{:<28}|", net), "1-ff00:0:110,2001:db8::/32  |");
+        let longest = "65535-ffff:ffff:ffff,ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff/128";
+        let padded = format!("{:>65}", ScionNet::from_str(longest).unwrap());
+        assert_eq!(padded, format!(" {}", longest));
+        assert!(net.contains(&ScionAddr::from_str("1-ff00:0:110,[2001:db8::1]").unwrap()));
+        assert!(!net.contains(&ScionAddr::from_str("1-ff00:0:111,[2001:db8::1]").unwrap()));
+        assert!(!net.contains(&ScionAddr::from_str("1-ff00:0:110,CS").unwrap()));
+        assert!(IpNet::from_str("10.0.0.1/8").is_err());
+
+        // the error is the one of the parser getting furthest
+        let err = parse_any("1-ff00:0:110,10.0.0.1/33").unwrap_err();
+        assert_eq!((err.kind(), err.offset()), (&AddrKind::ScionNet, Some(24)));
+        assert_eq!(parse_any("1-ff00:0:110,").unwrap_err().kind(), &AddrKind::Scion);
+        assert_eq!(parse_any("").unwrap_err().kind(), &AddrKind::Ia);
+    }
+
+    #[test]
+    fn path_db_roundtrip() {
+        use crate::path_db::{self, PathDb};
+        use crate::PathInterface;
+        use std::time::SystemTime;
+
+        let src = IsdAsn::from(make_ia(1, 0xff00_0000_0110));
+        let path = |dst: u64, raw: Vec<u8>, expires: u64| {
+            let dst = IsdAsn::from(make_ia(2, dst));
+            let mut path = ScionPath::empty(src);
+            path.dst = dst;
+            path.raw = raw;
+            path.next_hop = Some("10.0.0.1:31002".parse().unwrap());
+            path.interfaces =
+                vec![PathInterface { ia: src, id: 1 }, PathInterface { ia: dst, id: 7 }];
+            path.mtu = 1472;
+            path.expiration = SystemTime::UNIX_EPOCH + Duration::from_secs(expires);
+            path.latency = vec![Some(Duration::from_millis(5)), None];
+            path
+        };
+        let far = 4_000_000_000;
+        let a = path(0xff00_0000_0220, vec![1; 24], far);
+        let b = path(0xff00_0000_0210, vec![2; 36], far);
+        let c = path(0xff00_0000_0220, vec![3; 24], 1);
+        let paths = vec![a.clone(), b.clone(), c.clone(), a.clone()];
+
+        let file = std::env::temp_dir().join(format!("scionnet-path-db-{}", std::process::id()));
+        path_db::write(&file, &paths).unwrap();
+        let db = PathDb::open(&file).unwrap();
+        assert_eq!(db.len(), 4);
+        assert_eq!(db.ias().collect::<Vec<_>>(), vec![b.dst, a.dst]);
+        let to_a: Vec<_> = db.paths_to(a.dst).collect();
+        assert_eq!(to_a.len(), 3);
+        assert_eq!(to_a[0].to_path(), a);
+        assert_eq!(to_a[1].raw(), &c.raw[..]);
+        assert!(to_a[1].is_expired(SystemTime::now()));
+        assert_eq!(db.paths_to(b.dst).next().unwrap().to_path(), b);
+        assert_eq!(db.paths_to(src).count(), 0);
+        drop(db);
+
+        assert_eq!(path_db::compact(&file, &file, SystemTime::now()).unwrap(), 2);
+        let db = PathDb::open(&file).unwrap();
+        assert_eq!(db.iter().map(|p| p.to_path()).collect::<Vec<_>>(), vec![b, a]);
+        drop(db);
+        std::fs::remove_file(&file).unwrap();
+
+        let bytes = path_db::encode(&paths).unwrap();
+        assert!(PathDb::from_bytes(bytes.clone()).is_ok());
+        let mut flipped = bytes.clone();
+        flipped[100] ^= 1;
+        assert!(PathDb::from_bytes(flipped).is_err());
+        let mut magic = bytes.clone();
+        magic[0] = b'X';
+        assert!(PathDb::from_bytes(magic).is_err());
+        assert!(PathDb::from_bytes(bytes[..bytes.len() - 1].to_vec()).is_err());
+        assert!(PathDb::from_bytes(Vec::new()).is_err());
+        assert!(PathDb::from_bytes(path_db::encode(&[]).unwrap()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn host_addr_svc() {
+        use crate::udp_socket::{decode_host, encode_host};
+        use crate::wire::ADDR_TYPE_SVC;
+        use crate::{HostAddr, Svc};
+
+        let addr = ScionAddr::from_str("19-ffaa:1:1067,CS").unwrap();
+        assert_eq!(addr.get_host(), &HostAddr::Svc(Svc::CS));
+        assert_eq!(addr.ip(), None);
+        assert_eq!(addr.to_string(), "19-ffaa:1:1067,CS");
+        assert_eq!(ScionAddr::from_slug(&addr.to_slug()).unwrap(), addr);
+
+        let multicast = ScionAddr::from_str("1-ff00:0:110,DS_M").unwrap();
+        assert_eq!(multicast.get_host().svc(), Some(Svc::DS.multicast()));
+        assert_eq!(multicast.to_string(), "1-ff00:0:110,DS_M");
+        assert_eq!(ScionAddr::from_slug(&multicast.to_slug()).unwrap(), multicast);
+        assert_eq!(Svc::from_str("0x0003").unwrap().to_string(), "0x0003");
+        assert_eq!(Svc::from_str("Wildcard"), Ok(Svc::WILDCARD));
+        assert!(Svc::from_str("cs").is_err());
+        assert!(Svc::from_str("0x8001").is_err());
+        assert!(ScionAddr::from_str("1-ff00:0:110,XY").is_err());
+
+        let sock = SocketAddrScion::from_str("1-ff00:0:110,CS:30252").unwrap();
+        assert_eq!(sock.host(), &HostAddr::Svc(Svc::CS));
+        assert_eq!(sock.port(), 30252);
+        assert_eq!(sock.to_string(), "1-ff00:0:110,CS:30252");
+
+        let ip = ScionAddr::from_str("1-ff00:0:110,10.0.0.1").unwrap();
+        assert_eq!(*ip.get_host(), IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)));
+        assert_eq!(HostAddr::from_str("10.0.0.1").unwrap(), *ip.get_host());
+
+        let (type_len, raw) = encode_host(addr.get_host());
+        assert_eq!((type_len, &raw[..]), (ADDR_TYPE_SVC, &[0, 2, 0, 0][..]));
+        assert_eq!(decode_host(type_len, &raw), Some((HostAddr::Svc(Svc::CS), 4)));
+        assert_eq!(decode_host(type_len, &raw[..2]), None);
+        assert!(crate::ScionUdpSocket::bind(sock).is_err());
+    }
+
+    #[test]
+    fn unspecified_port() {
+        use crate::{RemoteEndpoint, SocketAddrV4};
+
+        let any = SocketAddrScion::parse_lenient("19-ffaa:1:1067,127.0.0.1").unwrap();
+        assert!(any.is_unspecified_port());
+        assert_eq!(any.to_string(), "19-ffaa:1:1067,127.0.0.1:0");
+        assert_eq!(SocketAddrScion::from_str(&any.to_string()), Ok(any));
+        assert!(SocketAddrScion::from_str("19-ffaa:1:1067,127.0.0.1").is_err());
+        assert!(!SocketAddrScion::parse_lenient("1-ff00:0:110,CS:30252")
+            .unwrap()
+            .is_unspecified_port());
+
+        let ip = SocketAddr::parse_lenient("10.0.0.1").unwrap();
+        assert_eq!(ip, SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(10, 0, 0, 1), 0)));
+        assert!(ip.is_unspecified_port());
+        assert!(SocketAddr::parse_lenient("[::1]").unwrap().is_unspecified_port());
+
+        let socket = ScionUdpSocket::bind(any).unwrap();
+        let local = socket.local_addr().unwrap();
+        assert!(!local.is_unspecified_port());
+        let err = socket.send_to(b"x", any).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+
+        let remote = RemoteEndpoint::new([SocketAddr::SCION(any), SocketAddr::SCION(local)]);
+        assert_eq!(remote.len(), 1);
+    }
+
+    #[test]
+    fn scion_addr_wire() {
+        use crate::wire::{ADDR_TYPE_IPV4, ADDR_TYPE_IPV6, ADDR_TYPE_SVC};
+
+        let dst = ScionAddr::from_str("1-ff00:0:110,[fe80::1]").unwrap();
+        let src = ScionAddr::from_str("19-ffaa:1:1067,CS").unwrap();
+        let v4 = ScionAddr::from_str("1-ff00:0:110,10.0.0.1").unwrap();
+        assert_eq!((dst.wire_type(), dst.wire_host_len()), (ADDR_TYPE_IPV6, 16));
+        assert_eq!((src.wire_type(), src.wire_host_len()), (ADDR_TYPE_SVC, 4));
+        assert_eq!((v4.wire_type(), v4.wire_host_len()), (ADDR_TYPE_IPV4, 4));
+
+        let mut buf = [0xffu8; 40];
+        assert_eq!(ScionAddr::write_wire(&dst, &src, &mut buf).unwrap(), 36);
+        assert_eq!(&buf[..8], &dst.get_ia().to_be_bytes());
+        assert_eq!(&buf[8..16], &src.get_ia().to_be_bytes());
+        assert_eq!(&buf[32..36], &[0, 2, 0, 0]);
+        assert_eq!(buf[36], 0xff);
+        let read = ScionAddr::read_wire(dst.wire_type(), src.wire_type(), &buf).unwrap();
+        assert_eq!(read, (dst, src, 36));
+
+        assert!(ScionAddr::write_wire(&dst, &src, &mut buf[..35]).is_err());
+        assert!(ScionAddr::read_wire(dst.wire_type(), src.wire_type(), &buf[..35]).is_err());
+        assert!(ScionAddr::read_wire(dst.wire_type(), 0b0111, &buf).is_err());
+        assert!(ScionAddr::read_wire(ADDR_TYPE_IPV4, ADDR_TYPE_IPV4, &buf[..12]).is_err());
+    }
+
+    #[test]
+    fn socket_addr_key_bytes() {
+        use std::collections::HashMap;
+
+        let addrs: Vec<SocketAddrScion> =
+            ["19-ffaa:1:1067,127.0.0.1:53", "1-ff00:0:110,[fe80::1]:443", "1-ff00:0:110,CS:0"]
+                .iter()
+                .map(|s| SocketAddrScion::from_str(s).unwrap())
+                .collect();
+        let mut map = HashMap::new();
+        for a in &addrs {
+            let key = a.to_key_bytes();
+            assert_eq!(SocketAddrScion::from_key_bytes(&key), Ok(*a));
+            map.insert(key, *a);
+        }
+        assert_eq!(map.len(), 3);
+
+        let key = addrs[0].to_key_bytes();
+        assert_eq!(&key[..8], &addrs[0].ia().to_be_bytes());
+        assert_eq!(&key[8..13], &[0, 127, 0, 0, 1]);
+        assert_eq!(&key[25..], &53u16.to_be_bytes());
+        let mut padded = key;
+        padded[20] = 1;
+        assert!(SocketAddrScion::from_key_bytes(&padded).is_err());
+        let mut unknown = key;
+        unknown[8] = 7;
+        assert!(SocketAddrScion::from_key_bytes(&unknown).is_err());
+    }
+
+    #[test]
+    fn family_negotiation() {
+        use crate::{pick_compatible, AddrFamily, FamilySet, SupportsFamilies};
+
+        struct Simulated;
+        impl SupportsFamilies for Simulated {
+            fn families(&self) -> FamilySet {
+                FamilySet::of(AddrFamily::Scion).with(AddrFamily::Ipv6)
+            }
+        }
+
+        let set: FamilySet = [AddrFamily::Scion, AddrFamily::Ipv4].into_iter().collect();
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![AddrFamily::Ipv4, AddrFamily::Scion]);
+        assert_eq!(format!("{:?}", set), "{Ipv4, Scion}");
+        assert!(set.intersection(FamilySet::IP).contains(AddrFamily::Ipv4));
+        assert!(!set.supports(AddrFamily::Ipv6));
+        assert_eq!(FamilySet::IP.union(FamilySet::SCION), FamilySet::ALL);
+        assert!(FamilySet::EMPTY.is_empty());
+
+        let candidates: Vec<SocketAddr> =
+            ["1-ff00:0:110,10.0.0.1:443", "10.0.0.1:443", "[::1]:443"]
+                .iter()
+                .map(|s| SocketAddr::from_str(s).unwrap())
+                .collect();
+        let transports: Vec<Box<dyn SupportsFamilies>> =
+            vec![Box::new(FamilySet::of(AddrFamily::Ipv4)), Box::new(Simulated)];
+        let pairs = pick_compatible(&candidates, &transports);
+        let picked: Vec<_> = pairs.iter().map(|(a, t)| (*a, t.families())).collect();
+        assert_eq!(picked, vec![
+            (candidates[0], Simulated.families()),
+            (candidates[1], FamilySet::of(AddrFamily::Ipv4)),
+            (candidates[2], Simulated.families()),
+        ]);
+        assert!(pick_compatible(&candidates, &[FamilySet::EMPTY]).is_empty());
+    }
+
+    #[test]
+    fn scion_addr_matches() {
+        let addr = SocketAddrScion::from_str("1-ff00:0:110,10.0.0.1:443").unwrap();
+        let matching = [
+            "1-ff00:0:110,10.0.0.1:443",
+            "0-0,0.0.0.0:0",
+            "1-0,10.0.0.1:0",
+            "0-ff00:0:110,[::]:443",
+        ];
+        for pattern in matching {
+            assert!(SocketAddrScion::from_str(pattern).unwrap().matches(&addr), "{}", pattern);
+        }
+        let other = [
+            "2-0,0.0.0.0:0",
+            "1-ff00:0:111,0.0.0.0:0",
+            "0-0,10.0.0.2:0",
+            "0-0,0.0.0.0:80",
+            "0-0,CS:0",
+        ];
+        for pattern in other {
+            assert!(!SocketAddrScion::from_str(pattern).unwrap().matches(&addr), "{}", pattern);
+        }
+        // wildcards only count on the pattern side
+        let any = SocketAddrScion::from_str("0-0,0.0.0.0:0").unwrap();
+        assert!(!addr.matches(&any));
+        let cs = ScionAddr::from_str("1-ff00:0:110,CS").unwrap();
+        assert!(ScionAddr::from_str("1-0,0.0.0.0").unwrap().matches(&cs));
+    }
+
+    #[test]
+    fn wildcard_constants() {
+        assert!(Isd::WILDCARD.is_wildcard() && !Isd::new(1).is_wildcard());
+        assert_eq!(IsdAsn::WILDCARD.to_u64(), 0);
+        assert!(IsdAsn::from_str("1-0").unwrap().is_wildcard());
+        assert!(IsdAsn::from_str("0-ff00:0:110").unwrap().is_wildcard());
+        assert!(!IsdAsn::from_str("1-ff00:0:110").unwrap().is_wildcard());
+        assert!(ScionAddr::default().is_unspecified());
+        assert!(ScionAddr::from_str("0-0,::").unwrap().is_unspecified());
+        assert!(!ScionAddr::from_str("1-0,0.0.0.0").unwrap().is_unspecified());
+        assert!(!ScionAddr::from_str("0-0,CS").unwrap().is_unspecified());
+    }
+
+    #[test]
+    fn asn_classes() {
+        let asn = |s: &str| Asn::from_str(s).unwrap();
+        assert!(asn("0").is_wildcard() && !asn("0").is_bgp());
+        assert!(asn("1").is_bgp() && asn("4294967295").is_bgp());
+        assert!(!asn("1:0:0").is_bgp());
+        assert!(asn("2:0:0").is_scion_only() && asn("2:ffff:ffff").is_scion_only());
+        assert!(!asn("1:ffff:ffff").is_scion_only() && !asn("3:0:0").is_scion_only());
+        assert!(!asn("ff00:0:110").is_scion_only() && !asn("ff00:0:110").is_wildcard());
+    }
+
+    #[test]
+    fn asn_prefix() {
+        use crate::{AddrKind, AsnPrefix};
+
+        let prefix = AsnPrefix::from_str("2:0:0/16").unwrap();
+        assert_eq!(prefix.to_string(), "2:0:0/16");
+        assert_eq!(prefix.last(), Asn::from_str("2:ffff:ffff").unwrap());
+        assert!(prefix.contains(Asn::from_str("2:0:1a").unwrap()));
+        assert!(!prefix.contains(Asn::from_str("3:0:0").unwrap()));
+        let small = AsnPrefix::from_str("ff00:0:110/46").unwrap();
+        let all: Vec<String> = small.iter().map(|a| a.to_string()).collect();
+        assert_eq!(all, ["ff00:0:110", "ff00:0:111", "ff00:0:112", "ff00:0:113"]);
+        let any = AsnPrefix::from_str("0/0").unwrap();
+        assert!(any.contains(Asn::MAX) && any.last() == Asn::MAX);
+        assert_eq!(AsnPrefix::from_str("ff00:0:110/48").unwrap().iter().count(), 1);
+        assert_eq!(AsnPrefix::new(Asn::from(65537_u32), 32).unwrap().to_string(), "0:1:0/32");
+        for bad in ["2:0:1/16", "2:0:0/49", "2:0:0", "2:0:0/", "2:0:0/016", "/16"] {
+            let err = AsnPrefix::from_str(bad).unwrap_err();
+            assert_eq!(err.kind(), &AddrKind::AsnPrefix, "{}", bad);
+        }
+    }
+
+    #[test]
+    fn isd_set() {
+        use crate::{IsdRange, IsdSet};
+
+        let range = IsdRange::from_str("16-63").unwrap();
+        assert!(range.contains(Isd::new(16)) && !range.contains(Isd::new(64)));
+        assert_eq!(IsdRange::from_str("5").unwrap().to_string(), "5");
+        assert!(IsdRange::from_str("63-16").is_err() && IsdRange::from_str("1-").is_err());
+
+        let set = IsdSet::from_str("64-100,1-15,16,90-4094").unwrap();
+        assert_eq!(set.to_string(), "1-16,64-4094");
+        assert!(set.contains(Isd::new(16)) && !set.contains(Isd::new(17)));
+        assert!(!set.contains(Isd::WILDCARD) && !set.contains(Isd::MAX));
+        assert_eq!(IsdSet::from_str("").unwrap(), IsdSet::new());
+        assert!(IsdSet::from_str("1,").is_err() && IsdSet::from_str(",1").is_err());
+
+        let test = IsdSet::from_str("10-70").unwrap();
+        assert_eq!(set.intersection(&test).to_string(), "10-16,64-70");
+        assert_eq!(set.union(&test).to_string(), "1-4094");
+        assert_eq!(set.difference(&test).to_string(), "1-9,71-4094");
+        assert_eq!(set.complement().to_string(), "0,17-63,4095-65535");
+        assert_eq!(IsdSet::all().complement(), IsdSet::new());
+        assert_eq!(set.iter().take(3).map(|i| i.to_u16()).collect::<Vec<_>>(), [1, 2, 3]);
+
+        let ia = |s: &str| IsdAsn::from_str(s).unwrap();
+        let mut path = ScionPath::empty(ia("1-ff00:0:110"));
+        path.dst = ia("64-ff00:0:210");
+        path.interfaces = vec![
+            PathInterface { ia: ia("1-ff00:0:110"), id: 1 },
+            PathInterface { ia: ia("64-ff00:0:210"), id: 2 },
+        ];
+        assert!(set.contains_path(&path));
+        path.interfaces[1].ia = ia("20-ff00:0:210");
+        assert!(!set.contains_path(&path));
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn parse_scion_impl_parts() {
+        use crate::parse_scion_impl;
+
+        let (ia, isd, asn, host, port) = parse_scion_impl("19-ffaa:1:1067,[::1]:8080", "53");
+        assert_eq!((ia, isd, asn), (make_ia(19, 0xffaa_0001_1067), 19, 0xffaa_0001_1067));
+        assert_eq!((host.as_str(), port), ("::1", 8080));
+        let (_, _, asn, host, port) = parse_scion_impl("1-65551,10.0.0.1", "53");
+        assert_eq!((asn, host.as_str(), port), (65551, "10.0.0.1", 53));
+        assert_eq!(parse_scion_impl("1-ff00:0:110,CS:30252", "0").3, "CS");
+        let bad = ["1-ff00:0:110,", "1-ff00:0:110,[::1", "1-ff00:0:110,::1", "1-ff00:0:110,a:99999"];
+        for bad in bad {
+            assert!(std::panic::catch_unwind(|| parse_scion_impl(bad, "0")).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn parse_scion_fallible() {
+        use crate::{parse_scion, AddrKind, AddrParseError};
+
+        let addr = parse_scion("19-ffaa:1:1067,[::1]", "53").unwrap();
+        assert_eq!(addr, SocketAddrScion::from_str("19-ffaa:1:1067,[::1]:53").unwrap());
+        assert_eq!(parse_scion("1-ff00:0:110,CS:30252", "0").unwrap().port(), 30252);
+        assert_eq!(parse_scion("1-ff00:0:110", "53").unwrap_err().kind(), &AddrKind::SocketScion);
+        let err = Err(AddrParseError::new(AddrKind::SocketScion));
+        assert_eq!(parse_scion("1-ff00:0:110,10.0.0.1", "dns"), err);
+    }
+
+    #[test]
+    fn try_as_from_dotted_hex_errors() {
+        use crate::{try_as_from_dotted_hex, AddrKind, AddrParseError};
+
+        assert_eq!(try_as_from_dotted_hex("ffaa:1:1067"), Ok(0xffaa_0001_1067));
+        assert_eq!(try_as_from_dotted_hex("0:0:0"), Ok(0));
+        for bad in ["ffaa:zz:1", "ffaa:1", "1:2:3:4", "ffaa::1", "fffff:0:0", "+1:0:0", ""] {
+            let err = Err(AddrParseError::new(AddrKind::Asn));
+            assert_eq!(try_as_from_dotted_hex(bad), err, "{}", bad);
+        }
+    }
+
+    #[test]
+    fn checked_ia() {
+        use crate::{try_make_ia, AddrKind, AddrParseError};
+
+        assert_eq!(try_make_ia(1, 0xff00_0000_0110), Ok(make_ia(1, 0xff00_0000_0110)));
+        assert_eq!(try_make_ia(1, 1 << 48), Err(AddrParseError::new(AddrKind::Asn)));
+        assert_eq!(IsdAsn::try_new(19, Asn::MAX.to_u64()).unwrap().isd(), Isd::new(19));
+        assert!(IsdAsn::try_new(19, u64::MAX).is_err());
+        assert!(IsdAsn::from_str("1-281474976710656").is_err());
+    }
+
+    #[test]
+    fn dotted_asn_groups() {
+        assert_eq!(Asn::from_str("ffff:ffff:ffff").unwrap(), Asn::MAX);
+        assert_eq!(Asn::from_str("0:1:f").unwrap().to_u64(), 65551);
+        assert_eq!(Asn::from_str("00ff:0001:0000").unwrap().to_string(), "ff:1:0");
+        for bad in ["1:fffff:0", "1:0", "1:0:0:0", "1::0", "g:0:0"] {
+            assert!(Asn::from_str(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn display_padding() {
+        let addr = SocketAddrScion::from_str("19-ffaa:1:1067,10.0.0.1:53").unwrap();
+        assert_eq!(format!("{:>30}|", addr), "    19-ffaa:1:1067,10.0.0.1:53|");
+        assert_eq!(format!("{:<16}|", addr.addr()), "19-ffaa:1:1067,10.0.0.1|");
+        assert_eq!(format!("{:.8}", addr.addr()), "19-ffaa:");
+        assert_eq!(format!("{:^10}", IsdAsn::from_str("1-65551").unwrap()), " 1-65551  ");
+        let svc = ScionAddr::from_str("1-ff00:0:110,CS_M").unwrap();
+        assert_eq!(format!("{}", svc), "1-ff00:0:110,CS_M");
+        assert_eq!(format!("{:>6}", svc.get_host()), "  CS_M");
+        assert_eq!(format!("{}", crate::Svc::new(3).multicast()), "0x0003_M");
+    }
+
+    #[test]
+    fn scion_display_brackets_ipv6() {
+        let addr = SocketAddrScion::from_str("19-ffaa:1:1067,[fe80::1]:443").unwrap();
+        assert_eq!(addr.to_string(), "19-ffaa:1:1067,[fe80::1]:443");
+        assert_eq!(addr.addr().to_string(), "19-ffaa:1:1067,[fe80::1]");
+        assert_eq!(format!("{:>26}", addr.addr()), "  19-ffaa:1:1067,[fe80::1]");
+        assert_eq!(SocketAddr::SCION(addr).to_string(), "19-ffaa:1:1067,[fe80::1]:443");
+        // the bare form is still accepted where no port follows
+        assert_eq!(ScionAddr::from_str("19-ffaa:1:1067,fe80::1").unwrap(), *addr.addr());
+        let inputs =
+            ["1-ff00:0:110,[::ffff:10.0.0.1]:80", "1-ff00:0:110,10.0.0.1:80", "1-ff00:0:110,CS:0"];
+        for input in inputs {
+            let addr = SocketAddrScion::from_str(input).unwrap();
+            assert_eq!(SocketAddrScion::from_str(&addr.to_string()).unwrap(), addr);
+            assert_eq!(ScionAddr::from_str(&addr.addr().to_string()).unwrap(), *addr.addr());
+            assert_eq!(SocketAddr::from_str(&addr.to_string()).unwrap(), SocketAddr::SCION(addr));
+        }
+        for bad in ["1-ff00:0:110,[::1:443", "1-ff00:0:110,::1]:443", "1-ff00:0:110,[CS]:0"] {
+            assert!(SocketAddrScion::from_str(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn scion_fully_bracketed_form() {
+        let addr = SocketAddrScion::from_str("[19-ffaa:1:1067,127.0.0.1]:53").unwrap();
+        assert_eq!(addr, SocketAddrScion::from_str("19-ffaa:1:1067,127.0.0.1:53").unwrap());
+        assert_eq!(format!("{:#}", addr), "[19-ffaa:1:1067,127.0.0.1]:53");
+        let v6 = SocketAddrScion::from_str("[1-ff00:0:110,::1]:443").unwrap();
+        assert_eq!(format!("{:#}", v6), "[1-ff00:0:110,::1]:443");
+        assert_eq!(v6.to_string(), "1-ff00:0:110,[::1]:443");
+        assert_eq!(SocketAddrScion::from_str(&format!("{:#}", v6)).unwrap(), v6);
+        assert_eq!(SocketAddr::from_str("[1-ff00:0:110,::1]:443").unwrap(), SocketAddr::SCION(v6));
+        assert_eq!(SocketAddrScion::parse_lenient("[1-ff00:0:110,CS]").unwrap().port(), 0);
+        assert!(SocketAddrScion::from_str("[1-ff00:0:110,::1:443").is_err());
+        assert!(SocketAddrScion::from_str("[1-ff00:0:110,::1]").is_err());
+    }
+
+    #[test]
+    fn split_host_port_forms() {
+        use crate::split_host_port;
+
+        let ok = [
+            ("10.0.0.1:80", "10.0.0.1", 80),
+            ("[fe80::1%eth0]:443", "fe80::1%eth0", 443),
+            ("19-ffaa:1:1067,10.0.0.1:53", "19-ffaa:1:1067,10.0.0.1", 53),
+            ("[19-ffaa:1:1067,10.0.0.1]:53", "19-ffaa:1:1067,10.0.0.1", 53),
+            ("1-ff00:0:110,CS:30252", "1-ff00:0:110,CS", 30252),
+            (":8080", "", 8080),
+        ];
+        for (input, host, port) in ok {
+            assert_eq!(split_host_port(input), Ok((host, port)), "{}", input);
+        }
+        let bad = ["10.0.0.1", "10.0.0.1:", "10.0.0.1:65536", "10.0.0.1:+1", "[::1]", "[::1]443",
+                   "1-ff00:0:110,::1:443", "1-ff00:0:110,[::1]", "[::1:443"];
+        for input in bad {
+            assert!(split_host_port(input).is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn addr_list() {
+        use crate::{parse_addr_list, with_parse_observer, ParseRejection};
+        use std::sync::Mutex;
+
+        let list = " 1-ff00:0:110,10.0.0.1:53 , 10.0.0.2:53,[::1]:53 ";
+        let addrs = parse_addr_list(list, ',').unwrap();
+        let expected: Vec<SocketAddr> = ["1-ff00:0:110,10.0.0.1:53", "10.0.0.2:53", "[::1]:53"]
+            .iter()
+            .map(|s| s.parse().unwrap())
+            .collect();
+        assert_eq!(addrs, expected);
+        assert_eq!(parse_addr_list("10.0.0.1:53;[1-ff00:0:110,::1]:53", ';').unwrap().len(), 2);
+        assert_eq!(parse_addr_list("", ',').unwrap(), vec![]);
+        assert!(parse_addr_list("10.0.0.1:53,", ',').is_err());
+
+        // the offset points into the element that failed
+        let offsets = Arc::new(Mutex::new(Vec::new()));
+        let sink = offsets.clone();
+        let record = move |r: &ParseRejection<'_>| sink.lock().unwrap().push(r.offset);
+        with_parse_observer(Arc::new(record), || {
+            assert!(parse_addr_list("10.0.0.1:53,1-ff00:0:110,10.0.0.1:99999", ',').is_err());
+        });
+        assert_eq!(*offsets.lock().unwrap(), vec![34]);
+    }
+
+    #[test]
+    fn parse_error_offset() {
+        use crate::{AddrKind, AddrParseError};
+
+        let err = SocketAddrScion::from_str("1-ff00:0:110 10.0.0.1:53").unwrap_err();
+        assert_eq!(err.kind(), &AddrKind::SocketScion);
+        assert_eq!((err.offset(), err.expected()), (Some(12), Some("','")));
+        let msg = "invalid ScionSocket address syntax: expected ',' at offset 12";
+        assert_eq!(err.to_string(), msg);
+        let err = SocketAddrScion::from_str("1-ff00:0:110,10.0.0.1:").unwrap_err();
+        assert_eq!((err.offset(), err.expected()), (Some(22), Some("a port number")));
+        let err = ScionAddr::from_str("1-ff00:0:110,10.0.0.1 ").unwrap_err();
+        assert_eq!((err.offset(), err.expected()), (Some(21), Some("end of input")));
+        assert_eq!(AddrParseError::new(AddrKind::Ip).to_string(), "invalid IP address syntax");
+    }
+
+    #[test]
+    fn parse_error_kinds() {
+        use crate::{AddrKind, AddrParseError, IsdAsn};
+
+        // the kind stays the one of the address, the reason tells more
+        let ia = |s| {
+            let err = IsdAsn::from_str(s).unwrap_err();
+            assert_eq!(err.kind(), &AddrKind::Ia);
+            err.reason().cloned()
+        };
+        assert_eq!(ia("70000-ff00:0:110"), Some(AddrKind::IsdOutOfRange));
+        assert_eq!(ia("1-fffff:0:110"), Some(AddrKind::AsnOutOfRange));
+        assert_eq!(ia("1-ff00:0:10000"), Some(AddrKind::AsnOutOfRange));
+        assert_eq!(ia("1-281474976710656"), Some(AddrKind::AsnOutOfRange));
+        assert_eq!(ia("1-ff00:0:11x"), Some(AddrKind::TrailingCharacters));
+        assert_eq!(ia("1-ff00:0"), None);
+        let sock = |s| {
+            let err = SocketAddr::from_str(s).unwrap_err();
+            assert_eq!(err.kind(), &AddrKind::Socket);
+            err.reason().cloned()
+        };
+        assert_eq!(sock("1-ff00:0:110,10.0.0.1"), Some(AddrKind::MissingPort));
+        assert_eq!(sock("[::1]"), Some(AddrKind::MissingPort));
+        assert_eq!(sock("10.0.0.1:"), Some(AddrKind::MissingPort));
+        assert_eq!(sock("1-ff00:0:110,10.0.0.1:53 x"), Some(AddrKind::TrailingCharacters));
+        assert_eq!(sock("10.0.0.1:99999"), None);
+        let err = SocketAddrScion::from_str("70000-ff00:0:110,10.0.0.1:53").unwrap_err();
+        assert_eq!((err.kind(), err.offset()), (&AddrKind::SocketScion, Some(0)));
+        assert_eq!(err.reason(), Some(&AddrKind::IsdOutOfRange));
+
+        let err = Ipv4Addr::from_str("1.2.3.4 ").unwrap_err();
+        assert_eq!(err.kind(), &AddrKind::Ipv4);
+        assert_eq!(err.reason(), Some(&AddrKind::TrailingCharacters));
+        let msg = "invalid IPv4 address syntax (trailing characters after the address): \
+                   expected end of input at offset 7";
+        assert_eq!(err.to_string(), msg);
+        // errors of the same kind differ in their details
+        assert_ne!(err, AddrParseError::new(AddrKind::Ipv4));
+    }
+
+    #[test]
+    fn l3_addr() {
+        use crate::{AddrKind, L3Addr};
+
+        let scion = ScionAddr::from_str("19-ffaa:1:1067,[fe80::1]").unwrap();
+        let addr = L3Addr::from_str("19-ffaa:1:1067,[fe80::1]").unwrap();
+        assert_eq!(addr, L3Addr::from(scion));
+        assert!(addr.is_scion() && addr.ip().is_none() && addr == scion);
+        assert_eq!(addr.to_string(), "19-ffaa:1:1067,[fe80::1]");
+        let ip = L3Addr::from_str("fe80::1").unwrap();
+        assert_eq!(ip.ip(), Some(IpAddr::from_str("fe80::1").unwrap()));
+        assert_eq!(ip.host(), addr.host());
+        assert_eq!(ip.to_string(), "fe80::1");
+        let err = L3Addr::from_str("1-ff00:0:110").unwrap_err();
+        assert_eq!(err.kind(), &AddrKind::L3Addr);
+
+        let mut sock = SocketAddr::from_str("10.0.0.1:53").unwrap();
+        sock.set_host(crate::Ipv4Addr::new(10, 0, 0, 2).into());
+        assert_eq!(sock, SocketAddr::from_str("10.0.0.2:53").unwrap());
+        sock.set_host(L3Addr::from_str("::1").unwrap());
+        assert_eq!(sock, SocketAddr::from_str("[::1]:53").unwrap());
+    }
+
+    #[test]
+    fn socket_addr_variant_accessors() {
+        use crate::SocketAddrV4;
+
+        let v4 = SocketAddr::from_str("10.0.0.1:53").unwrap();
+        let v6 = SocketAddr::from_str("[::1]:53").unwrap();
+        let scion = SocketAddr::from_str("1-ff00:0:110,10.0.0.1:53").unwrap();
+        assert!(scion.is_scion() && !v4.is_scion() && !v6.is_scion());
+        assert_eq!(v4.as_v4(), Some(&SocketAddrV4::from_str("10.0.0.1:53").unwrap()));
+        assert_eq!(v6.as_v6().map(|a| a.port()), Some(53));
+        let addr = SocketAddrScion::from_str("1-ff00:0:110,10.0.0.1:53").unwrap();
+        assert_eq!(scion.as_scion(), Some(&addr));
+        assert!(v4.as_v6().is_none() && v6.as_scion().is_none() && scion.as_v4().is_none());
+    }
+
+    #[test]
+    fn socket_addr_set_ia() {
+        let ia = IsdAsn::from_str("1-ff00:0:110").unwrap().to_u64();
+        let mut v6 = SocketAddr::from_str("[fe80::1%3]:443").unwrap();
+        assert_eq!(v6.ia(), None);
+        v6.set_ia(ia);
+        assert_eq!(v6, SocketAddr::from_str("1-ff00:0:110,[fe80::1]:443").unwrap());
+        assert_eq!(v6.ia(), Some(ia));
+        v6.set_ia(make_ia(2, 0xff00_0000_0111));
+        assert_eq!(v6.to_string(), "2-ff00:0:111,[fe80::1]:443");
+    }
+
+    #[test]
+    fn socket_addr_from_scion_tuples() {
+        let scion = ScionAddr::from_str("1-ff00:0:110,[fe80::1]").unwrap();
+        let expected = SocketAddr::from_str("1-ff00:0:110,[fe80::1]:443").unwrap();
+        assert_eq!(SocketAddr::from((scion, 443)), expected);
+        let ip = IpAddr::from_str("fe80::1").unwrap();
+        assert_eq!(SocketAddr::from((scion.isd_asn(), ip, 443)), expected);
+        assert_eq!(SocketAddr::from((ip, 443)).to_string(), "[fe80::1]:443");
+    }
+
+    #[test]
+    fn socket_addr_try_into_variant() {
+        use crate::{AddrFamily, SocketAddrV4, SocketAddrV6};
+
+        let scion = SocketAddr::from_str("1-ff00:0:110,10.0.0.1:53").unwrap();
+        let v4 = SocketAddr::from_str("10.0.0.1:53").unwrap();
+        assert_eq!(SocketAddrScion::try_from(scion), Ok(*scion.as_scion().unwrap()));
+        assert_eq!(SocketAddrV4::try_from(v4).unwrap().to_string(), "10.0.0.1:53");
+        let err = SocketAddrV4::try_from(scion).unwrap_err();
+        assert_eq!((err.expected(), err.found()), (AddrFamily::Ipv4, AddrFamily::Scion));
+        assert_eq!(err.to_string(), "expected an address of family ipv4, found scion");
+        assert_eq!(SocketAddrV6::try_from(v4).unwrap_err().found(), AddrFamily::Ipv4);
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn scion_constructors() {
+        let ia = IsdAsn::from_str("19-ffaa:1:1067").unwrap();
+        let host = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let addr = ScionAddr::from_ia(ia, host);
+        assert_eq!(addr, ScionAddr::from_parts(ia.isd(), ia.asn(), host));
+        assert_eq!(addr, ScionAddr::new(ia.to_u64(), host));
+        assert_eq!(addr, ScionAddr::new1(19, 0xffaa_0001_1067, host));
+        let sock = SocketAddrScion::from_scion_addr(addr, 53);
+        assert_eq!(sock.to_string(), "19-ffaa:1:1067,127.0.0.1:53");
+        assert_eq!(sock, SocketAddrScion::from_ia(ia, host, 53));
+        assert_eq!(sock, SocketAddrScion::from_parts(ia.isd(), ia.asn(), host, 53));
+        assert_eq!(sock, SocketAddrScion::new(ia.to_u64(), host, 53));
+        assert_eq!(sock, SocketAddrScion::new1(addr, 53));
+    }
+
+    #[test]
+    fn socket_addr_scion_builder() {
+        use crate::{AddrKind, Svc};
+
+        let ia = IsdAsn::from_str("1-ff00:0:110").unwrap();
+        let addr = SocketAddrScion::builder().ia(ia).host_addr(Svc::CS).port(30252).build();
+        assert_eq!(addr, SocketAddrScion::from_str("1-ff00:0:110,CS:30252"));
+        let kind = |b: crate::SocketAddrScionBuilder| b.build().unwrap_err().kind().clone();
+        let reason = |b: crate::SocketAddrScionBuilder| b.build().unwrap_err().reason().cloned();
+        let full = SocketAddrScion::builder().isd(1).asn("ff00:0:110").host("::1").port(53);
+        assert!(full.clone().build().is_ok());
+        assert_eq!(reason(full.clone().isd(70000)), Some(AddrKind::IsdOutOfRange));
+        assert_eq!(kind(full.clone().asn("ff00::110")), AddrKind::Asn);
+        assert_eq!(kind(full.clone().host("localhost")), AddrKind::Host);
+        // the first invalid part is reported, even if a later one is valid
+        assert_eq!(reason(full.isd(70000).host("x").isd(1)), Some(AddrKind::IsdOutOfRange));
+        let no_port = SocketAddrScion::builder().ia(ia).host("::1");
+        assert_eq!(reason(no_port), Some(AddrKind::MissingPort));
+        assert_eq!(kind(SocketAddrScion::builder().isd(1).port(53)), AddrKind::Asn);
+    }
+
+    #[test]
+    fn const_construction() {
+        use crate::{HostAddr, Svc};
+
+        const IA: IsdAsn = IsdAsn::from_str_const("19-ffaa:1:1067");
+        const CS: SocketAddrScion = SocketAddrScion::from_scion_addr(
+            ScionAddr::from_ia_host(IA, HostAddr::Svc(Svc::CS)),
+            30252,
+        );
+        const IA_NUM: u64 = make_ia(19, 0xffaa_0001_1067);
+        assert_eq!(CS, SocketAddrScion::from_str("19-ffaa:1:1067,CS:30252").unwrap());
+        assert_eq!(IA.to_u64(), IA_NUM);
+        let valid = ["1-ff00:0:110", "0-0", "65535-ffff:ffff:ffff", "1-64496", "1-FF00:0:1", "01-0:0:0"];
+        for s in valid {
+            assert_eq!(IsdAsn::from_str_const(s), IsdAsn::from_str(s).unwrap(), "{}", s);
+        }
+        assert_eq!(Asn::from_str_const("4294967295").to_u64(), u64::from(u32::MAX));
+        assert_eq!(Isd::from_str_const("19"), Isd::new(19));
+        let invalid = [
+            "", "1", "1-", "-1", "65536-1", "1-ff00:0", "1-ff00:0:0:1", "1-fffff:0:0", "1-0123",
+            "1-4294967296",
+        ];
+        for s in invalid {
+            assert!(IsdAsn::from_str(s).is_err(), "{}", s);
+            assert!(std::panic::catch_unwind(|| IsdAsn::from_str_const(s)).is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn address_defaults() {
+        use crate::{HostAddr, Ipv6Addr, L3Addr, SocketAddrV4, SocketAddrV6};
+
+        #[derive(Default)]
+        struct Endpoint {
+            ip: IpAddr,
+            v6: Ipv6Addr,
+            host: HostAddr,
+            l3: L3Addr,
+            bind: SocketAddr,
+            v4: SocketAddrV4,
+            sock6: SocketAddrV6,
+            scion: SocketAddrScion,
+        }
+
+        let e = Endpoint::default();
+        assert!(e.ip.is_unspecified() && e.v6.is_unspecified() && e.host.is_unspecified());
+        assert_eq!(e.l3, L3Addr::IP(IpAddr::V4(Ipv4Addr::UNSPECIFIED)));
+        assert_eq!(e.bind.to_string(), "0.0.0.0:0");
+        assert_eq!(e.v4.to_string(), "0.0.0.0:0");
+        assert_eq!(e.sock6.to_string(), "[::]:0");
+        assert_eq!(e.scion.port(), 0);
+        assert!(e.scion.addr().is_unspecified());
+    }
+
+    #[test]
+    fn ip_addr_classification() {
+        let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+        assert!(ip("10.1.2.3").is_private() && ip("fd00::1").is_private());
+        assert!(!ip("8.8.8.8").is_private() && !ip("2001:4860::1").is_private());
+        assert!(ip("169.254.0.1").is_link_local() && ip("fe80::1").is_link_local());
+        assert!(ip("100.64.0.1").is_shared() && !ip("fd00::1").is_shared());
+        assert!(ip("fe80::1").is_unicast_link_local());
+        assert!(!ip("169.254.0.1").is_unicast_link_local());
+        assert!(ip("fc00::1").is_unique_local() && !ip("10.0.0.1").is_unique_local());
+        assert!(ip("8.8.8.8").is_unicast_global() && ip("2001:4860::1").is_unicast_global());
+        assert!(!ip("224.0.0.1").is_unicast_global() && !ip("ff02::1").is_unicast_global());
+        assert!(!ip("192.168.0.1").is_unicast_global() && !ip("fe80::1").is_unicast_global());
+    }
+
+    #[test]
+    fn ipv6_std_parity() {
+        use crate::Ipv6Addr;
+
+        const MAPPED: Ipv6Addr = Ipv6Addr::from_segments([0, 0, 0, 0, 0, 0xffff, 0xc00a, 0x2ff]);
+        assert!(MAPPED.is_ipv4_mapped());
+        assert_eq!(MAPPED.to_string(), "::ffff:192.10.2.255");
+        assert_eq!(Ipv6Addr::from_octets(*MAPPED.as_octets()), MAPPED);
+        assert_eq!(Ipv6Addr::from_segments(MAPPED.segments()), MAPPED);
+        assert!(!Ipv6Addr::LOCALHOST.is_ipv4_mapped());
+        assert!(!"::192.10.2.255".parse::<Ipv6Addr>().unwrap().is_ipv4_mapped());
+    }
+
+    #[test]
+    fn ipv4_registry() {
+        use crate::registry;
+
+        let ip = |s: &str| s.parse::<Ipv4Addr>().unwrap();
+        assert!(registry::is_ietf_protocol_assignment(ip("192.0.0.200")));
+        assert!(registry::is_service_continuity(ip("192.0.0.7")));
+        assert!(!registry::is_service_continuity(ip("192.0.0.8")));
+        assert!(registry::is_dummy(ip("192.0.0.8")));
+        assert!(registry::is_pcp_anycast(ip("192.0.0.9")));
+        assert!(registry::is_turn_anycast(ip("192.0.0.10")));
+        assert!(registry::is_nat64_discovery(ip("192.0.0.171")));
+        assert!(registry::is_as112(ip("192.175.48.6")) && registry::is_as112(ip("192.31.196.1")));
+        assert!(registry::is_amt(ip("192.52.193.1")));
+        assert!(registry::is_6to4_relay_anycast(ip("192.88.99.1")));
+        assert!(registry::is_this_network(ip("0.1.2.3")));
+        let special = ["10.0.0.1", "100.64.0.1", "192.0.2.1", "198.18.0.1", "240.0.0.1"];
+        for s in special {
+            assert!(registry::is_special_purpose(ip(s)), "{}", s);
+        }
+        assert!(!registry::is_special_purpose(ip("192.0.1.1")));
+        assert!(!registry::is_special_purpose(ip("1.1.1.1")));
+    }
+
+    #[test]
+    fn std_net_conversions() {
+        use crate::{Ipv6Addr, SocketAddrV4, SocketAddrV6};
+        use std::mem::{align_of, size_of};
+
+        assert_eq!(size_of::<Ipv4Addr>(), 4);
+        assert_eq!((size_of::<Ipv6Addr>(), align_of::<Ipv6Addr>()), (16, 1));
+
+        let v6 = std::net::SocketAddrV6::new("fe80::1".parse().unwrap(), 443, 7, 3);
+        let ours = SocketAddrV6::from(v6);
+        assert_eq!((ours.port(), ours.flowinfo(), ours.scope_id()), (443, 7, 3));
+        assert_eq!(std::net::SocketAddrV6::from(ours), v6);
+
+        let v4: std::net::SocketAddrV4 = "10.0.0.1:53".parse().unwrap();
+        assert_eq!(std::net::SocketAddrV4::from(SocketAddrV4::from(v4)), v4);
+
+        for s in ["10.0.0.1:53", "[2001:db8::1]:443"] {
+            let std_addr: std::net::SocketAddr = s.parse().unwrap();
+            let addr = SocketAddr::from(std_addr);
+            assert_eq!(addr.to_string(), s);
+            assert_eq!(std::net::SocketAddr::try_from(addr).unwrap(), std_addr);
+            let ip = IpAddr::from(std_addr.ip());
+            assert_eq!(std::net::IpAddr::from(ip), std_addr.ip());
+        }
+        let scion: SocketAddr = "1-ff00:0:110,[10.0.0.1]:53".parse().unwrap();
+        let err = std::net::SocketAddr::try_from(scion).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn libc_sockaddr_conversions() {
+        use crate::{SocketAddrV4, SocketAddrV6};
+
+        let v4: SocketAddrV4 = "10.1.2.3:8080".parse().unwrap();
+        let sin = v4.to_sockaddr_in();
+        assert_eq!(sin.sin_family, libc::AF_INET as libc::sa_family_t);
+        assert_eq!(sin.sin_port, 8080u16.to_be());
+        assert_eq!(sin.sin_addr.s_addr, u32::from_be_bytes([10, 1, 2, 3]).to_be());
+        assert_eq!(SocketAddrV4::from_sockaddr_in(&sin), v4);
+
+        let v6 = SocketAddrV6::new("fe80::1".parse().unwrap(), 443, 5, 2);
+        let sin6 = v6.to_sockaddr_in6();
+        assert_eq!((sin6.sin6_port, sin6.sin6_scope_id), (443u16.to_be(), 2));
+        assert_eq!(SocketAddrV6::from_sockaddr_in6(&sin6), v6);
+
+        // the OS takes the structs as they are
+        let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let local = SocketAddr::from(socket.local_addr().unwrap());
+        let (storage, len) = local.to_sockaddr_storage().unwrap();
+        assert_eq!(len as usize, std::mem::size_of::<libc::sockaddr_in>());
+        let sin = unsafe { *(&storage as *const _ as *const libc::sockaddr_in) };
+        assert_eq!(SocketAddr::V4(SocketAddrV4::from_sockaddr_in(&sin)), local);
+
+        let scion: SocketAddr = "1-ff00:0:110,10.0.0.1:53".parse().unwrap();
+        assert!(scion.to_sockaddr_storage().is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn sockaddr_storage_decoding() {
+        use crate::SockaddrError;
+
+        for s in ["10.0.0.1:53", "[fe80::1%3]:443"] {
+            let addr: SocketAddr = s.parse().unwrap();
+            let (storage, len) = addr.to_sockaddr_storage().unwrap();
+            assert_eq!(SocketAddr::from_sockaddr_storage(&storage, len), Ok(addr));
+            let err = SocketAddr::from_sockaddr_storage(&storage, 4).unwrap_err();
+            assert!(matches!(err, SockaddrError::Truncated { len: 4, .. }), "{}", err);
+        }
+
+        let mut storage: libc::sockaddr_storage = unsafe { std::mem::zeroed() };
+        storage.ss_family = libc::AF_UNIX as libc::sa_family_t;
+        let len = std::mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+        let err = SocketAddr::from_sockaddr_storage(&storage, len).unwrap_err();
+        assert_eq!(err, SockaddrError::UnknownFamily { family: libc::AF_UNIX });
+        assert_eq!(std::io::Error::from(err).kind(), std::io::ErrorKind::InvalidInput);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn local_interfaces() {
+        let interfaces = crate::interfaces().unwrap();
+        let lo = interfaces.iter().find(|i| i.is_loopback).expect("a loopback interface");
+        assert!(lo.index > 0);
+        assert!(lo.addrs.iter().any(|a| a.ip.is_loopback()), "{:?}", lo);
+        for a in interfaces.iter().flat_map(|i| &i.addrs) {
+            assert_eq!(a.netmask.map(|m| m.is_ipv4()), a.netmask.map(|_| a.ip.is_ipv4()));
+            if a.ip.is_ipv4() {
+                assert_eq!(a.scope_id, 0);
+            }
+        }
+    }
+
+    #[cfg(feature = "socket2")]
+    #[test]
+    fn socket2_conversions() {
+        use socket2::{SockAddr, Type};
+
+        let addr: SocketAddr = "[fe80::1%2]:443".parse().unwrap();
+        let sock_addr = SockAddr::try_from(addr).unwrap();
+        assert_eq!(sock_addr.as_socket_ipv6().unwrap().scope_id(), 2);
+        assert_eq!(SocketAddr::try_from(&sock_addr).unwrap(), addr);
+
+        let scion: SocketAddrScion = "1-ff00:0:110,127.0.0.1:0".parse().unwrap();
+        assert!(SockAddr::try_from(SocketAddr::SCION(scion)).is_err());
+        let socket = scion.bind_underlay(Type::DGRAM, None).unwrap();
+        let local = SocketAddr::try_from(socket.local_addr().unwrap()).unwrap();
+        assert!(local.as_v4().unwrap().ip().is_loopback() && local.port() != 0);
+
+        let svc: SocketAddrScion = "1-ff00:0:110,CS:0".parse().unwrap();
+        assert!(svc.underlay_sock_addr().is_err());
+    }
+
+    #[test]
+    fn write_to_without_allocating() {
+        use std::fmt::{self, Write};
+
+        // a fixed buffer, as firmware would use
+        struct Fixed([u8; 80], usize);
+        impl Write for Fixed {
+            fn write_str(&mut self, s: &str) -> fmt::Result {
+                let buf = self.0.get_mut(self.1..self.1 + s.len()).ok_or(fmt::Error)?;
+                buf.copy_from_slice(s.as_bytes());
+                self.1 += s.len();
+                Ok(())
+            }
+        }
+
+        let longest = "65535-ffff:ffff:ffff,[ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff]:65535";
+        for s in ["10.0.0.1:53", "[::1]:443", "1-ff00:0:110,CS_M:0", longest] {
+            let addr: SocketAddr = s.parse().unwrap();
+            let mut fixed = Fixed([0; 80], 0);
+            addr.write_to(&mut fixed).unwrap();
+            assert_eq!(&fixed.0[..fixed.1], s.as_bytes());
+            assert!(fixed.1 <= SocketAddr::MAX_DISPLAY_LEN);
+        }
+
+        let addr: SocketAddrScion = longest.parse().unwrap();
+        let mut s = String::new();
+        addr.addr().write_to(&mut s).unwrap();
+        assert_eq!(s, addr.addr().to_string());
+        assert!(s.len() <= ScionAddr::MAX_DISPLAY_LEN);
+        let mut short = Fixed([0; 80], 75);
+        assert!(addr.write_to(&mut short).is_err());
+    }
+
+    #[test]
+    fn display_buffer_exposes_written_prefix() {
+        use crate::DisplayBuffer;
+        use std::fmt::Write;
+
+        let mut buf = DisplayBuffer::<8>::new();
+        assert_eq!(buf.as_str(), "");
+        write!(buf, "{}-ab", 1).unwrap();
+        assert_eq!(buf.as_str(), "1-ab");
+        // a string that does not fit is dropped whole
+        assert!(buf.write_str("ü-ü").is_err());
+        assert_eq!(buf.as_str(), "1-ab");
+        buf.write_str("ü-").unwrap();
+        assert_eq!(buf.as_str(), "1-abü-");
+        assert!(buf.write_str("xx").is_err());
+
+        let mut empty = DisplayBuffer::<0>::new();
+        assert!(empty.write_str("").is_ok() && empty.write_str("x").is_err());
+
+        // the padding paths of the address types format through the buffer
+        assert_eq!(format!("{:>10}", Ipv4Addr::new(1, 2, 3, 4)), "   1.2.3.4");
+        assert_eq!(format!("{:<12}|", "[::1]:80".parse::<SocketAddr>().unwrap()), "[::1]:80    |");
+        let addr: SocketAddr = "1-ff00:0:110,10.0.0.1:53".parse().unwrap();
+        assert_eq!(addr.to_display_buffer().as_str(), "1-ff00:0:110,10.0.0.1:53");
+    }
+
+    #[test]
+    fn display_buffer_api() {
+        use crate::DisplayBuffer;
+        use std::fmt::Write;
+
+        let mut buf = DisplayBuffer::<24>::try_from("1-ff00:0:110").unwrap();
+        assert_eq!((buf.len(), buf.capacity()), (12, 24));
+        assert_eq!(buf.as_bytes(), b"1-ff00:0:110");
+        assert!(buf.starts_with("1-") && buf.contains("ff00"));
+        assert_eq!(buf.clone(), buf);
+        assert_eq!(format!("{:?} {:>14}", buf, buf), "\"1-ff00:0:110\"   1-ff00:0:110");
+
+        buf.clear();
+        assert!(buf.is_empty());
+        write!(buf, "{}", "10.0.0.1:53".parse::<SocketAddr>().unwrap()).unwrap();
+        assert_eq!(&*buf, "10.0.0.1:53");
+        assert!(DisplayBuffer::<4>::try_from("12345").is_err());
+    }
+
+    #[cfg(feature = "simd")]
+    #[test]
+    fn simd_parser_matches_scalar() {
+        use crate::simd::tests::without_simd;
+        use crate::{Ipv6Addr, ScionAddr, SocketAddrScion, SocketAddrV4, SocketAddrV6};
+        use std::fmt::Debug;
+        use std::str::FromStr;
+
+        fn check<T>(s: &str)
+        where
+            T: FromStr + Debug,
+            T::Err: Debug,
+        {
+            let fast = format!("{:?}", s.parse::<T>());
+            let scalar = without_simd(|| format!("{:?}", s.parse::<T>()));
+            assert_eq!(fast, scalar, "{:?}", s);
+        }
+        fn check_all(s: &str) {
+            check::<Ipv4Addr>(s);
+            check::<Ipv6Addr>(s);
+            check::<IpAddr>(s);
+            check::<SocketAddrV4>(s);
+            check::<SocketAddrV6>(s);
+            check::<SocketAddr>(s);
+            check::<ScionAddr>(s);
+            check::<SocketAddrScion>(s);
+        }
+
+        let valid = [
+            "0.0.0.0", "255.255.255.255", "1.2.3.4", "10.0.0.1:53", "192.168.100.200",
+            "::", "::1", "1::", "1::2", "fe80::1%2", "[fe80::1%2]:443", "[::]:0",
+            "2001:db8:0:0:0:0:0:1", "2001:DB8::ff00:42:8329", "1:2:3:4:5:6:7::", "::2:3:4:5:6:7:8",
+            "1:2:3:4:5:6:7:8", "ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff", "::ffff:1.2.3.4",
+            "64:ff9b::10.0.0.1", "1-ff00:0:110,10.0.0.1", "1-ff00:0:110,[::1]:80",
+            "1-ff00:0:110,[2001:db8::1]:30041", "1-64512,127.0.0.1:8080",
+        ];
+        let mut inputs: Vec<String> = Vec::new();
+        for v in valid {
+            inputs.push(v.to_string());
+            // every truncation, and every single byte edit of a few kinds
+            for i in 0..v.len() {
+                inputs.push(v[..i].to_string());
+                for c in ["", "0", "9", "f", ":", ".", "::", "x", "00", "%", "]"] {
+                    inputs.push(format!("{}{}{}", &v[..i], c, &v[i + 1..]));
+                    inputs.push(format!("{}{}{}", &v[..i], c, &v[i..]));
+                }
+            }
+        }
+
+        // random strings over the bytes the address parsers care about
+        let alphabet = b"0123456789abcdefABCDEF:.:.[]%x ,-";
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        for _ in 0..20_000 {
+            let len = next() % 48;
+            let s: String = (0..len)
+                .map(|_| char::from(alphabet[next() as usize % alphabet.len()]))
+                .collect();
+            inputs.push(s);
+        }
+
+        for s in &inputs {
+            check_all(s);
+        }
+    }
+
+    #[test]
+    fn parse_ascii_prefix() {
+        use crate::{Ipv6Addr, IsdAsn, ScionAddr, SocketAddrV4};
+
+        let (ip, len) = Ipv4Addr::parse_ascii_prefix(b"10.0.0.1/24").unwrap();
+        assert_eq!((ip, len), (Ipv4Addr::new(10, 0, 0, 1), 8));
+        let (ip, len) = Ipv6Addr::parse_ascii_prefix(b"fe80::1%eth0").unwrap();
+        assert_eq!((ip.segments()[7], len), (1, 7));
+        let (ia, len) = IsdAsn::parse_ascii_prefix(b"1-ff00:0:110,10.0.0.1").unwrap();
+        assert_eq!((ia.to_string().as_str(), len), ("1-ff00:0:110", 12));
+        let token = "1-ff00:0:110,[::1] trailer";
+        let (addr, len) = ScionAddr::parse_ascii_prefix(token.as_bytes()).unwrap();
+        assert_eq!((addr, &token[len..]), (token[..18].parse().unwrap(), " trailer"));
+
+        // a whole address is consumed as a whole
+        let (addr, len) = SocketAddr::parse_ascii_prefix(b"[::1]:443").unwrap();
+        assert_eq!((addr, len), ("[::1]:443".parse().unwrap(), 9));
+        assert_eq!(SocketAddrV4::parse_ascii_prefix(b"1.2.3.4:5,").unwrap().1, 9);
+
+        // without an address at the start, the errors are those of parse_ascii
+        let err = SocketAddr::parse_ascii_prefix(b"10.0.0.1:x").unwrap_err();
+        assert_eq!(err, SocketAddr::parse_ascii(b"10.0.0.1:x").unwrap_err());
+        assert_eq!(Ipv4Addr::parse_ascii_prefix(b"x1.2.3.4").unwrap_err().offset(), Some(0));
+        assert!(IpAddr::parse_ascii_prefix(b"").is_err());
+    }
+
+    #[test]
+    fn public_parser_primitives() {
+        use crate::{AddrKind, IsdAsn, Parser};
+
+        // a hop predicate like `1-ff00:0:110#1,2`
+        let hop = |s: &str| {
+            Parser::new(s.as_bytes()).parse_with(
+                |p| {
+                    let ia = p.read_isd_asn()?;
+                    p.read_given_char('#')?;
+                    let mut ifids = [0u16; 2];
+                    for (i, ifid) in ifids.iter_mut().enumerate() {
+                        *ifid = p.read_separator(',', i, |p| p.read_number(10, Some(5), false))?;
+                    }
+                    Some((ia, ifids))
+                },
+                AddrKind::Ia,
+            )
+        };
+        let (ia, ifids) = hop("1-ff00:0:110#1,2").unwrap();
+        assert_eq!((ia, ifids), ("1-ff00:0:110".parse::<IsdAsn>().unwrap(), [1, 2]));
+        assert_eq!(hop("1-ff00:0:110#1").unwrap_err().expected(), Some("','"));
+        assert!(hop("1-ff00:0:110#1,70000").is_err());
+
+        // the primitives leave the input alone when they fail
+        let mut p = Parser::new(b"10.0.0.1:https rest");
+        assert!(p.read_ipv6_addr().is_none() && p.read_scion_addr().is_none());
+        assert_eq!(p.consumed(), 0);
+        assert_eq!(p.read_ipv4_addr(), Some(Ipv4Addr::new(10, 0, 0, 1)));
+        assert_eq!(p.read_port_or_service(), Some(443));
+        assert_eq!((p.read_char(), p.remaining()), (Some(' '), &b"rest"[..]));
+    }
+
+    #[cfg(feature = "arbitrary")]
+    #[test]
+    fn arbitrary_addresses() {
+        use crate::{IsdAsn, ScionAddr, SocketAddrScion};
+        use arbitrary::{Arbitrary, Unstructured};
+
+        let data: Vec<u8> =
+            (0..4096u32).map(|i| (i.wrapping_mul(2_654_435_761) >> 13) as u8).collect();
+        let mut u = Unstructured::new(&data);
+        let mut kinds = [0; 3];
+        while !u.is_empty() {
+            // the textual form of every generated address parses back
+            let ia = IsdAsn::arbitrary(&mut u).unwrap();
+            assert_eq!(ia.to_string().parse::<IsdAsn>().unwrap(), ia);
+            let addr = ScionAddr::arbitrary(&mut u).unwrap();
+            assert_eq!(addr.to_string().parse::<ScionAddr>().unwrap(), addr);
+            let addr = SocketAddrScion::arbitrary(&mut u).unwrap();
+            assert_eq!(addr.to_string().parse::<SocketAddrScion>().unwrap(), addr);
+            let ip = IpAddr::arbitrary(&mut u).unwrap();
+            assert_eq!(ip.to_string().parse::<IpAddr>().unwrap(), ip);
+            match SocketAddr::arbitrary(&mut u).unwrap() {
+                SocketAddr::V4(_) => kinds[0] += 1,
+                SocketAddr::V6(_) => kinds[1] += 1,
+                SocketAddr::SCION(_) => kinds[2] += 1,
+            }
+        }
+        assert!(kinds.iter().all(|&n| n > 0), "{:?}", kinds);
+
+        // an exhausted input still yields values
+        let mut empty = Unstructured::new(&[]);
+        assert!(SocketAddr::arbitrary(&mut empty).is_ok());
+        assert_eq!(Ipv4Addr::size_hint(0), (4, Some(4)));
+    }
+
+    #[cfg(feature = "proptest")]
+    #[test]
+    fn proptest_strategies() {
+        use crate::testing::strategies;
+        use crate::{Asn, AsnPrefix, SocketAddrScion};
+        use proptest::strategy::{Strategy, ValueTree};
+        use proptest::test_runner::TestRunner;
+
+        fn sample<S: Strategy>(s: S, runner: &mut TestRunner) -> S::Value {
+            s.new_tree(runner).unwrap().current()
+        }
+
+        let mut runner = TestRunner::deterministic();
+        let (mut max_asn, mut mapped) = (false, false);
+        for _ in 0..500 {
+            let addr = sample(strategies::socket_addr(), &mut runner);
+            assert_eq!(addr.to_string().parse::<SocketAddr>().unwrap(), addr);
+            let addr = sample(strategies::socket_addr_scion(), &mut runner);
+            assert_eq!(addr.to_string().parse::<SocketAddrScion>().unwrap(), addr);
+            let prefix = sample(strategies::asn_prefix(), &mut runner);
+            assert_eq!(prefix.to_string().parse::<AsnPrefix>().unwrap(), prefix);
+            let set = sample(strategies::isd_set(), &mut runner);
+            assert!(set.ranges().windows(2).all(|w| w[0].end() < w[1].start()), "{}", set);
+
+            max_asn |= sample(strategies::asn(), &mut runner) == Asn::MAX;
+            mapped |= sample(strategies::ipv6_addr(), &mut runner).to_ipv4_mapped().is_some();
+        }
+        // the edge cases come up far more often than by chance
+        assert!(max_asn && mapped);
+    }
+
+    #[test]
+    fn reference_test_vectors() {
+        use crate::testing::{test_vectors, VectorKind};
+        use crate::{Asn, Isd, IsdAsn, ScionAddr};
+
+        let mut count = 0;
+        for v in test_vectors() {
+            let parsed = match v.kind {
+                VectorKind::Isd => {
+                    v.input.parse::<Isd>().map(|i| (i.to_string(), Some(i.to_u16().into())))
+                }
+                VectorKind::Asn => {
+                    v.input.parse::<Asn>().map(|a| (a.to_string(), Some(a.to_u64())))
+                }
+                VectorKind::IsdAsn => {
+                    v.input.parse::<IsdAsn>().map(|ia| (ia.to_string(), Some(ia.to_u64())))
+                }
+                VectorKind::ScionAddr => {
+                    v.input.parse::<ScionAddr>().map(|a| (a.to_string(), None))
+                }
+            };
+            match (parsed, v.canonical) {
+                (Ok((text, value)), Some(canonical)) => {
+                    assert_eq!((text.as_str(), value), (canonical, v.value), "{:?}", v)
+                }
+                (Err(_), None) => {}
+                (parsed, _) => panic!("{:?} parsed as {:?}", v, parsed),
+            }
+            count += 1;
+        }
+        assert!(count > 50);
+    }
+
+    #[test]
+    fn ip_net() {
+        use crate::{AddrKind, IpNet, Ipv4Net, Ipv6Addr, Ipv6Net};
+
+        let net = Ipv4Net::from_str("10.0.0.0/8").unwrap();
+        assert_eq!(net.to_string(), "10.0.0.0/8");
+        assert_eq!(net.broadcast(), Ipv4Addr::new(10, 255, 255, 255));
+        assert_eq!(net.netmask(), Ipv4Addr::new(255, 0, 0, 0));
+        assert!(net.contains(Ipv4Addr::new(10, 1, 2, 3)));
+        assert!(!net.contains(Ipv4Addr::new(11, 0, 0, 0)));
+        let any = Ipv4Net::from_str("0.0.0.0/0").unwrap();
+        assert!(any.contains(Ipv4Addr::new(255, 255, 255, 255)));
+        assert_eq!(any.broadcast(), Ipv4Addr::new(255, 255, 255, 255));
+        let host = Ipv4Net::from(Ipv4Addr::new(192, 0, 2, 1));
+        assert_eq!((host.prefix_len(), host.broadcast()), (32, Ipv4Addr::new(192, 0, 2, 1)));
+        let net = Ipv4Net::new(Ipv4Addr::new(192, 168, 1, 77), 26).unwrap();
+        assert_eq!(format!("{:>20}", net), "     192.168.1.64/26");
+
+        let net = Ipv6Net::from_str("2001:db8::/32").unwrap();
+        assert_eq!(net.to_string(), "2001:db8::/32");
+        let last = Ipv6Addr::from_str("2001:db8:ffff:ffff:ffff:ffff:ffff:ffff").unwrap();
+        assert_eq!(net.broadcast(), last);
+        assert_eq!(net.netmask(), Ipv6Addr::from_str("ffff:ffff::").unwrap());
+        assert!(net.contains(Ipv6Addr::from_str("2001:db8:1::1").unwrap()));
+        assert!(!net.contains(Ipv6Addr::from_str("2001:db9::").unwrap()));
+        assert_eq!(Ipv6Net::from_str("::/0").unwrap().broadcast(), Ipv6Addr::from_bits(u128::MAX));
+        assert_eq!(Ipv6Net::from_str("::1/128").unwrap().prefix_len(), 128);
+
+        let v4 = IpNet::from_str("10.0.0.0/8").unwrap();
+        let v6 = IpNet::from_str("2001:db8::/32").unwrap();
+        assert!(v4.is_ipv4() && v6.is_ipv6());
+        assert!(v4.contains(IpAddr::from_str("10.0.0.1").unwrap()));
+        assert!(!v4.contains(IpAddr::from_str("::ffff:10.0.0.1").unwrap()));
+        assert_eq!(v6.network(), IpAddr::from_str("2001:db8::").unwrap());
+        assert_eq!(v4.broadcast(), IpAddr::from_str("10.255.255.255").unwrap());
+        let err = IpNet::new(IpAddr::from_str("::").unwrap(), 129).unwrap_err();
+        assert_eq!((err.prefix_len(), err.max()), (129, 128));
+        assert_eq!(err.to_string(), "prefix length 129 is longer than 128 bits");
+        assert_eq!(IpNet::parse_ascii_prefix(b"10.0.0.0/8 via").unwrap(), (v4, 10));
+
+        for bad in ["10.0.0.1/8", "10.0.0.0/33", "10.0.0.0/08", "10.0.0.0", "10.0.0.0/", "/8"] {
+            let err = Ipv4Net::from_str(bad).unwrap_err();
+            assert_eq!(err.kind(), &AddrKind::Ipv4Net, "{}", bad);
+        }
+        for bad in ["2001:db8::1/32", "::/129", "::/064", "2001:db8::", "10.0.0.0/8"] {
+            let err = Ipv6Net::from_str(bad).unwrap_err();
+            assert_eq!(err.kind(), &AddrKind::Ipv6Net, "{}", bad);
+        }
+        assert_eq!(IpNet::from_str("10.0.0.0/33").unwrap_err().kind(), &AddrKind::IpNet);
+    }
+
+    #[test]
+    fn prefix_map() {
+        use crate::{IpNet, Ipv4Net, PrefixMap};
+
+        let net = |s: &str| IpNet::from_str(s).unwrap();
+        let ip = |s: &str| IpAddr::from_str(s).unwrap();
+        let mut map: PrefixMap<u32> =
+            [("10.0.0.0/8", 8), ("10.1.0.0/16", 16), ("2001:db8::/32", 32), ("0.0.0.0/0", 0)]
+                .into_iter()
+                .map(|(s, v)| (net(s), v))
+                .collect();
+        assert_eq!(map.len(), 4);
+        assert_eq!(map.longest_match(ip("10.1.2.3")), Some((net("10.1.0.0/16"), &16)));
+        assert_eq!(map.longest_match(ip("10.2.0.0")), Some((net("10.0.0.0/8"), &8)));
+        assert_eq!(map.longest_match(ip("192.0.2.1")), Some((net("0.0.0.0/0"), &0)));
+        assert_eq!(map.longest_match(ip("2001:db8::1")), Some((net("2001:db8::/32"), &32)));
+        assert_eq!(map.longest_match(ip("::ffff:10.1.2.3")), None);
+        assert_eq!(map.get(net("10.1.0.0/16")), Some(&16));
+        assert_eq!(map.get(net("10.1.0.0/24")), None);
+        assert_eq!(map.insert(Ipv4Net::from_str("10.1.0.0/16").unwrap(), 17), Some(16));
+        *map.get_mut(net("10.0.0.0/8")).unwrap() += 1;
+        let order: Vec<String> = map.iter().map(|(n, v)| format!("{}={}", n, v)).collect();
+        assert_eq!(order, ["0.0.0.0/0=0", "10.0.0.0/8=9", "10.1.0.0/16=17", "2001:db8::/32=32"]);
+
+        assert_eq!(map.remove(net("10.0.0.0/8")), Some(9));
+        assert_eq!(map.remove(net("10.0.0.0/8")), None);
+        assert_eq!(map.longest_match(ip("10.2.0.0")), Some((net("0.0.0.0/0"), &0)));
+        assert_eq!(map.longest_match(ip("10.1.0.0")), Some((net("10.1.0.0/16"), &17)));
+        assert_eq!(map.len(), 3);
+        map.clear();
+        assert!(map.is_empty() && map.longest_match(ip("10.1.0.0")).is_none());
+
+        // against a linear scan, with prefixes nested and branching at random
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        let mut model = std::collections::BTreeMap::new();
+        for round in 0..2000 {
+            let bits = (next() as u32) & 0xff0f_0000 | 0x0a00_0000;
+            let len = (next() % 33) as u8;
+            let net = IpNet::V4(Ipv4Net::new(Ipv4Addr::from_bits(bits), len).unwrap());
+            if next() % 3 == 0 {
+                assert_eq!(map.remove(net), model.remove(&net));
+            } else {
+                assert_eq!(map.insert(net, round), model.insert(net, round));
+            }
+            let addr = IpAddr::V4(Ipv4Addr::from_bits((next() as u32) & 0xff0f_ffff | 0x0a00_0000));
+            let expected = model.iter().filter(|(n, _)| n.contains(addr)).last();
+            assert_eq!(map.longest_match(addr), expected.map(|(n, v)| (*n, v)));
+            assert_eq!(map.len(), model.len());
+        }
+        assert!(map.iter().eq(model.iter().map(|(n, v)| (*n, v))));
+    }
+
+    #[test]
+    fn parse_observer_probes() {
+        use crate::{parse_any, with_parse_observer, AddrKind, ParseRejection, ScionAddr};
+        use crate::{ToScionSocketAddrs, ToSocketAddrs};
+        use std::sync::Mutex;
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = seen.clone();
+        let record = move |r: &ParseRejection<'_>| {
+            sink.lock().unwrap().push((r.input_lossy().into_owned(), r.kind.clone()))
+        };
+
+        // the forms tried before the one that parses are no rejections
+        with_parse_observer(Arc::new(record), || {
+            assert!(parse_any("1-ff00:0:110,[::1]:53").is_ok());
+            assert!(("10.0.0.1", 80).to_socket_addrs().is_ok());
+            assert!(("1-ff00:0:110,10.0.0.1", 80).to_scion_socket_addrs().is_ok());
+            assert!("1-ff00:0:110,[10.0.0.1]:80".to_scion_socket_addrs().is_ok());
+            assert!(ScionAddr::from_slug("1-ff00_0_110_fd00--1").is_ok());
+            assert!(seen.lock().unwrap().is_empty());
+
+            // a failure is reported once, by the function called
+            assert!(parse_any("1-ff00:0:110,10.0.0.1/33").is_err());
+            assert!(ScionAddr::from_slug("1-ff00_0_110_x").is_err());
+        });
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                ("1-ff00:0:110,10.0.0.1/33".to_string(), AddrKind::ScionNet),
+                ("1-ff00_0_110_x".to_string(), AddrKind::Scion),
+            ]
+        );
+    }
+}
@@ -0,0 +1,146 @@
+use crate::{AddrFamily, SocketAddr};
+use std::fmt;
+use std::sync::Arc;
+
+/// A set of address families, i.e. the ones a transport can reach.
+#[derive(Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct FamilySet(u8);
+
+const FAMILIES: [AddrFamily; 3] = [AddrFamily::Ipv4, AddrFamily::Ipv6, AddrFamily::Scion];
+
+const fn bit(family: AddrFamily) -> u8 {
+    match family {
+        AddrFamily::Ipv4 => 1,
+        AddrFamily::Ipv6 => 2,
+        AddrFamily::Scion => 4,
+    }
+}
+
+impl FamilySet {
+    pub const EMPTY: FamilySet = FamilySet(0);
+    /// IPv4 and IPv6.
+    pub const IP: FamilySet = FamilySet(bit(AddrFamily::Ipv4) | bit(AddrFamily::Ipv6));
+    pub const SCION: FamilySet = FamilySet(bit(AddrFamily::Scion));
+    pub const ALL: FamilySet = FamilySet(FamilySet::IP.0 | FamilySet::SCION.0);
+
+    /// Returns the set holding only `family`.
+    #[must_use]
+    pub const fn of(family: AddrFamily) -> FamilySet {
+        FamilySet(bit(family))
+    }
+
+    /// Returns the set with `family` added.
+    #[must_use]
+    pub const fn with(self, family: AddrFamily) -> FamilySet {
+        FamilySet(self.0 | bit(family))
+    }
+
+    #[must_use]
+    pub const fn union(self, other: FamilySet) -> FamilySet {
+        FamilySet(self.0 | other.0)
+    }
+
+    #[must_use]
+    pub const fn intersection(self, other: FamilySet) -> FamilySet {
+        FamilySet(self.0 & other.0)
+    }
+
+    #[must_use]
+    pub const fn contains(self, family: AddrFamily) -> bool {
+        self.0 & bit(family) != 0
+    }
+
+    #[must_use]
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns the families in the set, in the order of [`AddrFamily`].
+    pub fn iter(self) -> impl Iterator<Item = AddrFamily> {
+        FAMILIES.into_iter().filter(move |&f| self.contains(f))
+    }
+}
+
+impl From<AddrFamily> for FamilySet {
+    fn from(family: AddrFamily) -> FamilySet {
+        FamilySet::of(family)
+    }
+}
+
+impl FromIterator<AddrFamily> for FamilySet {
+    fn from_iter<I: IntoIterator<Item = AddrFamily>>(iter: I) -> FamilySet {
+        iter.into_iter().fold(FamilySet::EMPTY, FamilySet::with)
+    }
+}
+
+impl fmt::Debug for FamilySet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+/// Something that reaches addresses of some families, i.e. a transport of
+/// a library layering several of them, see [`pick_compatible`].
+pub trait SupportsFamilies {
+    /// Returns the families this transport can reach.
+    fn families(&self) -> FamilySet;
+
+    /// Returns `true` if this transport can reach addresses of `family`.
+    fn supports(&self, family: AddrFamily) -> bool {
+        self.families().contains(family)
+    }
+}
+
+impl SupportsFamilies for FamilySet {
+    fn families(&self) -> FamilySet {
+        *self
+    }
+}
+
+impl<T: SupportsFamilies + ?Sized> SupportsFamilies for &T {
+    fn families(&self) -> FamilySet {
+        (**self).families()
+    }
+}
+
+impl<T: SupportsFamilies + ?Sized> SupportsFamilies for Box<T> {
+    fn families(&self) -> FamilySet {
+        (**self).families()
+    }
+}
+
+impl<T: SupportsFamilies + ?Sized> SupportsFamilies for Arc<T> {
+    fn families(&self) -> FamilySet {
+        (**self).families()
+    }
+}
+
+/// Pairs every candidate with the first of `transports` that supports its
+/// family, dropping the candidates none supports. The pairs keep the order
+/// of `candidates`, so the most preferred candidate that can be reached
+/// comes first.
+///
+/// ```
+/// use scionnet::{pick_compatible, FamilySet, SocketAddr};
+///
+/// let candidates: Vec<SocketAddr> = ["10.0.0.1:443", "1-ff00:0:110,10.0.0.1:443"]
+///     .iter()
+///     .map(|s| s.parse().unwrap())
+///     .collect();
+/// let transports = [FamilySet::SCION, FamilySet::ALL];
+/// let pairs = pick_compatible(&candidates, &transports);
+/// assert_eq!(pairs[0], (candidates[0], &FamilySet::ALL));
+/// assert_eq!(pairs[1], (candidates[1], &FamilySet::SCION));
+/// ```
+pub fn pick_compatible<'a, T: SupportsFamilies>(
+    candidates: &[SocketAddr],
+    transports: &'a [T],
+) -> Vec<(SocketAddr, &'a T)> {
+    candidates
+        .iter()
+        .filter_map(|addr| {
+            let transport = transports.iter().find(|t| t.supports(addr.family()))?;
+            Some((*addr, transport))
+        })
+        .collect()
+}
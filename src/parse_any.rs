@@ -0,0 +1,92 @@
+use crate::parse_observer::{quietly, report};
+use crate::{AddrParseError, IpAddr, IpNet, IsdAsn, ScionAddr, ScionNet, SocketAddr};
+use std::fmt;
+use std::str::FromStr;
+
+/// What [`parse_any`] recognized in its input.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[non_exhaustive]
+pub enum Parsed {
+    /// An ISD-AS, i.e. `1-ff00:0:110`.
+    Ia(IsdAsn),
+    /// An IP address, i.e. `10.0.0.1` or `::1`.
+    Ip(IpAddr),
+    /// An IP network, i.e. `10.0.0.0/8`.
+    IpNet(IpNet),
+    /// A SCION address, i.e. `1-ff00:0:110,10.0.0.1` or `1-ff00:0:110,CS`.
+    Scion(ScionAddr),
+    /// An IP network within an AS, i.e. `1-ff00:0:110,10.0.0.0/8`.
+    ScionNet(ScionNet),
+    /// A socket address of any family, i.e. `10.0.0.1:53` or
+    /// `1-ff00:0:110,[10.0.0.1]:53`.
+    Socket(SocketAddr),
+}
+
+type ParseFn = fn(&[u8]) -> Result<Parsed, AddrParseError>;
+
+/// The parsers in the order they are tried, which is the order of the
+/// variants of [`Parsed`].
+const PARSERS: [ParseFn; 6] = [
+    |b| IsdAsn::parse_ascii(b).map(Parsed::Ia),
+    |b| IpAddr::parse_ascii(b).map(Parsed::Ip),
+    |b| IpNet::parse_ascii(b).map(Parsed::IpNet),
+    |b| ScionAddr::parse_ascii(b).map(Parsed::Scion),
+    |b| ScionNet::parse_ascii(b).map(Parsed::ScionNet),
+    |b| SocketAddr::parse_ascii(b).map(Parsed::Socket),
+];
+
+/// Parses whatever address `s` holds, for tools and config loaders that
+/// accept any of them and dispatch on the result.
+///
+/// The forms exclude each other: an ISD-AS has a `-` and an IP address
+/// none, networks have a `/`, SCION addresses and networks a `,` after the
+/// ISD-AS, and socket addresses a port, behind brackets for IPv6 hosts. So
+/// an input is at most one of them, i.e. `1-ff00:0:110,::1:80` is a SCION
+/// address with the host `::1:80`. The parsers are tried in the order of
+/// the variants of [`Parsed`], and if none accepts all of the input, the
+/// error is the one of the parser that got furthest, the earlier one on a
+/// tie.
+///
+/// ```
+/// use scionnet::{parse_any, Parsed};
+///
+/// assert!(matches!(parse_any("1-ff00:0:110"), Ok(Parsed::Ia(_))));
+/// assert!(matches!(parse_any("10.0.0.0/8"), Ok(Parsed::IpNet(_))));
+/// assert!(matches!(parse_any("1-ff00:0:110,10.0.0.1"), Ok(Parsed::Scion(_))));
+/// assert!(matches!(parse_any("1-ff00:0:110,[10.0.0.1]:53"), Ok(Parsed::Socket(_))));
+/// assert_eq!(parse_any("1-ff00:0:110,10.0.0.1/33").unwrap_err().offset(), Some(24));
+/// ```
+pub fn parse_any(s: &str) -> Result<Parsed, AddrParseError> {
+    let mut furthest: Option<AddrParseError> = None;
+    for parse in PARSERS {
+        // the other forms failing is expected, only the overall result counts
+        match quietly(|| parse(s.as_bytes())) {
+            Ok(parsed) => return Ok(parsed),
+            Err(err) if furthest.as_ref().is_some_and(|e| e.offset() >= err.offset()) => {}
+            Err(err) => furthest = Some(err),
+        }
+    }
+    let err = furthest.expect("at least one parser");
+    report(s.as_bytes(), err.kind(), err.offset().unwrap_or(0));
+    Err(err)
+}
+
+impl FromStr for Parsed {
+    type Err = AddrParseError;
+    fn from_str(s: &str) -> Result<Parsed, AddrParseError> {
+        parse_any(s)
+    }
+}
+
+impl fmt::Display for Parsed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Parsed::Ia(ia) => ia.fmt(f),
+            Parsed::Ip(ip) => ip.fmt(f),
+            Parsed::IpNet(net) => net.fmt(f),
+            Parsed::Scion(addr) => addr.fmt(f),
+            Parsed::ScionNet(net) => net.fmt(f),
+            Parsed::Socket(addr) => addr.fmt(f),
+        }
+    }
+}
@@ -0,0 +1,211 @@
+use crate::AddrKind;
+use std::cell::{Cell, RefCell};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+/// A rejected input, as passed to a [`ParseObserver`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseRejection<'a> {
+    /// The rejected input, possibly [redacted](Redaction).
+    pub input: &'a [u8],
+    /// The length of the complete input.
+    pub input_len: usize,
+    /// The kind of address that was expected.
+    pub kind: AddrKind,
+    /// The offset of the first byte that could not be parsed.
+    pub offset: usize,
+    /// The number of rejections dropped by sampling since the previous one
+    /// was passed on, see [`SampledObserver`].
+    pub suppressed: u64,
+}
+
+impl ParseRejection<'_> {
+    /// Returns the input as text, replacing invalid UTF-8.
+    pub fn input_lossy(&self) -> std::borrow::Cow<'_, str> {
+        String::from_utf8_lossy(self.input)
+    }
+}
+
+/// Something that is told about address parse failures, i.e. to log
+/// malformed addresses sent by clients.
+///
+/// Observers are set globally with [`set_parse_observer`] or for the
+/// parses within a closure with [`with_parse_observer`]. They are called
+/// synchronously on the failing thread, so they should be cheap; wrap them
+/// in a [`SampledObserver`] to cap their rate.
+pub trait ParseObserver: Send + Sync {
+    fn on_reject(&self, rejection: &ParseRejection<'_>);
+}
+
+impl<F: Fn(&ParseRejection<'_>) + Send + Sync> ParseObserver for F {
+    fn on_reject(&self, rejection: &ParseRejection<'_>) {
+        self(rejection)
+    }
+}
+
+static GLOBAL: RwLock<Option<Arc<dyn ParseObserver>>> = RwLock::new(None);
+// the number of installed observers, global and scoped, so that parse
+// failures skip the lookup when nobody is listening
+static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static SCOPED: RefCell<Option<Arc<dyn ParseObserver>>> = const { RefCell::new(None) };
+    // the depth of `quietly` calls on this thread
+    static QUIET: Cell<u32> = const { Cell::new(0) };
+}
+
+/// Sets the observer told about all parse failures, replacing and returning
+/// the previous one. `None` removes the observer.
+pub fn set_parse_observer(observer: Option<Arc<dyn ParseObserver>>) -> Option<Arc<dyn ParseObserver>> {
+    if observer.is_some() {
+        ACTIVE.fetch_add(1, Ordering::Relaxed);
+    }
+    let previous = std::mem::replace(&mut *GLOBAL.write().unwrap(), observer);
+    if previous.is_some() {
+        ACTIVE.fetch_sub(1, Ordering::Relaxed);
+    }
+    previous
+}
+
+/// Calls `f`, telling `observer` instead of the global observer about the
+/// parse failures on this thread while `f` runs.
+pub fn with_parse_observer<R, F: FnOnce() -> R>(observer: Arc<dyn ParseObserver>, f: F) -> R {
+    struct Restore(Option<Arc<dyn ParseObserver>>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            SCOPED.with(|s| *s.borrow_mut() = self.0.take());
+            ACTIVE.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+
+    ACTIVE.fetch_add(1, Ordering::Relaxed);
+    let _restore = Restore(SCOPED.with(|s| s.borrow_mut().replace(observer)));
+    f()
+}
+
+/// Calls `f` without reporting the parse failures within it, for parses
+/// that probe what an input is and are expected to fail on valid input.
+/// The caller reports its own failure, if any.
+pub(crate) fn quietly<R>(f: impl FnOnce() -> R) -> R {
+    struct Restore;
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            QUIET.with(|q| q.set(q.get() - 1));
+        }
+    }
+
+    QUIET.with(|q| q.set(q.get() + 1));
+    let _restore = Restore;
+    f()
+}
+
+/// Tells the current observer, if any, that `input` was rejected.
+pub(crate) fn report(input: &[u8], kind: &AddrKind, offset: usize) {
+    if QUIET.with(Cell::get) > 0 {
+        return;
+    }
+    #[cfg(feature = "tracing")]
+    tracing::debug!(
+        target: "scionnet::parser",
+        ?kind,
+        offset = offset.min(input.len()),
+        input_len = input.len(),
+        "rejected address",
+    );
+    if ACTIVE.load(Ordering::Relaxed) == 0 {
+        return;
+    }
+    let observer = SCOPED
+        .with(|s| s.borrow().clone())
+        .or_else(|| GLOBAL.read().unwrap().clone());
+    if let Some(observer) = observer {
+        observer.on_reject(&ParseRejection {
+            input,
+            input_len: input.len(),
+            kind: kind.clone(),
+            offset: offset.min(input.len()),
+            suppressed: 0,
+        });
+    }
+}
+
+/// How much of a rejected input a [`SampledObserver`] passes on.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Redaction {
+    /// The complete input.
+    None,
+    /// At most the given number of leading bytes.
+    Truncate(usize),
+    /// Nothing but the length.
+    Full,
+}
+
+struct Window {
+    start: Instant,
+    passed: u32,
+    suppressed: u64,
+}
+
+/// A [`ParseObserver`] passing at most `limit` rejections per `interval`
+/// on to another observer, with the input redacted.
+///
+/// The first rejection passed on after some were dropped carries their
+/// number in [`ParseRejection::suppressed`].
+pub struct SampledObserver<O> {
+    inner: O,
+    limit: u32,
+    interval: Duration,
+    redaction: Redaction,
+    window: Mutex<Window>,
+}
+
+impl<O: ParseObserver> SampledObserver<O> {
+    pub fn new(inner: O, limit: u32, interval: Duration) -> SampledObserver<O> {
+        SampledObserver {
+            inner,
+            limit,
+            interval,
+            redaction: Redaction::None,
+            window: Mutex::new(Window { start: Instant::now(), passed: 0, suppressed: 0 }),
+        }
+    }
+
+    /// Sets how much of the input is passed on; the default is all of it.
+    #[must_use]
+    pub fn redact(mut self, redaction: Redaction) -> SampledObserver<O> {
+        self.redaction = redaction;
+        self
+    }
+}
+
+impl<O: ParseObserver> ParseObserver for SampledObserver<O> {
+    fn on_reject(&self, rejection: &ParseRejection<'_>) {
+        let suppressed = {
+            let mut window = self.window.lock().unwrap();
+            let now = Instant::now();
+            if now.saturating_duration_since(window.start) >= self.interval {
+                window.start = now;
+                window.passed = 0;
+            }
+            if window.passed >= self.limit {
+                window.suppressed += 1;
+                return;
+            }
+            window.passed += 1;
+            std::mem::take(&mut window.suppressed)
+        };
+        let input = match self.redaction {
+            Redaction::None => rejection.input,
+            Redaction::Truncate(n) => &rejection.input[..n.min(rejection.input.len())],
+            Redaction::Full => &[],
+        };
+        self.inner.on_reject(&ParseRejection {
+            input,
+            suppressed: rejection.suppressed + suppressed,
+            ..rejection.clone()
+        });
+    }
+}
@@ -1,26 +1,32 @@
-//! A private parser implementation of IPv4, IPv6, and socket addresses.
+//! A parser implementation of IPv4, IPv6, SCION and socket addresses.
 //!
 //! This module is "publicly exported" through the `FromStr` implementations
-//! below.
+//! below, and through the primitives of [`Parser`] for grammars embedding
+//! addresses.
 
-use crate::scion_parse_utils::{as_from_dotted_hex, make_ia};
 use crate::{
-    AddrKind, AddrParseError, IpAddr, Ipv4Addr, Ipv6Addr, ScionAddr, SocketAddr, SocketAddrScion,
-    SocketAddrV4, SocketAddrV6,
+    AddrKind, AddrParseError, Asn, AsnPrefix, HostAddr, IpAddr, IpNet, Ipv4Addr, Ipv4Net, Ipv6Addr,
+    Ipv6Net, Isd, IsdAsn, IsdRange, IsdSet, L3Addr, ScionAddr, ScionNet, SocketAddr,
+    SocketAddrScion, SocketAddrV4, SocketAddrV6, Svc,
 };
-use std::convert::TryInto;
-use std::error::Error;
-use std::fmt;
+use std::convert::{TryFrom, TryInto};
 use std::str::FromStr;
 
-trait ReadNumberHelper: Sized {
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// The integer types [`Parser::read_number`] reads: `u8`, `u16`, `u32` and
+/// `u64`.
+pub trait ReadNumberHelper: Sized + sealed::Sealed {
     const ZERO: Self;
     fn checked_mul(&self, other: u32) -> Option<Self>;
     fn checked_add(&self, other: u32) -> Option<Self>;
 }
 
 macro_rules! impl_helper {
-    ($($t:ty)*) => ($(impl ReadNumberHelper for $t {
+    ($($t:ty)*) => ($(impl sealed::Sealed for $t {}
+    impl ReadNumberHelper for $t {
         const ZERO: Self = 0;
         #[inline]
         fn checked_mul(&self, other: u32) -> Option<Self> {
@@ -35,46 +41,203 @@ macro_rules! impl_helper {
 
 impl_helper! { u8 u16 u32 u64}
 
+/// A recursive descent parser over ASCII bytes, whose `read_*` primitives
+/// downstream grammars can compose, i.e. for SCION URIs or config values
+/// that embed addresses.
+///
+/// Every `read_*` method returns `None` without consuming anything if the
+/// input does not start with what it reads. [`parse_with`](Self::parse_with)
+/// runs a whole grammar and turns a failure into an [`AddrParseError`] at
+/// the position the parsers got furthest:
+///
+/// ```
+/// use scionnet::{AddrKind, Parser, SocketAddrScion};
+///
+/// // `<SCION socket address>/<protocol>`
+/// let read = |input: &'static str| {
+///     Parser::new(input.as_bytes()).parse_with(
+///         |p| {
+///             let addr = p.read_socket_addr_scion()?;
+///             p.read_given_char('/')?;
+///             let proto = p.read_while(|b| b.is_ascii_lowercase());
+///             if proto.is_empty() {
+///                 p.expect("a protocol");
+///                 return None;
+///             }
+///             Some((addr, std::str::from_utf8(proto).ok()?))
+///         },
+///         AddrKind::SocketScion,
+///     )
+/// };
+///
+/// let (addr, proto) = read("19-ffaa:1:1067,[10.0.0.1]:80/tcp").unwrap();
+/// assert_eq!((addr.port(), proto), (80, "tcp"));
+/// let err = read("19-ffaa:1:1067,[10.0.0.1]:80/").unwrap_err();
+/// assert_eq!((err.offset(), err.expected()), (Some(29), Some("a protocol")));
+/// ```
 pub struct Parser<'a> {
     // Parsing as ASCII, so can use byte array.
+    input: &'a [u8],
     state: &'a [u8],
+    // the end of the longest prefix any sub-parser accepted, where a
+    // rejected input most likely went wrong
+    furthest: usize,
+    // what the latest failed sub-parser expected, and where
+    expected: Option<(usize, &'static str)>,
+    // the reason of the error, if more precise than the input not being an
+    // address of the kind, i.e. a number out of range, and where
+    cause: Option<(usize, AddrKind)>,
+}
+
+/// Names a character in "expected ..." error details.
+fn quoted(c: char) -> &'static str {
+    match c {
+        ',' => "','",
+        ':' => "':'",
+        '-' => "'-'",
+        '.' => "'.'",
+        '/' => "'/'",
+        '%' => "'%'",
+        '[' => "'['",
+        ']' => "']'",
+        ';' => "';'",
+        ' ' => "' '",
+        _ => "a separator",
+    }
+}
+
+/// Returns `true` if `digits` are a number in `radix` greater than `max`.
+fn exceeds(digits: &[u8], radix: u32, max: u64) -> bool {
+    let mut value: u64 = 0;
+    for &b in digits {
+        let digit = char::from(b).to_digit(radix).map(u64::from);
+        match digit.and_then(|d| value.checked_mul(radix.into())?.checked_add(d)) {
+            Some(v) => value = v,
+            None => return digit.is_some(),
+        }
+    }
+    value > max
 }
 
 impl<'a> Parser<'a> {
-    pub(crate) fn new(input: &'a [u8]) -> Parser<'a> {
-        Parser { state: input }
+    pub fn new(input: &'a [u8]) -> Parser<'a> {
+        Parser { input, state: input, furthest: 0, expected: None, cause: None }
+    }
+
+    /// Returns the number of bytes read so far.
+    pub fn consumed(&self) -> usize {
+        self.input.len() - self.state.len()
+    }
+
+    /// Returns the bytes not read yet.
+    pub fn remaining(&self) -> &'a [u8] {
+        self.state
+    }
+
+    /// Notes that `what` was expected at the current position. Of several
+    /// notes at the same position the latest wins, which is the one of the
+    /// enclosing sub-parser.
+    ///
+    /// If parsing fails here, the error names the latest note as
+    /// [`AddrParseError::expected`], i.e. `"a protocol"`.
+    pub fn expect(&mut self, what: &'static str) {
+        let at = self.consumed();
+        if self.expected.map_or(true, |(pos, _)| at >= pos) {
+            self.expected = Some((at, what));
+        }
+    }
+
+    /// Notes that parsing fails at the current position for the reason of
+    /// `kind`, like [`expect`](Self::expect) does for the expected token.
+    fn fail(&mut self, kind: AddrKind) {
+        let at = self.consumed();
+        if self.cause.as_ref().map_or(true, |&(pos, _)| at >= pos) {
+            self.cause = Some((at, kind));
+        }
+    }
+
+    /// Returns the leading bytes of the input failing none of `keep`,
+    /// without reading them.
+    fn peek_while(&self, keep: impl Fn(u8) -> bool) -> &'a [u8] {
+        let len = self.state.iter().take_while(|&&b| keep(b)).count();
+        &self.state[..len]
     }
 
     /// Run a parser, and restore the pre-parse state if it fails.
-    fn read_atomically<T, F>(&mut self, inner: F) -> Option<T>
+    pub fn read_atomically<T, F>(&mut self, inner: F) -> Option<T>
     where
-        F: FnOnce(&mut Parser<'_>) -> Option<T>,
+        F: FnOnce(&mut Parser<'a>) -> Option<T>,
     {
         let state = self.state;
         let result = inner(self);
         if result.is_none() {
             self.state = state;
+        } else {
+            self.furthest = self.furthest.max(self.consumed());
         }
         result
     }
 
     /// Run a parser, but fail if the entire input wasn't consumed.
     /// Doesn't run atomically.
-    pub(crate) fn parse_with<T, F>(&mut self, inner: F, kind: AddrKind) -> Result<T, AddrParseError>
+    ///
+    /// Failures are reported to the [`ParseObserver`](crate::ParseObserver)s
+    /// with `kind`, the kind of the error, which also gets the reason the
+    /// primitives noted if there is one, i.e. [`AddrKind::IsdOutOfRange`].
+    pub fn parse_with<T, F>(&mut self, inner: F, kind: AddrKind) -> Result<T, AddrParseError>
     where
-        F: FnOnce(&mut Parser<'_>) -> Option<T>,
+        F: FnOnce(&mut Parser<'a>) -> Option<T>,
     {
         let result = inner(self);
-        if self.state.is_empty() { result } else { None }.ok_or(AddrParseError(kind))
+        let trailing = result.is_some() && self.furthest <= self.consumed();
+        match result {
+            Some(value) if self.state.is_empty() => Ok(value),
+            _ => Err(self.error(kind, trailing)),
+        }
+    }
+
+    /// Run a parser on a prefix of the input, returning the value along with
+    /// the number of bytes it consumed. Fails like
+    /// [`parse_with`](Self::parse_with), except never for trailing characters.
+    pub fn parse_prefix_with<T, F>(
+        &mut self,
+        inner: F,
+        kind: AddrKind,
+    ) -> Result<(T, usize), AddrParseError>
+    where
+        F: FnOnce(&mut Parser<'a>) -> Option<T>,
+    {
+        match inner(self) {
+            Some(value) => Ok((value, self.consumed())),
+            None => Err(self.error(kind, false)),
+        }
+    }
+
+    /// Reports a rejected input and returns its error, placed where the
+    /// parsers got furthest.
+    fn error(&mut self, kind: AddrKind, trailing: bool) -> AddrParseError {
+        let offset = self.furthest.max(self.consumed());
+        crate::parse_observer::report(self.input, &kind, offset);
+        let (reason, expected) = if trailing {
+            (Some(AddrKind::TrailingCharacters), Some("end of input"))
+        } else {
+            let reason = self.cause.take().filter(|&(at, _)| at == offset).map(|(_, cause)| cause);
+            (reason, self.expected.filter(|&(at, _)| at == offset).map(|(_, what)| what))
+        };
+        let err = AddrParseError::at(kind, offset, expected);
+        match reason {
+            Some(reason) => err.with_reason(reason),
+            None => err,
+        }
     }
 
     /// Peek the next character from the input
-    fn peek_char(&self) -> Option<char> {
+    pub fn peek_char(&self) -> Option<char> {
         self.state.first().map(|&b| char::from(b))
     }
 
     /// Read the next character from the input
-    fn read_char(&mut self) -> Option<char> {
+    pub fn read_char(&mut self) -> Option<char> {
         self.state.split_first().map(|(&b, tail)| {
             self.state = tail;
             char::from(b)
@@ -83,20 +246,24 @@ impl<'a> Parser<'a> {
 
     #[must_use]
     /// Read the next character from the input if it matches the target.
-    fn read_given_char(&mut self, target: char) -> Option<()> {
-        self.read_atomically(|p| {
+    pub fn read_given_char(&mut self, target: char) -> Option<()> {
+        let read = self.read_atomically(|p| {
             p.read_char()
                 .and_then(|c| if c == target { Some(()) } else { None })
-        })
+        });
+        if read.is_none() {
+            self.expect(quoted(target));
+        }
+        read
     }
 
     /// Helper for reading separators in an indexed loop. Reads the separator
     /// character iff index > 0, then runs the parser. When used in a loop,
     /// the separator character will only be read on index > 0 (see
     /// read_ipv4_addr for an example)
-    fn read_separator<T, F>(&mut self, sep: char, index: usize, inner: F) -> Option<T>
+    pub fn read_separator<T, F>(&mut self, sep: char, index: usize, inner: F) -> Option<T>
     where
-        F: FnOnce(&mut Parser<'_>) -> Option<T>,
+        F: FnOnce(&mut Parser<'a>) -> Option<T>,
     {
         self.read_atomically(move |p| {
             if index > 0 {
@@ -106,16 +273,19 @@ impl<'a> Parser<'a> {
         })
     }
 
-    // Read a number off the front of the input in the given radix, stopping
-    // at the first non-digit character or eof. Fails if the number has more
-    // digits than max_digits or if there is no number.
-    fn read_number<T: ReadNumberHelper>(
+    /// Read a number off the front of the input in the given radix, stopping
+    /// at the first non-digit character or eof. Fails if the number has more
+    /// digits than max_digits, if it overflows `T` or if there is no number.
+    /// Numbers with leading zeros are rejected unless `allow_zero_prefix`.
+    pub fn read_number<T: ReadNumberHelper>(
         &mut self,
         radix: u32,
         max_digits: Option<usize>,
         allow_zero_prefix: bool,
     ) -> Option<T> {
-        self.read_atomically(move |p| {
+        // a rejected number is reported at its start, not at its last digit
+        let furthest = self.furthest;
+        let number = self.read_atomically(move |p| {
             let mut result = T::ZERO;
             let mut digit_count = 0;
             let has_leading_zero = p.peek_char() == Some('0');
@@ -138,11 +308,21 @@ impl<'a> Parser<'a> {
             } else {
                 Some(result)
             }
-        })
+        });
+        if number.is_none() {
+            self.furthest = furthest;
+        }
+        number
     }
 
     /// Read an IPv4 address.
-    pub(crate) fn read_ipv4_addr(&mut self) -> Option<Ipv4Addr> {
+    pub fn read_ipv4_addr(&mut self) -> Option<Ipv4Addr> {
+        #[cfg(feature = "simd")]
+        if let Some((addr, len)) = crate::simd::read_ipv4_addr(self.state) {
+            self.state = &self.state[len..];
+            self.furthest = self.furthest.max(self.consumed());
+            return Some(addr);
+        }
         self.read_atomically(|p| {
             let mut groups = [0; 4];
 
@@ -159,7 +339,7 @@ impl<'a> Parser<'a> {
     }
 
     /// Read an IPv6 Address.
-    pub(crate) fn read_ipv6_addr(&mut self) -> Option<Ipv6Addr> {
+    pub fn read_ipv6_addr(&mut self) -> Option<Ipv6Addr> {
         /// Read a chunk of an IPv6 address into `groups`. Returns the number
         /// of groups read, along with a bool indicating if an embedded
         /// trailing IPv4 address was read. Specifically, read a series of
@@ -192,6 +372,16 @@ impl<'a> Parser<'a> {
             (groups.len(), false)
         }
 
+        #[cfg(feature = "simd")]
+        if let Some(read) = crate::simd::read_ipv6_addr(self.state) {
+            self.state = &self.state[read.len..];
+            self.furthest = self.furthest.max(self.consumed());
+            if read.expects_colon {
+                self.expect(quoted(':'));
+            }
+            return Some(read.addr);
+        }
+
         self.read_atomically(|p| {
             // Read the front part of the address; either the whole thing, or up
             // to the first ::
@@ -225,114 +415,337 @@ impl<'a> Parser<'a> {
         })
     }
 
-    pub(crate) fn read_scion_addr(&mut self) -> Option<ScionAddr> {
-        /* valid AS numbers have:
-           - 2x colon ':' and 3x groups of max 4x hex digits i.e. 'ffaa:1:1067'
-           - no colon and 1x group of decimal digits
-        */
-        fn read_AS(p: &mut Parser<'_>) -> Option<u64> {
-            // parses an AS string of kind 'abcde:f013:4567'
-            let read_dotted_as = |p: &mut Parser<'_>| {
-                let mut n: u8 = 0;
-                p.read_atomically(|p| {
-                    let mut groups: [u32; 3] = [0; 3];
-
-                    for (i, slot) in groups.iter_mut().enumerate() {
-                        match p.read_separator(':', i, |p| p.read_number::<u32>(16, Some(4), true))
-                        {
-                            Some(token) => {
-                                n += 1;
-                                *slot = token;
-                            }
-                            None => {
-                                if n == 1 {
-                                    // this is an invalid AS of kind 'stuv:wxyz'
-                                    return None;
-                                }
-                            }
+    /// Read an ISD number in base 10.
+    pub fn read_isd(&mut self) -> Option<Isd> {
+        // is the ISD really encoded as a decimal Nr?!
+        let isd = self.read_number(10, Some(6), true).map(Isd::new);
+        if isd.is_none() {
+            if exceeds(self.peek_while(|b| b.is_ascii_digit()), 10, u16::MAX.into()) {
+                self.fail(AddrKind::IsdOutOfRange);
+            }
+            self.expect("an ISD number");
+        }
+        isd
+    }
+
+    /* valid AS numbers have:
+       - 2x colon ':' and 3x groups of max 4x hex digits i.e. 'ffaa:1:1067'
+       - no colon and 1x group of decimal digits
+    */
+    /// Read an AS number, either as three groups of hex digits like
+    /// `ffaa:1:1067` or as a decimal number like `64512`.
+    pub fn read_asn(&mut self) -> Option<Asn> {
+        // parses an AS string of kind 'abcde:f013:4567'
+        let read_dotted_as = |p: &mut Parser<'_>| {
+            p.read_atomically(|p| {
+                let mut asn: u64 = 0;
+
+                for i in 0..3 {
+                    // all three groups are mandatory, anything shorter
+                    // (i.e. 'stuv:wxyz' or a lone '150') is not a dotted AS
+                    let group = p.read_separator(':', i, |p| {
+                        let group = p.read_number::<u16>(16, Some(4), true);
+                        let digits = p.peek_while(|b| b.is_ascii_hexdigit());
+                        // a long first group is only a dotted one if a ':' follows
+                        let dotted = i > 0 || p.state.get(digits.len()) == Some(&b':');
+                        if group.is_none() && dotted && exceeds(digits, 16, u16::MAX.into()) {
+                            p.fail(AddrKind::AsnOutOfRange);
                         }
-                    }
-                    // println!("groups: {:?}",groups);
-                    //let as_ : u64 = ( (( groups[0]  <<16 as u64) | (groups[1] <<8 as u64)) as u64| ( groups[2]) as u64 ) as u64 ;
-                    /* let as_ : u64 = ( (( (groups[0]  as u64) <<16) | ((groups[1] as u64 )<<8 )) as u64| ( groups[2]) as u64 ) as u64 ;
-                    Some(as_) */
+                        group
+                    })?;
+                    // three 16-bit groups always fit into the 48 bits of an AS
+                    asn = (asn << 16) | u64::from(group);
+                }
 
-                    // why is this not the same :(  this is really worrying
-                    let as_string =
-                        format!("{:04x}:{:04x}:{:04x}", groups[0], groups[1], groups[2]);
+                Some(asn)
+            })
+        };
+
+        // parse a decimal AS number, which like in the Go implementation is
+        // a BGP AS number of 32 bits (max. 10 digits)
+        let read_decimal_as = |p: &mut Parser<'_>| {
+            // like a rejected number, one out of range is reported at its start
+            let furthest = p.furthest;
+            let asn = p.read_atomically(|p| {
+                p.read_number::<u32>(10, Some(10), false).map(u64::from)
+            });
+            if asn.is_none() {
+                p.furthest = furthest;
+            }
+            asn
+        };
+
+        let asn = self.read_atomically(|p| {
+            let asn = read_dotted_as(p).or_else(|| read_decimal_as(p))?;
+            Asn::try_from(asn).ok()
+        });
+        if asn.is_none() {
+            // a decimal AS that doesn't fit, dotted ones are checked above
+            let digits = self.peek_while(|b| b.is_ascii_digit());
+            let dotted = self.state.get(digits.len()) == Some(&b':');
+            if !dotted && exceeds(digits, 10, u32::MAX.into()) {
+                self.fail(AddrKind::AsnOutOfRange);
+            }
+            self.expect("an AS number");
+        }
+        asn
+    }
 
-                    //println!("as_string: {}", as_string);
+    /// Read the bytes up to the first one failing `keep`.
+    pub fn read_while(&mut self, keep: impl Fn(u8) -> bool) -> &'a [u8] {
+        let len = self.state.iter().take_while(|&&b| keep(b)).count();
+        let (head, tail) = self.state.split_at(len);
+        self.state = tail;
+        head
+    }
 
-                    Some(as_from_dotted_hex(&as_string))
-                })
-            };
+    /// Read the parts of a SCION socket address as [`parse_scion_impl`]
+    /// returns them: the IA, the host, which may be enclosed in brackets and
+    /// is not checked further, and the port if there is one.
+    ///
+    /// [`parse_scion_impl`]: crate::parse_scion_impl
+    pub(crate) fn read_scion_parts(&mut self) -> Option<(IsdAsn, String, Option<u16>)> {
+        let ia = self.read_isd_asn()?;
+        self.read_given_char(',')?;
+        let host = if self.read_given_char('[').is_some() {
+            let host = self.read_while(|b| b != b']');
+            self.read_given_char(']')?;
+            host
+        } else {
+            self.read_while(|b| !matches!(b, b'[' | b']' | b':'))
+        };
+        if host.is_empty() {
+            return None;
+        }
+        let host = std::str::from_utf8(host).ok()?.to_string();
+        let port = match self.read_given_char(':') {
+            Some(()) => Some(self.read_number(10, None, true)?),
+            None => None,
+        };
+        Some((ia, host, port))
+    }
 
-            // parse a decimal AS number in range 0-281474976710655 (max. 15 digits )
-            let read_decimal_as = |p: &mut Parser<'_>| {
-                p.read_atomically(|p| p.read_number::<u64>(10, Some(15), false))
+    /// Read an ISD range of the form `16-63`, or a single ISD.
+    pub(crate) fn read_isd_range(&mut self) -> Option<IsdRange> {
+        self.read_atomically(|p| {
+            let start = p.read_isd()?;
+            let end = match p.read_given_char('-') {
+                Some(()) => p.read_isd()?,
+                None => start,
             };
+            IsdRange::new(start, end).ok()
+        })
+    }
 
-            return read_dotted_as(p).or_else(|| {
-                let aas = read_decimal_as(p);
-                //println!("decimal_as: {}",aas.unwrap());
-                aas
-            });
-        }
+    /// Read comma-separated ISD ranges, possibly none.
+    pub(crate) fn read_isd_set(&mut self) -> Option<IsdSet> {
+        self.read_atomically(|p| {
+            let mut set = IsdSet::new();
+            if p.state.is_empty() {
+                return Some(set);
+            }
+            for i in 0.. {
+                set.insert(p.read_separator(',', i, |p| p.read_isd_range())?);
+                if p.state.is_empty() {
+                    break;
+                }
+            }
+            Some(set)
+        })
+    }
 
+    /// Read an AS prefix of the form `2:0:0/16`.
+    pub(crate) fn read_asn_prefix(&mut self) -> Option<AsnPrefix> {
         self.read_atomically(|p| {
-            // is the ISD really encoded as a decimal Nr?!
-            let isd = p.read_number(10, Some(6), true)?;
-            //  println!("isd: {}",isd);
+            let asn = p.read_asn()?;
+            p.read_given_char('/')?;
+            let len = p.read_number(10, Some(2), false)?;
+            let prefix = AsnPrefix::new(asn, len).ok()?;
+            (prefix.network() == asn).then_some(prefix)
+        })
+    }
 
+    /// Read an ISD-AS pair of the form `19-ffaa:1:1067`.
+    pub fn read_isd_asn(&mut self) -> Option<IsdAsn> {
+        self.read_atomically(|p| {
+            let isd = p.read_isd()?;
             p.read_given_char('-')?;
+            let asn = p.read_asn()?;
+            Some(IsdAsn::new(isd, asn))
+        })
+    }
 
-            let _as = read_AS(p)?;
-
-            //     println!("as: {}",_as);
+    /// Read a SCION address of the form `19-ffaa:1:1067,10.0.0.1`, whose host
+    /// may be an IP address, in brackets or not, or a service like `CS`.
+    pub fn read_scion_addr(&mut self) -> Option<ScionAddr> {
+        self.read_atomically(|p| {
+            let ia = p.read_isd_asn()?;
 
             p.read_given_char(',')?;
 
-            p.read_given_char('[');
-            let host = p.read_ipv4_addr().map(IpAddr::V4).or_else(|| {
-                //  p.read_given_char('[');
-                let res = //     p.read_ipv4_addr().map(IpAddr::V4).or_else(
-                    (||{p.read_ipv6_addr().map(IpAddr::V6)})();
-                //);
-                //  p.read_given_char(']');
-                res
+            // the host may be enclosed in brackets, as Display does for IPv6
+            let host = p.read_atomically(|p| {
+                let bracketed = p.read_given_char('[').is_some();
+                let host = p
+                    .read_ipv4_addr()
+                    .map(IpAddr::V4)
+                    .or_else(|| p.read_ipv6_addr().map(IpAddr::V6))?;
+                if bracketed {
+                    p.read_given_char(']')?;
+                }
+                Some(HostAddr::Ip(host))
             });
-            p.read_given_char(']');
             // let port = p.read_port();
 
-            Some(ScionAddr::new(make_ia(isd, _as), host?))
+            let host = host.or_else(|| p.read_svc().map(HostAddr::Svc));
+            if host.is_none() {
+                p.expect("a host address");
+            }
+            Some(ScionAddr::from_ia(ia, host?))
+        })
+    }
+
+    /// Read a service address like `CS` or `DS_M`.
+    fn read_svc(&mut self) -> Option<Svc> {
+        self.read_atomically(|p| {
+            let len =
+                p.state.iter().take_while(|&&b| b.is_ascii_alphanumeric() || b == b'_').count();
+            let (name, tail) = p.state.split_at(len);
+            let svc = Svc::from_str(std::str::from_utf8(name).ok()?).ok()?;
+            p.state = tail;
+            Some(svc)
         })
     }
 
     /// Read an IP Address, either IPv4 or IPv6.
-    fn read_ip_addr(&mut self) -> Option<IpAddr> {
+    pub fn read_ip_addr(&mut self) -> Option<IpAddr> {
         self.read_ipv4_addr()
             .map(IpAddr::V4)
             .or_else(move || self.read_ipv6_addr().map(IpAddr::V6))
     }
 
+    /// Read an IPv4 network of the form `10.0.0.0/8`.
+    pub fn read_ipv4_net(&mut self) -> Option<Ipv4Net> {
+        self.read_atomically(|p| {
+            let addr = p.read_ipv4_addr()?;
+            p.read_given_char('/')?;
+            let net = Ipv4Net::new(addr, p.read_number(10, Some(2), false)?).ok()?;
+            (net.network() == addr).then_some(net)
+        })
+    }
+
+    /// Read an IPv6 network of the form `2001:db8::/32`.
+    pub fn read_ipv6_net(&mut self) -> Option<Ipv6Net> {
+        self.read_atomically(|p| {
+            let addr = p.read_ipv6_addr()?;
+            p.read_given_char('/')?;
+            let net = Ipv6Net::new(addr, p.read_number(10, Some(3), false)?).ok()?;
+            (net.network() == addr).then_some(net)
+        })
+    }
+
+    /// Read an IP network, either IPv4 or IPv6.
+    pub fn read_ip_net(&mut self) -> Option<IpNet> {
+        self.read_ipv4_net()
+            .map(IpNet::V4)
+            .or_else(move || self.read_ipv6_net().map(IpNet::V6))
+    }
+
+    /// Read an IP network within an AS of the form `1-ff00:0:110,10.0.0.0/8`.
+    pub fn read_scion_net(&mut self) -> Option<ScionNet> {
+        self.read_atomically(|p| {
+            let ia = p.read_isd_asn()?;
+            p.read_given_char(',')?;
+            Some(ScionNet::new(ia, p.read_ip_net()?))
+        })
+    }
+
+    /// Read a SCION or an IP address.
+    pub fn read_l3_addr(&mut self) -> Option<L3Addr> {
+        self.read_scion_addr()
+            .map(L3Addr::SCION)
+            .or_else(|| self.read_ip_addr().map(L3Addr::IP))
+    }
+
     /// Read a `:` followed by a port in base 10.
-    fn read_port(&mut self) -> Option<u16> {
+    pub fn read_port(&mut self) -> Option<u16> {
+        self.read_port_with(|p| p.read_number(10, None, true))
+    }
+
+    /// Read a `:` followed by a port in base 10 or a service name, see
+    /// [`register_service`](crate::register_service). The parsers of
+    /// `std` compatible types only accept numbers, only those of SCION
+    /// addresses and the ones with a default port take names.
+    pub fn read_port_or_service(&mut self) -> Option<u16> {
+        self.read_port_with(|p| p.read_number(10, None, true).or_else(|| p.read_service_name()))
+    }
+
+    fn read_port_with(&mut self, read: impl FnOnce(&mut Parser<'_>) -> Option<u16>) -> Option<u16> {
         self.read_atomically(|p| {
+            if p.state.is_empty() {
+                p.fail(AddrKind::MissingPort);
+            }
             p.read_given_char(':')?;
-            p.read_number(10, None, true)
+            if p.state.is_empty() {
+                p.fail(AddrKind::MissingPort);
+            }
+            let port = read(p);
+            if port.is_none() {
+                p.expect("a port number");
+            }
+            port
+        })
+    }
+
+    /// Read a service name like `https` and look up its port.
+    fn read_service_name(&mut self) -> Option<u16> {
+        self.read_atomically(|p| {
+            if !p.peek_char()?.is_ascii_alphabetic() {
+                return None;
+            }
+            let len = p.state.iter().take_while(|&&b| b.is_ascii_alphanumeric() || b == b'-').count();
+            let (name, tail) = p.state.split_at(len);
+            let port = crate::services::service_port(std::str::from_utf8(name).ok()?)?;
+            p.state = tail;
+            Some(port)
         })
     }
 
-    /// Read a `%` followed by a scope ID in base 10.
+    /// Read a `%` followed by a scope ID in base 10, or by an interface
+    /// name that is resolved to its index (feature `os-zone`).
     fn read_scope_id(&mut self) -> Option<u32> {
         self.read_atomically(|p| {
             p.read_given_char('%')?;
-            p.read_number(10, None, true)
+            p.read_number(10, None, true).or_else(|| p.read_zone_name())
+        })
+    }
+
+    /// Read an interface name and resolve it to the interface index.
+    #[cfg(feature = "os-zone")]
+    fn read_zone_name(&mut self) -> Option<u32> {
+        self.read_atomically(|p| {
+            let len = p
+                .state
+                .iter()
+                .take_while(|&&b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_' || b == b'.')
+                .count();
+            if len == 0 || len >= crate::zone::IF_NAMESIZE {
+                return None;
+            }
+            let (name, tail) = p.state.split_at(len);
+            let index = crate::zone::if_name_to_index(std::str::from_utf8(name).ok()?)?;
+            p.state = tail;
+            Some(index)
         })
     }
 
+    #[cfg(not(feature = "os-zone"))]
+    fn read_zone_name(&mut self) -> Option<u32> {
+        None
+    }
+
     /// Read an IPv4 address with a port.
-    pub(crate) fn read_socket_addr_v4(&mut self) -> Option<SocketAddrV4> {
+    pub fn read_socket_addr_v4(&mut self) -> Option<SocketAddrV4> {
         self.read_atomically(|p| {
             let ip = p.read_ipv4_addr()?;
             let port = p.read_port()?;
@@ -341,7 +754,7 @@ impl<'a> Parser<'a> {
     }
 
     /// Read an IPv6 address with a port.
-    pub(crate) fn read_socket_addr_v6(&mut self) -> Option<SocketAddrV6> {
+    pub fn read_socket_addr_v6(&mut self) -> Option<SocketAddrV6> {
         self.read_atomically(|p| {
             p.read_given_char('[')?;
             let ip = p.read_ipv6_addr()?;
@@ -354,27 +767,151 @@ impl<'a> Parser<'a> {
     }
 
     /// Read an IP address with a port
-    pub(crate) fn read_socket_addr(&mut self) -> Option<SocketAddr> {
+    pub fn read_socket_addr(&mut self) -> Option<SocketAddr> {
         self.read_socket_addr_v4()
             .map(SocketAddr::V4)
             .or_else(|| self.read_socket_addr_v6().map(SocketAddr::V6))
             .or_else(|| self.read_socket_addr_scion().map(SocketAddr::SCION))
     }
 
-    pub(crate) fn read_socket_addr_scion(&mut self) -> Option<SocketAddrScion> {
+    /// Read socket addresses separated by `sep` and optional spaces, possibly
+    /// none.
+    fn read_socket_addr_list(&mut self, sep: char) -> Option<Vec<SocketAddr>> {
+        let mut addrs = Vec::new();
+        self.read_while(|b| b == b' ');
+        while !self.state.is_empty() {
+            if !addrs.is_empty() {
+                self.read_given_char(sep)?;
+                self.read_while(|b| b == b' ');
+            }
+            addrs.push(self.read_socket_addr()?);
+            self.read_while(|b| b == b' ');
+        }
+        Some(addrs)
+    }
+
+    /// Read a SCION address with a port, i.e. `19-ffaa:1:1067,[::1]:443` or
+    /// `[19-ffaa:1:1067,::1]:443`.
+    pub fn read_socket_addr_scion(&mut self) -> Option<SocketAddrScion> {
         self.read_atomically(|p| {
-            let scion_addr = p.read_scion_addr()?;
-            let port = p.read_port()?;
+            let scion_addr = p.read_bracketed_scion_addr().or_else(|| p.read_scion_addr())?;
+            let port = p.read_port_or_service()?;
 
-            Some(SocketAddrScion::new1(scion_addr, port))
+            Some(SocketAddrScion::from_scion_addr(scion_addr, port))
         })
     }
+
+    /// Read a SCION address with an optional port.
+    fn read_socket_addr_scion_or(&mut self, default_port: u16) -> Option<SocketAddrScion> {
+        self.read_atomically(|p| {
+            let scion_addr = p.read_bracketed_scion_addr().or_else(|| p.read_scion_addr())?;
+            let port = p.read_port_or_service().unwrap_or(default_port);
+            Some(SocketAddrScion::from_scion_addr(scion_addr, port))
+        })
+    }
+
+    /// Read a SCION address enclosed in brackets as a whole, the form URIs
+    /// and the `scion` tool use, i.e. `[19-ffaa:1:1067,127.0.0.1]`.
+    fn read_bracketed_scion_addr(&mut self) -> Option<ScionAddr> {
+        self.read_atomically(|p| {
+            p.read_given_char('[')?;
+            let scion_addr = p.read_scion_addr()?;
+            p.read_given_char(']')?;
+            Some(scion_addr)
+        })
+    }
+
+    /// Read an IP or SCION address with an optional port. IPv6 addresses
+    /// with a port must be enclosed in brackets.
+    fn read_socket_addr_or(&mut self, default_port: u16) -> Option<SocketAddr> {
+        let v4 = |p: &mut Parser<'_>| {
+            p.read_atomically(|p| {
+                let ip = p.read_ipv4_addr()?;
+                let port = p.read_port_or_service().unwrap_or(default_port);
+                Some(SocketAddr::V4(SocketAddrV4::new(ip, port)))
+            })
+        };
+        let v6 = |p: &mut Parser<'_>| {
+            p.read_atomically(|p| {
+                let bracketed = p.read_given_char('[').is_some();
+                let ip = p.read_ipv6_addr()?;
+                let scope_id = p.read_scope_id().unwrap_or(0);
+                let port = if bracketed {
+                    p.read_given_char(']')?;
+                    p.read_port_or_service().unwrap_or(default_port)
+                } else {
+                    default_port
+                };
+                Some(SocketAddr::V6(SocketAddrV6::new(ip, port, 0, scope_id)))
+            })
+        };
+        v4(self)
+            .or_else(|| v6(self))
+            .or_else(|| self.read_socket_addr_scion_or(default_port).map(SocketAddr::SCION))
+    }
+}
+
+impl SocketAddr {
+    /// Parses an IP or SCION socket address whose port may be omitted, using
+    /// `default_port` then; i.e. `10.0.0.1`, `::1` or `19-ffaa:1:1067,127.0.0.1`.
+    ///
+    /// IPv6 hosts must be enclosed in brackets to be followed by a port.
+    ///
+    /// This is the common case of a `--remote` command-line flag:
+    ///
+    /// ```
+    /// use scionnet::SocketAddr;
+    ///
+    /// for remote in ["19-ffaa:1:1067,[::1]", "[19-ffaa:1:1067,::1]:443", "10.0.0.1"] {
+    ///     let addr = SocketAddr::parse_with_default_port(remote, 443).unwrap();
+    ///     assert_eq!(addr.port(), 443);
+    /// }
+    /// ```
+    pub fn parse_with_default_port(s: &str, default_port: u16) -> Result<Self, AddrParseError> {
+        Parser::new(s.as_bytes()).parse_with(|p| p.read_socket_addr_or(default_port), AddrKind::Socket)
+    }
+
+    /// Parses an IP or SCION socket address in the lenient mode, where a
+    /// missing port means port 0, see
+    /// [`parse_with_default_port`](Self::parse_with_default_port).
+    ///
+    /// `FromStr` stays strict, and `Display` always writes the port, so the
+    /// result can be written back and read with `FromStr`.
+    pub fn parse_lenient(s: &str) -> Result<Self, AddrParseError> {
+        Self::parse_with_default_port(s, 0)
+    }
+}
+
+/// Parses a list of IP and SCION socket addresses separated by `sep`, i.e. a
+/// config value like `1-ff00:0:110,10.0.0.1:53, 10.0.0.2:53`. Spaces around
+/// the separators are ignored.
+///
+/// The addresses are parsed one after the other rather than split at `sep`
+/// first, so `sep` may also occur within them, like the `,` of SCION
+/// addresses. A [`ParseObserver`](crate::ParseObserver) is told the offset
+/// in the whole list where the first invalid element went wrong.
+///
+/// ```
+/// use scionnet::parse_addr_list;
+///
+/// let addrs = parse_addr_list("1-ff00:0:110,10.0.0.1:53, [::1]:53", ',').unwrap();
+/// assert_eq!(addrs.len(), 2);
+/// assert!(parse_addr_list("10.0.0.1:53,10.0.0.2", ',').is_err());
+/// ```
+pub fn parse_addr_list(s: &str, sep: char) -> Result<Vec<SocketAddr>, AddrParseError> {
+    Parser::new(s.as_bytes()).parse_with(|p| p.read_socket_addr_list(sep), AddrKind::Socket)
 }
 
 impl IpAddr {
     pub fn parse_ascii(b: &[u8]) -> Result<Self, AddrParseError> {
         Parser::new(b).parse_with(|p| p.read_ip_addr(), AddrKind::Ip)
     }
+
+    /// Like [`parse_ascii`](Self::parse_ascii), but parses only the start of
+    /// `b`, returning the number of bytes consumed along with the value.
+    pub fn parse_ascii_prefix(b: &[u8]) -> Result<(Self, usize), AddrParseError> {
+        Parser::new(b).parse_prefix_with(|p| p.read_ip_addr(), AddrKind::Ip)
+    }
 }
 
 impl FromStr for IpAddr {
@@ -388,11 +925,18 @@ impl Ipv4Addr {
     pub fn parse_ascii(b: &[u8]) -> Result<Self, AddrParseError> {
         // don't try to parse if too long
         if b.len() > 15 {
-            Err(AddrParseError(AddrKind::Ipv4))
+            crate::parse_observer::report(b, &AddrKind::Ipv4, 15);
+            Err(AddrParseError::at(AddrKind::Ipv4, 15, None))
         } else {
             Parser::new(b).parse_with(|p| p.read_ipv4_addr(), AddrKind::Ipv4)
         }
     }
+
+    /// Like [`parse_ascii`](Self::parse_ascii), but parses only the start of
+    /// `b`, returning the number of bytes consumed along with the value.
+    pub fn parse_ascii_prefix(b: &[u8]) -> Result<(Self, usize), AddrParseError> {
+        Parser::new(b).parse_prefix_with(|p| p.read_ipv4_addr(), AddrKind::Ipv4)
+    }
 }
 
 impl FromStr for Ipv4Addr {
@@ -406,6 +950,12 @@ impl Ipv6Addr {
     pub fn parse_ascii(b: &[u8]) -> Result<Self, AddrParseError> {
         Parser::new(b).parse_with(|p| p.read_ipv6_addr(), AddrKind::Ipv6)
     }
+
+    /// Like [`parse_ascii`](Self::parse_ascii), but parses only the start of
+    /// `b`, returning the number of bytes consumed along with the value.
+    pub fn parse_ascii_prefix(b: &[u8]) -> Result<(Self, usize), AddrParseError> {
+        Parser::new(b).parse_prefix_with(|p| p.read_ipv6_addr(), AddrKind::Ipv6)
+    }
 }
 
 impl FromStr for Ipv6Addr {
@@ -419,12 +969,24 @@ impl SocketAddrV4 {
     pub fn parse_ascii(b: &[u8]) -> Result<Self, AddrParseError> {
         Parser::new(b).parse_with(|p| p.read_socket_addr_v4(), AddrKind::SocketV4)
     }
+
+    /// Like [`parse_ascii`](Self::parse_ascii), but parses only the start of
+    /// `b`, returning the number of bytes consumed along with the value.
+    pub fn parse_ascii_prefix(b: &[u8]) -> Result<(Self, usize), AddrParseError> {
+        Parser::new(b).parse_prefix_with(|p| p.read_socket_addr_v4(), AddrKind::SocketV4)
+    }
 }
 
 impl ScionAddr {
     pub fn parse_ascii(b: &[u8]) -> Result<Self, AddrParseError> {
         Parser::new(b).parse_with(|p| p.read_scion_addr(), AddrKind::Scion)
     }
+
+    /// Like [`parse_ascii`](Self::parse_ascii), but parses only the start of
+    /// `b`, returning the number of bytes consumed along with the value.
+    pub fn parse_ascii_prefix(b: &[u8]) -> Result<(Self, usize), AddrParseError> {
+        Parser::new(b).parse_prefix_with(|p| p.read_scion_addr(), AddrKind::Scion)
+    }
 }
 
 impl FromStr for SocketAddrV4 {
@@ -438,12 +1000,89 @@ impl SocketAddrV6 {
     pub fn parse_ascii(b: &[u8]) -> Result<Self, AddrParseError> {
         Parser::new(b).parse_with(|p| p.read_socket_addr_v6(), AddrKind::SocketV6)
     }
+
+    /// Like [`parse_ascii`](Self::parse_ascii), but parses only the start of
+    /// `b`, returning the number of bytes consumed along with the value.
+    pub fn parse_ascii_prefix(b: &[u8]) -> Result<(Self, usize), AddrParseError> {
+        Parser::new(b).parse_prefix_with(|p| p.read_socket_addr_v6(), AddrKind::SocketV6)
+    }
 }
 
+/// The longest decoded SCION socket address accepted by `from_url_encoded`,
+/// comfortably above `65535-ffff:ffff:ffff,[<IPv6 with IPv4 tail>%<zone>]:65535`.
+const MAX_URL_DECODED_LEN: usize = 128;
+
 impl SocketAddrScion {
     pub fn parse_ascii(b: &[u8]) -> Result<Self, AddrParseError> {
         Parser::new(b).parse_with(|p| p.read_socket_addr_scion(), AddrKind::SocketScion)
     }
+
+    /// Like [`parse_ascii`](Self::parse_ascii), but parses only the start of
+    /// `b`, returning the number of bytes consumed along with the value.
+    pub fn parse_ascii_prefix(b: &[u8]) -> Result<(Self, usize), AddrParseError> {
+        Parser::new(b).parse_prefix_with(|p| p.read_socket_addr_scion(), AddrKind::SocketScion)
+    }
+
+    /// Parses a SCION socket address whose port may be omitted, using
+    /// `default_port` then; i.e. `19-ffaa:1:1067,127.0.0.1`.
+    ///
+    /// IPv6 hosts must be enclosed in brackets to be followed by a port.
+    pub fn parse_with_default_port(s: &str, default_port: u16) -> Result<Self, AddrParseError> {
+        Parser::new(s.as_bytes())
+            .parse_with(|p| p.read_socket_addr_scion_or(default_port), AddrKind::SocketScion)
+    }
+
+    /// Parses a SCION socket address in the lenient mode, where a missing
+    /// port means port 0; i.e. `19-ffaa:1:1067,127.0.0.1` for binding an
+    /// ephemeral port. `FromStr` stays strict.
+    pub fn parse_lenient(s: &str) -> Result<Self, AddrParseError> {
+        Self::parse_with_default_port(s, 0)
+    }
+
+    /// Parses a SCION socket address that was percent-encoded for use in a URL,
+    /// i.e. `19-ffaa%3A1%3A1067%2C%5B127.0.0.1%5D%3A53`.
+    ///
+    /// Encoded and plain characters may be mixed. Decoding is strict: every `%`
+    /// must be followed by two hex digits, the decoded text must be printable
+    /// ASCII and inputs longer than three times the longest textual SCION socket
+    /// address are rejected without being decoded. `+` is not treated as a space.
+    pub fn from_url_encoded(s: &str) -> Result<Self, AddrParseError> {
+        let reject = |offset: usize| {
+            crate::parse_observer::report(s.as_bytes(), &AddrKind::SocketScion, offset);
+            AddrParseError::at(AddrKind::SocketScion, offset, None)
+        };
+        if s.len() > 3 * MAX_URL_DECODED_LEN {
+            return Err(reject(3 * MAX_URL_DECODED_LEN));
+        }
+
+        fn hex_digit(c: u8) -> Option<u8> {
+            (c as char).to_digit(16).map(|d| d as u8)
+        }
+
+        let mut buf = [0u8; MAX_URL_DECODED_LEN];
+        let mut len = 0;
+        let mut input = s.as_bytes();
+        while let Some((&c, rest)) = input.split_first() {
+            let offset = s.len() - input.len();
+            let (decoded, rest) = match c {
+                b'%' => match rest {
+                    [hi, lo, rest @ ..] => match (hex_digit(*hi), hex_digit(*lo)) {
+                        (Some(hi), Some(lo)) => (hi << 4 | lo, rest),
+                        _ => return Err(reject(offset)),
+                    },
+                    _ => return Err(reject(offset)),
+                },
+                _ => (c, rest),
+            };
+            if !decoded.is_ascii_graphic() || len == buf.len() {
+                return Err(reject(offset));
+            }
+            buf[len] = decoded;
+            len += 1;
+            input = rest;
+        }
+        Self::parse_ascii(&buf[..len])
+    }
 }
 
 impl FromStr for SocketAddrV6 {
@@ -453,6 +1092,25 @@ impl FromStr for SocketAddrV6 {
     }
 }
 
+impl L3Addr {
+    pub fn parse_ascii(b: &[u8]) -> Result<Self, AddrParseError> {
+        Parser::new(b).parse_with(|p| p.read_l3_addr(), AddrKind::L3Addr)
+    }
+
+    /// Like [`parse_ascii`](Self::parse_ascii), but parses only the start of
+    /// `b`, returning the number of bytes consumed along with the value.
+    pub fn parse_ascii_prefix(b: &[u8]) -> Result<(Self, usize), AddrParseError> {
+        Parser::new(b).parse_prefix_with(|p| p.read_l3_addr(), AddrKind::L3Addr)
+    }
+}
+
+impl FromStr for L3Addr {
+    type Err = AddrParseError;
+    fn from_str(s: &str) -> Result<L3Addr, AddrParseError> {
+        Self::parse_ascii(s.as_bytes())
+    }
+}
+
 impl FromStr for ScionAddr {
     type Err = AddrParseError;
     fn from_str(s: &str) -> Result<ScionAddr, AddrParseError> {
@@ -460,6 +1118,207 @@ impl FromStr for ScionAddr {
     }
 }
 
+impl Isd {
+    pub fn parse_ascii(b: &[u8]) -> Result<Self, AddrParseError> {
+        Parser::new(b).parse_with(|p| p.read_isd(), AddrKind::Isd)
+    }
+
+    /// Like [`parse_ascii`](Self::parse_ascii), but parses only the start of
+    /// `b`, returning the number of bytes consumed along with the value.
+    pub fn parse_ascii_prefix(b: &[u8]) -> Result<(Self, usize), AddrParseError> {
+        Parser::new(b).parse_prefix_with(|p| p.read_isd(), AddrKind::Isd)
+    }
+}
+
+impl FromStr for Isd {
+    type Err = AddrParseError;
+    fn from_str(s: &str) -> Result<Isd, AddrParseError> {
+        Self::parse_ascii(s.as_bytes())
+    }
+}
+
+impl Asn {
+    pub fn parse_ascii(b: &[u8]) -> Result<Self, AddrParseError> {
+        Parser::new(b).parse_with(|p| p.read_asn(), AddrKind::Asn)
+    }
+
+    /// Like [`parse_ascii`](Self::parse_ascii), but parses only the start of
+    /// `b`, returning the number of bytes consumed along with the value.
+    pub fn parse_ascii_prefix(b: &[u8]) -> Result<(Self, usize), AddrParseError> {
+        Parser::new(b).parse_prefix_with(|p| p.read_asn(), AddrKind::Asn)
+    }
+}
+
+impl FromStr for Asn {
+    type Err = AddrParseError;
+    fn from_str(s: &str) -> Result<Asn, AddrParseError> {
+        Self::parse_ascii(s.as_bytes())
+    }
+}
+
+impl IsdRange {
+    pub fn parse_ascii(b: &[u8]) -> Result<Self, AddrParseError> {
+        Parser::new(b).parse_with(|p| p.read_isd_range(), AddrKind::IsdRange)
+    }
+
+    /// Like [`parse_ascii`](Self::parse_ascii), but parses only the start of
+    /// `b`, returning the number of bytes consumed along with the value.
+    pub fn parse_ascii_prefix(b: &[u8]) -> Result<(Self, usize), AddrParseError> {
+        Parser::new(b).parse_prefix_with(|p| p.read_isd_range(), AddrKind::IsdRange)
+    }
+}
+
+impl FromStr for IsdRange {
+    type Err = AddrParseError;
+    fn from_str(s: &str) -> Result<IsdRange, AddrParseError> {
+        Self::parse_ascii(s.as_bytes())
+    }
+}
+
+impl IsdSet {
+    /// Parses comma-separated ranges, i.e. `1-15,64-4094`. The ranges may
+    /// overlap and come in any order.
+    pub fn parse_ascii(b: &[u8]) -> Result<Self, AddrParseError> {
+        Parser::new(b).parse_with(|p| p.read_isd_set(), AddrKind::IsdRange)
+    }
+
+    /// Like [`parse_ascii`](Self::parse_ascii), but parses only the start of
+    /// `b`, returning the number of bytes consumed along with the value.
+    pub fn parse_ascii_prefix(b: &[u8]) -> Result<(Self, usize), AddrParseError> {
+        Parser::new(b).parse_prefix_with(|p| p.read_isd_set(), AddrKind::IsdRange)
+    }
+}
+
+impl FromStr for IsdSet {
+    type Err = AddrParseError;
+    fn from_str(s: &str) -> Result<IsdSet, AddrParseError> {
+        Self::parse_ascii(s.as_bytes())
+    }
+}
+
+impl AsnPrefix {
+    /// Parses a prefix of the form `2:0:0/16`. Unlike [`AsnPrefix::new`],
+    /// this fails if bits past the prefix length are set, as they hint at a
+    /// typo.
+    pub fn parse_ascii(b: &[u8]) -> Result<Self, AddrParseError> {
+        Parser::new(b).parse_with(|p| p.read_asn_prefix(), AddrKind::AsnPrefix)
+    }
+
+    /// Like [`parse_ascii`](Self::parse_ascii), but parses only the start of
+    /// `b`, returning the number of bytes consumed along with the value.
+    pub fn parse_ascii_prefix(b: &[u8]) -> Result<(Self, usize), AddrParseError> {
+        Parser::new(b).parse_prefix_with(|p| p.read_asn_prefix(), AddrKind::AsnPrefix)
+    }
+}
+
+impl FromStr for AsnPrefix {
+    type Err = AddrParseError;
+    fn from_str(s: &str) -> Result<AsnPrefix, AddrParseError> {
+        Self::parse_ascii(s.as_bytes())
+    }
+}
+
+impl Ipv4Net {
+    /// Parses a network of the form `10.0.0.0/8`. Unlike [`Ipv4Net::new`], this
+    /// fails if bits past the prefix length are set, as they hint at a typo.
+    pub fn parse_ascii(b: &[u8]) -> Result<Self, AddrParseError> {
+        Parser::new(b).parse_with(|p| p.read_ipv4_net(), AddrKind::Ipv4Net)
+    }
+
+    /// Like [`parse_ascii`](Self::parse_ascii), but parses only the start of
+    /// `b`, returning the number of bytes consumed along with the value.
+    pub fn parse_ascii_prefix(b: &[u8]) -> Result<(Self, usize), AddrParseError> {
+        Parser::new(b).parse_prefix_with(|p| p.read_ipv4_net(), AddrKind::Ipv4Net)
+    }
+}
+
+impl FromStr for Ipv4Net {
+    type Err = AddrParseError;
+    fn from_str(s: &str) -> Result<Ipv4Net, AddrParseError> {
+        Self::parse_ascii(s.as_bytes())
+    }
+}
+
+impl Ipv6Net {
+    /// Parses a network of the form `2001:db8::/32`, rejecting set host bits
+    /// like [`Ipv4Net::parse_ascii`].
+    pub fn parse_ascii(b: &[u8]) -> Result<Self, AddrParseError> {
+        Parser::new(b).parse_with(|p| p.read_ipv6_net(), AddrKind::Ipv6Net)
+    }
+
+    /// Like [`parse_ascii`](Self::parse_ascii), but parses only the start of
+    /// `b`, returning the number of bytes consumed along with the value.
+    pub fn parse_ascii_prefix(b: &[u8]) -> Result<(Self, usize), AddrParseError> {
+        Parser::new(b).parse_prefix_with(|p| p.read_ipv6_net(), AddrKind::Ipv6Net)
+    }
+}
+
+impl FromStr for Ipv6Net {
+    type Err = AddrParseError;
+    fn from_str(s: &str) -> Result<Ipv6Net, AddrParseError> {
+        Self::parse_ascii(s.as_bytes())
+    }
+}
+
+impl IpNet {
+    /// Parses an IPv4 or an IPv6 network, like [`Ipv4Net::parse_ascii`] and
+    /// [`Ipv6Net::parse_ascii`].
+    pub fn parse_ascii(b: &[u8]) -> Result<Self, AddrParseError> {
+        Parser::new(b).parse_with(|p| p.read_ip_net(), AddrKind::IpNet)
+    }
+
+    /// Like [`parse_ascii`](Self::parse_ascii), but parses only the start of
+    /// `b`, returning the number of bytes consumed along with the value.
+    pub fn parse_ascii_prefix(b: &[u8]) -> Result<(Self, usize), AddrParseError> {
+        Parser::new(b).parse_prefix_with(|p| p.read_ip_net(), AddrKind::IpNet)
+    }
+}
+
+impl FromStr for IpNet {
+    type Err = AddrParseError;
+    fn from_str(s: &str) -> Result<IpNet, AddrParseError> {
+        Self::parse_ascii(s.as_bytes())
+    }
+}
+
+impl ScionNet {
+    pub fn parse_ascii(b: &[u8]) -> Result<Self, AddrParseError> {
+        Parser::new(b).parse_with(|p| p.read_scion_net(), AddrKind::ScionNet)
+    }
+
+    /// Like [`parse_ascii`](Self::parse_ascii), but parses only the start of
+    /// `b`, returning the number of bytes consumed along with the value.
+    pub fn parse_ascii_prefix(b: &[u8]) -> Result<(Self, usize), AddrParseError> {
+        Parser::new(b).parse_prefix_with(|p| p.read_scion_net(), AddrKind::ScionNet)
+    }
+}
+
+impl FromStr for ScionNet {
+    type Err = AddrParseError;
+    fn from_str(s: &str) -> Result<ScionNet, AddrParseError> {
+        Self::parse_ascii(s.as_bytes())
+    }
+}
+
+impl IsdAsn {
+    pub fn parse_ascii(b: &[u8]) -> Result<Self, AddrParseError> {
+        Parser::new(b).parse_with(|p| p.read_isd_asn(), AddrKind::Ia)
+    }
+
+    /// Like [`parse_ascii`](Self::parse_ascii), but parses only the start of
+    /// `b`, returning the number of bytes consumed along with the value.
+    pub fn parse_ascii_prefix(b: &[u8]) -> Result<(Self, usize), AddrParseError> {
+        Parser::new(b).parse_prefix_with(|p| p.read_isd_asn(), AddrKind::Ia)
+    }
+}
+
+impl FromStr for IsdAsn {
+    type Err = AddrParseError;
+    fn from_str(s: &str) -> Result<IsdAsn, AddrParseError> {
+        Self::parse_ascii(s.as_bytes())
+    }
+}
+
 impl FromStr for SocketAddrScion {
     type Err = AddrParseError;
     fn from_str(s: &str) -> Result<SocketAddrScion, AddrParseError> {
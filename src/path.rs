@@ -0,0 +1,259 @@
+//! The standard SCION dataplane path: a path meta header followed by up to
+//! three info fields, one per path segment, and the hop fields of all segments.
+//!
+//! ```text
+//!  0                   1                   2                   3
+//!  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! | C |  CurrHF   |    RSV    |  Seg0Len  |  Seg1Len  |  Seg2Len  |
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! ```
+
+use std::io;
+
+/// The encoded length of an [`InfoField`].
+pub const INFO_FIELD_LEN: usize = 8;
+/// The encoded length of a [`HopField`].
+pub const HOP_FIELD_LEN: usize = 12;
+/// The encoded length of the path meta header.
+pub const META_LEN: usize = 4;
+/// The most hop fields a path can have, as CurrHF has 6 bits.
+pub const MAX_HOP_FIELDS: usize = 64;
+
+fn invalid_path(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn buffer_too_short() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, "buffer too short for the encoded path")
+}
+
+/// The info field of a path segment.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct InfoField {
+    /// Whether the segment is traversed in construction direction.
+    pub cons_dir: bool,
+    /// Whether the segment ends with a peering hop.
+    pub peer: bool,
+    /// The updatable segment identifier used for MAC chaining.
+    pub seg_id: u16,
+    /// The creation time of the segment, in seconds since the Unix epoch.
+    pub timestamp: u32,
+}
+
+impl InfoField {
+    /// Writes the field to the start of `buf`, which must hold [`INFO_FIELD_LEN`] bytes.
+    pub fn encode_to(&self, buf: &mut [u8]) -> io::Result<usize> {
+        let buf = buf.get_mut(..INFO_FIELD_LEN).ok_or_else(buffer_too_short)?;
+        buf[0] = u8::from(self.peer) << 1 | u8::from(self.cons_dir);
+        buf[1] = 0;
+        buf[2..4].copy_from_slice(&self.seg_id.to_be_bytes());
+        buf[4..8].copy_from_slice(&self.timestamp.to_be_bytes());
+        Ok(INFO_FIELD_LEN)
+    }
+
+    /// Reads the field from the start of `buf`.
+    pub fn decode_from(buf: &[u8]) -> io::Result<InfoField> {
+        let buf = buf
+            .get(..INFO_FIELD_LEN)
+            .ok_or_else(|| invalid_path("truncated info field"))?;
+        Ok(InfoField {
+            cons_dir: buf[0] & 0x01 != 0,
+            peer: buf[0] & 0x02 != 0,
+            seg_id: u16::from_be_bytes([buf[2], buf[3]]),
+            timestamp: u32::from_be_bytes([buf[4], buf[5], buf[6], buf[7]]),
+        })
+    }
+}
+
+/// The hop field of an AS on a path segment.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct HopField {
+    /// Whether the router at the ingress interface should process the packet.
+    pub ingress_router_alert: bool,
+    /// Whether the router at the egress interface should process the packet.
+    pub egress_router_alert: bool,
+    /// The relative expiration time, in units of 24h/256 after the timestamp
+    /// of the info field.
+    pub exp_time: u8,
+    /// The ingress interface in construction direction.
+    pub cons_ingress: u16,
+    /// The egress interface in construction direction.
+    pub cons_egress: u16,
+    pub mac: [u8; 6],
+}
+
+impl HopField {
+    /// Writes the field to the start of `buf`, which must hold [`HOP_FIELD_LEN`] bytes.
+    pub fn encode_to(&self, buf: &mut [u8]) -> io::Result<usize> {
+        let buf = buf.get_mut(..HOP_FIELD_LEN).ok_or_else(buffer_too_short)?;
+        buf[0] = u8::from(self.ingress_router_alert) << 1 | u8::from(self.egress_router_alert);
+        buf[1] = self.exp_time;
+        buf[2..4].copy_from_slice(&self.cons_ingress.to_be_bytes());
+        buf[4..6].copy_from_slice(&self.cons_egress.to_be_bytes());
+        buf[6..12].copy_from_slice(&self.mac);
+        Ok(HOP_FIELD_LEN)
+    }
+
+    /// Reads the field from the start of `buf`.
+    pub fn decode_from(buf: &[u8]) -> io::Result<HopField> {
+        let buf = buf
+            .get(..HOP_FIELD_LEN)
+            .ok_or_else(|| invalid_path("truncated hop field"))?;
+        let mut mac = [0u8; 6];
+        mac.copy_from_slice(&buf[6..12]);
+        Ok(HopField {
+            ingress_router_alert: buf[0] & 0x02 != 0,
+            egress_router_alert: buf[0] & 0x01 != 0,
+            exp_time: buf[1],
+            cons_ingress: u16::from_be_bytes([buf[2], buf[3]]),
+            cons_egress: u16::from_be_bytes([buf[4], buf[5]]),
+            mac,
+        })
+    }
+}
+
+/// A decoded standard SCION path.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Path {
+    /// The index of the info field of the current segment.
+    pub curr_inf: u8,
+    /// The index of the current hop field.
+    pub curr_hf: u8,
+    /// The number of hop fields of each segment; unused segments are `0`.
+    pub seg_len: [u8; 3],
+    pub info_fields: Vec<InfoField>,
+    pub hop_fields: Vec<HopField>,
+}
+
+impl Path {
+    /// Returns the length of the encoded path.
+    #[must_use]
+    pub fn encoded_len(&self) -> usize {
+        META_LEN + INFO_FIELD_LEN * self.info_fields.len() + HOP_FIELD_LEN * self.hop_fields.len()
+    }
+
+    fn check(&self) -> io::Result<()> {
+        let [s0, s1, s2] = self.seg_len;
+        if s0 == 0 || (s1 == 0 && s2 != 0) || self.seg_len.iter().any(|&s| s > 0x3f) {
+            return Err(invalid_path("SCION path has an invalid segment sequence"));
+        }
+        let num_inf = self.seg_len.iter().filter(|&&s| s != 0).count();
+        let num_hf = self.seg_len.iter().map(|&s| usize::from(s)).sum::<usize>();
+        if self.info_fields.len() != num_inf || self.hop_fields.len() != num_hf {
+            return Err(invalid_path("SCION path length does not match its segment lengths"));
+        }
+        if num_hf > MAX_HOP_FIELDS || usize::from(self.curr_hf) >= MAX_HOP_FIELDS {
+            return Err(invalid_path("SCION path has more hop fields than CurrHF can index"));
+        }
+        if usize::from(self.curr_inf) >= num_inf || usize::from(self.curr_hf) >= num_hf {
+            return Err(invalid_path("SCION path points past its last info or hop field"));
+        }
+        Ok(())
+    }
+
+    /// Writes the path to the start of `buf`, returning the number of bytes
+    /// written.
+    ///
+    /// Fails if the segment lengths do not match the fields or `buf` is
+    /// shorter than [`encoded_len`](Self::encoded_len).
+    pub fn encode_to(&self, buf: &mut [u8]) -> io::Result<usize> {
+        self.check()?;
+        let len = self.encoded_len();
+        let buf = buf.get_mut(..len).ok_or_else(buffer_too_short)?;
+        let meta = u32::from(self.curr_inf) << 30
+            | u32::from(self.curr_hf) << 24
+            | u32::from(self.seg_len[0]) << 12
+            | u32::from(self.seg_len[1]) << 6
+            | u32::from(self.seg_len[2]);
+        buf[..META_LEN].copy_from_slice(&meta.to_be_bytes());
+        let mut offset = META_LEN;
+        for info in &self.info_fields {
+            offset += info.encode_to(&mut buf[offset..])?;
+        }
+        for hop in &self.hop_fields {
+            offset += hop.encode_to(&mut buf[offset..])?;
+        }
+        Ok(offset)
+    }
+
+    /// Returns the encoded path.
+    #[must_use = "use encode_to to check for errors"]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = vec![0u8; self.encoded_len()];
+        match self.encode_to(&mut buf) {
+            Ok(_) => buf,
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Reads a path from the start of `buf`, which may be followed by other
+    /// data; [`encoded_len`](Self::encoded_len) tells how many bytes were read.
+    pub fn decode_from(buf: &[u8]) -> io::Result<Path> {
+        let meta = buf
+            .get(..META_LEN)
+            .ok_or_else(|| invalid_path("SCION path shorter than its meta header"))?;
+        let meta = u32::from_be_bytes([meta[0], meta[1], meta[2], meta[3]]);
+        let seg_len = [(meta >> 12 & 0x3f) as u8, (meta >> 6 & 0x3f) as u8, (meta & 0x3f) as u8];
+        let num_inf = seg_len.iter().filter(|&&s| s != 0).count();
+        let num_hf = seg_len.iter().map(|&s| usize::from(s)).sum::<usize>();
+        if buf.len() < META_LEN + INFO_FIELD_LEN * num_inf + HOP_FIELD_LEN * num_hf {
+            return Err(invalid_path("SCION path length does not match its segment lengths"));
+        }
+
+        let mut offset = META_LEN;
+        let mut info_fields = Vec::with_capacity(num_inf);
+        for _ in 0..num_inf {
+            info_fields.push(InfoField::decode_from(&buf[offset..])?);
+            offset += INFO_FIELD_LEN;
+        }
+        let mut hop_fields = Vec::with_capacity(num_hf);
+        for _ in 0..num_hf {
+            hop_fields.push(HopField::decode_from(&buf[offset..])?);
+            offset += HOP_FIELD_LEN;
+        }
+        let path = Path {
+            curr_inf: (meta >> 30) as u8,
+            curr_hf: (meta >> 24 & 0x3f) as u8,
+            seg_len,
+            info_fields,
+            hop_fields,
+        };
+        path.check()?;
+        Ok(path)
+    }
+
+    /// Returns the number of segments of the path.
+    #[must_use]
+    #[inline]
+    pub fn num_segments(&self) -> usize {
+        self.info_fields.len()
+    }
+
+    /// Reverses the path in place, so that it leads back to the sender, i.e.
+    /// for replying to a received packet.
+    ///
+    /// The segments and hop fields are reversed, the direction of every segment
+    /// is flipped, and the current info and hop field point to the same fields
+    /// as before.
+    pub fn reverse(&mut self) -> io::Result<()> {
+        self.check()?;
+        let num_inf = self.info_fields.len();
+        self.seg_len[..num_inf].reverse();
+        self.info_fields.reverse();
+        for info in &mut self.info_fields {
+            info.cons_dir = !info.cons_dir;
+        }
+        self.hop_fields.reverse();
+        self.curr_inf = (num_inf - 1 - usize::from(self.curr_inf)) as u8;
+        self.curr_hf = (self.hop_fields.len() - 1 - usize::from(self.curr_hf)) as u8;
+        Ok(())
+    }
+
+    /// Returns the reversed path, see [`reverse`](Self::reverse).
+    pub fn reversed(&self) -> io::Result<Path> {
+        let mut path = self.clone();
+        path.reverse()?;
+        Ok(path)
+    }
+}
@@ -0,0 +1,522 @@
+//! A read-only on-disk database of paths, for forwarders that restart often
+//! and want their paths back without asking the daemon.
+//!
+//! The file is memory-mapped and the paths are read in place through
+//! [`ScionPathRef`] views. All integers are little endian:
+//!
+//! ```text
+//! header   magic "SCPATHDB" (8), version (2), flags (2), IA count (4),
+//!          path count (4), reserved (4), checksum (8)
+//! index    per destination IA, sorted: IA (8), first path (4), path count (4)
+//! offsets  per path: offset of its record after the offsets (4)
+//! records  src IA (8), dst IA (8), expiration in Unix seconds (8),
+//!          path type (1), next hop family (1: 0, 4 or 6), MTU (2),
+//!          next hop port (2), interface count (2), latency count (2),
+//!          reserved (2), raw path length (4), next hop address (16),
+//!          then the interfaces (IA (8), id (8) each), the latencies
+//!          (nanoseconds (8) each, all ones if unknown) and the raw path
+//! ```
+//!
+//! The checksum is the 64-bit FNV-1a hash of everything after the header;
+//! [`PathDb::open`] rejects files that fail it or any structural check.
+
+use crate::{IsdAsn, PathInterface, ScionPath};
+use std::hash::Hasher;
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The current version of the format.
+pub const VERSION: u16 = 1;
+
+const MAGIC: &[u8; 8] = b"SCPATHDB";
+const HEADER_LEN: usize = 32;
+const INDEX_ENTRY_LEN: usize = 16;
+const RECORD_LEN: usize = 56;
+const INTERFACE_LEN: usize = 16;
+const UNKNOWN_LATENCY: u64 = u64::MAX;
+
+fn corrupt(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("corrupt path database: {}", msg))
+}
+
+fn le_u16(b: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([b[at], b[at + 1]])
+}
+
+fn le_u32(b: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([b[at], b[at + 1], b[at + 2], b[at + 3]])
+}
+
+fn le_u64(b: &[u8], at: usize) -> u64 {
+    let mut v = [0u8; 8];
+    v.copy_from_slice(&b[at..at + 8]);
+    u64::from_le_bytes(v)
+}
+
+fn fnv1a(data: &[u8]) -> u64 {
+    let mut hasher = Fnv1a::default();
+    hasher.write(data);
+    hasher.finish()
+}
+
+/// The 64-bit FNV-1a hash, which unlike `DefaultHasher` is the same in
+/// every release and on every run.
+pub(crate) struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for Fnv1a {
+    fn write(&mut self, bytes: &[u8]) {
+        self.0 = bytes
+            .iter()
+            .fold(self.0, |h, &b| (h ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3));
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// A path stored in a [`PathDb`], read in place.
+#[derive(Copy, Clone)]
+pub struct ScionPathRef<'a> {
+    record: &'a [u8],
+}
+
+impl<'a> ScionPathRef<'a> {
+    fn interface_count(&self) -> usize {
+        usize::from(le_u16(self.record, 30))
+    }
+
+    fn latency_count(&self) -> usize {
+        usize::from(le_u16(self.record, 32))
+    }
+
+    fn raw_offset(&self) -> usize {
+        RECORD_LEN + INTERFACE_LEN * self.interface_count() + 8 * self.latency_count()
+    }
+
+    /// Returns the length of the record, checking that `record` holds it.
+    fn checked_len(record: &[u8]) -> Option<usize> {
+        if record.len() < RECORD_LEN {
+            return None;
+        }
+        let path = ScionPathRef { record };
+        let len = path.raw_offset() + le_u32(record, 36) as usize;
+        let family_ok = matches!(record[25], 0 | 4 | 6);
+        (len <= record.len() && family_ok).then_some(len)
+    }
+
+    #[must_use]
+    pub fn src(&self) -> IsdAsn {
+        IsdAsn::from(le_u64(self.record, 0))
+    }
+
+    #[must_use]
+    pub fn dst(&self) -> IsdAsn {
+        IsdAsn::from(le_u64(self.record, 8))
+    }
+
+    #[must_use]
+    pub fn expiration(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(le_u64(self.record, 16))
+    }
+
+    #[must_use]
+    pub fn path_type(&self) -> u8 {
+        self.record[24]
+    }
+
+    #[must_use]
+    pub fn mtu(&self) -> u16 {
+        le_u16(self.record, 26)
+    }
+
+    #[must_use]
+    pub fn next_hop(&self) -> Option<std::net::SocketAddr> {
+        let port = le_u16(self.record, 28);
+        let mut octets = [0u8; 16];
+        octets.copy_from_slice(&self.record[40..56]);
+        let ip: std::net::IpAddr = match self.record[25] {
+            4 => std::net::Ipv4Addr::new(octets[0], octets[1], octets[2], octets[3]).into(),
+            6 => std::net::Ipv6Addr::from(octets).into(),
+            _ => return None,
+        };
+        Some(std::net::SocketAddr::new(ip, port))
+    }
+
+    /// Returns the encoded dataplane path, borrowed from the database.
+    #[must_use]
+    pub fn raw(&self) -> &'a [u8] {
+        let start = self.raw_offset();
+        &self.record[start..start + le_u32(self.record, 36) as usize]
+    }
+
+    pub fn interfaces(&self) -> impl Iterator<Item = PathInterface> + 'a {
+        let record = self.record;
+        (0..self.interface_count()).map(move |i| {
+            let at = RECORD_LEN + INTERFACE_LEN * i;
+            PathInterface { ia: IsdAsn::from(le_u64(record, at)), id: le_u64(record, at + 8) }
+        })
+    }
+
+    pub fn latency(&self) -> impl Iterator<Item = Option<Duration>> + 'a {
+        let record = self.record;
+        let start = RECORD_LEN + INTERFACE_LEN * self.interface_count();
+        (0..self.latency_count()).map(move |i| match le_u64(record, start + 8 * i) {
+            UNKNOWN_LATENCY => None,
+            nanos => Some(Duration::from_nanos(nanos)),
+        })
+    }
+
+    /// Returns `true` if the path is no longer valid at `now`, see
+    /// [`ScionPath::is_expired`].
+    #[must_use]
+    pub fn is_expired(&self, now: SystemTime) -> bool {
+        !self.raw().is_empty() && self.expiration() <= now
+    }
+
+    /// Copies the path out of the database.
+    #[must_use]
+    pub fn to_path(&self) -> ScionPath {
+        ScionPath {
+            src: self.src(),
+            dst: self.dst(),
+            path_type: self.path_type(),
+            raw: self.raw().to_vec(),
+            next_hop: self.next_hop(),
+            interfaces: self.interfaces().collect(),
+            mtu: self.mtu(),
+            expiration: self.expiration(),
+            latency: self.latency().collect(),
+        }
+    }
+}
+
+impl std::fmt::Debug for ScionPathRef<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ScionPathRef")
+            .field("src", &self.src())
+            .field("dst", &self.dst())
+            .field("raw_len", &self.raw().len())
+            .finish()
+    }
+}
+
+fn encode_record(path: &ScionPath, out: &mut Vec<u8>) -> io::Result<()> {
+    let too_long =
+        || io::Error::new(io::ErrorKind::InvalidInput, "path too long for the path database");
+    let interfaces = u16::try_from(path.interfaces.len()).map_err(|_| too_long())?;
+    let latencies = u16::try_from(path.latency.len()).map_err(|_| too_long())?;
+    let raw_len = u32::try_from(path.raw.len()).map_err(|_| too_long())?;
+    let expiration = path.expiration.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let (family, port, mut addr) = match path.next_hop {
+        Some(std::net::SocketAddr::V4(a)) => (4u8, a.port(), a.ip().octets().to_vec()),
+        Some(std::net::SocketAddr::V6(a)) => (6u8, a.port(), a.ip().octets().to_vec()),
+        None => (0u8, 0, Vec::new()),
+    };
+    addr.resize(16, 0);
+
+    out.extend_from_slice(&path.src.to_u64().to_le_bytes());
+    out.extend_from_slice(&path.dst.to_u64().to_le_bytes());
+    out.extend_from_slice(&expiration.to_le_bytes());
+    out.extend_from_slice(&[path.path_type, family]);
+    out.extend_from_slice(&path.mtu.to_le_bytes());
+    out.extend_from_slice(&port.to_le_bytes());
+    out.extend_from_slice(&interfaces.to_le_bytes());
+    out.extend_from_slice(&latencies.to_le_bytes());
+    out.extend_from_slice(&[0, 0]);
+    out.extend_from_slice(&raw_len.to_le_bytes());
+    out.extend_from_slice(&addr);
+    for intf in &path.interfaces {
+        out.extend_from_slice(&intf.ia.to_u64().to_le_bytes());
+        out.extend_from_slice(&intf.id.to_le_bytes());
+    }
+    for latency in &path.latency {
+        let nanos = latency.map_or(UNKNOWN_LATENCY, |d| {
+            u64::try_from(d.as_nanos()).unwrap_or(UNKNOWN_LATENCY - 1)
+        });
+        out.extend_from_slice(&nanos.to_le_bytes());
+    }
+    out.extend_from_slice(&path.raw);
+    Ok(())
+}
+
+/// Returns the encoded database of `paths`, grouped by destination IA and
+/// otherwise in the given order.
+pub fn encode<'a, I: IntoIterator<Item = &'a ScionPath>>(paths: I) -> io::Result<Vec<u8>> {
+    let mut paths: Vec<&ScionPath> = paths.into_iter().collect();
+    paths.sort_by_key(|p| p.dst.to_u64());
+    let too_many =
+        || io::Error::new(io::ErrorKind::InvalidInput, "too many paths for the path database");
+    let path_count = u32::try_from(paths.len()).map_err(|_| too_many())?;
+
+    let mut index: Vec<(u64, u32, u32)> = Vec::new();
+    for (i, path) in paths.iter().enumerate() {
+        match index.last_mut() {
+            Some(entry) if entry.0 == path.dst.to_u64() => entry.2 += 1,
+            _ => index.push((path.dst.to_u64(), i as u32, 1)),
+        }
+    }
+    let mut records = Vec::new();
+    let mut offsets = Vec::with_capacity(paths.len());
+    for path in &paths {
+        offsets.push(u32::try_from(records.len()).map_err(|_| too_many())?);
+        encode_record(path, &mut records)?;
+    }
+
+    let body_len = INDEX_ENTRY_LEN * index.len() + 4 * offsets.len() + records.len();
+    let mut body = Vec::with_capacity(body_len);
+    for (ia, first, count) in &index {
+        body.extend_from_slice(&ia.to_le_bytes());
+        body.extend_from_slice(&first.to_le_bytes());
+        body.extend_from_slice(&count.to_le_bytes());
+    }
+    for offset in &offsets {
+        body.extend_from_slice(&offset.to_le_bytes());
+    }
+    body.extend_from_slice(&records);
+
+    let mut db = Vec::with_capacity(HEADER_LEN + body.len());
+    db.extend_from_slice(MAGIC);
+    db.extend_from_slice(&VERSION.to_le_bytes());
+    db.extend_from_slice(&0u16.to_le_bytes());
+    db.extend_from_slice(&(index.len() as u32).to_le_bytes());
+    db.extend_from_slice(&path_count.to_le_bytes());
+    db.extend_from_slice(&0u32.to_le_bytes());
+    db.extend_from_slice(&fnv1a(&body).to_le_bytes());
+    db.extend_from_slice(&body);
+    Ok(db)
+}
+
+/// Writes the database of `paths` to `file`, replacing it atomically.
+pub fn write<'a, P, I>(file: P, paths: I) -> io::Result<()>
+where
+    P: AsRef<Path>,
+    I: IntoIterator<Item = &'a ScionPath>,
+{
+    let file = file.as_ref();
+    let db = encode(paths)?;
+    let mut tmp = file.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let mut out = std::fs::File::create(&tmp)?;
+    out.write_all(&db)?;
+    out.sync_all()?;
+    std::fs::rename(&tmp, file)
+}
+
+/// Rewrites the database `input` to `output` without the paths expired at
+/// `now` and without duplicates, returning the number of paths kept.
+/// `input` and `output` may be the same file.
+pub fn compact<P, Q>(input: P, output: Q, now: SystemTime) -> io::Result<usize>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    let db = PathDb::open(input)?;
+    let mut kept: Vec<ScionPath> = Vec::with_capacity(db.len());
+    for path in db.iter().filter(|p| !p.is_expired(now)) {
+        let path = path.to_path();
+        if !kept.iter().any(|k| k.dst == path.dst && k.src == path.src && k.raw == path.raw) {
+            kept.push(path);
+        }
+    }
+    drop(db);
+    write(output, &kept)?;
+    Ok(kept.len())
+}
+
+enum Bytes {
+    #[cfg(unix)]
+    Mapped { ptr: *mut libc::c_void, len: usize },
+    Owned(Vec<u8>),
+}
+
+// SAFETY: the mapping is read-only and owned by `Bytes`
+#[cfg(unix)]
+unsafe impl Send for Bytes {}
+#[cfg(unix)]
+unsafe impl Sync for Bytes {}
+
+impl Bytes {
+    #[cfg(unix)]
+    fn map(file: &std::fs::File) -> io::Result<Bytes> {
+        use std::os::unix::io::AsRawFd;
+        let len = usize::try_from(file.metadata()?.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "path database too large"))?;
+        if len == 0 {
+            return Ok(Bytes::Owned(Vec::new()));
+        }
+        // SAFETY: a fresh private read-only mapping of the whole file; the
+        // pointer is checked before use
+        let ptr = unsafe {
+            let fd = file.as_raw_fd();
+            libc::mmap(std::ptr::null_mut(), len, libc::PROT_READ, libc::MAP_PRIVATE, fd, 0)
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Bytes::Mapped { ptr, len })
+    }
+
+    #[cfg(not(unix))]
+    fn map(mut file: &std::fs::File) -> io::Result<Bytes> {
+        use std::io::Read;
+        let mut buf = Vec::new();
+        file.read_to_end(&mut buf)?;
+        Ok(Bytes::Owned(buf))
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        match self {
+            // SAFETY: the mapping stays valid and readable for `len` bytes
+            // until `self` is dropped
+            #[cfg(unix)]
+            Bytes::Mapped { ptr, len } => unsafe {
+                std::slice::from_raw_parts(*ptr as *const u8, *len)
+            },
+            Bytes::Owned(buf) => buf,
+        }
+    }
+}
+
+impl Drop for Bytes {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Bytes::Mapped { ptr, len } = *self {
+            // SAFETY: `ptr` and `len` describe a mapping made by `map`
+            unsafe { libc::munmap(ptr, len) };
+        }
+    }
+}
+
+/// A path database opened for reading, see the [module docs](self).
+///
+/// The file must not be modified while it is open; [`write`] and
+/// [`compact`] replace files instead of modifying them.
+pub struct PathDb {
+    bytes: Bytes,
+    ias: usize,
+    paths: usize,
+}
+
+impl PathDb {
+    /// Maps and checks the database at `file`.
+    pub fn open<P: AsRef<Path>>(file: P) -> io::Result<PathDb> {
+        let file = std::fs::File::open(file)?;
+        Self::check(Bytes::map(&file)?)
+    }
+
+    /// Checks the encoded database `bytes`, i.e. as returned by [`encode`].
+    pub fn from_bytes(bytes: Vec<u8>) -> io::Result<PathDb> {
+        Self::check(Bytes::Owned(bytes))
+    }
+
+    fn check(bytes: Bytes) -> io::Result<PathDb> {
+        let b = bytes.as_slice();
+        if b.len() < HEADER_LEN || &b[..8] != MAGIC {
+            return Err(corrupt("bad magic"));
+        }
+        if le_u16(b, 8) != VERSION {
+            let msg = "unsupported path database version";
+            return Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+        }
+        let (ias, paths) = (le_u32(b, 12) as usize, le_u32(b, 16) as usize);
+        if fnv1a(&b[HEADER_LEN..]) != le_u64(b, 24) {
+            return Err(corrupt("checksum mismatch"));
+        }
+        let records_start = HEADER_LEN + INDEX_ENTRY_LEN * ias + 4 * paths;
+        if records_start > b.len() {
+            return Err(corrupt("truncated index"));
+        }
+        let db = PathDb { bytes, ias, paths };
+        let (mut prev, mut next) = (None, 0);
+        for i in 0..ias {
+            let (ia, first, count) = db.index_entry(i);
+            if prev.is_some_and(|p| p >= ia) || first != next || count == 0 {
+                return Err(corrupt("unsorted or inconsistent IA index"));
+            }
+            prev = Some(ia);
+            next = first + count;
+        }
+        if next != paths {
+            return Err(corrupt("IA index does not cover all paths"));
+        }
+        let records = &db.bytes.as_slice()[records_start..];
+        for i in 0..paths {
+            let at = HEADER_LEN + INDEX_ENTRY_LEN * ias + 4 * i;
+            let offset = le_u32(db.bytes.as_slice(), at) as usize;
+            let valid = records.get(offset..).and_then(ScionPathRef::checked_len).is_some();
+            if !valid {
+                return Err(corrupt("path record out of bounds"));
+            }
+        }
+        for i in 0..ias {
+            let (ia, first, count) = db.index_entry(i);
+            if (first..first + count).any(|p| db.path(p).dst().to_u64() != ia) {
+                return Err(corrupt("path filed under the wrong IA"));
+            }
+        }
+        Ok(db)
+    }
+
+    fn index_entry(&self, i: usize) -> (u64, usize, usize) {
+        let at = HEADER_LEN + INDEX_ENTRY_LEN * i;
+        let b = self.bytes.as_slice();
+        (le_u64(b, at), le_u32(b, at + 8) as usize, le_u32(b, at + 12) as usize)
+    }
+
+    fn path(&self, i: usize) -> ScionPathRef<'_> {
+        let b = self.bytes.as_slice();
+        let offsets = HEADER_LEN + INDEX_ENTRY_LEN * self.ias;
+        let start = offsets + 4 * self.paths + le_u32(b, offsets + 4 * i) as usize;
+        let len = ScionPathRef::checked_len(&b[start..]).unwrap_or(0);
+        ScionPathRef { record: &b[start..start + len] }
+    }
+
+    /// Returns the number of paths.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.paths
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.paths == 0
+    }
+
+    /// Returns the destination IAs with paths, in ascending order.
+    pub fn ias(&self) -> impl Iterator<Item = IsdAsn> + '_ {
+        (0..self.ias).map(move |i| IsdAsn::from(self.index_entry(i).0))
+    }
+
+    /// Returns all paths, ordered by destination IA.
+    pub fn iter(&self) -> impl Iterator<Item = ScionPathRef<'_>> + '_ {
+        (0..self.paths).map(move |i| self.path(i))
+    }
+
+    /// Returns the paths to `dst`, found by binary search.
+    pub fn paths_to(&self, dst: IsdAsn) -> impl Iterator<Item = ScionPathRef<'_>> + '_ {
+        let mut lo = 0;
+        let mut hi = self.ias;
+        let mut found = 0..0;
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            let (ia, first, count) = self.index_entry(mid);
+            match ia.cmp(&dst.to_u64()) {
+                std::cmp::Ordering::Less => lo = mid + 1,
+                std::cmp::Ordering::Greater => hi = mid,
+                std::cmp::Ordering::Equal => {
+                    found = first..first + count;
+                    break;
+                }
+            }
+        }
+        found.map(move |i| self.path(i))
+    }
+}
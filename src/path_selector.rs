@@ -0,0 +1,84 @@
+use crate::ScionPath;
+use std::time::{Duration, SystemTime};
+
+/// A strategy for choosing the path to send on among the paths to a
+/// destination, i.e. those returned by the SCION daemon.
+///
+/// Selectors only pick; filtering by a [`Policy`](crate::Policy) happens before.
+pub trait PathSelector: Send + Sync {
+    /// Returns the index of the path to use, or `None` if none is usable.
+    fn select(&self, paths: &[ScionPath]) -> Option<usize>;
+}
+
+impl<F: Fn(&[ScionPath]) -> Option<usize> + Send + Sync> PathSelector for F {
+    fn select(&self, paths: &[ScionPath]) -> Option<usize> {
+        self(paths)
+    }
+}
+
+/// Returns the indices of the paths that have not expired yet.
+fn usable(paths: &[ScionPath]) -> impl Iterator<Item = usize> + '_ {
+    let now = SystemTime::now();
+    (0..paths.len()).filter(move |&i| !paths[i].is_expired(now))
+}
+
+/// Selects the first path that has not expired.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct FirstPath;
+
+impl PathSelector for FirstPath {
+    fn select(&self, paths: &[ScionPath]) -> Option<usize> {
+        usable(paths).next()
+    }
+}
+
+/// Selects the path with the fewest AS hops, the first one on ties.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ShortestPath;
+
+impl PathSelector for ShortestPath {
+    fn select(&self, paths: &[ScionPath]) -> Option<usize> {
+        usable(paths).min_by_key(|&i| paths[i].hop_count())
+    }
+}
+
+/// Selects the path with the lowest sum of the latencies declared for its
+/// hops. Paths with hops of unknown latency come after all paths whose
+/// latency is fully known.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct LowestLatency;
+
+impl LowestLatency {
+    fn key(path: &ScionPath) -> (bool, Duration) {
+        let unknown = path.latency.iter().any(Option::is_none);
+        (unknown, path.latency.iter().flatten().sum())
+    }
+}
+
+impl PathSelector for LowestLatency {
+    fn select(&self, paths: &[ScionPath]) -> Option<usize> {
+        usable(paths).min_by_key(|&i| Self::key(&paths[i]))
+    }
+}
+
+/// Selects the shortest path whose MTU fits packets of `min_mtu` bytes, or
+/// the path with the largest MTU if none does.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct MtuAware {
+    pub min_mtu: u16,
+}
+
+impl MtuAware {
+    pub fn new(min_mtu: u16) -> MtuAware {
+        MtuAware { min_mtu }
+    }
+}
+
+impl PathSelector for MtuAware {
+    fn select(&self, paths: &[ScionPath]) -> Option<usize> {
+        usable(paths)
+            .filter(|&i| paths[i].mtu >= self.min_mtu)
+            .min_by_key(|&i| paths[i].hop_count())
+            .or_else(|| usable(paths).max_by_key(|&i| (paths[i].mtu, std::cmp::Reverse(i))))
+    }
+}
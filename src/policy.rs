@@ -0,0 +1,433 @@
+use crate::{Asn, Isd, IsdAsn, ScionPath};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// An error parsing a path policy.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PolicyError {
+    /// A hop predicate is malformed.
+    InvalidPredicate { predicate: String, reason: String },
+    /// An ACL entry does not start with `+` or `-`.
+    InvalidAclEntry { entry: String },
+    /// The last entry of an ACL does not match every hop.
+    MissingDefault,
+    /// A sequence is malformed.
+    InvalidSequence { sequence: String, reason: String },
+}
+
+impl fmt::Display for PolicyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PolicyError::InvalidPredicate { predicate, reason } => {
+                write!(f, "invalid hop predicate '{}': {}", predicate, reason)
+            }
+            PolicyError::InvalidAclEntry { entry } => {
+                write!(f, "invalid ACL entry '{}': expected '+' or '-'", entry)
+            }
+            PolicyError::MissingDefault => f.write_str("the last ACL entry must match every hop"),
+            PolicyError::InvalidSequence { sequence, reason } => {
+                write!(f, "invalid sequence '{}': {}", sequence, reason)
+            }
+        }
+    }
+}
+
+impl Error for PolicyError {}
+
+/// A pattern for the hops of a path: `ISD-AS#IF,IF`, where `0` is a wildcard
+/// for each part and trailing parts may be omitted.
+///
+/// * `1` and `1-0` match every AS of ISD 1,
+/// * `1-ff00:0:133#2` matches AS `1-ff00:0:133` entered or left through interface 2,
+/// * `1-ff00:0:133#2,1` matches the AS entered through 2 and left through 1.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct HopPredicate {
+    pub isd: Isd,
+    pub asn: Asn,
+    /// No, one, or an ingress and an egress interface id.
+    pub ifids: Vec<u64>,
+}
+
+impl HopPredicate {
+    /// Returns the predicate matching every hop.
+    pub fn any() -> HopPredicate {
+        HopPredicate::default()
+    }
+
+    /// Returns `true` if the predicate matches every hop.
+    #[must_use]
+    pub fn is_any(&self) -> bool {
+        self.isd.is_wildcard() && self.asn.is_wildcard() && self.ifids.iter().all(|&id| id == 0)
+    }
+
+    fn matches_ia(&self, ia: IsdAsn) -> bool {
+        (self.isd.is_wildcard() || self.isd == ia.isd())
+            && (self.asn.is_wildcard() || self.asn == ia.asn())
+    }
+
+    /// Returns `true` if the predicate matches a hop through `ia`, entered
+    /// through `ingress` and left through `egress`; `0` marks the ends of a path.
+    #[must_use]
+    pub fn matches(&self, ia: IsdAsn, ingress: u64, egress: u64) -> bool {
+        self.matches_ia(ia)
+            && match self.ifids[..] {
+                [] | [0] => true,
+                [id] => id == ingress || id == egress,
+                [i, e] => (i == 0 || i == ingress) && (e == 0 || e == egress),
+                _ => false,
+            }
+    }
+
+    /// Returns `true` if the predicate matches the interface `id` of `ia`.
+    #[must_use]
+    pub fn matches_interface(&self, ia: IsdAsn, id: u64) -> bool {
+        self.matches_ia(ia) && self.ifids.iter().all(|&i| i == 0 || i == id)
+    }
+}
+
+impl FromStr for HopPredicate {
+    type Err = PolicyError;
+
+    fn from_str(s: &str) -> Result<HopPredicate, PolicyError> {
+        let invalid = |reason: &str| PolicyError::InvalidPredicate {
+            predicate: s.to_string(),
+            reason: reason.to_string(),
+        };
+        let (ia, ifids) = match s.split_once('#') {
+            Some((ia, ifids)) => (ia, Some(ifids)),
+            None => (s, None),
+        };
+        let (isd, asn) = match ia.split_once('-') {
+            Some((isd, asn)) => (isd, Some(asn)),
+            None => (ia, None),
+        };
+        let isd: Isd = isd.parse().map_err(|_| invalid("invalid ISD"))?;
+        let asn: Asn = match asn {
+            Some(asn) => asn.parse().map_err(|_| invalid("invalid AS"))?,
+            None => Asn::WILDCARD,
+        };
+        if isd.is_wildcard() && !asn.is_wildcard() {
+            return Err(invalid("a wildcard ISD requires a wildcard AS"));
+        }
+        let ifids = match ifids {
+            Some(ifids) => ifids
+                .split(',')
+                .map(|id| id.parse::<u64>().map_err(|_| invalid("invalid interface id")))
+                .collect::<Result<Vec<_>, _>>()?,
+            None => Vec::new(),
+        };
+        if ifids.len() > 2 {
+            return Err(invalid("at most two interface ids are allowed"));
+        }
+        if asn.is_wildcard() && ifids.iter().any(|&id| id != 0) {
+            return Err(invalid("interface ids require a specific AS"));
+        }
+        Ok(HopPredicate { isd, asn, ifids })
+    }
+}
+
+impl fmt::Display for HopPredicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.isd, self.asn)?;
+        for (i, id) in self.ifids.iter().enumerate() {
+            f.write_str(if i == 0 { "#" } else { "," })?;
+            write!(f, "{}", id)?;
+        }
+        Ok(())
+    }
+}
+
+/// An entry of an [`Acl`]: `+ 1-ff00:0:133#1` allows, `- 1` denies the hops
+/// matching the predicate. A lone `+` or `-` matches every hop.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct AclEntry {
+    pub allow: bool,
+    pub rule: HopPredicate,
+}
+
+impl FromStr for AclEntry {
+    type Err = PolicyError;
+
+    fn from_str(s: &str) -> Result<AclEntry, PolicyError> {
+        let s = s.trim();
+        let allow = match s.chars().next() {
+            Some('+') => true,
+            Some('-') => false,
+            _ => return Err(PolicyError::InvalidAclEntry { entry: s.to_string() }),
+        };
+        let rule = match s[1..].trim() {
+            "" => HopPredicate::any(),
+            rule => rule.parse()?,
+        };
+        Ok(AclEntry { allow, rule })
+    }
+}
+
+impl fmt::Display for AclEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(if self.allow { "+" } else { "-" })?;
+        if !self.rule.is_any() {
+            write!(f, " {}", self.rule)?;
+        }
+        Ok(())
+    }
+}
+
+/// An access list for the interfaces of a path.
+///
+/// Every interface of a path is checked against the entries in order, and the
+/// first matching entry decides; a path is allowed if all its interfaces are.
+/// The last entry has to match every hop, so that every interface is decided.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Acl {
+    entries: Vec<AclEntry>,
+}
+
+impl Acl {
+    pub fn new(entries: Vec<AclEntry>) -> Result<Acl, PolicyError> {
+        match entries.last() {
+            Some(last) if last.rule.is_any() => Ok(Acl { entries }),
+            _ => Err(PolicyError::MissingDefault),
+        }
+    }
+
+    /// Parses the entries of an ACL, i.e. `["+ 1-ff00:0:133#1", "- 1", "+"]`.
+    pub fn parse<'a, I: IntoIterator<Item = &'a str>>(entries: I) -> Result<Acl, PolicyError> {
+        Acl::new(entries.into_iter().map(str::parse).collect::<Result<_, _>>()?)
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn entries(&self) -> &[AclEntry] {
+        &self.entries
+    }
+
+    /// Returns `true` if every interface of `path` is allowed.
+    #[must_use]
+    pub fn allows(&self, path: &ScionPath) -> bool {
+        path.interfaces.iter().all(|intf| {
+            self.entries
+                .iter()
+                .find(|e| e.rule.matches_interface(intf.ia, intf.id))
+                .is_some_and(|e| e.allow)
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum SeqNode {
+    Hop(HopPredicate),
+    Concat(Vec<SeqNode>),
+    Alt(Vec<SeqNode>),
+    Repeat { node: Box<SeqNode>, min: usize, max: Option<usize> },
+}
+
+type Hop = (IsdAsn, u64, u64);
+
+fn match_node(node: &SeqNode, hops: &[Hop], pos: usize, k: &mut dyn FnMut(usize) -> bool) -> bool {
+    match node {
+        SeqNode::Hop(pred) => match hops.get(pos) {
+            Some(&(ia, ingress, egress)) if pred.matches(ia, ingress, egress) => k(pos + 1),
+            _ => false,
+        },
+        SeqNode::Concat(nodes) => match_concat(nodes, hops, pos, k),
+        SeqNode::Alt(nodes) => nodes.iter().any(|n| match_node(n, hops, pos, k)),
+        SeqNode::Repeat { node, min, max } => match_repeat(node, *min, *max, 0, hops, pos, k),
+    }
+}
+
+fn match_concat(nodes: &[SeqNode], hops: &[Hop], pos: usize, k: &mut dyn FnMut(usize) -> bool) -> bool {
+    match nodes.split_first() {
+        None => k(pos),
+        Some((first, rest)) => match_node(first, hops, pos, &mut |p| match_concat(rest, hops, p, k)),
+    }
+}
+
+fn match_repeat(
+    node: &SeqNode,
+    min: usize,
+    max: Option<usize>,
+    count: usize,
+    hops: &[Hop],
+    pos: usize,
+    k: &mut dyn FnMut(usize) -> bool,
+) -> bool {
+    // greedy: try one more repetition first; repetitions matching no hop
+    // only count towards the minimum, so that the recursion terminates
+    if max.map_or(true, |max| count < max)
+        && match_node(node, hops, pos, &mut |p| {
+            (p > pos || count < min) && match_repeat(node, min, max, count + 1, hops, p, k)
+        })
+    {
+        return true;
+    }
+    count >= min && k(pos)
+}
+
+struct SeqParser<'a> {
+    src: &'a str,
+    tokens: Vec<&'a str>,
+    pos: usize,
+}
+
+impl<'a> SeqParser<'a> {
+    fn tokenize(src: &'a str) -> Vec<&'a str> {
+        let mut tokens = Vec::new();
+        let mut start = None;
+        for (i, c) in src.char_indices() {
+            let special = matches!(c, '(' | ')' | '|' | '?' | '*' | '+');
+            if special || c.is_whitespace() {
+                if let Some(s) = start.take() {
+                    tokens.push(&src[s..i]);
+                }
+                if special {
+                    tokens.push(&src[i..i + 1]);
+                }
+            } else if start.is_none() {
+                start = Some(i);
+            }
+        }
+        if let Some(s) = start {
+            tokens.push(&src[s..]);
+        }
+        tokens
+    }
+
+    fn error(&self, reason: &str) -> PolicyError {
+        PolicyError::InvalidSequence { sequence: self.src.to_string(), reason: reason.to_string() }
+    }
+
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.pos).copied()
+    }
+
+    fn alternation(&mut self) -> Result<SeqNode, PolicyError> {
+        let mut alts = vec![self.concatenation()?];
+        while self.peek() == Some("|") {
+            self.pos += 1;
+            alts.push(self.concatenation()?);
+        }
+        Ok(if alts.len() == 1 { alts.remove(0) } else { SeqNode::Alt(alts) })
+    }
+
+    fn concatenation(&mut self) -> Result<SeqNode, PolicyError> {
+        let mut nodes = Vec::new();
+        while let Some(token) = self.peek() {
+            let node = match token {
+                "|" | ")" => break,
+                "?" | "*" | "+" => return Err(self.error("modifier without a hop predicate")),
+                "(" => {
+                    self.pos += 1;
+                    let group = self.alternation()?;
+                    if self.peek() != Some(")") {
+                        return Err(self.error("unbalanced parentheses"));
+                    }
+                    self.pos += 1;
+                    group
+                }
+                hop => {
+                    self.pos += 1;
+                    SeqNode::Hop(hop.parse()?)
+                }
+            };
+            let (min, max) = match self.peek() {
+                Some("?") => (0, Some(1)),
+                Some("*") => (0, None),
+                Some("+") => (1, None),
+                _ => {
+                    nodes.push(node);
+                    continue;
+                }
+            };
+            self.pos += 1;
+            nodes.push(SeqNode::Repeat { node: Box::new(node), min, max });
+        }
+        if nodes.is_empty() {
+            return Err(self.error("empty alternative"));
+        }
+        Ok(if nodes.len() == 1 { nodes.remove(0) } else { SeqNode::Concat(nodes) })
+    }
+}
+
+/// A pattern for the sequence of ASes of a path, i.e.
+/// `1-ff00:0:133#0 (1-ff00:0:120 | 1-ff00:0:121)+ 0* 2-ff00:0:210`.
+///
+/// The sequence is made of [`HopPredicate`]s matching one AS each, which may be
+/// grouped with parentheses and combined with `|`, and followed by `?`, `*` or
+/// `+` to match them optionally or repeatedly. It has to match the whole path.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Sequence {
+    src: String,
+    root: SeqNode,
+}
+
+impl Sequence {
+    /// Returns `true` if the hops of `path` match the sequence.
+    #[must_use]
+    pub fn matches(&self, path: &ScionPath) -> bool {
+        let hops = path_hops(path);
+        match_node(&self.root, &hops, 0, &mut |end| end == hops.len())
+    }
+}
+
+impl FromStr for Sequence {
+    type Err = PolicyError;
+
+    fn from_str(s: &str) -> Result<Sequence, PolicyError> {
+        let mut parser = SeqParser { src: s, tokens: SeqParser::tokenize(s), pos: 0 };
+        let root = parser.alternation()?;
+        if parser.pos != parser.tokens.len() {
+            return Err(parser.error("unbalanced parentheses"));
+        }
+        Ok(Sequence { src: s.trim().to_string(), root })
+    }
+}
+
+impl fmt::Display for Sequence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.src)
+    }
+}
+
+/// Returns the ASes a path traverses with their ingress and egress interface.
+fn path_hops(path: &ScionPath) -> Vec<Hop> {
+    let intfs = &path.interfaces;
+    let (first, last) = match (intfs.first(), intfs.last()) {
+        (Some(first), Some(last)) if intfs.len() >= 2 => (first, last),
+        _ => return vec![(path.src, 0, 0)],
+    };
+    let mut hops = vec![(first.ia, 0, first.id)];
+    hops.extend(
+        intfs[1..intfs.len() - 1]
+            .chunks(2)
+            .map(|pair| (pair[0].ia, pair[0].id, pair[pair.len() - 1].id)),
+    );
+    hops.push((last.ia, last.id, 0));
+    hops
+}
+
+/// A path policy: an optional [`Acl`] and an optional [`Sequence`] that the
+/// paths to a destination have to satisfy.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Policy {
+    pub acl: Option<Acl>,
+    pub sequence: Option<Sequence>,
+}
+
+impl Policy {
+    /// Returns `true` if `path` satisfies the policy.
+    #[must_use]
+    pub fn accepts(&self, path: &ScionPath) -> bool {
+        self.acl.as_ref().map_or(true, |acl| acl.allows(path))
+            && self.sequence.as_ref().map_or(true, |seq| seq.matches(path))
+    }
+
+    /// Returns the paths satisfying the policy, with the fewest AS hops first
+    /// and the order of the input kept otherwise.
+    pub fn filter<I: IntoIterator<Item = ScionPath>>(&self, paths: I) -> Vec<ScionPath> {
+        let mut paths: Vec<ScionPath> = paths.into_iter().filter(|p| self.accepts(p)).collect();
+        paths.sort_by_key(ScionPath::hop_count);
+        paths
+    }
+}
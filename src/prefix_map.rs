@@ -0,0 +1,328 @@
+use crate::{IpAddr, IpNet, Ipv4Addr, Ipv4Net, Ipv6Addr, Ipv6Net};
+use std::fmt;
+
+/// A map from IP networks to values with longest-prefix-match lookups, i.e.
+/// the prefix to IA table of a SIG or the routes of an underlay.
+///
+/// IPv4 and IPv6 networks are kept in separate path-compressed binary tries,
+/// so a lookup visits at most one node per distinct prefix length on the
+/// way to the address. Iteration is in prefix order, the order of [`IpNet`]:
+/// by network address, then shorter prefixes first, IPv4 before IPv6.
+///
+/// ```
+/// use scionnet::{IpAddr, IpNet, IsdAsn, PrefixMap};
+///
+/// let mut sig = PrefixMap::new();
+/// sig.insert("10.0.0.0/8".parse::<IpNet>().unwrap(), "1-ff00:0:110".parse::<IsdAsn>().unwrap());
+/// sig.insert("10.1.0.0/16".parse::<IpNet>().unwrap(), "1-ff00:0:111".parse().unwrap());
+///
+/// let (net, ia) = sig.longest_match("10.1.2.3".parse::<IpAddr>().unwrap()).unwrap();
+/// assert_eq!((net.to_string(), ia.to_string()), ("10.1.0.0/16".into(), "1-ff00:0:111".into()));
+/// let (net, _) = sig.longest_match("10.2.0.1".parse().unwrap()).unwrap();
+/// assert_eq!(net.to_string(), "10.0.0.0/8");
+/// assert!(sig.longest_match("192.0.2.1".parse().unwrap()).is_none());
+/// ```
+#[derive(Clone)]
+pub struct PrefixMap<T> {
+    v4: Trie<T>,
+    v6: Trie<T>,
+}
+
+impl<T> PrefixMap<T> {
+    #[must_use]
+    pub fn new() -> PrefixMap<T> {
+        PrefixMap { v4: Trie::new(), v6: Trie::new() }
+    }
+
+    /// Returns the number of networks in the map.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.v4.len + self.v6.len
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Maps `net` to `value`, returning the value it was mapped to before.
+    pub fn insert<N: Into<IpNet>>(&mut self, net: N, value: T) -> Option<T> {
+        let (trie, bits, len) = self.trie_mut(net.into());
+        trie.insert(bits, len, value)
+    }
+
+    /// Returns the value of exactly `net`, not of a network holding it.
+    #[must_use]
+    pub fn get<N: Into<IpNet>>(&self, net: N) -> Option<&T> {
+        let (trie, bits, len) = self.trie(net.into());
+        trie.get(bits, len)
+    }
+
+    #[must_use]
+    pub fn get_mut<N: Into<IpNet>>(&mut self, net: N) -> Option<&mut T> {
+        let (trie, bits, len) = self.trie_mut(net.into());
+        trie.get_mut(bits, len)
+    }
+
+    #[must_use]
+    pub fn contains_key<N: Into<IpNet>>(&self, net: N) -> bool {
+        self.get(net).is_some()
+    }
+
+    /// Removes `net` from the map, returning its value. Networks inside or
+    /// around it stay.
+    pub fn remove<N: Into<IpNet>>(&mut self, net: N) -> Option<T> {
+        let (trie, bits, len) = self.trie_mut(net.into());
+        trie.remove(bits, len)
+    }
+
+    /// Returns the longest network holding `addr`, with its value. Addresses
+    /// only match networks of their own family, so IPv4-mapped IPv6
+    /// addresses do not match IPv4 networks.
+    #[must_use]
+    pub fn longest_match(&self, addr: IpAddr) -> Option<(IpNet, &T)> {
+        match addr {
+            IpAddr::V4(addr) => {
+                let (bits, len, value) = self.v4.longest_match(v4_bits(addr), 32)?;
+                Some((IpNet::V4(v4_net(bits, len)), value))
+            }
+            IpAddr::V6(addr) => {
+                let (bits, len, value) = self.v6.longest_match(addr.to_bits(), 128)?;
+                Some((IpNet::V6(v6_net(bits, len)), value))
+            }
+        }
+    }
+
+    /// Returns the networks of the map and their values in prefix order.
+    pub fn iter(&self) -> impl Iterator<Item = (IpNet, &T)> + '_ {
+        let v4 = self.v4.iter().map(|(bits, len, value)| (IpNet::V4(v4_net(bits, len)), value));
+        let v6 = self.v6.iter().map(|(bits, len, value)| (IpNet::V6(v6_net(bits, len)), value));
+        v4.chain(v6)
+    }
+
+    pub fn clear(&mut self) {
+        *self = PrefixMap::new();
+    }
+
+    fn trie(&self, net: IpNet) -> (&Trie<T>, u128, u8) {
+        match net {
+            IpNet::V4(net) => (&self.v4, v4_bits(net.network()), net.prefix_len()),
+            IpNet::V6(net) => (&self.v6, net.network().to_bits(), net.prefix_len()),
+        }
+    }
+
+    fn trie_mut(&mut self, net: IpNet) -> (&mut Trie<T>, u128, u8) {
+        match net {
+            IpNet::V4(net) => (&mut self.v4, v4_bits(net.network()), net.prefix_len()),
+            IpNet::V6(net) => (&mut self.v6, net.network().to_bits(), net.prefix_len()),
+        }
+    }
+}
+
+/// IPv4 addresses take the upper 32 bits of the keys, so both tries index
+/// bits from the most significant one.
+fn v4_bits(addr: Ipv4Addr) -> u128 {
+    u128::from(addr.to_bits()) << 96
+}
+
+fn v4_net(bits: u128, len: u8) -> Ipv4Net {
+    Ipv4Net::new(Ipv4Addr::from_bits((bits >> 96) as u32), len).expect("a length of at most 32")
+}
+
+fn v6_net(bits: u128, len: u8) -> Ipv6Net {
+    Ipv6Net::new(Ipv6Addr::from_bits(bits), len).expect("a length of at most 128")
+}
+
+impl<T> Default for PrefixMap<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: PartialEq> PartialEq for PrefixMap<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Eq> Eq for PrefixMap<T> {}
+
+impl<T: fmt::Debug> fmt::Debug for PrefixMap<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<N: Into<IpNet>, T> FromIterator<(N, T)> for PrefixMap<T> {
+    fn from_iter<I: IntoIterator<Item = (N, T)>>(iter: I) -> PrefixMap<T> {
+        let mut map = PrefixMap::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<N: Into<IpNet>, T> Extend<(N, T)> for PrefixMap<T> {
+    fn extend<I: IntoIterator<Item = (N, T)>>(&mut self, iter: I) {
+        iter.into_iter().for_each(|(net, value)| {
+            self.insert(net, value);
+        });
+    }
+}
+
+/// A path-compressed binary trie of the prefixes of one address family.
+/// Nodes without a value only exist where two prefixes branch off.
+#[derive(Clone)]
+struct Trie<T> {
+    root: Option<Box<Node<T>>>,
+    len: usize,
+}
+
+#[derive(Clone)]
+struct Node<T> {
+    bits: u128,
+    len: u8,
+    value: Option<T>,
+    children: [Option<Box<Node<T>>>; 2],
+}
+
+const fn mask(len: u8) -> u128 {
+    match u128::MAX.checked_shl(128 - len as u32) {
+        Some(mask) => mask,
+        None => 0,
+    }
+}
+
+/// Returns bit `i` of `bits`, counting from the most significant one.
+fn bit(bits: u128, i: u8) -> usize {
+    ((bits >> (127 - i)) & 1) as usize
+}
+
+impl<T> Node<T> {
+    fn new(bits: u128, len: u8, value: Option<T>) -> Box<Node<T>> {
+        Box::new(Node { bits, len, value, children: [None, None] })
+    }
+
+    /// Returns `true` if the node is `bits` or one of its prefixes.
+    fn holds(&self, bits: u128, len: u8) -> bool {
+        self.len <= len && (self.bits ^ bits) & mask(self.len) == 0
+    }
+}
+
+impl<T> Trie<T> {
+    fn new() -> Trie<T> {
+        Trie { root: None, len: 0 }
+    }
+
+    fn insert(&mut self, bits: u128, len: u8, value: T) -> Option<T> {
+        let old = Self::insert_into(&mut self.root, bits, len, value);
+        self.len += usize::from(old.is_none());
+        old
+    }
+
+    fn insert_into(slot: &mut Option<Box<Node<T>>>, bits: u128, len: u8, value: T) -> Option<T> {
+        let Some(node) = slot else {
+            *slot = Some(Node::new(bits, len, Some(value)));
+            return None;
+        };
+        if node.holds(bits, len) {
+            if node.len == len {
+                return node.value.replace(value);
+            }
+            return Self::insert_into(&mut node.children[bit(bits, node.len)], bits, len, value);
+        }
+        // the new prefix branches off within the node's, or is one of its
+        // prefixes: a node at their common prefix takes the place of it
+        let common = ((node.bits ^ bits).leading_zeros() as u8).min(len).min(node.len);
+        let old = slot.take().unwrap();
+        let mut split = Node::new(bits & mask(common), common, None);
+        let old_bit = bit(old.bits, common);
+        split.children[old_bit] = Some(old);
+        if common == len {
+            split.value = Some(value);
+        } else {
+            split.children[1 - old_bit] = Some(Node::new(bits, len, Some(value)));
+        }
+        *slot = Some(split);
+        None
+    }
+
+    fn find(&self, bits: u128, len: u8) -> Option<&Node<T>> {
+        let mut node = self.root.as_deref()?;
+        while node.holds(bits, len) {
+            if node.len == len {
+                return Some(node);
+            }
+            node = node.children[bit(bits, node.len)].as_deref()?;
+        }
+        None
+    }
+
+    fn get(&self, bits: u128, len: u8) -> Option<&T> {
+        self.find(bits, len)?.value.as_ref()
+    }
+
+    fn get_mut(&mut self, bits: u128, len: u8) -> Option<&mut T> {
+        let mut node = self.root.as_deref_mut()?;
+        while node.holds(bits, len) {
+            if node.len == len {
+                return node.value.as_mut();
+            }
+            node = node.children[bit(bits, node.len)].as_deref_mut()?;
+        }
+        None
+    }
+
+    fn remove(&mut self, bits: u128, len: u8) -> Option<T> {
+        let value = Self::remove_from(&mut self.root, bits, len)?;
+        self.len -= 1;
+        Some(value)
+    }
+
+    fn remove_from(slot: &mut Option<Box<Node<T>>>, bits: u128, len: u8) -> Option<T> {
+        let node = slot.as_mut().filter(|node| node.holds(bits, len))?;
+        let value = if node.len == len {
+            node.value.take()?
+        } else {
+            Self::remove_from(&mut node.children[bit(bits, node.len)], bits, len)?
+        };
+        // a node left without a value is only needed where two prefixes branch
+        if node.value.is_none() {
+            match &mut node.children {
+                [Some(_), Some(_)] => {}
+                [child @ Some(_), None] | [None, child @ Some(_)] => *slot = child.take(),
+                [None, None] => *slot = None,
+            }
+        }
+        Some(value)
+    }
+
+    fn longest_match(&self, bits: u128, len: u8) -> Option<(u128, u8, &T)> {
+        let mut best = None;
+        let mut next = self.root.as_deref();
+        while let Some(node) = next.filter(|node| node.holds(bits, len)) {
+            if let Some(value) = &node.value {
+                best = Some((node.bits, node.len, value));
+            }
+            if node.len == len {
+                break;
+            }
+            next = node.children[bit(bits, node.len)].as_deref();
+        }
+        best
+    }
+
+    /// Visits the nodes depth first, parents before their children and the
+    /// 0 branch before the 1 branch, which is prefix order.
+    fn iter(&self) -> impl Iterator<Item = (u128, u8, &T)> + '_ {
+        let mut stack: Vec<&Node<T>> = self.root.as_deref().into_iter().collect();
+        std::iter::from_fn(move || {
+            while let Some(node) = stack.pop() {
+                stack.extend(node.children.iter().rev().filter_map(|c| c.as_deref()));
+                if let Some(value) = &node.value {
+                    return Some((node.bits, node.len, value));
+                }
+            }
+            None
+        })
+    }
+}
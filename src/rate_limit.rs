@@ -0,0 +1,210 @@
+use crate::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddrScion};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How a [`RateLimiter`] groups sources into a shared bucket.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum RateLimitKey {
+    /// One bucket per source socket address.
+    Addr,
+    /// One bucket per source ISD-AS.
+    Ia,
+    /// One bucket per source ISD.
+    Isd,
+    /// One bucket per host prefix within the source ISD-AS, with the given
+    /// prefix lengths for IPv4 and IPv6 hosts; service addresses get one
+    /// bucket each.
+    HostPrefix { v4: u8, v6: u8 },
+}
+
+/// The outcome of [`RateLimiter::check`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RateDecision {
+    Allow,
+    /// The bucket is empty; the next request can pass after `retry_after`.
+    Deny { retry_after: Duration },
+}
+
+impl RateDecision {
+    #[must_use]
+    #[inline]
+    pub fn is_allowed(&self) -> bool {
+        matches!(*self, RateDecision::Allow)
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+enum BucketKey {
+    Addr(SocketAddrScion),
+    Ia(u64),
+    Isd(u16),
+    HostPrefix(u64, IpAddr),
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// How many buckets a [`RateLimiter`] tracks by default, see
+/// [`RateLimiter::with_max_buckets`].
+pub const DEFAULT_MAX_BUCKETS: usize = 65536;
+
+/// A token-bucket rate limiter for incoming requests, keyed by source address.
+///
+/// Every bucket holds up to `burst` tokens and is refilled at `rate` tokens per
+/// second; each allowed request takes one token. Which sources share a bucket is
+/// decided by the [`RateLimitKey`]. The buckets are spread over independently
+/// locked shards, so the limiter can be shared between the threads of a server.
+///
+/// The number of buckets is bounded, so spoofed sources cannot exhaust the
+/// memory of a server. A shard that is full drops its refilled buckets, and
+/// if that frees less than an eighth of it, the least recently used ones, so
+/// the cost of a sweep is spread over the buckets added until the next.
+pub struct RateLimiter {
+    key: RateLimitKey,
+    rate: f64,
+    burst: f64,
+    shard_capacity: usize,
+    shards: Box<[Mutex<HashMap<BucketKey, Bucket>>]>,
+}
+
+fn mask_host(host: &IpAddr, v4: u8, v6: u8) -> IpAddr {
+    match *host {
+        IpAddr::V4(ip) => {
+            let mask = u32::MAX.checked_shl(32 - u32::from(v4.min(32))).unwrap_or(0);
+            IpAddr::V4(Ipv4Addr::from(u32::from(ip) & mask))
+        }
+        IpAddr::V6(ip) => {
+            let mask = u128::MAX.checked_shl(128 - u32::from(v6.min(128))).unwrap_or(0);
+            IpAddr::V6(Ipv6Addr::from(u128::from(ip) & mask))
+        }
+    }
+}
+
+impl RateLimiter {
+    /// Creates a limiter allowing `rate` requests per second with bursts of up
+    /// to `burst` requests per bucket.
+    pub fn new(key: RateLimitKey, rate: u32, burst: u32) -> RateLimiter {
+        let shards = std::thread::available_parallelism().map_or(4, |n| n.get() * 4);
+        Self::with_shards(key, rate, burst, shards)
+    }
+
+    /// Like [`new`](Self::new), with an explicit number of shards.
+    pub fn with_shards(key: RateLimitKey, rate: u32, burst: u32, shards: usize) -> RateLimiter {
+        let shards = shards.max(1);
+        RateLimiter {
+            key,
+            rate: f64::from(rate),
+            burst: f64::from(burst.max(1)),
+            shard_capacity: DEFAULT_MAX_BUCKETS.div_ceil(shards),
+            shards: (0..shards).map(|_| Mutex::new(HashMap::new())).collect(),
+        }
+    }
+
+    /// Tracks at most about `max` buckets, [`DEFAULT_MAX_BUCKETS`] by default.
+    /// The limit is split evenly between the shards, rounding up.
+    #[must_use]
+    pub fn with_max_buckets(mut self, max: usize) -> RateLimiter {
+        self.shard_capacity = max.div_ceil(self.shards.len()).max(1);
+        self
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn key(&self) -> RateLimitKey {
+        self.key
+    }
+
+    fn bucket_key(&self, src: &SocketAddrScion) -> BucketKey {
+        match self.key {
+            RateLimitKey::Addr => BucketKey::Addr(*src),
+            RateLimitKey::Ia => BucketKey::Ia(src.ia()),
+            RateLimitKey::Isd => BucketKey::Isd((src.ia() >> 48) as u16),
+            RateLimitKey::HostPrefix { v4, v6 } => match src.ip() {
+                Some(ip) => BucketKey::HostPrefix(src.ia(), mask_host(&ip.to_canonical(), v4, v6)),
+                None => BucketKey::Addr(*src),
+            },
+        }
+    }
+
+    fn shard(&self, key: &BucketKey) -> &Mutex<HashMap<BucketKey, Bucket>> {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        &self.shards[hasher.finish() as usize % self.shards.len()]
+    }
+
+    /// Takes a token from the bucket of `src`.
+    pub fn check(&self, src: &SocketAddrScion) -> RateDecision {
+        self.check_at(src, Instant::now())
+    }
+
+    /// Takes a token from the bucket of `src` as of `now`.
+    pub fn check_at(&self, src: &SocketAddrScion, now: Instant) -> RateDecision {
+        let key = self.bucket_key(src);
+        let mut buckets = self.shard(&key).lock().unwrap();
+        if buckets.len() >= self.shard_capacity && !buckets.contains_key(&key) {
+            self.evict(&mut buckets, now);
+        }
+        let bucket = buckets.entry(key).or_insert(Bucket { tokens: self.burst, updated: now });
+
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.burst);
+        bucket.updated = bucket.updated.max(now);
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            RateDecision::Allow
+        } else if self.rate > 0.0 {
+            let retry_after = Duration::from_secs_f64((1.0 - bucket.tokens) / self.rate);
+            RateDecision::Deny { retry_after }
+        } else {
+            RateDecision::Deny { retry_after: Duration::MAX }
+        }
+    }
+
+    /// Returns `true` if `bucket` has been refilled completely as of `now`.
+    fn is_idle(&self, bucket: &Bucket, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens + elapsed * self.rate >= self.burst
+    }
+
+    /// Makes room for new buckets in the full shard `buckets`, freeing at
+    /// least an eighth of it, so a flood of new sources sweeps a shard only
+    /// once every `shard_capacity / 8` of them.
+    fn evict(&self, buckets: &mut HashMap<BucketKey, Bucket>, now: Instant) {
+        buckets.retain(|_, b| !self.is_idle(b, now));
+        let keep = self.shard_capacity - self.shard_capacity.div_ceil(8);
+        if buckets.len() > keep {
+            let mut by_age: Vec<_> = buckets.iter().map(|(key, b)| (b.updated, *key)).collect();
+            let excess = buckets.len() - keep;
+            by_age.select_nth_unstable_by_key(excess - 1, |&(updated, _)| updated);
+            for (_, key) in &by_age[..excess] {
+                buckets.remove(key);
+            }
+        }
+    }
+
+    /// Drops the buckets that have been refilled completely,
+    /// as they behave exactly like new ones.
+    pub fn purge_idle(&self) {
+        let now = Instant::now();
+        for shard in self.shards.iter() {
+            shard.lock().unwrap().retain(|_, b| !self.is_idle(b, now));
+        }
+    }
+
+    /// Returns the number of buckets currently tracked.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|s| s.lock().unwrap().len()).sum()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
@@ -0,0 +1,114 @@
+//! Classification of IPv4 addresses by the IANA IPv4 Special-Purpose Address
+//! Registry, beyond the predicates of [`Ipv4Addr`].
+//!
+//! Most of these blocks are never valid as the underlay address of a border
+//! router or a SCION end host, so policies may use them to filter endpoints:
+//!
+//! ```
+//! use scionnet::{registry, Ipv4Addr};
+//!
+//! let underlay = Ipv4Addr::new(192, 31, 196, 1);
+//! assert!(registry::is_as112(underlay));
+//! assert!(registry::is_special_purpose(underlay));
+//! assert!(!registry::is_special_purpose(Ipv4Addr::new(8, 8, 8, 8)));
+//! ```
+
+use crate::Ipv4Addr;
+
+/// Returns `true` for `0.0.0.0/8`, "this network" (RFC 791).
+#[must_use]
+#[inline]
+pub const fn is_this_network(ip: Ipv4Addr) -> bool {
+    ip.octets()[0] == 0
+}
+
+/// Returns `true` for `192.0.0.0/24`, the block of IETF protocol
+/// assignments (RFC 6890).
+#[must_use]
+#[inline]
+pub const fn is_ietf_protocol_assignment(ip: Ipv4Addr) -> bool {
+    matches!(ip.octets(), [192, 0, 0, _])
+}
+
+/// Returns `true` for `192.0.0.0/29`, the IPv4 service continuity prefix of
+/// DS-Lite (RFC 7335).
+#[must_use]
+#[inline]
+pub const fn is_service_continuity(ip: Ipv4Addr) -> bool {
+    matches!(ip.octets(), [192, 0, 0, 0..=7])
+}
+
+/// Returns `true` for `192.0.0.8`, the IPv4 dummy address (RFC 7600).
+#[must_use]
+#[inline]
+pub const fn is_dummy(ip: Ipv4Addr) -> bool {
+    matches!(ip.octets(), [192, 0, 0, 8])
+}
+
+/// Returns `true` for `192.0.0.9`, the Port Control Protocol anycast address
+/// (RFC 7723).
+#[must_use]
+#[inline]
+pub const fn is_pcp_anycast(ip: Ipv4Addr) -> bool {
+    matches!(ip.octets(), [192, 0, 0, 9])
+}
+
+/// Returns `true` for `192.0.0.10`, the TURN anycast address (RFC 8155).
+#[must_use]
+#[inline]
+pub const fn is_turn_anycast(ip: Ipv4Addr) -> bool {
+    matches!(ip.octets(), [192, 0, 0, 10])
+}
+
+/// Returns `true` for `192.0.0.170` and `192.0.0.171`, the well-known
+/// addresses NAT64 prefixes are discovered by (RFC 7050).
+#[must_use]
+#[inline]
+pub const fn is_nat64_discovery(ip: Ipv4Addr) -> bool {
+    matches!(ip.octets(), [192, 0, 0, 170 | 171])
+}
+
+/// Returns `true` for `192.31.196.0/24` and `192.175.48.0/24`, the AS112
+/// sinks of reverse DNS queries for private addresses (RFC 7535, RFC 7534).
+#[must_use]
+#[inline]
+pub const fn is_as112(ip: Ipv4Addr) -> bool {
+    matches!(ip.octets(), [192, 31, 196, _] | [192, 175, 48, _])
+}
+
+/// Returns `true` for `192.52.193.0/24`, the Automatic Multicast Tunneling
+/// relay anycast block (RFC 7450).
+#[must_use]
+#[inline]
+pub const fn is_amt(ip: Ipv4Addr) -> bool {
+    matches!(ip.octets(), [192, 52, 193, _])
+}
+
+/// Returns `true` for `192.88.99.0/24`, the deprecated 6to4 relay anycast
+/// block (RFC 7526).
+#[must_use]
+#[inline]
+pub const fn is_6to4_relay_anycast(ip: Ipv4Addr) -> bool {
+    matches!(ip.octets(), [192, 88, 99, _])
+}
+
+/// Returns `true` for the addresses of any block of the registry, including
+/// the ones [`Ipv4Addr`] classifies itself, i.e. private or documentation
+/// addresses.
+#[must_use]
+#[inline]
+pub const fn is_special_purpose(ip: Ipv4Addr) -> bool {
+    is_this_network(ip)
+        || ip.is_private()
+        || ip.is_shared()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || is_ietf_protocol_assignment(ip)
+        || ip.is_documentation()
+        || is_as112(ip)
+        || is_amt(ip)
+        || is_6to4_relay_anycast(ip)
+        || ip.is_benchmarking()
+        || ip.is_reserved()
+        || ip.is_broadcast()
+}
@@ -0,0 +1,193 @@
+use crate::SocketAddr;
+use std::io;
+use std::time::{Duration, Instant};
+
+/// The default time a failed candidate is passed over, see
+/// [`RemoteEndpoint::with_retry_after`].
+pub const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Debug)]
+struct Candidate {
+    addr: SocketAddr,
+    failures: u32,
+    failed_at: Option<Instant>,
+}
+
+/// A remote service reachable at several addresses, SCION and IP, tried in
+/// order of preference.
+///
+/// The endpoint tracks which candidate is in use and which ones failed
+/// recently. A failed candidate is passed over until its retry time has
+/// elapsed or no healthy candidate is left.
+///
+/// ```
+/// use scionnet::{RemoteEndpoint, SocketAddr};
+///
+/// let mut remote = RemoteEndpoint::new([
+///     "1-ff00:0:110,10.0.0.1:443".parse::<SocketAddr>().unwrap(),
+///     "192.0.2.1:443".parse().unwrap(),
+/// ]);
+/// assert_eq!(remote.current().unwrap().to_string(), "1-ff00:0:110,10.0.0.1:443");
+/// remote.mark_failed();
+/// assert_eq!(remote.current().unwrap().to_string(), "192.0.2.1:443");
+/// ```
+#[derive(Clone, Debug)]
+pub struct RemoteEndpoint {
+    candidates: Vec<Candidate>,
+    current: usize,
+    retry_after: Duration,
+}
+
+impl RemoteEndpoint {
+    /// Creates an endpoint with the given candidates, the most preferred first.
+    /// Duplicates and addresses with port 0, which cannot be connected to,
+    /// are dropped.
+    pub fn new<I: IntoIterator<Item = SocketAddr>>(addrs: I) -> RemoteEndpoint {
+        let mut candidates: Vec<Candidate> = Vec::new();
+        for addr in addrs {
+            if !addr.is_unspecified_port() && !candidates.iter().any(|c| c.addr == addr) {
+                candidates.push(Candidate { addr, failures: 0, failed_at: None });
+            }
+        }
+        RemoteEndpoint { candidates, current: 0, retry_after: DEFAULT_RETRY_AFTER }
+    }
+
+    /// Sets how long a failed candidate is passed over.
+    #[must_use]
+    pub fn with_retry_after(mut self, retry_after: Duration) -> RemoteEndpoint {
+        self.retry_after = retry_after;
+        self
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn retry_after(&self) -> Duration {
+        self.retry_after
+    }
+
+    /// Returns all candidates in order of preference.
+    pub fn candidates(&self) -> impl Iterator<Item = &SocketAddr> + '_ {
+        self.candidates.iter().map(|c| &c.addr)
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.candidates.len()
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.candidates.is_empty()
+    }
+
+    /// Returns the candidate in use, or `None` if there are no candidates.
+    #[must_use]
+    pub fn current(&self) -> Option<SocketAddr> {
+        self.candidates.get(self.current).map(|c| c.addr)
+    }
+
+    fn is_healthy_at(&self, i: usize, now: Instant) -> bool {
+        match self.candidates[i].failed_at {
+            Some(at) => now.saturating_duration_since(at) >= self.retry_after,
+            None => true,
+        }
+    }
+
+    /// Returns `true` unless `addr` is a candidate that failed within the
+    /// retry time.
+    #[must_use]
+    pub fn is_healthy(&self, addr: &SocketAddr) -> bool {
+        let now = Instant::now();
+        self.candidates
+            .iter()
+            .position(|c| c.addr == *addr)
+            .map_or(true, |i| self.is_healthy_at(i, now))
+    }
+
+    /// Returns the number of failures of `addr` since it last succeeded.
+    #[must_use]
+    pub fn failures(&self, addr: &SocketAddr) -> u32 {
+        self.candidates.iter().find(|c| c.addr == *addr).map_or(0, |c| c.failures)
+    }
+
+    /// Marks the current candidate as failed and moves on to the next
+    /// healthy one, or simply the next one if none is healthy.
+    pub fn mark_failed(&mut self) {
+        let now = Instant::now();
+        if let Some(c) = self.candidates.get_mut(self.current) {
+            c.failures = c.failures.saturating_add(1);
+            c.failed_at = Some(now);
+        } else {
+            return;
+        }
+        let n = self.candidates.len();
+        self.current = (1..=n)
+            .map(|k| (self.current + k) % n)
+            .find(|&i| self.is_healthy_at(i, now))
+            .unwrap_or((self.current + 1) % n);
+    }
+
+    /// Marks the current candidate as working again.
+    pub fn mark_ok(&mut self) {
+        if let Some(c) = self.candidates.get_mut(self.current) {
+            c.failures = 0;
+            c.failed_at = None;
+        }
+    }
+
+    /// Moves on to the next candidate, regardless of its health; i.e. to
+    /// spread load or to probe a failed preferred candidate.
+    pub fn rotate(&mut self) {
+        if !self.candidates.is_empty() {
+            self.current = (self.current + 1) % self.candidates.len();
+        }
+    }
+
+    /// Returns the order in which candidates are tried: the healthy ones
+    /// starting at the current one, then those that failed recently.
+    fn attempt_order(&self) -> Vec<usize> {
+        let now = Instant::now();
+        let n = self.candidates.len();
+        let (mut healthy, failed): (Vec<usize>, Vec<usize>) =
+            (0..n).map(|k| (self.current + k) % n).partition(|&i| self.is_healthy_at(i, now));
+        healthy.extend(failed);
+        healthy
+    }
+
+    /// Calls `connect` with each candidate until one succeeds, making it the
+    /// current candidate, and returns the result of the first success or the
+    /// last error. Failing candidates are marked as failed.
+    ///
+    /// Candidates `connect` rejects with [`io::ErrorKind::InvalidInput`],
+    /// i.e. IP addresses given to a SCION-only transport, are skipped without
+    /// being marked as failed.
+    pub fn connect_with<T, F>(&mut self, mut connect: F) -> io::Result<T>
+    where
+        F: FnMut(&SocketAddr) -> io::Result<T>,
+    {
+        let mut last_err = None;
+        for i in self.attempt_order() {
+            let addr = self.candidates[i].addr;
+            match connect(&addr) {
+                Ok(v) => {
+                    self.current = i;
+                    self.mark_ok();
+                    return Ok(v);
+                }
+                Err(e) => {
+                    if e.kind() != io::ErrorKind::InvalidInput {
+                        let c = &mut self.candidates[i];
+                        c.failures = c.failures.saturating_add(1);
+                        c.failed_at = Some(Instant::now());
+                    }
+                    last_err = Some(e);
+                }
+            }
+        }
+        Err(last_err.unwrap_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "remote endpoint has no candidates")
+        }))
+    }
+}
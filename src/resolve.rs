@@ -0,0 +1,487 @@
+//! Asynchronous name resolution for SCION and IP endpoints.
+//!
+//! [`AsyncResolve`] is the common interface of all resolvers. The crate ships
+//! a hosts-file resolver, a DNS TXT resolver (`scion=<addr>` records) and the
+//! system resolver, which [`ResolverChain`] queries in a configurable order.
+//! Custom resolvers, i.e. for a service registry or the SCION daemon, are
+//! plugged in by implementing [`AsyncResolve`] and pushing them onto a chain.
+//!
+//! The futures do not depend on any particular async runtime. Blocking work
+//! (file and socket I/O) is moved to a helper thread.
+
+use crate::parse_observer::quietly;
+use crate::{IpAddr, ScionAddr, SocketAddr};
+use std::fs;
+use std::future::Future;
+use std::io;
+use std::net::UdpSocket;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// The future returned by [`AsyncResolve::resolve`].
+pub type ResolveFuture<'a> = Pin<Box<dyn Future<Output = io::Result<Vec<SocketAddr>>> + Send + 'a>>;
+
+/// A resolver mapping a host name and port to socket addresses.
+pub trait AsyncResolve: Send + Sync {
+    /// Resolves `host` to a list of socket addresses using `port`,
+    /// ordered from most to least preferred.
+    ///
+    /// An empty list means the name is unknown to this resolver.
+    fn resolve<'a>(&'a self, host: &'a str, port: u16) -> ResolveFuture<'a>;
+}
+
+/// Orders `addrs` by preference and removes duplicates.
+///
+/// SCION addresses are preferred over IPv6 addresses, which are preferred
+/// over IPv4 addresses. The relative order within a family is kept.
+pub fn rank_addrs(addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    fn family_rank(addr: &SocketAddr) -> u8 {
+        match *addr {
+            SocketAddr::SCION(_) => 0,
+            SocketAddr::V6(_) => 1,
+            SocketAddr::V4(_) => 2,
+        }
+    }
+
+    let mut ranked: Vec<SocketAddr> = Vec::with_capacity(addrs.len());
+    for addr in addrs.iter().map(SocketAddr::to_canonical) {
+        if !ranked.contains(&addr) {
+            ranked.push(addr);
+        }
+    }
+    ranked.sort_by_key(family_rank);
+    ranked
+}
+
+/// Runs `f` on a helper thread and completes with its result.
+fn blocking<T, F>(f: F) -> impl Future<Output = T> + Send
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    struct State<T> {
+        result: Option<T>,
+        waker: Option<Waker>,
+    }
+
+    struct Blocking<T> {
+        state: Arc<Mutex<State<T>>>,
+    }
+
+    impl<T> Future for Blocking<T> {
+        type Output = T;
+
+        fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+            let mut state = self.state.lock().unwrap();
+            match state.result.take() {
+                Some(result) => Poll::Ready(result),
+                None => {
+                    state.waker = Some(cx.waker().clone());
+                    Poll::Pending
+                }
+            }
+        }
+    }
+
+    let state = Arc::new(Mutex::new(State { result: None, waker: None }));
+    let shared = Arc::clone(&state);
+    thread::spawn(move || {
+        let result = f();
+        let mut state = shared.lock().unwrap();
+        state.result = Some(result);
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    });
+    Blocking { state }
+}
+
+/// Fails `inner` with [`io::ErrorKind::TimedOut`] if it does not complete
+/// within `timeout`.
+struct Timeout<'a> {
+    inner: ResolveFuture<'a>,
+    deadline: Instant,
+    timer_started: bool,
+}
+
+impl Future for Timeout<'_> {
+    type Output = io::Result<Vec<SocketAddr>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if let Poll::Ready(result) = self.inner.as_mut().poll(cx) {
+            return Poll::Ready(result);
+        }
+
+        let now = Instant::now();
+        if now >= self.deadline {
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "name resolution timed out",
+            )));
+        }
+
+        if !self.timer_started {
+            self.timer_started = true;
+            let waker = cx.waker().clone();
+            let remaining = self.deadline - now;
+            thread::spawn(move || {
+                thread::sleep(remaining);
+                waker.wake();
+            });
+        }
+        Poll::Pending
+    }
+}
+
+/// Resolves names from a hosts file.
+///
+/// Besides IP addresses, entries may contain SCION addresses, with or without
+/// a bracketed host:
+///
+/// ```text
+/// 19-ffaa:1:1067,[127.0.0.1]  server.local
+/// 10.0.0.1                    legacy.local
+/// ```
+#[derive(Clone, Debug)]
+pub struct HostsFileResolver {
+    path: PathBuf,
+}
+
+impl HostsFileResolver {
+    pub fn new<P: Into<PathBuf>>(path: P) -> HostsFileResolver {
+        HostsFileResolver { path: path.into() }
+    }
+}
+
+impl Default for HostsFileResolver {
+    fn default() -> Self {
+        HostsFileResolver::new("/etc/hosts")
+    }
+}
+
+/// Looks up `host` in the contents of a hosts file.
+pub(crate) fn lookup_hosts(contents: &str, host: &str, port: u16) -> Vec<SocketAddr> {
+    contents
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or(""))
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let addr = fields.next()?;
+            if !fields.any(|name| name.eq_ignore_ascii_case(host)) {
+                return None;
+            }
+            if let Ok(ip) = quietly(|| IpAddr::from_str(addr)) {
+                Some(SocketAddr::new_ip(ip, port))
+            } else {
+                ScionAddr::from_str(addr).ok().map(|a| (a, port).into())
+            }
+        })
+        .collect()
+}
+
+impl AsyncResolve for HostsFileResolver {
+    fn resolve<'a>(&'a self, host: &'a str, port: u16) -> ResolveFuture<'a> {
+        let path = self.path.clone();
+        let host = host.to_string();
+        Box::pin(blocking(move || {
+            let contents = fs::read_to_string(&path)?;
+            Ok(lookup_hosts(&contents, &host, port))
+        }))
+    }
+}
+
+const DNS_TYPE_TXT: u16 = 16;
+const DNS_CLASS_IN: u16 = 1;
+
+/// Resolves names via DNS TXT records of the form `scion=<ScionAddr>`.
+#[derive(Clone, Debug)]
+pub struct DnsTxtResolver {
+    nameserver: Option<std::net::SocketAddr>,
+    timeout: Duration,
+}
+
+impl DnsTxtResolver {
+    /// Queries the given name server instead of the first one
+    /// listed in `/etc/resolv.conf`.
+    pub fn with_nameserver(nameserver: std::net::SocketAddr) -> DnsTxtResolver {
+        DnsTxtResolver { nameserver: Some(nameserver), ..Default::default() }
+    }
+
+    /// Sets the timeout of a single DNS query.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+}
+
+impl Default for DnsTxtResolver {
+    fn default() -> Self {
+        DnsTxtResolver { nameserver: None, timeout: Duration::from_secs(2) }
+    }
+}
+
+fn system_nameserver() -> io::Result<std::net::SocketAddr> {
+    let conf = fs::read_to_string("/etc/resolv.conf")?;
+    conf.lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            match fields.next() {
+                Some("nameserver") => fields.next()?.parse::<std::net::IpAddr>().ok(),
+                _ => None,
+            }
+        })
+        .map(|ip| std::net::SocketAddr::new(ip, 53))
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no nameserver configured"))
+}
+
+/// Builds a recursive DNS query for the TXT records of `name`.
+pub(crate) fn build_txt_query(id: u16, name: &str) -> io::Result<Vec<u8>> {
+    let mut msg = Vec::with_capacity(18 + name.len());
+    msg.extend_from_slice(&id.to_be_bytes());
+    // standard query, recursion desired, one question
+    msg.extend_from_slice(&[0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0]);
+    for label in name.trim_end_matches('.').split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid DNS name"));
+        }
+        msg.push(label.len() as u8);
+        msg.extend_from_slice(label.as_bytes());
+    }
+    msg.push(0);
+    msg.extend_from_slice(&DNS_TYPE_TXT.to_be_bytes());
+    msg.extend_from_slice(&DNS_CLASS_IN.to_be_bytes());
+    Ok(msg)
+}
+
+/// Extracts the TXT record strings from a DNS response to query `id`.
+pub(crate) fn parse_txt_response(id: u16, msg: &[u8]) -> io::Result<Vec<String>> {
+    fn malformed() -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, "malformed DNS response")
+    }
+
+    fn skip_name(msg: &[u8], mut pos: usize) -> io::Result<usize> {
+        loop {
+            let len = *msg.get(pos).ok_or_else(malformed)? as usize;
+            if len == 0 {
+                return Ok(pos + 1);
+            }
+            if len & 0xc0 == 0xc0 {
+                return Ok(pos + 2);
+            }
+            pos += 1 + len;
+        }
+    }
+
+    fn read_u16(msg: &[u8], pos: usize) -> io::Result<u16> {
+        msg.get(pos..pos + 2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]))
+            .ok_or_else(malformed)
+    }
+
+    // the header alone is 12 bytes
+    if msg.len() < 12 || read_u16(msg, 0)? != id || msg[2] & 0x80 == 0 {
+        return Err(malformed());
+    }
+    match msg[3] & 0x0f {
+        0 => {}
+        // NXDOMAIN
+        3 => return Ok(Vec::new()),
+        _ => return Err(io::Error::new(io::ErrorKind::Other, "DNS server reported an error")),
+    }
+
+    let questions = read_u16(msg, 4)?;
+    let answers = read_u16(msg, 6)?;
+    let mut pos = 12;
+    for _ in 0..questions {
+        pos = skip_name(msg, pos)? + 4;
+    }
+
+    let mut records = Vec::new();
+    for _ in 0..answers {
+        pos = skip_name(msg, pos)?;
+        let rtype = read_u16(msg, pos)?;
+        let rdlen = read_u16(msg, pos + 8)? as usize;
+        pos += 10;
+        let rdata = msg.get(pos..pos + rdlen).ok_or_else(malformed)?;
+        pos += rdlen;
+
+        if rtype != DNS_TYPE_TXT {
+            continue;
+        }
+        // a TXT record consists of length-prefixed character strings
+        let mut txt = String::new();
+        let mut i = 0;
+        while i < rdata.len() {
+            let len = rdata[i] as usize;
+            let chunk = rdata.get(i + 1..i + 1 + len).ok_or_else(malformed)?;
+            txt.push_str(&String::from_utf8_lossy(chunk));
+            i += 1 + len;
+        }
+        records.push(txt);
+    }
+    Ok(records)
+}
+
+impl AsyncResolve for DnsTxtResolver {
+    fn resolve<'a>(&'a self, host: &'a str, port: u16) -> ResolveFuture<'a> {
+        let resolver = self.clone();
+        let host = host.to_string();
+        Box::pin(blocking(move || {
+            let nameserver = match resolver.nameserver {
+                Some(ns) => ns,
+                None => system_nameserver()?,
+            };
+            let bind: std::net::SocketAddr = if nameserver.is_ipv4() {
+                ([0, 0, 0, 0], 0).into()
+            } else {
+                (std::net::Ipv6Addr::UNSPECIFIED, 0).into()
+            };
+            let socket = UdpSocket::bind(bind)?;
+            socket.set_read_timeout(Some(resolver.timeout))?;
+            socket.connect(nameserver)?;
+
+            let nanos = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.subsec_nanos());
+            let id = (nanos ^ std::process::id()) as u16;
+            socket.send(&build_txt_query(id, &host)?)?;
+            let mut buf = [0u8; 1500];
+            let len = socket.recv(&mut buf)?;
+
+            Ok(parse_txt_response(id, &buf[..len])?
+                .iter()
+                .filter_map(|txt| txt.strip_prefix("scion="))
+                .filter_map(|addr| ScionAddr::from_str(addr.trim()).ok())
+                .map(|addr| (addr, port).into())
+                .collect())
+        }))
+    }
+}
+
+/// Resolves names to IP addresses with the operating system's resolver.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct SystemResolver;
+
+impl AsyncResolve for SystemResolver {
+    fn resolve<'a>(&'a self, host: &'a str, port: u16) -> ResolveFuture<'a> {
+        let host = host.to_string();
+        Box::pin(blocking(move || {
+            std::net::ToSocketAddrs::to_socket_addrs(&(host.as_str(), port))
+                .map(|addrs| addrs.map(SocketAddr::from).collect())
+        }))
+    }
+}
+
+/// Queries a list of resolvers in order and returns the ranked result
+/// of the first one that knows the name.
+///
+/// The default chain consults `/etc/hosts`, then DNS TXT records and
+/// finally the system resolver. Failing or timed out resolvers are skipped.
+pub struct ResolverChain {
+    resolvers: Vec<(Box<dyn AsyncResolve>, Option<Duration>)>,
+}
+
+impl ResolverChain {
+    /// Creates a chain without any resolvers.
+    pub fn new() -> ResolverChain {
+        ResolverChain { resolvers: Vec::new() }
+    }
+
+    /// Appends a resolver without a timeout.
+    pub fn push<R: AsyncResolve + 'static>(&mut self, resolver: R) -> &mut Self {
+        self.resolvers.push((Box::new(resolver), None));
+        self
+    }
+
+    /// Appends a resolver that is skipped if it does not answer within `timeout`.
+    pub fn push_with_timeout<R: AsyncResolve + 'static>(
+        &mut self,
+        resolver: R,
+        timeout: Duration,
+    ) -> &mut Self {
+        self.resolvers.push((Box::new(resolver), Some(timeout)));
+        self
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.resolvers.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.resolvers.is_empty()
+    }
+}
+
+impl Default for ResolverChain {
+    fn default() -> Self {
+        let mut chain = ResolverChain::new();
+        chain
+            .push(HostsFileResolver::default())
+            .push_with_timeout(DnsTxtResolver::default(), Duration::from_secs(3))
+            .push_with_timeout(SystemResolver, Duration::from_secs(5));
+        chain
+    }
+}
+
+impl AsyncResolve for ResolverChain {
+    fn resolve<'a>(&'a self, host: &'a str, port: u16) -> ResolveFuture<'a> {
+        Box::pin(async move {
+            // literal addresses need no resolution
+            if let Ok(ip) = quietly(|| IpAddr::from_str(host)) {
+                return Ok(vec![SocketAddr::new_ip(ip, port)]);
+            }
+            if let Ok(addr) = quietly(|| ScionAddr::from_str(host)) {
+                return Ok(vec![(addr, port).into()]);
+            }
+
+            let mut last_err = None;
+            for (resolver, timeout) in &self.resolvers {
+                let lookup = resolver.resolve(host, port);
+                let result = match *timeout {
+                    Some(timeout) => {
+                        Timeout { inner: lookup, deadline: Instant::now() + timeout, timer_started: false }
+                            .await
+                    }
+                    None => lookup.await,
+                };
+                match result {
+                    Ok(addrs) if !addrs.is_empty() => return Ok(rank_addrs(addrs)),
+                    Ok(_) => {}
+                    Err(e) => last_err = Some(e),
+                }
+            }
+            Err(last_err.unwrap_or_else(|| {
+                io::Error::new(io::ErrorKind::NotFound, format!("failed to resolve '{}'", host))
+            }))
+        })
+    }
+}
+
+struct ThreadWaker(thread::Thread);
+
+impl std::task::Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Resolves `host` with the default [`ResolverChain`], blocking the current thread.
+pub(crate) fn resolve_blocking(host: &str, port: u16) -> io::Result<Vec<SocketAddr>> {
+    let chain = ResolverChain::default();
+    let mut lookup = chain.resolve(host, port);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        match lookup.as_mut().poll(&mut cx) {
+            Poll::Ready(addrs) => return addrs,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
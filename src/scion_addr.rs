@@ -1,6 +1,8 @@
 use crate::scion_parse_utils::{as_from_ia, as_to_dotted_hex, isd_from_ia, make_ia};
-use crate::{IpAddr, Ipv4Addr, Ipv6Addr, Parser, SocketAddrScion, SocketAddrV4, SocketAddrV6};
-use std::error::Error;
+use crate::{
+    AddrKind, AddrParseError, Asn, DisplayBuffer, HostAddr, IpAddr, Isd, IsdAsn, Ipv4Addr, Ipv6Addr,
+};
+use std::convert::TryFrom;
 use std::str::FromStr;
 
 const MAX_BGP_AS_NR: u32 = 4294967295;
@@ -28,33 +30,57 @@ assigned in ascending order, without gaps and without vanity numbers
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Ord, PartialOrd, Debug)]
 
 pub struct ScionAddr {
-    pub ia: u64,
-    pub host: IpAddr,
+    ia: u64,
+    host: HostAddr,
 }
 
 impl Default for ScionAddr {
     fn default() -> Self {
         Self {
             ia: 0,
-            host: IpAddr::default(),
+            host: HostAddr::default(),
         }
     }
 }
 
 impl ScionAddr {
-    pub fn new(_ia: u64, _host: IpAddr) -> ScionAddr {
-        Self {
-            ia: _ia,
-            host: _host,
-        }
+    /// Creates a new SCION address of `host` in the AS `ia`.
+    #[must_use]
+    pub fn from_ia<H: Into<HostAddr>>(ia: IsdAsn, host: H) -> ScionAddr {
+        Self { ia: ia.to_u64(), host: host.into() }
     }
 
-    pub fn new1(_isd: u16, _as: u64, _host: IpAddr) -> ScionAddr {
-        Self {
-            ia: make_ia(_isd, _as),
-            host: _host,
-        }
+    /// Creates a new SCION address of `host` in the AS `ia`, like
+    /// [`from_ia`](Self::from_ia) but usable in constants:
+    ///
+    /// ```
+    /// use scionnet::{HostAddr, IsdAsn, ScionAddr, Svc};
+    ///
+    /// const IA: IsdAsn = IsdAsn::from_str_const("19-ffaa:1:1067");
+    /// const CS: ScionAddr = ScionAddr::from_ia_host(IA, HostAddr::Svc(Svc::CS));
+    /// assert_eq!(CS.to_string(), "19-ffaa:1:1067,CS");
+    /// ```
+    #[must_use]
+    pub const fn from_ia_host(ia: IsdAsn, host: HostAddr) -> ScionAddr {
+        Self { ia: ia.to_u64(), host }
+    }
+
+    /// Creates a new SCION address from a range-checked ISD and AS number.
+    #[must_use]
+    pub fn from_parts<H: Into<HostAddr>>(isd: Isd, asn: Asn, host: H) -> ScionAddr {
+        Self::from_ia(IsdAsn::new(isd, asn), host)
+    }
+
+    #[deprecated(note = "use `ScionAddr::from_ia` instead")]
+    pub fn new<H: Into<HostAddr>>(_ia: u64, _host: H) -> ScionAddr {
+        Self::from_ia(IsdAsn::from(_ia), _host)
+    }
+
+    #[deprecated(note = "use `ScionAddr::from_parts` instead")]
+    pub fn new1<H: Into<HostAddr>>(_isd: u16, _as: u64, _host: H) -> ScionAddr {
+        Self::from_ia(IsdAsn::from(make_ia(_isd, _as)), _host)
     }
+
     pub fn set_ia(&mut self, ia_: u64) {
         self.ia = ia_;
     }
@@ -63,6 +89,10 @@ impl ScionAddr {
         self.ia
     }
 
+    pub fn isd_asn(&self) -> IsdAsn {
+        IsdAsn::from(self.ia)
+    }
+
     pub fn set_isd(&mut self, isd_: u16) {
         self.set_ia(make_ia(isd_, self.get_as()));
     }
@@ -75,16 +105,95 @@ impl ScionAddr {
         as_from_ia(self.get_ia())
     }
 
+    pub fn isd(&self) -> Isd {
+        Isd::new(self.get_isd())
+    }
+
+    pub fn asn(&self) -> Asn {
+        // the lower 48 bits of the IA always hold a valid AS number
+        Asn::try_from(self.get_as()).unwrap()
+    }
+
     pub fn set_as(&mut self, as_: u64) {
         self.set_ia(make_ia(self.get_isd(), as_));
     }
 
-    pub fn get_host(&self) -> &IpAddr {
+    pub const fn get_host(&self) -> &HostAddr {
         &self.host
     }
 
-    pub fn set_host(&mut self, h: IpAddr) {
-        self.host = h;
+    pub fn set_host<H: Into<HostAddr>>(&mut self, h: H) {
+        self.host = h.into();
+    }
+
+    /// Returns the IP address of the host, or `None` for a service address.
+    pub const fn ip(&self) -> Option<IpAddr> {
+        self.host.ip()
+    }
+
+    /// Returns `true` for the wildcard IA with an unspecified IP host, the
+    /// address that matches any other, see [`matches`](ScionAddr::matches).
+    #[must_use]
+    pub fn is_unspecified(&self) -> bool {
+        self.isd_asn() == IsdAsn::WILDCARD && self.host.is_unspecified()
+    }
+
+    /// Returns `true` if `other` matches this address used as a pattern,
+    /// as SCION firewall rules and path lookups compare addresses: ISD 0,
+    /// AS 0 and an unspecified host (`0.0.0.0` or `::`) match anything.
+    ///
+    /// ```
+    /// use scionnet::ScionAddr;
+    ///
+    /// let rule: ScionAddr = "1-0,0.0.0.0".parse().unwrap();
+    /// assert!(rule.matches(&"1-ff00:0:110,10.0.0.1".parse().unwrap()));
+    /// assert!(!rule.matches(&"2-ff00:0:210,10.0.0.1".parse().unwrap()));
+    /// ```
+    #[must_use]
+    pub fn matches(&self, other: &ScionAddr) -> bool {
+        let isd_ok = self.isd().is_wildcard() || self.isd() == other.isd();
+        let as_ok = self.asn().is_wildcard() || self.asn() == other.asn();
+        isd_ok && as_ok && (self.host.is_unspecified() || self.host == other.host)
+    }
+
+    /// Returns the address in the form SCIONLab uses for file names, which is
+    /// also safe as a metric label value or database key: the colons of the
+    /// AS number become underscores, the separators of the host dashes, and
+    /// both are joined by an underscore, i.e. `1-ff00_0_110_10-0-0-1`.
+    ///
+    /// [`from_slug`](ScionAddr::from_slug) reverses the conversion.
+    pub fn to_slug(&self) -> String {
+        let host = match self.host {
+            HostAddr::Ip(IpAddr::V4(ip)) => ip.to_string().replace('.', "-"),
+            HostAddr::Ip(IpAddr::V6(ip)) => ip.to_string().replace(':', "-"),
+            HostAddr::Svc(svc) => svc.to_string().replace('_', "-"),
+        };
+        format!("{}_{}", self.isd_asn().to_string().replace(':', "_"), host)
+    }
+
+    /// Parses an address in the form returned by [`to_slug`](ScionAddr::to_slug).
+    pub fn from_slug(slug: &str) -> Result<ScionAddr, AddrParseError> {
+        // the parts are parsed after rewriting them, so their failures are
+        // not the ones to report, but a failure of the slug as a whole
+        crate::parse_observer::quietly(|| Self::parse_slug(slug)).map_err(|err| {
+            crate::parse_observer::report(slug.as_bytes(), err.kind(), 0);
+            err
+        })
+    }
+
+    fn parse_slug(slug: &str) -> Result<ScionAddr, AddrParseError> {
+        let err = || AddrParseError::new(AddrKind::Scion);
+        // the host never contains an underscore
+        let (ia, host) = slug.rsplit_once('_').ok_or_else(err)?;
+        let ia = IsdAsn::from_str(&ia.replace('_', ":")).map_err(|_| err())?;
+        let host = match Ipv4Addr::from_str(&host.replace('-', ".")) {
+            Ok(ip) => HostAddr::from(ip),
+            Err(_) => match crate::Svc::from_str(&host.replace('-', "_")) {
+                Ok(svc) => HostAddr::Svc(svc),
+                Err(_) => Ipv6Addr::from_str(&host.replace('-', ":")).map_err(|_| err())?.into(),
+            },
+        };
+        Ok(ScionAddr::from_ia(ia, host))
     }
 }
 
@@ -99,13 +208,46 @@ pub fn format_AS(asn: u64) -> String {
     }
 }
 
+impl ScionAddr {
+    /// The length of the longest textual form of an address,
+    /// `65535-ffff:ffff:ffff,[ffff:ffff:ffff:ffff:ffff:ffff:255.255.255.255]`.
+    pub const MAX_DISPLAY_LEN: usize = 68;
+
+    /// Writes the textual form of the address to `w`, without allocating.
+    pub fn write_to<W: std::fmt::Write>(&self, w: &mut W) -> std::fmt::Result {
+        // IPv6 hosts are bracketed, so a port can follow them unambiguously
+        let (open, close) = match self.host {
+            HostAddr::Ip(IpAddr::V6(_)) => ("[", "]"),
+            _ => ("", ""),
+        };
+        write!(w, "{},{}{}{}", self.isd_asn(), open, self.host, close)
+    }
+
+    /// Returns the textual form of the address in a stack buffer.
+    ///
+    /// ```
+    /// use scionnet::ScionAddr;
+    ///
+    /// let addr: ScionAddr = "1-ff00:0:110,[::1]".parse().unwrap();
+    /// assert_eq!(addr.to_display_buffer().as_str(), "1-ff00:0:110,[::1]");
+    /// ```
+    #[must_use]
+    pub fn to_display_buffer(&self) -> DisplayBuffer<{ ScionAddr::MAX_DISPLAY_LEN }> {
+        let mut buf = DisplayBuffer::new();
+        // the buffer is long enough for the longest address
+        self.write_to(&mut buf).unwrap();
+        buf
+    }
+}
+
 impl std::fmt::Display for ScionAddr {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.pad(&format!(
-            "{}-{},{}",
-            self.get_isd(),
-            format_AS(self.get_as()),
-            &self.host.to_string()
-        ))
+        // only padding needs the whole address up front
+        if f.precision().is_none() && f.width().is_none() {
+            return self.write_to(f);
+        }
+        let mut s = String::new();
+        self.write_to(&mut s)?;
+        f.pad(&s)
     }
 }
@@ -0,0 +1,379 @@
+//! A single entry point for applications, enabled by the `daemon` feature.
+
+use crate::daemon::{AsInfo, DaemonAddr, DaemonClient};
+use crate::{
+    AsyncScionUdpSocket, IpAddr, IsdAsn, PathSelector, Policy, ScionAddr, ScionPath,
+    ShortestPath, SocketAddrScion,
+};
+use std::collections::HashMap;
+use std::io;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+/// The environment variable naming the daemon to connect to, in the form
+/// of a [`DaemonAddr`].
+pub const DAEMON_ADDRESS_ENV: &str = "SCION_DAEMON_ADDRESS";
+/// The environment variable naming the local host address to bind sockets to.
+pub const LOCAL_ADDR_ENV: &str = "SCION_LOCAL_ADDR";
+/// How long paths are cached by default, see [`ScionEndpoint::set_path_ttl`].
+pub const DEFAULT_PATH_TTL: Duration = Duration::from_secs(300);
+/// How many destination IAs paths are cached for by default, see
+/// [`ScionEndpoint::set_path_cache_capacity`].
+pub const DEFAULT_PATH_CACHE_CAPACITY: usize = 1024;
+
+struct CacheEntry {
+    fetched: Instant,
+    used: Instant,
+    paths: Vec<ScionPath>,
+}
+
+impl CacheEntry {
+    fn is_stale(&self, ttl: Duration, now: Instant, wall: SystemTime) -> bool {
+        now.saturating_duration_since(self.fetched) >= ttl
+            || self.paths.iter().all(|p| p.is_expired(wall))
+    }
+}
+
+/// The paths to each destination IA, with the time they were fetched and
+/// last used. When full, the least recently used destination is dropped.
+pub(crate) struct PathCache {
+    ttl: Duration,
+    capacity: usize,
+    entries: HashMap<IsdAsn, CacheEntry>,
+}
+
+impl PathCache {
+    pub(crate) fn new(ttl: Duration, capacity: usize) -> PathCache {
+        PathCache { ttl, capacity, entries: HashMap::new() }
+    }
+
+    /// Returns the cached paths to `dst`, unless they are older than the TTL
+    /// or have all expired.
+    pub(crate) fn get(&mut self, dst: IsdAsn, now: Instant) -> Option<&[ScionPath]> {
+        let entry = self.entries.get_mut(&dst)?;
+        if entry.is_stale(self.ttl, now, SystemTime::now()) {
+            return None;
+        }
+        entry.used = now;
+        Some(&entry.paths)
+    }
+
+    pub(crate) fn insert(&mut self, dst: IsdAsn, paths: Vec<ScionPath>, now: Instant) {
+        if self.capacity == 0 {
+            return;
+        }
+        if !self.entries.contains_key(&dst) {
+            self.shrink_to(self.capacity - 1, now);
+        }
+        self.entries.insert(dst, CacheEntry { fetched: now, used: now, paths });
+    }
+
+    /// Drops the stale destinations, and then the least recently used ones
+    /// until at most `len` are left.
+    fn shrink_to(&mut self, len: usize, now: Instant) {
+        if self.entries.len() <= len {
+            return;
+        }
+        let (ttl, wall) = (self.ttl, SystemTime::now());
+        self.entries.retain(|_, entry| !entry.is_stale(ttl, now, wall));
+        while self.entries.len() > len {
+            let lru = self.entries.iter().min_by_key(|(_, entry)| entry.used).map(|(ia, _)| *ia);
+            self.entries.remove(&lru.expect("a non-empty cache"));
+        }
+    }
+
+    pub(crate) fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.shrink_to(capacity, Instant::now());
+    }
+
+    pub(crate) fn remove(&mut self, dst: IsdAsn) {
+        self.entries.remove(&dst);
+    }
+
+    /// Returns the destinations used since their paths were fetched that are
+    /// due for a refresh within `margin`: their paths get older than the TTL
+    /// or one of them expires. Drops the stale destinations not used since.
+    pub(crate) fn due(&mut self, now: Instant, margin: Duration) -> Vec<IsdAsn> {
+        let (ttl, wall) = (self.ttl, SystemTime::now());
+        self.entries
+            .retain(|_, entry| entry.used > entry.fetched || !entry.is_stale(ttl, now, wall));
+        self.entries
+            .iter()
+            .filter(|(_, entry)| {
+                entry.used > entry.fetched
+                    && ((now + margin).saturating_duration_since(entry.fetched) >= ttl
+                        || entry.paths.iter().any(|p| p.is_expired(wall + margin)))
+            })
+            .map(|(ia, _)| *ia)
+            .collect()
+    }
+
+    /// Returns all cached paths, fresh or not.
+    pub(crate) fn paths(&self) -> impl Iterator<Item = &ScionPath> + '_ {
+        self.entries.values().flat_map(|entry| &entry.paths)
+    }
+}
+
+/// Returns the local host address to use: the one in [`LOCAL_ADDR_ENV`], or
+/// the one the operating system picks to reach `router`, or localhost.
+fn discover_host(router: Option<std::net::SocketAddr>) -> io::Result<IpAddr> {
+    if let Ok(addr) = std::env::var(LOCAL_ADDR_ENV) {
+        return IpAddr::from_str(addr.trim())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e));
+    }
+    let router = match router {
+        Some(router) => router,
+        None => return Ok(IpAddr::V4(crate::Ipv4Addr::LOCALHOST)),
+    };
+    let unspecified: std::net::IpAddr = match router {
+        std::net::SocketAddr::V4(_) => std::net::Ipv4Addr::UNSPECIFIED.into(),
+        std::net::SocketAddr::V6(_) => std::net::Ipv6Addr::UNSPECIFIED.into(),
+    };
+    // connecting a UDP socket sends nothing, it only selects the route
+    let probe = std::net::UdpSocket::bind((unspecified, 0))?;
+    probe.connect(router)?;
+    Ok(IpAddr::from(probe.local_addr()?.ip()))
+}
+
+/// The SCION stack of an application: the daemon, the local address, a
+/// cache of paths and the policy for choosing among them.
+///
+/// ```ignore
+/// let endpoint = ScionEndpoint::new().await?;
+/// let socket = endpoint.bind(0).await?;
+/// let server: SocketAddrScion = "2-ff00:0:220,10.0.0.2:8080".parse()?;
+/// endpoint.send_to(&socket, b"hello", server).await?;
+/// ```
+pub struct ScionEndpoint {
+    daemon: DaemonClient,
+    info: AsInfo,
+    host: IpAddr,
+    paths: Mutex<PathCache>,
+    policy: Option<Policy>,
+    selector: Arc<dyn PathSelector>,
+}
+
+impl ScionEndpoint {
+    /// Connects to the daemon named by [`DAEMON_ADDRESS_ENV`], or else the
+    /// default one, and learns the local IA and host address.
+    pub async fn new() -> io::Result<ScionEndpoint> {
+        let addr = match std::env::var(DAEMON_ADDRESS_ENV) {
+            Ok(addr) => DaemonAddr::from_str(addr.trim())
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?,
+            Err(_) => DaemonAddr::default(),
+        };
+        Self::with_daemon(&addr).await
+    }
+
+    /// Connects to the daemon at `addr` and learns the local IA and host
+    /// address.
+    pub async fn with_daemon(addr: &DaemonAddr) -> io::Result<ScionEndpoint> {
+        let mut daemon = DaemonClient::connect_to(addr).await?;
+        let info = daemon.as_info(None).await?;
+        let interfaces = daemon.interfaces().await?;
+        let router = interfaces.keys().min().map(|id| interfaces[id]);
+        Ok(ScionEndpoint {
+            daemon,
+            info,
+            host: discover_host(router)?,
+            paths: Mutex::new(PathCache::new(DEFAULT_PATH_TTL, DEFAULT_PATH_CACHE_CAPACITY)),
+            policy: None,
+            selector: Arc::new(ShortestPath),
+        })
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn local_ia(&self) -> IsdAsn {
+        self.info.ia
+    }
+
+    /// Returns the SCION address sockets are bound to.
+    #[must_use]
+    pub fn local_addr(&self) -> ScionAddr {
+        ScionAddr::from_ia(self.info.ia, self.host)
+    }
+
+    /// Returns what the daemon told about the local AS.
+    #[must_use]
+    pub fn as_info(&self) -> &AsInfo {
+        &self.info
+    }
+
+    /// Returns a client of the daemon, i.e. for RPCs this type does not cover.
+    #[must_use]
+    pub fn daemon(&self) -> DaemonClient {
+        self.daemon.clone()
+    }
+
+    /// Only uses paths satisfying `policy`.
+    pub fn set_policy(&mut self, policy: Option<Policy>) {
+        self.policy = policy;
+    }
+
+    /// Chooses paths with `selector`; the default is [`ShortestPath`].
+    pub fn set_selector(&mut self, selector: Arc<dyn PathSelector>) {
+        self.selector = selector;
+    }
+
+    /// Sets how long paths are cached before they are fetched again.
+    pub fn set_path_ttl(&mut self, ttl: Duration) {
+        self.paths.get_mut().unwrap().ttl = ttl;
+    }
+
+    /// Sets how many destination IAs paths are cached for, dropping the
+    /// least recently used ones beyond that.
+    pub fn set_path_cache_capacity(&mut self, capacity: usize) {
+        self.paths.get_mut().unwrap().set_capacity(capacity);
+    }
+
+    /// Returns the unexpired paths to `dst` satisfying the policy, from the
+    /// cache or else fetched from the daemon.
+    pub async fn paths(&self, dst: IsdAsn) -> io::Result<Vec<ScionPath>> {
+        let wall = SystemTime::now();
+        if let Some(paths) = self.paths.lock().unwrap().get(dst, Instant::now()) {
+            return Ok(paths.iter().filter(|p| !p.is_expired(wall)).cloned().collect());
+        }
+        let mut paths = self.refresh_paths(dst).await?;
+        paths.retain(|p| !p.is_expired(wall));
+        Ok(paths)
+    }
+
+    /// Fetches fresh paths to `dst` from the daemon, i.e. after the path in
+    /// use stopped working.
+    pub async fn refresh_paths(&self, dst: IsdAsn) -> io::Result<Vec<ScionPath>> {
+        let paths = self.daemon.clone().paths_with(self.info.ia, dst, true, false).await?;
+        let paths = match &self.policy {
+            Some(policy) => policy.filter(paths),
+            None => paths,
+        };
+        self.paths.lock().unwrap().insert(dst, paths.clone(), Instant::now());
+        Ok(paths)
+    }
+
+    /// Writes the cached paths to the path database `file`, returning how
+    /// many were written; see [`path_db`](crate::path_db).
+    pub fn save_paths<P: AsRef<std::path::Path>>(&self, file: P) -> io::Result<usize> {
+        let cache = self.paths.lock().unwrap();
+        let paths: Vec<&ScionPath> = cache.paths().collect();
+        crate::path_db::write(file, paths.iter().copied())?;
+        Ok(paths.len())
+    }
+
+    /// Fills the cache with the unexpired paths from the local IA in the path
+    /// database `file`, i.e. one written by [`save_paths`](Self::save_paths)
+    /// before a restart. Returns how many paths were loaded.
+    pub fn load_paths<P: AsRef<std::path::Path>>(&self, file: P) -> io::Result<usize> {
+        let db = crate::path_db::PathDb::open(file)?;
+        let (now, wall) = (Instant::now(), SystemTime::now());
+        let mut cache = self.paths.lock().unwrap();
+        let mut loaded = 0;
+        for dst in db.ias() {
+            let paths: Vec<ScionPath> = db
+                .paths_to(dst)
+                .filter(|p| p.src() == self.info.ia && !p.is_expired(wall))
+                .map(|p| p.to_path())
+                .collect();
+            if !paths.is_empty() {
+                loaded += paths.len();
+                cache.insert(dst, paths, now);
+            }
+        }
+        Ok(loaded)
+    }
+
+    /// Drops the cached paths to `dst`.
+    pub fn forget_paths(&self, dst: IsdAsn) {
+        self.paths.lock().unwrap().remove(dst);
+    }
+
+    /// Refreshes the cached paths that are in use and go stale within
+    /// `margin`, returning how many destinations were refreshed. Paths not
+    /// used since they were fetched are dropped once stale instead.
+    ///
+    /// Stops at the first destination the daemon fails to return paths for.
+    pub async fn refresh_due_paths(&self, margin: Duration) -> io::Result<usize> {
+        let due = self.paths.lock().unwrap().due(Instant::now(), margin);
+        for &dst in &due {
+            self.refresh_paths(dst).await?;
+        }
+        Ok(due.len())
+    }
+
+    /// Starts the path monitor: a task on the current Tokio runtime calling
+    /// [`refresh_due_paths`](Self::refresh_due_paths) every `interval`, so
+    /// sending never waits for the daemon while a destination is in use.
+    /// The task ends when the endpoint is dropped.
+    ///
+    /// Failed refreshes are retried on the next interval.
+    pub fn spawn_path_monitor(self: &Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let endpoint = Arc::downgrade(self);
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                let Some(endpoint) = endpoint.upgrade() else {
+                    return;
+                };
+                // a refresh falling due before the next round is done in this one
+                if let Err(_e) = endpoint.refresh_due_paths(interval).await {
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!(target: "scionnet::endpoint", error = %_e, "refresh failed");
+                }
+            }
+        })
+    }
+
+    /// Binds an async socket to `port` of the local address, 0 for an
+    /// ephemeral port.
+    pub async fn bind(&self, port: u16) -> io::Result<AsyncScionUdpSocket> {
+        AsyncScionUdpSocket::bind(SocketAddrScion::from_scion_addr(self.local_addr(), port)).await
+    }
+
+    /// Chooses a path to `dst` and installs it as the route of `socket`,
+    /// returning the path. Nothing is installed for the local IA.
+    pub async fn route(&self, socket: &AsyncScionUdpSocket, dst: IsdAsn) -> io::Result<ScionPath> {
+        if dst == self.info.ia {
+            return Ok(ScionPath::empty(dst));
+        }
+        let paths = self.paths(dst).await?;
+        let path = self
+            .selector
+            .select(&paths)
+            .and_then(|i| paths.get(i))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("no path to {}", dst)))?;
+        let route = path.route().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("path to {} has no next hop", dst))
+        })?;
+        socket.set_route(dst, route);
+        Ok(path.clone())
+    }
+
+    /// Sends `buf` from `socket` to `dst`, installing a route first if the
+    /// socket has none for the IA of `dst`, or its path expired or is no
+    /// longer among the cached ones, i.e. after they were refreshed.
+    pub async fn send_to(
+        &self,
+        socket: &AsyncScionUdpSocket,
+        buf: &[u8],
+        dst: SocketAddrScion,
+    ) -> io::Result<usize> {
+        let ia = IsdAsn::from(dst.ia());
+        if ia != self.info.ia && !self.has_current_route(socket, ia) {
+            self.route(socket, ia).await?;
+        }
+        socket.send_to(buf, dst).await
+    }
+
+    fn has_current_route(&self, socket: &AsyncScionUdpSocket, ia: IsdAsn) -> bool {
+        let Some(route) = socket.route(ia) else {
+            return false;
+        };
+        let wall = SystemTime::now();
+        let mut cache = self.paths.lock().unwrap();
+        cache.get(ia, Instant::now()).is_some_and(|paths| {
+            paths.iter().any(|p| p.raw == route.raw_path && !p.is_expired(wall))
+        })
+    }
+}
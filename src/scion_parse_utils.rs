@@ -1,81 +1,67 @@
-use crate::{Ipv4Addr, Ipv6Addr, SocketAddr};
-use std::{convert::TryInto, str::FromStr};
-
-extern crate regex;
-use self::regex::Regex;
-
-fn tokenize(s: &str, re: &Regex) -> Vec<String> {
-    re.split(s)
-        .filter(|s| !s.is_empty())
-        .map(String::from)
-        .collect()
-}
-
+use crate::parser::Parser;
+use crate::{AddrKind, AddrParseError, Ipv4Addr, Ipv6Addr, IsdAsn, SocketAddrScion};
+
+/// Converts an AS number in the colon-separated hex notation to an integer,
+/// i.e. `ffaa:1:1067` to `0xffaa_0001_1067`.
+///
+/// # Panics
+///
+/// Panics if `s` is not hex digits separated by colons, see
+/// [`try_as_from_dotted_hex`] for a fallible version.
 pub fn as_from_dotted_hex(s: &str) -> u64 {
-    let re = Regex::new(r"[:]+").unwrap();
-    let token: Vec<_> = tokenize(s, &re);
-    // println!("{:?}",token);
-
-    let hex_str: String = token
-        .iter()
-        .map(|t| pad_to_4(&t.to_string()))
+    let hex_str: String = s
+        .split(':')
+        .filter(|t| !t.is_empty())
+        .map(pad_to_4)
         .collect::<Vec<String>>()
         .concat();
 
-    //println!("{}",hex_str);
-
     u64::from_str_radix(&hex_str, 16).unwrap()
 }
 
+/// Converts an AS number in the colon-separated hex notation to an integer,
+/// i.e. `ffaa:1:1067` to `0xffaa_0001_1067`.
+///
+/// Fails unless `s` consists of exactly three groups of one to four hex
+/// digits.
+pub fn try_as_from_dotted_hex(s: &str) -> Result<u64, AddrParseError> {
+    let err = || AddrParseError::new(AddrKind::Asn);
+    let mut asn = 0u64;
+    let mut groups = 0;
+    for group in s.split(':') {
+        if group.is_empty() || group.len() > 4 || !group.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(err());
+        }
+        asn = (asn << 16) | u64::from(u16::from_str_radix(group, 16).map_err(|_| err())?);
+        groups += 1;
+    }
+    if groups != 3 {
+        return Err(err());
+    }
+    Ok(asn)
+}
+
 type IA_t = u64;
 type AS_t = u64;
 type ISD_t = u16;
 
 pub fn as_to_dotted_hex(as_num: AS_t) -> String {
-    let hex_str = format!("{:x}", as_num);
-    let mut result = String::new();
-    let mut begin = true;
-    let mut encountered_zeros_in_row = 0;
-
-    for (pos, s) in hex_str.chars().enumerate() {
-        if pos != 0 && pos % 4 == 0 && !begin {
-            result.push(':');
-            encountered_zeros_in_row = 0;
-            begin = true;
-        }
-
-        if begin {
-            if s == '0' {
-                encountered_zeros_in_row += 1;
-                if encountered_zeros_in_row == 4 {
-                    result.push('0');
-                    result.push(':');
-                    begin = true;
-                    encountered_zeros_in_row = 0;
-                }
-                continue;
-            } else {
-                result.push(s);
-                encountered_zeros_in_row = 0;
-                begin = false;
-            }
-        } else {
-            result.push(s);
-        }
-    }
-
-    result
+    // each of the three 16 bit groups is printed without leading zeros
+    format!(
+        "{:x}:{:x}:{:x}",
+        (as_num >> 32) & 0xffff,
+        (as_num >> 16) & 0xffff,
+        as_num & 0xffff
+    )
 }
 
-const IPV6_ADDR_REGEX: &str =
-    r"((([0-9A-Fa-f]{1,4}:){1,6}:)|(([0-9A-Fa-f]{1,4}:){7}))([0-9A-Fa-f]{1,4})";
-
-pub fn as_from_ia(ia: u64) -> u64 {
+pub const fn as_from_ia(ia: u64) -> u64 {
     (ia << 16) >> 16
 }
 
-pub fn isd_from_ia(ia: u64) -> u16 {
-    (ia >> 48).try_into().unwrap()
+pub const fn isd_from_ia(ia: u64) -> u16 {
+    // the shift leaves 16 bits
+    (ia >> 48) as u16
 }
 
 /*
@@ -99,10 +85,20 @@ macro_rules! MAKE_BIG_IA {
     };
 } */
 
-pub fn make_ia(isd: u16, as_: u64) -> u64 {
+/// Packs an ISD and an AS number into an IA.
+///
+/// An AS number wider than 48 bits spills into the ISD bits, see
+/// [`try_make_ia`] for a checked version.
+pub const fn make_ia(isd: u16, as_: u64) -> u64 {
     ((isd as u64) << 48) | as_
 }
 
+/// Packs an ISD and an AS number into an IA, failing if the AS number does
+/// not fit into 48 bits.
+pub fn try_make_ia(isd: u16, as_: u64) -> Result<u64, AddrParseError> {
+    IsdAsn::try_new(isd, as_).map(IsdAsn::to_u64)
+}
+
 fn is_valid_ipv4(ip_address: &str) -> bool {
     ip_address.parse::<Ipv4Addr>().is_ok()
 }
@@ -111,33 +107,76 @@ fn is_ipv6_address(s: &str) -> bool {
     s.parse::<Ipv6Addr>().is_ok()
 }
 
-pub fn parse_scion_impl(host_scion_addr: &str, port_str: &str) -> (IA_t, ISD_t, AS_t, String, u16) {
-    let re =
-        regex::Regex::new(r"^(?:(\d+)-([\d:A-Fa-f]+)),(?:\[([^\]]+)\]|([^\[\]:]+))(?::(\d+))?$")
-            .unwrap();
-    let captures = re.captures(host_scion_addr).unwrap();
-
-    /*println!("{}", captures[0].len());
-    println!("{}", captures[1].len());
-    println!("{}", captures[2].len());*/
-
-    let isd: ISD_t = captures[1].parse().unwrap();
-    let as_str = &captures[2];
-    let as_num = as_from_ia(as_from_dotted_hex(&captures[2]));
-
-    let host = if let Some(ipv6) = captures.get(3) {
-        ipv6.as_str().to_string()
-    } else {
-        captures[4].to_string()
+/// Parses a SCION socket address, using `port_str` as the port if the address
+/// has none; i.e. `parse_scion("19-ffaa:1:1067,[::1]", "53")`.
+///
+/// Fails if the address is malformed or `port_str` is not a port number,
+/// even if the address has a port.
+pub fn parse_scion(
+    host_scion_addr: &str,
+    port_str: &str,
+) -> Result<SocketAddrScion, AddrParseError> {
+    let default_port = port_str.parse().map_err(|_| AddrParseError::new(AddrKind::SocketScion))?;
+    SocketAddrScion::parse_with_default_port(host_scion_addr, default_port)
+}
+
+/// Splits `host:port` into the host and the port, like Go's
+/// `net.SplitHostPort`, so the host can be resolved before it is parsed.
+///
+/// The host is a name, an IPv4 address, a bracketed IPv6 address or a SCION
+/// address, whose own IPv6 host must be bracketed unless the SCION address is
+/// bracketed as a whole. The brackets enclosing the whole host are removed:
+///
+/// ```
+/// use scionnet::split_host_port;
+///
+/// assert_eq!(split_host_port("[::1]:443"), Ok(("::1", 443)));
+/// assert_eq!(split_host_port("[1-ff00:0:110,::1]:443"), Ok(("1-ff00:0:110,::1", 443)));
+/// assert_eq!(split_host_port("1-ff00:0:110,[::1]:443"), Ok(("1-ff00:0:110,[::1]", 443)));
+/// assert_eq!(split_host_port("example.org:80"), Ok(("example.org", 80)));
+/// assert!(split_host_port("::1:443").is_err());
+/// ```
+pub fn split_host_port(s: &str) -> Result<(&str, u16), AddrParseError> {
+    let err = || AddrParseError::new(AddrKind::Socket);
+    let (host, port) = match s.strip_prefix('[') {
+        Some(rest) => {
+            let (host, rest) = rest.split_once(']').ok_or_else(err)?;
+            (host, rest.strip_prefix(':').ok_or_else(err)?)
+        }
+        None => {
+            let (host, port) = s.rsplit_once(':').ok_or_else(err)?;
+            // the host of a SCION address follows the last comma
+            let ip_host = host.rsplit_once(',').map_or(host, |(_, h)| h);
+            if ip_host.contains(':') && !(ip_host.starts_with('[') && ip_host.ends_with(']')) {
+                return Err(err());
+            }
+            (host, port)
+        }
     };
+    if port.is_empty() || port.len() > 5 || !port.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(err());
+    }
+    Ok((host, port.parse().map_err(|_| err())?))
+}
 
-    let port: u16 = if let Some(port_match) = captures.get(5) {
-        port_match.as_str().parse().unwrap()
-    } else {
-        port_str.parse().unwrap()
+/// Splits a SCION socket address into its IA, ISD, AS, host and port.
+///
+/// # Panics
+///
+/// Panics if the address is malformed.
+#[deprecated(note = "use `parse_scion`, which returns an error instead of panicking")]
+pub fn parse_scion_impl(host_scion_addr: &str, port_str: &str) -> (IA_t, ISD_t, AS_t, String, u16) {
+    let (ia, host, port) = Parser::new(host_scion_addr.as_bytes())
+        .parse_with(|p| p.read_scion_parts(), AddrKind::SocketScion)
+        .unwrap();
+    let (isd, as_num) = (ia.isd().to_u16(), ia.asn().to_u64());
+
+    let port: u16 = match port {
+        Some(port) => port,
+        None => port_str.parse().unwrap(),
     };
 
-    (make_ia(isd, as_num), isd, as_num, host, port)
+    (ia.to_u64(), isd, as_num, host, port)
 }
 
 fn pad_to_4(x: &str) -> String {
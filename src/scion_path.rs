@@ -0,0 +1,242 @@
+use crate::path::Path;
+use crate::{IsdAsn, ScionRoute};
+use std::fmt;
+use std::io;
+use std::time::{Duration, SystemTime};
+
+/// The SCION path type of the standard SCION dataplane path.
+pub const PATH_TYPE_SCION: u8 = 1;
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+fn invalid_path(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Decodes `raw`, which must be exactly one well-formed standard SCION path.
+fn decode_exact(raw: &[u8]) -> io::Result<Path> {
+    let path = Path::decode_from(raw)?;
+    if path.encoded_len() != raw.len() {
+        return Err(invalid_path("SCION path length does not match its segment lengths"));
+    }
+    Ok(path)
+}
+
+fn encode_base64(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, &b)| n | u32::from(b) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64_ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+fn decode_base64(s: &str) -> io::Result<Vec<u8>> {
+    let s = s.as_bytes();
+    if s.len() % 4 != 0 {
+        return Err(invalid_path("base64 input length is not a multiple of 4"));
+    }
+    let mut out = Vec::with_capacity(s.len() / 4 * 3);
+    for (k, chunk) in s.chunks(4).enumerate() {
+        let last = k + 1 == s.len() / 4;
+        let pad = chunk.iter().rev().take_while(|&&c| c == b'=').count();
+        if pad > 2 || (pad > 0 && !last) {
+            return Err(invalid_path("misplaced base64 padding"));
+        }
+        let mut n = 0u32;
+        for &c in &chunk[..4 - pad] {
+            let v = BASE64_ALPHABET
+                .iter()
+                .position(|&a| a == c)
+                .ok_or_else(|| invalid_path("invalid base64 character"))?;
+            n = n << 6 | v as u32;
+        }
+        n <<= 6 * pad as u32;
+        // the bits below the last encoded byte must be zero, so that every
+        // byte sequence has exactly one encoding
+        if n & ((1 << (8 * pad)) - 1) != 0 {
+            return Err(invalid_path("non-canonical base64 encoding"));
+        }
+        out.extend_from_slice(&n.to_be_bytes()[1..4 - pad]);
+    }
+    Ok(out)
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len() * 2);
+    for &b in bytes {
+        out.push(HEX_DIGITS[usize::from(b >> 4)] as char);
+        out.push(HEX_DIGITS[usize::from(b & 0xf)] as char);
+    }
+    out
+}
+
+fn decode_hex(s: &str) -> io::Result<Vec<u8>> {
+    let s = s.as_bytes();
+    if s.len() % 2 != 0 {
+        return Err(invalid_path("hex input has an odd number of digits"));
+    }
+    s.chunks(2)
+        .map(|pair| {
+            let digit = |c: u8| (c as char).to_digit(16).ok_or_else(|| invalid_path("invalid hex digit"));
+            Ok((digit(pair[0])? << 4 | digit(pair[1])?) as u8)
+        })
+        .collect()
+}
+
+/// An interface of an AS that a path traverses.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PathInterface {
+    pub ia: IsdAsn,
+    pub id: u64,
+}
+
+impl fmt::Display for PathInterface {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}#{}", self.ia, self.id)
+    }
+}
+
+/// A path between two ASes, as handed out by the SCION daemon: the raw
+/// dataplane path plus the metadata needed to pick and use it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScionPath {
+    /// The AS the path starts in.
+    pub src: IsdAsn,
+    /// The AS the path leads to.
+    pub dst: IsdAsn,
+    /// The SCION path type of `raw`.
+    pub path_type: u8,
+    /// The encoded dataplane path.
+    pub raw: Vec<u8>,
+    /// The underlay address of the border router of the first hop,
+    /// `None` for paths within the local AS.
+    pub next_hop: Option<std::net::SocketAddr>,
+    /// The interfaces traversed, two per AS boundary.
+    pub interfaces: Vec<PathInterface>,
+    /// The MTU along the path, `0` if unknown.
+    pub mtu: u16,
+    /// The point in time the path stops being valid.
+    pub expiration: SystemTime,
+    /// The latency of each hop, if known.
+    pub latency: Vec<Option<Duration>>,
+}
+
+impl ScionPath {
+    /// Returns the empty path of the AS `ia`, used for packets within the AS.
+    pub fn empty(ia: IsdAsn) -> ScionPath {
+        ScionPath {
+            src: ia,
+            dst: ia,
+            path_type: 0,
+            raw: Vec::new(),
+            next_hop: None,
+            interfaces: Vec::new(),
+            mtu: 0,
+            expiration: SystemTime::UNIX_EPOCH,
+            latency: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.raw.is_empty()
+    }
+
+    /// Returns `true` if the path is no longer valid at `now`.
+    #[must_use]
+    pub fn is_expired(&self, now: SystemTime) -> bool {
+        !self.is_empty() && self.expiration <= now
+    }
+
+    /// Returns the number of AS hops, counting both ends.
+    #[must_use]
+    pub fn hop_count(&self) -> usize {
+        if self.interfaces.is_empty() {
+            1
+        } else {
+            self.interfaces.len() / 2 + 1
+        }
+    }
+
+    /// Encodes the raw dataplane path in standard base64 with padding,
+    /// i.e. for logs and REST APIs.
+    #[must_use]
+    pub fn to_base64(&self) -> String {
+        encode_base64(&self.raw)
+    }
+
+    /// Encodes the raw dataplane path as lowercase hex.
+    #[must_use]
+    pub fn to_hex(&self) -> String {
+        encode_hex(&self.raw)
+    }
+
+    /// Decodes a path from `src` to `dst` produced by [`to_base64`](Self::to_base64).
+    ///
+    /// Fails if the input is not canonical base64 or does not decode to a
+    /// well-formed SCION path; the empty string decodes to the empty path.
+    /// The metadata, including the next hop, is left unset.
+    pub fn from_base64(src: IsdAsn, dst: IsdAsn, encoded: &str) -> io::Result<ScionPath> {
+        Self::from_raw(src, dst, decode_base64(encoded)?)
+    }
+
+    /// Decodes a path from `src` to `dst` produced by [`to_hex`](Self::to_hex),
+    /// accepting digits of either case, with the checks of [`from_base64`](Self::from_base64).
+    pub fn from_hex(src: IsdAsn, dst: IsdAsn, encoded: &str) -> io::Result<ScionPath> {
+        Self::from_raw(src, dst, decode_hex(encoded)?)
+    }
+
+    /// Decodes the raw dataplane path, i.e. to inspect its hop fields.
+    pub fn decode(&self) -> io::Result<Path> {
+        if self.path_type != PATH_TYPE_SCION {
+            return Err(io::Error::new(io::ErrorKind::Unsupported, "not a standard SCION path"));
+        }
+        decode_exact(&self.raw)
+    }
+
+    fn from_raw(src: IsdAsn, dst: IsdAsn, raw: Vec<u8>) -> io::Result<ScionPath> {
+        if raw.is_empty() {
+            return Ok(ScionPath { dst, ..ScionPath::empty(src) });
+        }
+        decode_exact(&raw)?;
+        Ok(ScionPath { dst, path_type: PATH_TYPE_SCION, raw, ..ScionPath::empty(src) })
+    }
+
+    /// Returns the route to use for a [`ScionUdpSocket`](crate::ScionUdpSocket),
+    /// or `None` if the next hop is unknown.
+    #[must_use]
+    pub fn route(&self) -> Option<ScionRoute> {
+        Some(ScionRoute {
+            next_hop: self.next_hop?,
+            path_type: self.path_type,
+            raw_path: self.raw.clone(),
+        })
+    }
+}
+
+impl fmt::Display for ScionPath {
+    /// Formats the path as its sequence of interfaces, i.e.
+    /// `1-ff00:0:110#1 1-ff00:0:111#2`, or `[empty]` for the empty path.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.interfaces.is_empty() {
+            return f.write_str("[empty]");
+        }
+        for (i, intf) in self.interfaces.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{}", intf)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,530 @@
+//! The SCION Control Message Protocol (SCMP), the SCION counterpart of ICMP.
+//!
+//! Every message starts with a type, a code and a checksum:
+//!
+//! ```text
+//!  0                   1                   2                   3
+//!  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! |     Type      |     Code      |           Checksum            |
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! |            type-specific info block, then the data            |
+//! ```
+//!
+//! Error messages (types below 128) carry as much of the offending packet as
+//! fits, its *quote*, as their data.
+
+use crate::checksum::scion_checksum;
+use crate::path::Path;
+use crate::udp_socket::{decode_host, decode_scion_header, encode_scion_header, MAX_DATAGRAM};
+use crate::wire::{CommonHeader, COMMON_HEADER_LEN, PATH_TYPE_EMPTY};
+use crate::{
+    IsdAsn, RateLimiter, ScionAddr, ScionPath, ScionUdpSocket, SocketAddrScion, PATH_TYPE_SCION,
+};
+use std::io;
+use std::net::UdpSocket;
+use std::time::{Duration, Instant};
+
+/// The protocol number of SCMP in the SCION next header field.
+pub const PROTO_SCMP: u8 = 202;
+
+pub const TYPE_DESTINATION_UNREACHABLE: u8 = 1;
+pub const TYPE_PACKET_TOO_BIG: u8 = 2;
+pub const TYPE_PARAMETER_PROBLEM: u8 = 4;
+pub const TYPE_EXTERNAL_INTERFACE_DOWN: u8 = 5;
+pub const TYPE_INTERNAL_CONNECTIVITY_DOWN: u8 = 6;
+pub const TYPE_ECHO_REQUEST: u8 = 128;
+pub const TYPE_ECHO_REPLY: u8 = 129;
+pub const TYPE_TRACEROUTE_REQUEST: u8 = 130;
+pub const TYPE_TRACEROUTE_REPLY: u8 = 131;
+
+/// The length of the type, code and checksum fields.
+const HDR_LEN: usize = 4;
+
+/// The codes of [`ScmpMessage::DestinationUnreachable`].
+pub mod unreachable {
+    pub const NO_ROUTE: u8 = 0;
+    pub const ADMIN_DENIED: u8 = 1;
+    pub const BEYOND_SCOPE: u8 = 2;
+    pub const ADDRESS_UNREACHABLE: u8 = 3;
+    pub const PORT_UNREACHABLE: u8 = 4;
+    pub const SOURCE_ADDRESS_FAILED_POLICY: u8 = 5;
+    pub const REJECT_ROUTE: u8 = 6;
+}
+
+/// An SCMP message.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ScmpMessage {
+    /// The destination could not be reached; `code` tells why, see [`unreachable`].
+    DestinationUnreachable { code: u8, quote: Vec<u8> },
+    /// The packet exceeded the `mtu` of a link.
+    PacketTooBig { mtu: u16, quote: Vec<u8> },
+    /// The byte at `pointer` within the quoted packet is invalid.
+    ParameterProblem { code: u8, pointer: u16, quote: Vec<u8> },
+    /// The link behind `interface` of `ia` is down.
+    ExternalInterfaceDown { ia: IsdAsn, interface: u64, quote: Vec<u8> },
+    /// `ia` cannot forward packets from `ingress` to `egress`.
+    InternalConnectivityDown { ia: IsdAsn, ingress: u64, egress: u64, quote: Vec<u8> },
+    EchoRequest { id: u16, seq: u16, data: Vec<u8> },
+    EchoReply { id: u16, seq: u16, data: Vec<u8> },
+    /// Asks the border router the packet is alerted at to answer.
+    TracerouteRequest { id: u16, seq: u16 },
+    /// The answer of the router at `interface` of `ia`.
+    TracerouteReply { id: u16, seq: u16, ia: IsdAsn, interface: u64 },
+    /// A message of a type this crate does not know, with everything after
+    /// the checksum as `body`.
+    Unknown { kind: u8, code: u8, body: Vec<u8> },
+}
+
+fn traceroute_info(id: u16, seq: u16, ia: IsdAsn, interface: u64) -> Vec<u8> {
+    let mut info = id.to_be_bytes().to_vec();
+    info.extend_from_slice(&seq.to_be_bytes());
+    info.extend_from_slice(&ia.to_u64().to_be_bytes());
+    info.extend_from_slice(&interface.to_be_bytes());
+    info
+}
+
+fn truncated() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "truncated SCMP message")
+}
+
+fn be_u16(b: &[u8]) -> u16 {
+    u16::from_be_bytes([b[0], b[1]])
+}
+
+fn be_u64(b: &[u8]) -> u64 {
+    u64::from_be_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]])
+}
+
+impl ScmpMessage {
+    /// Returns the type and code fields of the message.
+    #[must_use]
+    pub fn type_code(&self) -> (u8, u8) {
+        match *self {
+            ScmpMessage::DestinationUnreachable { code, .. } => (TYPE_DESTINATION_UNREACHABLE, code),
+            ScmpMessage::PacketTooBig { .. } => (TYPE_PACKET_TOO_BIG, 0),
+            ScmpMessage::ParameterProblem { code, .. } => (TYPE_PARAMETER_PROBLEM, code),
+            ScmpMessage::ExternalInterfaceDown { .. } => (TYPE_EXTERNAL_INTERFACE_DOWN, 0),
+            ScmpMessage::InternalConnectivityDown { .. } => (TYPE_INTERNAL_CONNECTIVITY_DOWN, 0),
+            ScmpMessage::EchoRequest { .. } => (TYPE_ECHO_REQUEST, 0),
+            ScmpMessage::EchoReply { .. } => (TYPE_ECHO_REPLY, 0),
+            ScmpMessage::TracerouteRequest { .. } => (TYPE_TRACEROUTE_REQUEST, 0),
+            ScmpMessage::TracerouteReply { .. } => (TYPE_TRACEROUTE_REPLY, 0),
+            ScmpMessage::Unknown { kind, code, .. } => (kind, code),
+        }
+    }
+
+    /// Returns `true` for error messages, `false` for informational ones.
+    #[must_use]
+    #[inline]
+    pub fn is_error(&self) -> bool {
+        self.type_code().0 < 128
+    }
+
+    /// Returns the quoted packet of an error message.
+    #[must_use]
+    pub fn quote(&self) -> Option<&[u8]> {
+        match self {
+            ScmpMessage::DestinationUnreachable { quote, .. }
+            | ScmpMessage::PacketTooBig { quote, .. }
+            | ScmpMessage::ParameterProblem { quote, .. }
+            | ScmpMessage::ExternalInterfaceDown { quote, .. }
+            | ScmpMessage::InternalConnectivityDown { quote, .. } => Some(quote),
+            _ => None,
+        }
+    }
+
+    /// Returns the source and destination address of the quoted packet,
+    /// i.e. to find the socket an error message is meant for.
+    #[must_use]
+    pub fn quoted_addrs(&self) -> Option<(ScionAddr, ScionAddr)> {
+        let quote = self.quote()?;
+        // common header (12 bytes), then the IAs and hosts of the address header
+        let tl = *quote.get(9)?;
+        let dst_ia = be_u64(quote.get(12..20)?);
+        let src_ia = be_u64(quote.get(20..28)?);
+        let (dst_host, dst_len) = decode_host(tl >> 4, quote.get(28..)?)?;
+        let (src_host, _) = decode_host(tl & 0x0f, quote.get(28 + dst_len..)?)?;
+        let src = ScionAddr::from_ia(IsdAsn::from(src_ia), src_host);
+        Some((src, ScionAddr::from_ia(IsdAsn::from(dst_ia), dst_host)))
+    }
+
+    fn info_and_data(&self) -> (Vec<u8>, &[u8]) {
+        match self {
+            ScmpMessage::DestinationUnreachable { quote, .. } => (vec![0; 4], quote),
+            ScmpMessage::PacketTooBig { mtu, quote } => {
+                let mut info = vec![0, 0];
+                info.extend_from_slice(&mtu.to_be_bytes());
+                (info, quote)
+            }
+            ScmpMessage::ParameterProblem { pointer, quote, .. } => {
+                let mut info = vec![0, 0];
+                info.extend_from_slice(&pointer.to_be_bytes());
+                (info, quote)
+            }
+            ScmpMessage::ExternalInterfaceDown { ia, interface, quote } => {
+                let mut info = ia.to_u64().to_be_bytes().to_vec();
+                info.extend_from_slice(&interface.to_be_bytes());
+                (info, quote)
+            }
+            ScmpMessage::InternalConnectivityDown { ia, ingress, egress, quote } => {
+                let mut info = ia.to_u64().to_be_bytes().to_vec();
+                info.extend_from_slice(&ingress.to_be_bytes());
+                info.extend_from_slice(&egress.to_be_bytes());
+                (info, quote)
+            }
+            ScmpMessage::EchoRequest { id, seq, data } | ScmpMessage::EchoReply { id, seq, data } => {
+                let mut info = id.to_be_bytes().to_vec();
+                info.extend_from_slice(&seq.to_be_bytes());
+                (info, data)
+            }
+            ScmpMessage::TracerouteRequest { id, seq } => {
+                (traceroute_info(*id, *seq, IsdAsn::from(0), 0), &[])
+            }
+            ScmpMessage::TracerouteReply { id, seq, ia, interface } => {
+                (traceroute_info(*id, *seq, *ia, *interface), &[])
+            }
+            ScmpMessage::Unknown { body, .. } => (Vec::new(), body),
+        }
+    }
+
+    /// Encodes the message with a zero checksum; see [`set_checksum`].
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let (kind, code) = self.type_code();
+        let (info, data) = self.info_and_data();
+        let mut buf = Vec::with_capacity(HDR_LEN + info.len() + data.len());
+        buf.extend_from_slice(&[kind, code, 0, 0]);
+        buf.extend_from_slice(&info);
+        buf.extend_from_slice(data);
+        buf
+    }
+
+    /// Encodes the message sent from `src` to `dst`, with its checksum.
+    #[must_use]
+    pub fn encode(&self, src: &ScionAddr, dst: &ScionAddr) -> Vec<u8> {
+        let mut buf = self.to_bytes();
+        set_checksum(&mut buf, src, dst);
+        buf
+    }
+
+    /// Decodes a message, without checking its checksum; see [`verify_checksum`].
+    pub fn decode_from(buf: &[u8]) -> io::Result<ScmpMessage> {
+        if buf.len() < HDR_LEN {
+            return Err(truncated());
+        }
+        let (kind, code) = (buf[0], buf[1]);
+        let body = &buf[HDR_LEN..];
+        let info = |len: usize| body.get(..len).ok_or_else(truncated);
+        Ok(match kind {
+            TYPE_DESTINATION_UNREACHABLE => {
+                info(4)?;
+                ScmpMessage::DestinationUnreachable { code, quote: body[4..].to_vec() }
+            }
+            TYPE_PACKET_TOO_BIG => {
+                let mtu = be_u16(&info(4)?[2..]);
+                ScmpMessage::PacketTooBig { mtu, quote: body[4..].to_vec() }
+            }
+            TYPE_PARAMETER_PROBLEM => {
+                let pointer = be_u16(&info(4)?[2..]);
+                ScmpMessage::ParameterProblem { code, pointer, quote: body[4..].to_vec() }
+            }
+            TYPE_EXTERNAL_INTERFACE_DOWN => {
+                let i = info(16)?;
+                ScmpMessage::ExternalInterfaceDown {
+                    ia: IsdAsn::from(be_u64(i)),
+                    interface: be_u64(&i[8..]),
+                    quote: body[16..].to_vec(),
+                }
+            }
+            TYPE_INTERNAL_CONNECTIVITY_DOWN => {
+                let i = info(24)?;
+                ScmpMessage::InternalConnectivityDown {
+                    ia: IsdAsn::from(be_u64(i)),
+                    ingress: be_u64(&i[8..]),
+                    egress: be_u64(&i[16..]),
+                    quote: body[24..].to_vec(),
+                }
+            }
+            TYPE_ECHO_REQUEST | TYPE_ECHO_REPLY => {
+                let i = info(4)?;
+                let (id, seq, data) = (be_u16(i), be_u16(&i[2..]), body[4..].to_vec());
+                if kind == TYPE_ECHO_REQUEST {
+                    ScmpMessage::EchoRequest { id, seq, data }
+                } else {
+                    ScmpMessage::EchoReply { id, seq, data }
+                }
+            }
+            TYPE_TRACEROUTE_REQUEST | TYPE_TRACEROUTE_REPLY => {
+                let i = info(20)?;
+                let (id, seq) = (be_u16(i), be_u16(&i[2..]));
+                if kind == TYPE_TRACEROUTE_REQUEST {
+                    ScmpMessage::TracerouteRequest { id, seq }
+                } else {
+                    ScmpMessage::TracerouteReply {
+                        id,
+                        seq,
+                        ia: IsdAsn::from(be_u64(&i[4..])),
+                        interface: be_u64(&i[12..]),
+                    }
+                }
+            }
+            _ => ScmpMessage::Unknown { kind, code, body: body.to_vec() },
+        })
+    }
+
+    /// Returns the reply to an echo request.
+    #[must_use]
+    pub fn echo_reply(&self) -> Option<ScmpMessage> {
+        match self {
+            ScmpMessage::EchoRequest { id, seq, data } => {
+                Some(ScmpMessage::EchoReply { id: *id, seq: *seq, data: data.clone() })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Fills in the checksum of the encoded message `buf` sent from `src` to `dst`.
+pub fn set_checksum(buf: &mut [u8], src: &ScionAddr, dst: &ScionAddr) {
+    if buf.len() < HDR_LEN {
+        return;
+    }
+    buf[2..4].copy_from_slice(&[0, 0]);
+    let checksum = scion_checksum(src, dst, PROTO_SCMP, buf);
+    buf[2..4].copy_from_slice(&checksum.to_be_bytes());
+}
+
+/// Returns `true` if the checksum of the encoded message `buf` sent from
+/// `src` to `dst` is correct.
+#[must_use]
+pub fn verify_checksum(buf: &[u8], src: &ScionAddr, dst: &ScionAddr) -> bool {
+    if buf.len() < HDR_LEN {
+        return false;
+    }
+    let mut copy = buf.to_vec();
+    copy[2..4].copy_from_slice(&[0, 0]);
+    scion_checksum(src, dst, PROTO_SCMP, &copy) == be_u16(&buf[2..4])
+}
+
+/// A border router that answered a [`traceroute`] probe.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TracerouteHop {
+    pub ia: IsdAsn,
+    /// The interface the probe was alerted at.
+    pub interface: u64,
+    pub rtt: Duration,
+}
+
+/// Returns the probes of a traceroute along `path`: for every interface
+/// crossed, in order, the hop field and whether its ingress (or else its
+/// egress) router alert flag makes the router there answer.
+fn traceroute_probes(path: &Path) -> Vec<(usize, bool)> {
+    let mut probes = Vec::new();
+    let mut hf = 0;
+    for (info, &len) in path.info_fields.iter().zip(&path.seg_len) {
+        for hop in &path.hop_fields[hf..hf + usize::from(len)] {
+            // against construction direction, packets enter through the egress
+            let (ingress, egress) = if info.cons_dir {
+                (hop.cons_ingress, hop.cons_egress)
+            } else {
+                (hop.cons_egress, hop.cons_ingress)
+            };
+            if ingress != 0 {
+                probes.push((hf, info.cons_dir));
+            }
+            if egress != 0 {
+                probes.push((hf, !info.cons_dir));
+            }
+            hf += 1;
+        }
+    }
+    probes
+}
+
+/// Sends a traceroute request to the border router of every interface that
+/// `path` to `dst` crosses, like `scion traceroute`, and collects the answers.
+///
+/// The probes are sent from `socket` one at a time, each waiting up to
+/// `timeout` for its answer; `None` stands for a router that did not answer
+/// in time. Datagrams other than the answers that arrive at `socket` in the
+/// meantime are dropped.
+pub fn traceroute(
+    socket: &ScionUdpSocket,
+    dst: &ScionAddr,
+    path: &ScionPath,
+    timeout: Duration,
+) -> io::Result<Vec<Option<TracerouteHop>>> {
+    if path.is_empty() {
+        return Ok(Vec::new());
+    }
+    let next_hop = path
+        .next_hop
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no next hop"))?;
+    let decoded = path.decode()?;
+    let local = socket.local_addr()?;
+    let src = ScionAddr::from(local);
+    let id = local.port();
+    let underlay = socket.underlay();
+    let previous_timeout = underlay.read_timeout()?;
+
+    let result = (|| {
+        let mut hops = Vec::new();
+        let mut packet = vec![0u8; MAX_DATAGRAM];
+        for (seq, (hf, ingress)) in traceroute_probes(&decoded).into_iter().enumerate() {
+            let seq = seq as u16;
+            let mut probe_path = decoded.clone();
+            let hop = &mut probe_path.hop_fields[hf];
+            if ingress {
+                hop.ingress_router_alert = true;
+            } else {
+                hop.egress_router_alert = true;
+            }
+            let raw = probe_path.to_bytes();
+            let scmp = ScmpMessage::TracerouteRequest { id, seq }.encode(&src, dst);
+            let mut probe = encode_scion_header(&src, dst, PROTO_SCMP, path.path_type, &raw, scmp.len())?;
+            probe.extend_from_slice(&scmp);
+
+            let sent = Instant::now();
+            underlay.send_to(&probe, next_hop)?;
+            hops.push(loop {
+                let left = timeout.saturating_sub(sent.elapsed());
+                if left.is_zero() {
+                    break None;
+                }
+                underlay.set_read_timeout(Some(left))?;
+                let len = match underlay.recv_from(&mut packet) {
+                    Ok((len, _)) => len,
+                    Err(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => {
+                        break None
+                    }
+                    Err(e) => return Err(e),
+                };
+                let reply = decode_scion_header(&packet[..len])
+                    .filter(|&(_, next_hdr, _, _)| next_hdr == PROTO_SCMP)
+                    .and_then(|(_, _, hdr_len, l4_len)| packet.get(hdr_len..hdr_len + l4_len))
+                    .and_then(|scmp| ScmpMessage::decode_from(scmp).ok());
+                if let Some(ScmpMessage::TracerouteReply { id: i, seq: s, ia, interface }) = reply {
+                    if (i, s) == (id, seq) {
+                        break Some(TracerouteHop { ia, interface, rtt: sent.elapsed() });
+                    }
+                }
+            });
+        }
+        Ok(hops)
+    })();
+    underlay.set_read_timeout(previous_timeout)?;
+    result
+}
+
+/// Answers SCMP echo requests and traceroute requests addressed to this
+/// host, so that it can be pinged by `scion ping`.
+///
+/// A responder either serves a socket of its own with [`serve`](Self::serve)
+/// or is attached to a [`ShimDispatcher`](crate::underlay::ShimDispatcher),
+/// which receives the SCMP requests to hosts of the AS.
+///
+/// ```no_run
+/// use scionnet::scmp::ScmpResponder;
+/// use scionnet::{RateLimitKey, RateLimiter};
+///
+/// let socket = std::net::UdpSocket::bind("10.0.0.1:30041").unwrap();
+/// let limiter = RateLimiter::new(RateLimitKey::Ia, 10, 20);
+/// let responder = ScmpResponder::new().with_rate_limit(limiter);
+/// responder.serve(&socket).unwrap();
+/// ```
+#[derive(Default)]
+pub struct ScmpResponder {
+    limiter: Option<RateLimiter>,
+    traceroute: bool,
+}
+
+impl ScmpResponder {
+    /// Creates a responder answering echo and traceroute requests without
+    /// limits.
+    #[must_use]
+    pub fn new() -> ScmpResponder {
+        ScmpResponder { limiter: None, traceroute: true }
+    }
+
+    /// Answers only the requests `limiter` allows, keyed by the source
+    /// address and the identifier of the request.
+    #[must_use]
+    pub fn with_rate_limit(mut self, limiter: RateLimiter) -> ScmpResponder {
+        self.limiter = Some(limiter);
+        self
+    }
+
+    /// Sets whether traceroute requests are answered, which they are by default.
+    #[must_use]
+    pub fn with_traceroute(mut self, answer: bool) -> ScmpResponder {
+        self.traceroute = answer;
+        self
+    }
+
+    /// Returns the reply to the SCION packet `packet`, to be sent back to the
+    /// underlay address it came from, or `None` if it is not a request to
+    /// answer, is malformed, or exceeds the rate limit.
+    #[must_use]
+    pub fn reply(&self, packet: &[u8]) -> Option<Vec<u8>> {
+        let common = CommonHeader::decode_from(packet).ok()?;
+        let (src, next_hdr, hdr_len, l4_len) = decode_scion_header(packet)?;
+        if next_hdr != PROTO_SCMP {
+            return None;
+        }
+        let dst_ia = be_u64(packet.get(COMMON_HEADER_LEN..COMMON_HEADER_LEN + 8)?);
+        let (dst_host, _) = decode_host(common.dst_addr_type, packet.get(COMMON_HEADER_LEN + 16..)?)?;
+        let dst = ScionAddr::from_ia(IsdAsn::from(dst_ia), dst_host);
+        let body = packet.get(hdr_len..hdr_len + l4_len)?;
+        if !verify_checksum(body, &src, &dst) {
+            return None;
+        }
+        let (id, answer) = match ScmpMessage::decode_from(body).ok()? {
+            ScmpMessage::EchoRequest { id, seq, data } => (id, ScmpMessage::EchoReply { id, seq, data }),
+            ScmpMessage::TracerouteRequest { id, seq } if self.traceroute => {
+                (id, ScmpMessage::TracerouteReply { id, seq, ia: IsdAsn::from(dst_ia), interface: 0 })
+            }
+            _ => return None,
+        };
+        if let Some(limiter) = &self.limiter {
+            if !limiter.check(&SocketAddrScion::from_scion_addr(src, id)).is_allowed() {
+                return None;
+            }
+        }
+
+        let raw_path = packet.get(hdr_len - common.path_len()..hdr_len)?;
+        let reversed = match common.path_type {
+            PATH_TYPE_EMPTY => Vec::new(),
+            PATH_TYPE_SCION => Path::decode_from(raw_path).ok()?.reversed().ok()?.to_bytes(),
+            _ => return None,
+        };
+        let scmp = answer.encode(&dst, &src);
+        let mut reply =
+            encode_scion_header(&dst, &src, PROTO_SCMP, common.path_type, &reversed, scmp.len()).ok()?;
+        reply.extend_from_slice(&scmp);
+        Some(reply)
+    }
+
+    /// Receives one packet from `socket` and answers it, returning `true` if
+    /// a reply was sent.
+    pub fn serve_one(&self, socket: &UdpSocket) -> io::Result<bool> {
+        let mut packet = vec![0u8; MAX_DATAGRAM];
+        self.serve_with(socket, &mut packet)
+    }
+
+    fn serve_with(&self, socket: &UdpSocket, packet: &mut [u8]) -> io::Result<bool> {
+        let (len, from) = socket.recv_from(packet)?;
+        match self.reply(&packet[..len]) {
+            Some(reply) => socket.send_to(&reply, from).map(|_| true),
+            None => Ok(false),
+        }
+    }
+
+    /// Answers the packets arriving at `socket` until receiving fails.
+    pub fn serve(&self, socket: &UdpSocket) -> io::Result<()> {
+        let mut packet = vec![0u8; MAX_DATAGRAM];
+        loop {
+            self.serve_with(socket, &mut packet)?;
+        }
+    }
+}
@@ -0,0 +1,296 @@
+//! `Serialize`/`Deserialize` implementations, enabled by the `serde` feature.
+//!
+//! Human-readable formats (JSON, TOML, ...) get the canonical textual form,
+//! i.e. `"19-ffaa:1:1067,[127.0.0.1]:53"`. Compact formats (bincode, CBOR, ...)
+//! get raw octets and IA integers instead, which avoids parsing on the way in:
+//!
+//! | type              | compact representation                            |
+//! |-------------------|---------------------------------------------------|
+//! | `Ipv4Addr`        | `[u8; 4]`                                         |
+//! | `Ipv6Addr`        | `[u8; 16]`                                        |
+//! | `IpAddr`          | enum `V4(Ipv4Addr)`, `V6(Ipv6Addr)`               |
+//! | `Svc`             | `u16`                                             |
+//! | `HostAddr`        | enum `V4(Ipv4Addr)`, `V6(Ipv6Addr)`, `SVC(Svc)`   |
+//! | `Isd`, `Asn`      | `u16`, `u64`                                      |
+//! | `IsdAsn`          | `u64`                                             |
+//! | `AsnPrefix`       | `(Asn, u8)`                                       |
+//! | `IsdRange`        | `(Isd, Isd)`                                      |
+//! | `IsdSet`          | `[IsdRange]`                                      |
+//! | `ScionAddr`       | `(u64, HostAddr)`                                 |
+//! | `SocketAddrV4`    | `(Ipv4Addr, u16)`                                 |
+//! | `SocketAddrV6`    | `(Ipv6Addr, u16, u32, u32)`                       |
+//! | `SocketAddrScion` | `(ScionAddr, u16)`                                |
+//! | `SocketAddr`      | enum `V4(..)`, `V6(..)`, `SCION(..)`              |
+
+use crate::{
+    Asn, AsnPrefix, HostAddr, IpAddr, Ipv4Addr, Ipv6Addr, Isd, IsdAsn, IsdRange, IsdSet,
+    ScionAddr, SocketAddr, SocketAddrScion, SocketAddrV4, SocketAddrV6, Svc,
+};
+use serde::de::{self, DeserializeSeed, Deserialize, Deserializer, EnumAccess, VariantAccess, Visitor};
+use serde::ser::{Serialize, Serializer};
+use std::convert::TryFrom;
+use std::fmt;
+use std::marker::PhantomData;
+use std::str::FromStr;
+
+struct FromStrVisitor<T> {
+    expecting: &'static str,
+    marker: PhantomData<T>,
+}
+
+impl<'de, T> Visitor<'de> for FromStrVisitor<T>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    type Value = T;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.expecting)
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<T, E> {
+        s.parse().map_err(E::custom)
+    }
+}
+
+fn deserialize_str<'de, D, T>(deserializer: D, expecting: &'static str) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    deserializer.deserialize_str(FromStrVisitor { expecting, marker: PhantomData })
+}
+
+/// Implements the textual form plus a compact form that converts
+/// to and from a plain serde type.
+macro_rules! serde_impls {
+    ($($ty:ty => $expecting:expr, $compact:ty, |$v:ident| $to:expr, |$c:ident| $from:expr;)*) => {$(
+        impl Serialize for $ty {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                if serializer.is_human_readable() {
+                    serializer.collect_str(self)
+                } else {
+                    let $v = self;
+                    let compact: $compact = $to;
+                    compact.serialize(serializer)
+                }
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $ty {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                if deserializer.is_human_readable() {
+                    deserialize_str(deserializer, $expecting)
+                } else {
+                    let $c = <$compact>::deserialize(deserializer)?;
+                    $from.map_err(de::Error::custom)
+                }
+            }
+        }
+    )*};
+}
+
+serde_impls! {
+    Ipv4Addr => "an IPv4 address", [u8; 4],
+        |v| v.octets(), |c| Ok::<_, &str>(Ipv4Addr::from(c));
+    Ipv6Addr => "an IPv6 address", [u8; 16],
+        |v| v.octets(), |c| Ok::<_, &str>(Ipv6Addr::from(c));
+    Isd => "an ISD number", u16,
+        |v| v.to_u16(), |c| Ok::<_, &str>(Isd::new(c));
+    Asn => "an AS number", u64,
+        |v| v.to_u64(), |c| Asn::try_from(c);
+    IsdAsn => "an ISD-AS", u64,
+        |v| v.to_u64(), |c| Ok::<_, &str>(IsdAsn::from(c));
+    AsnPrefix => "an AS prefix", (Asn, u8),
+        |v| (v.network(), v.prefix_len()), |c| AsnPrefix::new(c.0, c.1);
+    IsdRange => "an ISD range", (Isd, Isd),
+        |v| (v.start(), v.end()), |c| IsdRange::new(c.0, c.1);
+    IsdSet => "a set of ISD ranges", Vec<IsdRange>,
+        |v| v.ranges().to_vec(), |c| Ok::<_, &str>(c.into_iter().collect::<IsdSet>());
+    Svc => "a SCION service address", u16,
+        |v| v.to_u16(), |c| Ok::<_, &str>(Svc::new(c));
+    ScionAddr => "a SCION address", (u64, HostAddr),
+        |v| (v.get_ia(), *v.get_host()),
+        |c| Ok::<_, &str>(ScionAddr::from_ia(IsdAsn::from(c.0), c.1));
+    SocketAddrV4 => "an IPv4 socket address", (Ipv4Addr, u16),
+        |v| (*v.ip(), v.port()), |c| Ok::<_, &str>(SocketAddrV4::new(c.0, c.1));
+    SocketAddrV6 => "an IPv6 socket address", (Ipv6Addr, u16, u32, u32),
+        |v| (*v.ip(), v.port(), v.flowinfo(), v.scope_id()),
+        |c| Ok::<_, &str>(SocketAddrV6::new(c.0, c.1, c.2, c.3));
+    SocketAddrScion => "a SCION socket address", (ScionAddr, u16),
+        |v| (*v.addr(), v.port()), |c| Ok::<_, &str>(SocketAddrScion::from_scion_addr(c.0, c.1));
+}
+
+/// Deserializes an enum variant identifier, given either by name or by index.
+struct VariantSeed(&'static [&'static str]);
+
+impl<'de> DeserializeSeed<'de> for VariantSeed {
+    type Value = usize;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<usize, D::Error> {
+        deserializer.deserialize_identifier(self)
+    }
+}
+
+impl<'de> Visitor<'de> for VariantSeed {
+    type Value = usize;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "one of {:?}", self.0)
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<usize, E> {
+        match usize::try_from(v) {
+            Ok(i) if i < self.0.len() => Ok(i),
+            _ => Err(E::invalid_value(de::Unexpected::Unsigned(v), &self)),
+        }
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<usize, E> {
+        self.0
+            .iter()
+            .position(|name| *name == v)
+            .ok_or_else(|| E::unknown_variant(v, self.0))
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<usize, E> {
+        match std::str::from_utf8(v) {
+            Ok(s) => self.visit_str(s),
+            Err(_) => Err(E::invalid_value(de::Unexpected::Bytes(v), &self)),
+        }
+    }
+}
+
+const IP_ADDR_VARIANTS: &[&str] = &["V4", "V6"];
+// an IP host has the same compact form as the `IpAddr`
+const HOST_ADDR_VARIANTS: &[&str] = &["V4", "V6", "SVC"];
+const SOCKET_ADDR_VARIANTS: &[&str] = &["V4", "V6", "SCION"];
+
+impl Serialize for IpAddr {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            return serializer.collect_str(self);
+        }
+        match *self {
+            IpAddr::V4(ref a) => serializer.serialize_newtype_variant("IpAddr", 0, "V4", a),
+            IpAddr::V6(ref a) => serializer.serialize_newtype_variant("IpAddr", 1, "V6", a),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for IpAddr {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct EnumVisitor;
+
+        impl<'de> Visitor<'de> for EnumVisitor {
+            type Value = IpAddr;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("an IP address")
+            }
+
+            fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<IpAddr, A::Error> {
+                match data.variant_seed(VariantSeed(IP_ADDR_VARIANTS))? {
+                    (0, v) => v.newtype_variant().map(IpAddr::V4),
+                    (_, v) => v.newtype_variant().map(IpAddr::V6),
+                }
+            }
+        }
+
+        if deserializer.is_human_readable() {
+            deserialize_str(deserializer, "an IP address")
+        } else {
+            deserializer.deserialize_enum("IpAddr", IP_ADDR_VARIANTS, EnumVisitor)
+        }
+    }
+}
+
+impl Serialize for HostAddr {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            return serializer.collect_str(self);
+        }
+        match *self {
+            HostAddr::Ip(IpAddr::V4(ref a)) => {
+                serializer.serialize_newtype_variant("HostAddr", 0, "V4", a)
+            }
+            HostAddr::Ip(IpAddr::V6(ref a)) => {
+                serializer.serialize_newtype_variant("HostAddr", 1, "V6", a)
+            }
+            HostAddr::Svc(ref a) => serializer.serialize_newtype_variant("HostAddr", 2, "SVC", a),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for HostAddr {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct EnumVisitor;
+
+        impl<'de> Visitor<'de> for EnumVisitor {
+            type Value = HostAddr;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a SCION host address")
+            }
+
+            fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<HostAddr, A::Error> {
+                match data.variant_seed(VariantSeed(HOST_ADDR_VARIANTS))? {
+                    (0, v) => v.newtype_variant::<Ipv4Addr>().map(HostAddr::from),
+                    (1, v) => v.newtype_variant::<Ipv6Addr>().map(HostAddr::from),
+                    (_, v) => v.newtype_variant().map(HostAddr::Svc),
+                }
+            }
+        }
+
+        if deserializer.is_human_readable() {
+            deserialize_str(deserializer, "a SCION host address")
+        } else {
+            deserializer.deserialize_enum("HostAddr", HOST_ADDR_VARIANTS, EnumVisitor)
+        }
+    }
+}
+
+impl Serialize for SocketAddr {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            return serializer.collect_str(self);
+        }
+        match *self {
+            SocketAddr::V4(ref a) => serializer.serialize_newtype_variant("SocketAddr", 0, "V4", a),
+            SocketAddr::V6(ref a) => serializer.serialize_newtype_variant("SocketAddr", 1, "V6", a),
+            SocketAddr::SCION(ref a) => {
+                serializer.serialize_newtype_variant("SocketAddr", 2, "SCION", a)
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for SocketAddr {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct EnumVisitor;
+
+        impl<'de> Visitor<'de> for EnumVisitor {
+            type Value = SocketAddr;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a socket address")
+            }
+
+            fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<SocketAddr, A::Error> {
+                match data.variant_seed(VariantSeed(SOCKET_ADDR_VARIANTS))? {
+                    (0, v) => v.newtype_variant().map(SocketAddr::V4),
+                    (1, v) => v.newtype_variant().map(SocketAddr::V6),
+                    (_, v) => v.newtype_variant().map(SocketAddr::SCION),
+                }
+            }
+        }
+
+        if deserializer.is_human_readable() {
+            deserialize_str(deserializer, "a socket address")
+        } else {
+            deserializer.deserialize_enum("SocketAddr", SOCKET_ADDR_VARIANTS, EnumVisitor)
+        }
+    }
+}
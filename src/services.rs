@@ -0,0 +1,69 @@
+use std::io;
+use std::sync::RwLock;
+
+/// The service names known without registration, after the IANA registry
+/// and the well-known ports of SCION's own services.
+const BUILTIN: &[(&str, u16)] = &[
+    ("ftp", 21),
+    ("ssh", 22),
+    ("telnet", 23),
+    ("smtp", 25),
+    ("domain", 53),
+    ("dns", 53),
+    ("http", 80),
+    ("ntp", 123),
+    ("imap", 143),
+    ("snmp", 161),
+    ("ldap", 389),
+    ("https", 443),
+    ("submission", 587),
+    ("ldaps", 636),
+    ("dns-over-tls", 853),
+    ("imaps", 993),
+    ("pop3s", 995),
+    ("mqtt", 1883),
+    ("http-alt", 8080),
+    ("scion-dispatcher", 30041),
+    ("scion-daemon", 30255),
+];
+
+static REGISTERED: RwLock<Vec<(String, u16)>> = RwLock::new(Vec::new());
+
+/// Makes `name` usable in place of a port number when parsing SCION socket
+/// addresses or ones with a default port, i.e. `1-ff00:0:110,10.0.0.1:my-service`,
+/// returning the port it stood for before. Registered names take precedence
+/// over the built-in ones.
+///
+/// Names are matched case-insensitively; they must start with a letter and
+/// contain only letters, digits and `-`, so they are never taken for a
+/// port number or the end of an address. Fails for other names.
+pub fn register_service(name: &str, port: u16) -> io::Result<Option<u16>> {
+    let mut chars = name.chars();
+    let valid = chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '-');
+    if !valid {
+        let msg = format!("invalid service name '{}'", name);
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, msg));
+    }
+    let mut registered = REGISTERED.write().unwrap();
+    match registered.iter_mut().find(|(n, _)| n.eq_ignore_ascii_case(name)) {
+        Some(entry) => Ok(Some(std::mem::replace(&mut entry.1, port))),
+        None => {
+            registered.push((name.to_ascii_lowercase(), port));
+            Ok(builtin_port(name))
+        }
+    }
+}
+
+fn builtin_port(name: &str) -> Option<u16> {
+    BUILTIN.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|&(_, p)| p)
+}
+
+/// Returns the port of the service `name`, registered or built-in.
+pub fn service_port(name: &str) -> Option<u16> {
+    let registered = REGISTERED.read().unwrap();
+    match registered.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)) {
+        Some(&(_, port)) => Some(port),
+        None => builtin_port(name),
+    }
+}
@@ -0,0 +1,251 @@
+//! SIMD fast paths of the IP address parsers, enabled by the `simd` feature.
+//!
+//! The input is classified 16 bytes at a time into bit masks of digits, hex
+//! digits, dots and colons, with SSE2 on x86_64, NEON on aarch64 and plain
+//! code elsewhere. The common forms of addresses are decoded from the masks
+//! without backtracking. Everything else, every invalid input included, is
+//! left to the byte-at-a-time [`Parser`](crate::Parser), so the results and
+//! errors are the same with or without the feature.
+
+use crate::{Ipv4Addr, Ipv6Addr};
+
+const CHUNK: usize = 16;
+
+/// Bit `i` of a mask is set if byte `i` is of the class.
+#[derive(Copy, Clone, Default)]
+struct Masks {
+    digit: u16,
+    hex: u16,
+    dot: u16,
+    colon: u16,
+}
+
+#[cfg(target_arch = "x86_64")]
+fn classify(chunk: &[u8; CHUNK]) -> Masks {
+    use std::arch::x86_64::*;
+    // SAFETY: SSE2 is part of the x86_64 baseline, and the load is unaligned.
+    // Bytes above 0x7f compare as negative, so they fall in no class.
+    unsafe {
+        let v = _mm_loadu_si128(chunk.as_ptr() as *const __m128i);
+        let between = |v: __m128i, lo: u8, hi: u8| {
+            let above = _mm_cmpgt_epi8(v, _mm_set1_epi8(lo as i8 - 1));
+            _mm_and_si128(above, _mm_cmpgt_epi8(_mm_set1_epi8(hi as i8 + 1), v))
+        };
+        let mask = |m: __m128i| _mm_movemask_epi8(m) as u16;
+        let digit = between(v, b'0', b'9');
+        let letter = between(_mm_or_si128(v, _mm_set1_epi8(0x20)), b'a', b'f');
+        Masks {
+            digit: mask(digit),
+            hex: mask(_mm_or_si128(digit, letter)),
+            dot: mask(_mm_cmpeq_epi8(v, _mm_set1_epi8(b'.' as i8))),
+            colon: mask(_mm_cmpeq_epi8(v, _mm_set1_epi8(b':' as i8))),
+        }
+    }
+}
+
+#[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
+fn classify(chunk: &[u8; CHUNK]) -> Masks {
+    use std::arch::aarch64::*;
+    const WEIGHTS: [u8; CHUNK] = [1, 2, 4, 8, 16, 32, 64, 128, 1, 2, 4, 8, 16, 32, 64, 128];
+    // SAFETY: the target has NEON, and the loads read 16 bytes of arrays
+    unsafe {
+        let v = vld1q_u8(chunk.as_ptr());
+        let between = |v: uint8x16_t, lo: u8, hi: u8| {
+            vandq_u8(vcgeq_u8(v, vdupq_n_u8(lo)), vcleq_u8(v, vdupq_n_u8(hi)))
+        };
+        // NEON has no movemask: weigh the lanes by their bit and add them up
+        let weights = vld1q_u8(WEIGHTS.as_ptr());
+        let mask = |m: uint8x16_t| {
+            let bits = vandq_u8(m, weights);
+            u16::from(vaddv_u8(vget_low_u8(bits))) | u16::from(vaddv_u8(vget_high_u8(bits))) << 8
+        };
+        let digit = between(v, b'0', b'9');
+        let letter = between(vorrq_u8(v, vdupq_n_u8(0x20)), b'a', b'f');
+        Masks {
+            digit: mask(digit),
+            hex: mask(vorrq_u8(digit, letter)),
+            dot: mask(vceqq_u8(v, vdupq_n_u8(b'.'))),
+            colon: mask(vceqq_u8(v, vdupq_n_u8(b':'))),
+        }
+    }
+}
+
+#[cfg(not(any(target_arch = "x86_64", all(target_arch = "aarch64", target_feature = "neon"))))]
+fn classify(chunk: &[u8; CHUNK]) -> Masks {
+    let mut masks = Masks::default();
+    for (i, &b) in chunk.iter().enumerate() {
+        let bit = 1 << i;
+        if b.is_ascii_digit() {
+            masks.digit |= bit;
+        }
+        if b.is_ascii_hexdigit() {
+            masks.hex |= bit;
+        }
+        if b == b'.' {
+            masks.dot |= bit;
+        }
+        if b == b':' {
+            masks.colon |= bit;
+        }
+    }
+    masks
+}
+
+/// Classifies the 16 bytes of `input` from `at` on, as if it was padded
+/// with zeros, which fall in no class.
+fn classify_at(input: &[u8], at: usize) -> Masks {
+    let tail = input.get(at..).unwrap_or_default();
+    match tail.get(..CHUNK) {
+        Some(chunk) => classify(chunk.try_into().unwrap()),
+        None => {
+            let mut chunk = [0; CHUNK];
+            chunk[..tail.len()].copy_from_slice(tail);
+            classify(&chunk)
+        }
+    }
+}
+
+/// Returns the octet written as `digits`, without leading zeros.
+fn decimal_octet(digits: &[u8]) -> Option<u8> {
+    let value = match *digits {
+        [d] => u16::from(d - b'0'),
+        [b'0', ..] => return None,
+        [a, b] => u16::from(a - b'0') * 10 + u16::from(b - b'0'),
+        [a, b, c] => u16::from(a - b'0') * 100 + u16::from(b - b'0') * 10 + u16::from(c - b'0'),
+        _ => return None,
+    };
+    u8::try_from(value).ok()
+}
+
+/// Returns the group written as 1 to 4 hex digits.
+fn hex_group(digits: &[u8]) -> Option<u16> {
+    if digits.is_empty() || digits.len() > 4 {
+        return None;
+    }
+    let digit = |b: u8| char::from(b).to_digit(16).unwrap() as u16;
+    Some(digits.iter().fold(0, |group, &b| group << 4 | digit(b)))
+}
+
+/// Reads the IPv4 address at the start of `input`, returning it with its
+/// length, or `None` if the scalar parser has to decide.
+pub(crate) fn read_ipv4_addr(input: &[u8]) -> Option<(Ipv4Addr, usize)> {
+    #[cfg(test)]
+    if tests::scalar_only() {
+        return None;
+    }
+    let masks = classify_at(input, 0);
+    // the address runs up to the first byte that is neither digit nor dot,
+    // which must come within the chunk
+    let len = (!(masks.digit | masks.dot)).trailing_zeros() as usize;
+    if len == CHUNK {
+        return None;
+    }
+    let mut dots = masks.dot & ((1 << len) - 1);
+    if dots.count_ones() != 3 {
+        return None;
+    }
+    let mut octets = [0; 4];
+    let mut start = 0;
+    for octet in &mut octets {
+        let end = if dots == 0 { len } else { dots.trailing_zeros() as usize };
+        // clears the lowest dot
+        dots &= dots.wrapping_sub(1);
+        *octet = decimal_octet(&input[start..end])?;
+        start = end + 1;
+    }
+    Some((Ipv4Addr::from(octets), len))
+}
+
+/// The longest IPv6 address without an embedded IPv4 address.
+const MAX_IPV6_LEN: usize = "ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff".len();
+
+/// An IPv6 address read by [`read_ipv6_addr`].
+pub(crate) struct Ipv6Read {
+    pub(crate) addr: Ipv6Addr,
+    pub(crate) len: usize,
+    /// Whether the scalar parser notes that it expected another `:` after
+    /// the address, as it does when a compressed address has room for more
+    /// groups. The caller notes it as well, so errors come out the same.
+    pub(crate) expects_colon: bool,
+}
+
+/// Reads the IPv6 address at the start of `input` if it has no embedded
+/// IPv4 address, or returns `None` if the scalar parser has to decide.
+pub(crate) fn read_ipv6_addr(input: &[u8]) -> Option<Ipv6Read> {
+    #[cfg(test)]
+    if tests::scalar_only() {
+        return None;
+    }
+    let (mut hex, mut colon, mut dot) = (0u64, 0u64, 0u64);
+    for at in (0..=MAX_IPV6_LEN).step_by(CHUNK) {
+        let masks = classify_at(input, at);
+        hex |= u64::from(masks.hex) << at;
+        colon |= u64::from(masks.colon) << at;
+        dot |= u64::from(masks.dot) << at;
+    }
+    // the address runs up to the first byte that is neither hex digit nor
+    // colon, which must not be the dot of an embedded IPv4 address
+    let len = (!(hex | colon)).trailing_zeros() as usize;
+    if len > MAX_IPV6_LEN || dot & (1 << len) != 0 {
+        return None;
+    }
+    let colon = colon & ((1 << len) - 1);
+    let double = colon & (colon >> 1);
+    let (head, tail) = match double.count_ones() {
+        0 => (&input[..len], None),
+        1 => {
+            let at = double.trailing_zeros() as usize;
+            (&input[..at], Some(&input[at + 2..len]))
+        }
+        _ => return None,
+    };
+
+    let mut groups = [0; 8];
+    let read = |part: &[u8], groups: &mut [u16]| -> Option<usize> {
+        if part.is_empty() {
+            return Some(0);
+        }
+        let mut count = 0;
+        for digits in part.split(|&b| b == b':') {
+            *groups.get_mut(count)? = hex_group(digits)?;
+            count += 1;
+        }
+        Some(count)
+    };
+    let head_size = read(head, &mut groups)?;
+    let Some(tail) = tail else {
+        return if head_size == 8 {
+            Some(Ipv6Read { addr: groups.into(), len, expects_colon: false })
+        } else {
+            None
+        };
+    };
+    // `::` stands for at least one group
+    let limit = 7usize.checked_sub(head_size)?;
+    let mut tail_groups = [0; 7];
+    let tail_size = read(tail, &mut tail_groups[..limit])?;
+    groups[8 - tail_size..].copy_from_slice(&tail_groups[..tail_size]);
+    let expects_colon = tail_size > 0 && tail_size < limit;
+    Some(Ipv6Read { addr: groups.into(), len, expects_colon })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::cell::Cell;
+
+    thread_local! {
+        static SCALAR_ONLY: Cell<bool> = const { Cell::new(false) };
+    }
+
+    pub(crate) fn scalar_only() -> bool {
+        SCALAR_ONLY.with(Cell::get)
+    }
+
+    /// Runs `f` with the fast paths disabled, to compare the parsers.
+    pub(crate) fn without_simd<T>(f: impl FnOnce() -> T) -> T {
+        SCALAR_ONLY.with(|s| s.set(true));
+        let result = f();
+        SCALAR_ONLY.with(|s| s.set(false));
+        result
+    }
+}
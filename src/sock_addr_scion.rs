@@ -1,17 +1,66 @@
-use crate::{IpAddr, ScionAddr};
-use std::fmt::*;
+use crate::wire::{ADDR_TYPE_IPV4, ADDR_TYPE_IPV6, ADDR_TYPE_SVC};
+use std::convert::TryFrom;
+use std::str::FromStr;
+use crate::{
+    AddrKind, AddrParseError, Asn, DisplayBuffer, HostAddr, IpAddr, Ipv4Addr, Ipv6Addr, Isd, IsdAsn,
+    ScionAddr, Svc,
+};
+
+impl SocketAddrScion {
+    /// The length of the longest textual form of an address, see
+    /// [`ScionAddr::MAX_DISPLAY_LEN`].
+    pub const MAX_DISPLAY_LEN: usize = ScionAddr::MAX_DISPLAY_LEN + ":65535".len();
+
+    /// Writes the textual form of the address to `w`, without allocating.
+    pub fn write_to<W: std::fmt::Write>(&self, w: &mut W) -> std::fmt::Result {
+        self.addr.write_to(w)?;
+        write!(w, ":{}", self.port)
+    }
+
+    /// Returns the textual form of the address in a stack buffer.
+    #[must_use]
+    pub fn to_display_buffer(&self) -> DisplayBuffer<{ SocketAddrScion::MAX_DISPLAY_LEN }> {
+        let mut buf = DisplayBuffer::new();
+        // the buffer is long enough for the longest address
+        self.write_to(&mut buf).unwrap();
+        buf
+    }
+}
 
 impl std::fmt::Display for SocketAddrScion {
+    /// The alternate form `{:#}` encloses the SCION address in brackets as a
+    /// whole, as URIs and the `scion` tool do: `[19-ffaa:1:1067,::1]:443`
+    /// instead of `19-ffaa:1:1067,[::1]:443`. Both forms can be parsed.
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.pad(&format!("{}:{}", self.addr, self.port))
+        if f.alternate() {
+            let (ia, host, port) = (self.addr.isd_asn(), *self.addr.get_host(), self.port);
+            return if f.precision().is_none() && f.width().is_none() {
+                write!(f, "[{},{}]:{}", ia, host, port)
+            } else {
+                f.pad(&format!("[{},{}]:{}", ia, host, port))
+            };
+        }
+        // only padding needs the whole address up front
+        if f.precision().is_none() && f.width().is_none() {
+            return self.write_to(f);
+        }
+        let mut s = String::new();
+        self.write_to(&mut s)?;
+        f.pad(&s)
+    }
+}
+
+impl std::fmt::Debug for SocketAddrScion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(self, f)
     }
 }
 
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 
 pub struct SocketAddrScion {
-    pub addr: ScionAddr,
-    pub port: u16,
+    addr: ScionAddr,
+    port: u16,
 }
 
 impl Default for SocketAddrScion {
@@ -24,20 +73,79 @@ impl Default for SocketAddrScion {
 }
 
 impl SocketAddrScion {
+    /// Creates a new SCION socket address of `host` in the AS `ia`.
     #[must_use]
     #[inline]
-    pub fn new(ia: u64, ip: IpAddr, port: u16) -> SocketAddrScion {
-        SocketAddrScion {
-            addr: ScionAddr::new(ia, ip),
-            port,
-        }
+    pub fn from_ia<H: Into<HostAddr>>(ia: IsdAsn, host: H, port: u16) -> SocketAddrScion {
+        SocketAddrScion::from_scion_addr(ScionAddr::from_ia(ia, host), port)
+    }
+
+    /// Creates a new SCION socket address from a range-checked ISD and AS
+    /// number.
+    #[must_use]
+    #[inline]
+    pub fn from_parts<H: Into<HostAddr>>(
+        isd: Isd,
+        asn: Asn,
+        host: H,
+        port: u16,
+    ) -> SocketAddrScion {
+        SocketAddrScion::from_scion_addr(ScionAddr::from_parts(isd, asn, host), port)
     }
 
-    pub fn new1(add: ScionAddr, p: u16) -> SocketAddrScion {
-        SocketAddrScion { addr: add, port: p }
+    /// Creates a new SCION socket address, also in constants.
+    #[must_use]
+    #[inline]
+    pub const fn from_scion_addr(addr: ScionAddr, port: u16) -> SocketAddrScion {
+        SocketAddrScion { addr, port }
     }
 
-    pub fn ia(&self) -> u64 {
+    #[deprecated(note = "use `SocketAddrScion::from_ia` instead")]
+    #[must_use]
+    #[inline]
+    pub fn new<H: Into<HostAddr>>(ia: u64, ip: H, port: u16) -> SocketAddrScion {
+        SocketAddrScion::from_ia(IsdAsn::from(ia), ip, port)
+    }
+
+    #[deprecated(note = "use `SocketAddrScion::from_scion_addr` instead")]
+    pub const fn new1(add: ScionAddr, p: u16) -> SocketAddrScion {
+        SocketAddrScion::from_scion_addr(add, p)
+    }
+
+    /// Returns a builder that checks each part of the address, for
+    /// addresses put together from configuration:
+    ///
+    /// ```
+    /// use scionnet::SocketAddrScion;
+    ///
+    /// let addr = SocketAddrScion::builder()
+    ///     .isd(19)
+    ///     .asn("ffaa:1:1067")
+    ///     .host("127.0.0.1")
+    ///     .port(53)
+    ///     .build()?;
+    /// assert_eq!(addr.to_string(), "19-ffaa:1:1067,127.0.0.1:53");
+    /// # Ok::<(), scionnet::AddrParseError>(())
+    /// ```
+    #[must_use]
+    pub fn builder() -> SocketAddrScionBuilder {
+        SocketAddrScionBuilder::default()
+    }
+
+    /// Returns the SCION address, without the port.
+    #[must_use]
+    #[inline]
+    pub const fn addr(&self) -> &ScionAddr {
+        &self.addr
+    }
+
+    /// Returns the SCION address for changing it in place.
+    #[inline]
+    pub fn addr_mut(&mut self) -> &mut ScionAddr {
+        &mut self.addr
+    }
+
+    pub const fn ia(&self) -> u64 {
         self.addr.get_ia()
     }
 
@@ -47,13 +155,20 @@ impl SocketAddrScion {
 
     #[must_use]
     #[inline]
-    pub fn host(&self) -> &IpAddr {
-        &self.addr.get_host()
+    pub const fn host(&self) -> &HostAddr {
+        self.addr.get_host()
+    }
+
+    /// Returns the IP address of the host, or `None` for a service address.
+    #[must_use]
+    #[inline]
+    pub const fn ip(&self) -> Option<IpAddr> {
+        self.addr.ip()
     }
 
     #[inline]
-    pub fn set_host(&mut self, new_ip: IpAddr) {
-        self.addr.set_host(new_ip);
+    pub fn set_host<H: Into<HostAddr>>(&mut self, new_host: H) {
+        self.addr.set_host(new_host);
     }
 
     #[must_use]
@@ -62,14 +177,156 @@ impl SocketAddrScion {
         self.port
     }
 
+    /// Returns `true` if the port is 0, which binds an ephemeral port and
+    /// cannot be sent to.
+    #[must_use]
+    #[inline]
+    pub const fn is_unspecified_port(&self) -> bool {
+        self.port == 0
+    }
+
     #[inline]
     pub fn set_port(&mut self, new_port: u16) {
         self.port = new_port;
     }
+
+    /// Returns `true` if `other` matches this address used as a pattern,
+    /// see [`ScionAddr::matches`]; port 0 matches any port.
+    #[must_use]
+    pub fn matches(&self, other: &SocketAddrScion) -> bool {
+        self.addr.matches(&other.addr) && (self.port == 0 || self.port == other.port)
+    }
+
+    /// Returns the address as a fixed-size key, for maps and caches keyed
+    /// by raw bytes: the IA (8 bytes), the address type of the host as in
+    /// the SCION header (1), the host padded with zeros (16) and the port
+    /// (2), all big-endian.
+    ///
+    /// [`from_key_bytes`](Self::from_key_bytes) reverses the conversion.
+    #[must_use]
+    pub fn to_key_bytes(&self) -> [u8; 27] {
+        let mut key = [0u8; 27];
+        key[..8].copy_from_slice(&self.ia().to_be_bytes());
+        key[8] = self.addr.wire_type();
+        match *self.host() {
+            HostAddr::Ip(IpAddr::V4(ip)) => key[9..13].copy_from_slice(&ip.octets()),
+            HostAddr::Ip(IpAddr::V6(ip)) => key[9..25].copy_from_slice(&ip.octets()),
+            HostAddr::Svc(svc) => key[9..11].copy_from_slice(&svc.to_u16().to_be_bytes()),
+        }
+        key[25..].copy_from_slice(&self.port.to_be_bytes());
+        key
+    }
+
+    /// Reads a key written by [`to_key_bytes`](Self::to_key_bytes).
+    ///
+    /// Fails if the host type is unknown or the padding is not zero.
+    pub fn from_key_bytes(key: &[u8; 27]) -> Result<SocketAddrScion, AddrParseError> {
+        let err = || AddrParseError::new(AddrKind::SocketScion);
+        let mut ia = [0u8; 8];
+        ia.copy_from_slice(&key[..8]);
+        let (host, len) = match key[8] {
+            ADDR_TYPE_IPV4 => (HostAddr::from(Ipv4Addr::new(key[9], key[10], key[11], key[12])), 4),
+            ADDR_TYPE_IPV6 => {
+                let mut octets = [0u8; 16];
+                octets.copy_from_slice(&key[9..25]);
+                (HostAddr::from(Ipv6Addr::from(octets)), 16)
+            }
+            ADDR_TYPE_SVC => (HostAddr::from(Svc::new(u16::from_be_bytes([key[9], key[10]]))), 2),
+            _ => return Err(err()),
+        };
+        if key[9 + len..25].iter().any(|&b| b != 0) {
+            return Err(err());
+        }
+        let port = u16::from_be_bytes([key[25], key[26]]);
+        Ok(SocketAddrScion::from_ia(IsdAsn::from(u64::from_be_bytes(ia)), host, port))
+    }
+}
+
+impl From<SocketAddrScion> for ScionAddr {
+    fn from(addr: SocketAddrScion) -> ScionAddr {
+        addr.addr
+    }
+}
+
+/// A builder of [`SocketAddrScion`]s, see [`SocketAddrScion::builder`].
+///
+/// Each part is checked when it is set, and [`build`](Self::build) returns
+/// the error of the first invalid part, or of the first missing one.
+#[derive(Clone, Debug, Default)]
+pub struct SocketAddrScionBuilder {
+    isd: Option<Isd>,
+    asn: Option<Asn>,
+    host: Option<HostAddr>,
+    port: Option<u16>,
+    error: Option<AddrParseError>,
 }
 
-impl Into<ScionAddr> for SocketAddrScion {
-    fn into(self) -> ScionAddr {
-        self.addr.clone()
+impl SocketAddrScionBuilder {
+    fn check<T>(&mut self, value: Result<T, AddrParseError>) -> Option<T> {
+        match value {
+            Ok(value) => Some(value),
+            Err(err) => {
+                self.error.get_or_insert(err);
+                None
+            }
+        }
+    }
+
+    /// Sets the ISD, which must fit into 16 bits.
+    #[must_use]
+    pub fn isd(mut self, isd: u32) -> Self {
+        let isd = Isd::try_from(isd)
+            .map_err(|_| AddrParseError::new(AddrKind::Isd).with_reason(AddrKind::IsdOutOfRange));
+        self.isd = self.check(isd);
+        self
+    }
+
+    /// Sets the AS from its textual form, i.e. `ffaa:1:1067` or `64496`.
+    #[must_use]
+    pub fn asn(mut self, asn: &str) -> Self {
+        self.asn = self.check(Asn::from_str(asn));
+        self
+    }
+
+    /// Sets both the ISD and the AS.
+    #[must_use]
+    pub fn ia(mut self, ia: IsdAsn) -> Self {
+        self.isd = Some(ia.isd());
+        self.asn = Some(ia.asn());
+        self
+    }
+
+    /// Sets the host from its textual form, i.e. `127.0.0.1`, `::1` or `CS`.
+    #[must_use]
+    pub fn host(mut self, host: &str) -> Self {
+        self.host = self.check(HostAddr::from_str(host));
+        self
+    }
+
+    /// Sets the host from an IP or a service address.
+    #[must_use]
+    pub fn host_addr<H: Into<HostAddr>>(mut self, host: H) -> Self {
+        self.host = Some(host.into());
+        self
+    }
+
+    #[must_use]
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = Some(port);
+        self
+    }
+
+    /// Returns the address, or the error of the first part that is invalid
+    /// or missing.
+    pub fn build(self) -> Result<SocketAddrScion, AddrParseError> {
+        if let Some(err) = self.error {
+            return Err(err);
+        }
+        let isd = self.isd.ok_or(AddrParseError::new(AddrKind::Isd))?;
+        let asn = self.asn.ok_or(AddrParseError::new(AddrKind::Asn))?;
+        let host = self.host.ok_or(AddrParseError::new(AddrKind::Host))?;
+        let missing_port = AddrParseError::new(AddrKind::SocketScion);
+        let port = self.port.ok_or(missing_port.with_reason(AddrKind::MissingPort))?;
+        Ok(SocketAddrScion::from_parts(isd, asn, host, port))
     }
 }
@@ -4,16 +4,16 @@
 
 use std::io;
 use std::iter;
-use std::mem;
 // use std::net::LookupHost;
 use std::net::ToSocketAddrs as netToSocketAddrs;
+use crate::parse_observer::quietly;
 use crate::ScionAddr;
 use crate::SocketAddrScion;
+use crate::IsdAsn;
 use crate::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::option;
 use std::slice;
 
-use libc as c;
 //use std::sys_common::net::LookupHost;
 //use std::sys_common::{FromInner, IntoInner};
 use std::vec;
@@ -171,11 +171,9 @@ impl IntoInner<c::sockaddr_in6> for SocketAddrV6 {
 /// ```
 ///
 /// [`TcpStream::connect`]: crate::net::TcpStream::connect
-
 pub trait ToSocketAddrs {
     /// Returned iterator over socket addresses which this type may correspond
     /// to.
-
     type Iter: Iterator<Item = SocketAddr>;
 
     /// Converts this object to an iterator of resolved [`SocketAddr`]s.
@@ -185,7 +183,6 @@ pub trait ToSocketAddrs {
     ///
     /// Note that this function may block the current thread while resolution is
     /// performed.
-    
     fn to_socket_addrs(&self) -> io::Result<Self::Iter>;
 }
 
@@ -261,17 +258,18 @@ impl ToSocketAddrs for (&str, u16) {
     fn to_socket_addrs(&self) -> io::Result<vec::IntoIter<SocketAddr>> {
         let (host, port) = *self;
 
-        // try to parse the host as a regular IP address first
-        if let Ok(addr) = host.parse::<Ipv4Addr>() {
+        // try to parse the host as a regular IP address first, host names
+        // failing these is no rejected address
+        if let Ok(addr) = quietly(|| host.parse::<Ipv4Addr>()) {
             let addr = SocketAddrV4::new(addr, port);
             return Ok(vec![SocketAddr::V4(addr)].into_iter());
         }
-        if let Ok(addr) = host.parse::<Ipv6Addr>() {
+        if let Ok(addr) = quietly(|| host.parse::<Ipv6Addr>()) {
             let addr = SocketAddrV6::new(addr, port, 0, 0);
             return Ok(vec![SocketAddr::V6(addr)].into_iter());
         }
-        if let Ok(addr) = host.parse::<ScionAddr>() {
-            let addr = SocketAddrScion::new1( addr,port);
+        if let Ok(addr) = quietly(|| host.parse::<ScionAddr>()) {
+            let addr = SocketAddrScion::from_scion_addr( addr,port);
             return Ok(vec![SocketAddr::SCION(addr)].into_iter())
         }
 
@@ -332,4 +330,164 @@ impl ToSocketAddrs for String {
        // (&**self).to_socket_addrs()
        self::ToSocketAddrs::to_socket_addrs(&(&**self))
     }
-}
\ No newline at end of file
+}
+
+
+/// A trait for objects which can be converted or resolved to one or more
+/// [`SocketAddrScion`] values, the SCION counterpart of [`ToSocketAddrs`].
+///
+/// The SCION sockets accept any such value as address. By default it is
+/// implemented for the following types:
+///
+///  * [`SocketAddrScion`]: [`to_scion_socket_addrs`] is the identity function.
+///
+///  * [`SocketAddr`]: yields the [`SocketAddr::SCION`] address,
+///    IP socket addresses fail with [`io::ErrorKind::InvalidInput`].
+///
+///  * <code>([ScionAddr], [u16])</code>, <code>([IsdAsn], [IpAddr], [u16])</code>,
+///    <code>([IsdAsn], [Ipv4Addr], [u16])</code>, <code>([IsdAsn], [Ipv6Addr], [u16])</code>:
+///    [`to_scion_socket_addrs`] constructs a [`SocketAddrScion`] trivially.
+///
+///  * <code>&[str]</code>, <code>([&str], [u16])</code> and their [`String`] variants:
+///    either a literal SCION (socket) address, or a `host:port` pair whose host is
+///    resolved with the default [`ResolverChain`](crate::ResolverChain), keeping
+///    only the SCION addresses.
+///
+///  * <code>&[[SocketAddrScion]]</code> and <code>[Vec]<[SocketAddrScion]></code>:
+///    all addresses in order.
+///
+/// [`to_scion_socket_addrs`]: ToScionSocketAddrs::to_scion_socket_addrs
+pub trait ToScionSocketAddrs {
+    /// Returned iterator over SCION socket addresses which this type may correspond to.
+    type Iter: Iterator<Item = SocketAddrScion>;
+
+    /// Converts this object to an iterator of resolved [`SocketAddrScion`]s.
+    ///
+    /// Note that this function may block the current thread while resolution is
+    /// performed.
+    fn to_scion_socket_addrs(&self) -> io::Result<Self::Iter>;
+}
+
+impl ToScionSocketAddrs for SocketAddrScion {
+    type Iter = option::IntoIter<SocketAddrScion>;
+    fn to_scion_socket_addrs(&self) -> io::Result<Self::Iter> {
+        Ok(Some(*self).into_iter())
+    }
+}
+
+impl ToScionSocketAddrs for SocketAddr {
+    type Iter = option::IntoIter<SocketAddrScion>;
+    fn to_scion_socket_addrs(&self) -> io::Result<Self::Iter> {
+        match *self {
+            SocketAddr::SCION(ref a) => a.to_scion_socket_addrs(),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidInput, "not a SCION socket address")),
+        }
+    }
+}
+
+impl ToScionSocketAddrs for (ScionAddr, u16) {
+    type Iter = option::IntoIter<SocketAddrScion>;
+    fn to_scion_socket_addrs(&self) -> io::Result<Self::Iter> {
+        SocketAddrScion::from_scion_addr(self.0, self.1).to_scion_socket_addrs()
+    }
+}
+
+impl ToScionSocketAddrs for (IsdAsn, IpAddr, u16) {
+    type Iter = option::IntoIter<SocketAddrScion>;
+    fn to_scion_socket_addrs(&self) -> io::Result<Self::Iter> {
+        let (ia, host, port) = *self;
+        SocketAddrScion::from_ia(ia, host, port).to_scion_socket_addrs()
+    }
+}
+
+impl ToScionSocketAddrs for (IsdAsn, Ipv4Addr, u16) {
+    type Iter = option::IntoIter<SocketAddrScion>;
+    fn to_scion_socket_addrs(&self) -> io::Result<Self::Iter> {
+        (self.0, IpAddr::V4(self.1), self.2).to_scion_socket_addrs()
+    }
+}
+
+impl ToScionSocketAddrs for (IsdAsn, Ipv6Addr, u16) {
+    type Iter = option::IntoIter<SocketAddrScion>;
+    fn to_scion_socket_addrs(&self) -> io::Result<Self::Iter> {
+        (self.0, IpAddr::V6(self.1), self.2).to_scion_socket_addrs()
+    }
+}
+
+fn resolve_scion(host: &str, port: u16) -> io::Result<vec::IntoIter<SocketAddrScion>> {
+    let addrs: Vec<_> = crate::resolve::resolve_blocking(host, port)?
+        .into_iter()
+        .filter_map(|a| match a {
+            SocketAddr::SCION(a) => Some(a),
+            _ => None,
+        })
+        .collect();
+    if addrs.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no SCION address for '{}'", host),
+        ));
+    }
+    Ok(addrs.into_iter())
+}
+
+impl ToScionSocketAddrs for (&str, u16) {
+    type Iter = vec::IntoIter<SocketAddrScion>;
+    fn to_scion_socket_addrs(&self) -> io::Result<Self::Iter> {
+        let (host, port) = *self;
+        if let Ok(addr) = quietly(|| host.parse::<ScionAddr>()) {
+            return Ok(vec![SocketAddrScion::from_scion_addr(addr, port)].into_iter());
+        }
+        resolve_scion(host, port)
+    }
+}
+
+impl ToScionSocketAddrs for (String, u16) {
+    type Iter = vec::IntoIter<SocketAddrScion>;
+    fn to_scion_socket_addrs(&self) -> io::Result<Self::Iter> {
+        (&*self.0, self.1).to_scion_socket_addrs()
+    }
+}
+
+// accepts strings like '1-ff00:0:110,[10.0.0.1]:80' or 'example.org:80'
+impl ToScionSocketAddrs for str {
+    type Iter = vec::IntoIter<SocketAddrScion>;
+    fn to_scion_socket_addrs(&self) -> io::Result<Self::Iter> {
+        if let Ok(addr) = quietly(|| self.parse::<SocketAddrScion>()) {
+            return Ok(vec![addr].into_iter());
+        }
+
+        let invalid = || io::Error::new(io::ErrorKind::InvalidInput, "invalid socket address");
+        let (host, port) = self.rsplit_once(':').ok_or_else(invalid)?;
+        let port = port.parse::<u16>().map_err(|_| invalid())?;
+        resolve_scion(host, port)
+    }
+}
+
+impl ToScionSocketAddrs for String {
+    type Iter = vec::IntoIter<SocketAddrScion>;
+    fn to_scion_socket_addrs(&self) -> io::Result<Self::Iter> {
+        (**self).to_scion_socket_addrs()
+    }
+}
+
+impl<'a> ToScionSocketAddrs for &'a [SocketAddrScion] {
+    type Iter = iter::Copied<slice::Iter<'a, SocketAddrScion>>;
+    fn to_scion_socket_addrs(&self) -> io::Result<Self::Iter> {
+        Ok(self.iter().copied())
+    }
+}
+
+impl ToScionSocketAddrs for Vec<SocketAddrScion> {
+    type Iter = vec::IntoIter<SocketAddrScion>;
+    fn to_scion_socket_addrs(&self) -> io::Result<Self::Iter> {
+        Ok(self.clone().into_iter())
+    }
+}
+
+impl<T: ToScionSocketAddrs + ?Sized> ToScionSocketAddrs for &T {
+    type Iter = T::Iter;
+    fn to_scion_socket_addrs(&self) -> io::Result<T::Iter> {
+        (**self).to_scion_socket_addrs()
+    }
+}
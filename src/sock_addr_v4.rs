@@ -1,7 +1,8 @@
 use std::{fmt::{Debug,Result,Display,Write}, str::FromStr};
 use crate::{IpAddr, Ipv4Addr, Ipv6Addr,SocketAddrScion, SocketAddrV6,  ScionAddr,Parser,DisplayBuffer};
 
-#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+/// An IPv4 socket address. The default is `0.0.0.0:0`.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Default)]
 
 pub struct SocketAddrV4 {
     ip: Ipv4Addr,
@@ -35,18 +36,17 @@ impl std::fmt::Debug for SocketAddrV4 {
     }
 }
 
-impl From<std::net::SocketAddrV4> for SocketAddrV4{
-    fn from(sock4: std::net::SocketAddrV4) -> SocketAddrV4
-    {
-        SocketAddrV4::new( Ipv4Addr::from_str( &sock4.ip().to_string() ).unwrap() , sock4.port())
+impl From<std::net::SocketAddrV4> for SocketAddrV4 {
+    #[inline]
+    fn from(sock4: std::net::SocketAddrV4) -> SocketAddrV4 {
+        SocketAddrV4::new((*sock4.ip()).into(), sock4.port())
     }
 }
 
-impl Into<std::net::SocketAddrV4> for SocketAddrV4
-{
-    fn into(self) -> std::net::SocketAddrV4
-    {
-        std::net::SocketAddrV4::from_str( &self.to_string() ).unwrap()
+impl From<SocketAddrV4> for std::net::SocketAddrV4 {
+    #[inline]
+    fn from(sock4: SocketAddrV4) -> std::net::SocketAddrV4 {
+        std::net::SocketAddrV4::new(sock4.ip.into(), sock4.port)
     }
 }
 
@@ -117,6 +117,14 @@ impl SocketAddrV4 {
         self.port
     }
 
+    /// Returns `true` if the port is 0, which binds an ephemeral port and
+    /// cannot be sent to.
+    #[must_use]
+    #[inline]
+    pub const fn is_unspecified_port(&self) -> bool {
+        self.port == 0
+    }
+
     /// Changes the port number associated with this socket address.
     ///
     /// # Examples
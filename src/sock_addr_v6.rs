@@ -1,9 +1,9 @@
 
 use std::fmt::*;
 use crate::{IpAddr, Ipv4Addr, Ipv6Addr,SocketAddrScion,  SocketAddrV4, ScionAddr,Parser,DisplayBuffer};
-use std::str::FromStr;
 
-#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+/// An IPv6 socket address. The default is `[::]:0`.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Default)]
 
 pub struct SocketAddrV6 {
     ip: Ipv6Addr,
@@ -13,6 +13,23 @@ pub struct SocketAddrV6 {
 }
 
 
+/// Formats a scope id, by interface name if the `os-zone` feature
+/// is enabled and an interface with this index exists.
+struct ScopeId(u32);
+
+impl std::fmt::Display for ScopeId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        #[cfg(feature = "os-zone")]
+        {
+            let mut buf = [0; crate::zone::IF_NAMESIZE];
+            if let Some(name) = crate::zone::if_index_to_name(self.0, &mut buf) {
+                return f.write_str(name);
+            }
+        }
+        write!(f, "{}", self.0)
+    }
+}
+
 impl std::fmt::Display for SocketAddrV6 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         // If there are no alignment requirements, write the socket address directly to `f`.
@@ -20,16 +37,17 @@ impl std::fmt::Display for SocketAddrV6 {
         if f.precision().is_none() && f.width().is_none() {
             match self.scope_id() {
                 0 => write!(f, "[{}]:{}", self.ip(), self.port()),
-                scope_id => write!(f, "[{}%{}]:{}", self.ip(), scope_id, self.port()),
+                scope_id => write!(f, "[{}%{}]:{}", self.ip(), ScopeId(scope_id), self.port()),
             }
         } else {
+            // interface names are at most 15 bytes long, which exceeds any decimal scope id
             const LONGEST_IPV6_SOCKET_ADDR: &str =
-                "[ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff%4294967296]:65536";
+                "[ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff%interface-name0]:65536";
 
             let mut buf = DisplayBuffer::<{ LONGEST_IPV6_SOCKET_ADDR.len() }>::new();
             match self.scope_id() {
                 0 => write!(buf, "[{}]:{}", self.ip(), self.port()),
-                scope_id => write!(buf, "[{}%{}]:{}", self.ip(), scope_id, self.port()),
+                scope_id => write!(buf, "[{}%{}]:{}", self.ip(), ScopeId(scope_id), self.port()),
             }
             // Buffer is long enough for the longest possible IPv6 socket address, so this should never fail.
             .unwrap();
@@ -100,6 +118,14 @@ impl SocketAddrV6 {
         self.port
     }
 
+    /// Returns `true` if the port is 0, which binds an ephemeral port and
+    /// cannot be sent to.
+    #[must_use]
+    #[inline]
+    pub const fn is_unspecified_port(&self) -> bool {
+        self.port == 0
+    }
+
     /// Changes the port number associated with this socket address.
     ///
     /// # Examples
@@ -207,18 +233,19 @@ impl SocketAddrV6 {
     }
 }
 
-impl From<std::net::SocketAddrV6> for SocketAddrV6{
-    fn from(sock6: std::net::SocketAddrV6) -> SocketAddrV6
-    {
-        SocketAddrV6::from_str(&sock6.to_string() ).unwrap()
+impl From<std::net::SocketAddrV6> for SocketAddrV6 {
+    #[inline]
+    fn from(sock6: std::net::SocketAddrV6) -> SocketAddrV6 {
+        let ip = (*sock6.ip()).into();
+        SocketAddrV6::new(ip, sock6.port(), sock6.flowinfo(), sock6.scope_id())
     }
 }
 
-impl Into<std::net::SocketAddrV6> for SocketAddrV6
-{
-    fn into(self) -> std::net::SocketAddrV6
-    {
-        std::net::SocketAddrV6::from_str( &self.to_string() ).unwrap()
+impl From<SocketAddrV6> for std::net::SocketAddrV6 {
+    #[inline]
+    fn from(sock6: SocketAddrV6) -> std::net::SocketAddrV6 {
+        let ip = sock6.ip.into();
+        std::net::SocketAddrV6::new(ip, sock6.port, sock6.flowinfo, sock6.scope_id)
     }
 }
 
@@ -0,0 +1,154 @@
+//! Conversions between the socket addresses and the `libc` socket address
+//! structs, to pass them to `bind`, `sendto` and the like directly.
+
+use crate::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+use std::error::Error;
+use std::{fmt, io, mem};
+
+/// The error of [`SocketAddr::from_sockaddr_storage`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SockaddrError {
+    /// The address family is neither `AF_INET` nor `AF_INET6`.
+    UnknownFamily { family: libc::c_int },
+    /// The length is too short for the struct of the family.
+    Truncated { family: libc::c_int, len: usize },
+}
+
+impl fmt::Display for SockaddrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SockaddrError::UnknownFamily { family } => {
+                write!(f, "unsupported socket address family {}", family)
+            }
+            SockaddrError::Truncated { family, len } => {
+                write!(f, "socket address of family {} truncated to {} bytes", family, len)
+            }
+        }
+    }
+}
+
+impl Error for SockaddrError {}
+
+impl From<SockaddrError> for io::Error {
+    fn from(e: SockaddrError) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidInput, e)
+    }
+}
+
+impl SocketAddrV4 {
+    /// Returns the address as `sockaddr_in`, with the port and the address in
+    /// network byte order.
+    #[must_use]
+    pub fn to_sockaddr_in(&self) -> libc::sockaddr_in {
+        libc::sockaddr_in {
+            sin_family: libc::AF_INET as libc::sa_family_t,
+            sin_port: self.port().to_be(),
+            sin_addr: libc::in_addr { s_addr: u32::from_ne_bytes(self.ip().octets()) },
+            // SAFETY: all-zero is a valid `sockaddr_in`
+            ..unsafe { mem::zeroed() }
+        }
+    }
+
+    /// Returns the address held by `addr`, whose family is not checked.
+    #[must_use]
+    pub fn from_sockaddr_in(addr: &libc::sockaddr_in) -> SocketAddrV4 {
+        let ip = Ipv4Addr::from(addr.sin_addr.s_addr.to_ne_bytes());
+        SocketAddrV4::new(ip, u16::from_be(addr.sin_port))
+    }
+}
+
+impl SocketAddrV6 {
+    /// Returns the address as `sockaddr_in6`, with the port in network byte
+    /// order. The flow info and the scope id are passed on as they are.
+    #[must_use]
+    pub fn to_sockaddr_in6(&self) -> libc::sockaddr_in6 {
+        // SAFETY: all-zero is a valid `sockaddr_in6`; some platforms have
+        // more fields than the ones set here
+        let mut sin6: libc::sockaddr_in6 = unsafe { mem::zeroed() };
+        sin6.sin6_family = libc::AF_INET6 as libc::sa_family_t;
+        sin6.sin6_port = self.port().to_be();
+        sin6.sin6_flowinfo = self.flowinfo();
+        sin6.sin6_addr = libc::in6_addr { s6_addr: self.ip().octets() };
+        sin6.sin6_scope_id = self.scope_id();
+        sin6
+    }
+
+    /// Returns the address held by `addr`, whose family is not checked.
+    #[must_use]
+    pub fn from_sockaddr_in6(addr: &libc::sockaddr_in6) -> SocketAddrV6 {
+        SocketAddrV6::new(
+            Ipv6Addr::from_octets(addr.sin6_addr.s6_addr),
+            u16::from_be(addr.sin6_port),
+            addr.sin6_flowinfo,
+            addr.sin6_scope_id,
+        )
+    }
+}
+
+impl SocketAddr {
+    /// Returns the address as `sockaddr_in` or `sockaddr_in6` in a
+    /// `sockaddr_storage`, along with the length of the struct used, or `None`
+    /// for a SCION address, which the OS does not know of.
+    ///
+    /// ```
+    /// use scionnet::SocketAddr;
+    ///
+    /// let addr: SocketAddr = "[::1]:443".parse().unwrap();
+    /// let (storage, len) = addr.to_sockaddr_storage().unwrap();
+    /// assert_eq!(storage.ss_family, libc::AF_INET6 as libc::sa_family_t);
+    /// assert_eq!(len as usize, std::mem::size_of::<libc::sockaddr_in6>());
+    /// ```
+    #[must_use]
+    pub fn to_sockaddr_storage(&self) -> Option<(libc::sockaddr_storage, libc::socklen_t)> {
+        // SAFETY: all-zero is a valid `sockaddr_storage`, which is large and
+        // aligned enough to hold any socket address.
+        let mut storage: libc::sockaddr_storage = unsafe { mem::zeroed() };
+        let len = match self {
+            SocketAddr::V4(a) => {
+                let sin = a.to_sockaddr_in();
+                // SAFETY: `sockaddr_in` fits into `sockaddr_storage`
+                unsafe { *(&mut storage as *mut _ as *mut libc::sockaddr_in) = sin };
+                mem::size_of::<libc::sockaddr_in>()
+            }
+            SocketAddr::V6(a) => {
+                let sin6 = a.to_sockaddr_in6();
+                // SAFETY: `sockaddr_in6` fits into `sockaddr_storage`
+                unsafe { *(&mut storage as *mut _ as *mut libc::sockaddr_in6) = sin6 };
+                mem::size_of::<libc::sockaddr_in6>()
+            }
+            SocketAddr::SCION(_) => return None,
+        };
+        Some((storage, len as libc::socklen_t))
+    }
+
+    /// Returns the address held by `storage`, i.e. as filled in by
+    /// `recvfrom` or `accept`, of which `len` bytes are valid.
+    ///
+    /// ```
+    /// use scionnet::SocketAddr;
+    ///
+    /// let addr: SocketAddr = "10.0.0.1:53".parse().unwrap();
+    /// let (storage, len) = addr.to_sockaddr_storage().unwrap();
+    /// assert_eq!(SocketAddr::from_sockaddr_storage(&storage, len), Ok(addr));
+    /// ```
+    pub fn from_sockaddr_storage(
+        storage: &libc::sockaddr_storage,
+        len: libc::socklen_t,
+    ) -> Result<SocketAddr, SockaddrError> {
+        let (family, len) = (libc::c_int::from(storage.ss_family), len as usize);
+        match family {
+            libc::AF_INET if len >= mem::size_of::<libc::sockaddr_in>() => {
+                // SAFETY: the family says `storage` holds a `sockaddr_in`
+                let sin = unsafe { &*(storage as *const _ as *const libc::sockaddr_in) };
+                Ok(SocketAddr::V4(SocketAddrV4::from_sockaddr_in(sin)))
+            }
+            libc::AF_INET6 if len >= mem::size_of::<libc::sockaddr_in6>() => {
+                // SAFETY: the family says `storage` holds a `sockaddr_in6`
+                let sin6 = unsafe { &*(storage as *const _ as *const libc::sockaddr_in6) };
+                Ok(SocketAddr::V6(SocketAddrV6::from_sockaddr_in6(sin6)))
+            }
+            libc::AF_INET | libc::AF_INET6 => Err(SockaddrError::Truncated { family, len }),
+            _ => Err(SockaddrError::UnknownFamily { family }),
+        }
+    }
+}
@@ -0,0 +1,83 @@
+//! Conversions to and from `socket2` addresses, enabled by the `socket2`
+//! feature, for users building their own underlay sockets.
+
+use crate::udp_socket::ip_socket_addr;
+use crate::{SocketAddr, SocketAddrScion, SocketAddrV4, SocketAddrV6};
+use socket2::{Protocol, SockAddr, Socket, Type};
+use std::io;
+
+impl From<SocketAddrV4> for SockAddr {
+    fn from(addr: SocketAddrV4) -> SockAddr {
+        SockAddr::from(std::net::SocketAddrV4::from(addr))
+    }
+}
+
+impl From<SocketAddrV6> for SockAddr {
+    fn from(addr: SocketAddrV6) -> SockAddr {
+        SockAddr::from(std::net::SocketAddrV6::from(addr))
+    }
+}
+
+impl TryFrom<SocketAddr> for SockAddr {
+    type Error = io::Error;
+
+    /// Fails for SCION addresses, which the OS does not know of; see
+    /// [`SocketAddrScion::underlay_sock_addr`] for their underlay address.
+    fn try_from(addr: SocketAddr) -> io::Result<SockAddr> {
+        match addr {
+            SocketAddr::V4(a) => Ok(a.into()),
+            SocketAddr::V6(a) => Ok(a.into()),
+            SocketAddr::SCION(_) => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "a SCION socket address has no OS counterpart",
+            )),
+        }
+    }
+}
+
+impl TryFrom<&SockAddr> for SocketAddr {
+    type Error = io::Error;
+
+    /// Fails for addresses of families other than IPv4 and IPv6.
+    fn try_from(addr: &SockAddr) -> io::Result<SocketAddr> {
+        addr.as_socket().map(SocketAddr::from).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "not an IP socket address")
+        })
+    }
+}
+
+impl TryFrom<SockAddr> for SocketAddr {
+    type Error = io::Error;
+
+    fn try_from(addr: SockAddr) -> io::Result<SocketAddr> {
+        SocketAddr::try_from(&addr)
+    }
+}
+
+impl SocketAddrScion {
+    /// Returns the underlay address of the host part and the port, failing
+    /// for service addresses.
+    pub fn underlay_sock_addr(&self) -> io::Result<SockAddr> {
+        ip_socket_addr(self).map(SockAddr::from)
+    }
+
+    /// Creates a socket of type `ty` bound to the underlay address, see
+    /// [`underlay_sock_addr`](Self::underlay_sock_addr). Options that must
+    /// be set before binding are left to [`Socket::bind`] on a socket of
+    /// one's own.
+    ///
+    /// ```
+    /// use scionnet::SocketAddrScion;
+    /// use socket2::Type;
+    ///
+    /// let addr: SocketAddrScion = "1-ff00:0:110,127.0.0.1:0".parse().unwrap();
+    /// let socket = addr.bind_underlay(Type::DGRAM, None).unwrap();
+    /// assert!(socket.local_addr().unwrap().as_socket_ipv4().is_some());
+    /// ```
+    pub fn bind_underlay(&self, ty: Type, protocol: Option<Protocol>) -> io::Result<Socket> {
+        let addr = self.underlay_sock_addr()?;
+        let socket = Socket::new(addr.domain(), ty, protocol)?;
+        socket.bind(&addr)?;
+        Ok(socket)
+    }
+}
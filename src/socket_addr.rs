@@ -1,18 +1,275 @@
 use crate::{
-    DisplayBuffer, IpAddr, Ipv4Addr, Ipv6Addr, Parser, ScionAddr, SocketAddrScion, SocketAddrV4,
-    SocketAddrV6,
+    DisplayBuffer, HostAddr, IpAddr, Ipv4Addr, Ipv6Addr, IsdAsn, Parser, ScionAddr,
+    SocketAddrScion, SocketAddrV4, SocketAddrV6,
 };
+use std::convert::TryFrom;
 use std::error::Error;
 use std::fmt::{self, Write};
 use std::str::FromStr;
 
+/// A network layer address, either IP or SCION.
+///
+/// More kinds of addresses may be added, so matches outside this crate need a
+/// wildcard arm; [`family`](L3Addr::family) and [`visit`](L3Addr::visit) cover
+/// all of them.
+///
+/// The textual form is the one of the address, i.e. `10.0.0.1` or
+/// `19-ffaa:1:1067,10.0.0.1`.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[non_exhaustive]
 pub enum L3Addr {
     IP(IpAddr),
     SCION(ScionAddr),
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+/// The family of an address, telling the variants of [`SocketAddr`] and
+/// [`L3Addr`] apart.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[non_exhaustive]
+pub enum AddrFamily {
+    Ipv4,
+    Ipv6,
+    Scion,
+}
+
+impl AddrFamily {
+    /// Returns the lowercase name of the family, i.e. `"scion"`.
+    #[must_use]
+    pub const fn as_str(&self) -> &'static str {
+        match *self {
+            AddrFamily::Ipv4 => "ipv4",
+            AddrFamily::Ipv6 => "ipv6",
+            AddrFamily::Scion => "scion",
+        }
+    }
+}
+
+impl fmt::Display for AddrFamily {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+/// An error returned when converting a [`SocketAddr`] of one family into the
+/// socket address type of another, i.e. a SCION address into a
+/// [`SocketAddrV4`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AddrFamilyError {
+    expected: AddrFamily,
+    found: AddrFamily,
+}
+
+impl AddrFamilyError {
+    /// Returns the family the conversion needed.
+    #[must_use]
+    pub const fn expected(&self) -> AddrFamily {
+        self.expected
+    }
+
+    /// Returns the family of the address that was converted.
+    #[must_use]
+    pub const fn found(&self) -> AddrFamily {
+        self.found
+    }
+}
+
+impl fmt::Display for AddrFamilyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "expected an address of family {}, found {}", self.expected, self.found)
+    }
+}
+
+impl Error for AddrFamilyError {}
+
+/// Handles a [`SocketAddr`] by its family, see [`SocketAddr::visit`].
+///
+/// Every family has its own method, all of which fall back to
+/// [`visit_other`](Self::visit_other). Families added later get a method of
+/// their own that falls back the same way, so visitors keep compiling.
+pub trait SocketAddrVisitor {
+    type Output;
+
+    /// Handles an address no other method was implemented for.
+    fn visit_other(self, family: AddrFamily, addr: &SocketAddr) -> Self::Output;
 
+    fn visit_v4(self, addr: &SocketAddrV4) -> Self::Output
+    where
+        Self: Sized,
+    {
+        self.visit_other(AddrFamily::Ipv4, &SocketAddr::V4(*addr))
+    }
+
+    fn visit_v6(self, addr: &SocketAddrV6) -> Self::Output
+    where
+        Self: Sized,
+    {
+        self.visit_other(AddrFamily::Ipv6, &SocketAddr::V6(*addr))
+    }
+
+    fn visit_scion(self, addr: &SocketAddrScion) -> Self::Output
+    where
+        Self: Sized,
+    {
+        self.visit_other(AddrFamily::Scion, &SocketAddr::SCION(*addr))
+    }
+}
+
+/// Handles an [`L3Addr`] by its family, see [`L3Addr::visit`] and
+/// [`SocketAddrVisitor`].
+pub trait L3AddrVisitor {
+    type Output;
+
+    /// Handles an address no other method was implemented for.
+    fn visit_other(self, family: AddrFamily, addr: &L3Addr) -> Self::Output;
+
+    fn visit_ip(self, addr: &IpAddr) -> Self::Output
+    where
+        Self: Sized,
+    {
+        let family = if addr.is_ipv4() { AddrFamily::Ipv4 } else { AddrFamily::Ipv6 };
+        self.visit_other(family, &L3Addr::IP(*addr))
+    }
+
+    fn visit_scion(self, addr: &ScionAddr) -> Self::Output
+    where
+        Self: Sized,
+    {
+        self.visit_other(AddrFamily::Scion, &L3Addr::SCION(*addr))
+    }
+}
+
+impl L3Addr {
+    #[must_use]
+    pub fn family(&self) -> AddrFamily {
+        match *self {
+            L3Addr::IP(IpAddr::V4(_)) => AddrFamily::Ipv4,
+            L3Addr::IP(IpAddr::V6(_)) => AddrFamily::Ipv6,
+            L3Addr::SCION(_) => AddrFamily::Scion,
+        }
+    }
+
+    /// Calls the method of `visitor` for the family of this address.
+    pub fn visit<V: L3AddrVisitor>(&self, visitor: V) -> V::Output {
+        match *self {
+            L3Addr::IP(ref a) => visitor.visit_ip(a),
+            L3Addr::SCION(ref a) => visitor.visit_scion(a),
+        }
+    }
+
+    /// Returns the IP address, or `None` for a SCION address.
+    #[must_use]
+    #[inline]
+    pub const fn ip(&self) -> Option<IpAddr> {
+        match *self {
+            L3Addr::IP(ip) => Some(ip),
+            L3Addr::SCION(_) => None,
+        }
+    }
+
+    /// Returns the SCION address, or `None` for an IP address.
+    #[must_use]
+    #[inline]
+    pub const fn scion(&self) -> Option<ScionAddr> {
+        match *self {
+            L3Addr::IP(_) => None,
+            L3Addr::SCION(addr) => Some(addr),
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn is_ip(&self) -> bool {
+        matches!(self, L3Addr::IP(_))
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn is_scion(&self) -> bool {
+        matches!(self, L3Addr::SCION(_))
+    }
+
+    /// Returns the host of the address, the IP address itself for IP.
+    #[must_use]
+    #[inline]
+    pub fn host(&self) -> HostAddr {
+        match *self {
+            L3Addr::IP(ip) => HostAddr::Ip(ip),
+            L3Addr::SCION(ref addr) => *addr.get_host(),
+        }
+    }
+}
+
+impl Default for L3Addr {
+    /// Returns the unspecified IPv4 address `0.0.0.0`, like [`IpAddr`].
+    fn default() -> Self {
+        L3Addr::IP(IpAddr::default())
+    }
+}
+
+impl fmt::Display for L3Addr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            L3Addr::IP(ref a) => fmt::Display::fmt(a, f),
+            L3Addr::SCION(ref a) => fmt::Display::fmt(a, f),
+        }
+    }
+}
+
+impl fmt::Debug for L3Addr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl From<IpAddr> for L3Addr {
+    fn from(ip: IpAddr) -> L3Addr {
+        L3Addr::IP(ip)
+    }
+}
+
+impl From<Ipv4Addr> for L3Addr {
+    fn from(ip: Ipv4Addr) -> L3Addr {
+        L3Addr::IP(IpAddr::V4(ip))
+    }
+}
+
+impl From<Ipv6Addr> for L3Addr {
+    fn from(ip: Ipv6Addr) -> L3Addr {
+        L3Addr::IP(IpAddr::V6(ip))
+    }
+}
+
+impl From<std::net::IpAddr> for L3Addr {
+    fn from(ip: std::net::IpAddr) -> L3Addr {
+        L3Addr::IP(ip.into())
+    }
+}
+
+impl From<ScionAddr> for L3Addr {
+    fn from(addr: ScionAddr) -> L3Addr {
+        L3Addr::SCION(addr)
+    }
+}
+
+impl PartialEq<IpAddr> for L3Addr {
+    fn eq(&self, other: &IpAddr) -> bool {
+        matches!(self, L3Addr::IP(ip) if ip == other)
+    }
+}
+
+impl PartialEq<ScionAddr> for L3Addr {
+    fn eq(&self, other: &ScionAddr) -> bool {
+        matches!(self, L3Addr::SCION(addr) if addr == other)
+    }
+}
+
+/// An IP or SCION socket address.
+///
+/// More kinds of addresses may be added, so matches outside this crate need a
+/// wildcard arm; [`family`](SocketAddr::family) and [`visit`](SocketAddr::visit)
+/// cover all of them.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[non_exhaustive]
 pub enum SocketAddr {
     /// An IPv4 socket address.
     V4(SocketAddrV4),
@@ -26,6 +283,23 @@ impl SocketAddr {
     pub fn parse_ascii(b: &[u8]) -> Result<Self, AddrParseError> {
         Parser::new(b).parse_with(|p| p.read_socket_addr(), AddrKind::Socket)
     }
+
+    /// Parses the IP or SCION socket address at the start of `b`, returning
+    /// it along with the number of bytes it takes up, for addresses embedded
+    /// in a larger token. What follows is left to the caller; unlike
+    /// [`parse_ascii`](Self::parse_ascii), it is not an error.
+    ///
+    /// ```
+    /// use scionnet::SocketAddr;
+    ///
+    /// let token = "19-ffaa:1:1067,10.0.0.1:80/tcp";
+    /// let (addr, len) = SocketAddr::parse_ascii_prefix(token.as_bytes()).unwrap();
+    /// assert_eq!(addr.port(), 80);
+    /// assert_eq!(&token[len..], "/tcp");
+    /// ```
+    pub fn parse_ascii_prefix(b: &[u8]) -> Result<(Self, usize), AddrParseError> {
+        Parser::new(b).parse_prefix_with(|p| p.read_socket_addr(), AddrKind::Socket)
+    }
 }
 
 impl FromStr for SocketAddr {
@@ -35,43 +309,175 @@ impl FromStr for SocketAddr {
     }
 }
 
+/// The kind of an [`AddrParseError`]: mostly the kind of address that failed
+/// to parse, or, if the parser knows better, why it failed.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum AddrKind {
     L3Addr,
+    Isd,
+    IsdRange,
+    Asn,
+    AsnPrefix,
+    Ia,    // -> IsdAsn
     Scion, // -> ScionAddr
     Ip,    // -> IpAddr (either one of the below 2x)
     Ipv4,
     Ipv6,
+    IpNet, // -> IpNet (either one of the below 2x)
+    Ipv4Net,
+    Ipv6Net,
+    ScionNet,
+    Host, // -> HostAddr (an IP or a service address)
+    Svc,
 
     Socket,      // L4Addr  -> SocketAddr   (either one of the below 3x)
     SocketScion, // -> SocketAddrScion
     SocketV4,
     SocketV6,
+
+    // the kinds below are reasons, see `AddrParseError::reason`
+
+    /// An ISD number greater than 65535.
+    IsdOutOfRange,
+    /// A decimal AS number that doesn't fit into 32 bits, or a group of a
+    /// dotted AS with more than four hex digits.
+    AsnOutOfRange,
+    /// A socket address that ends before its port.
+    MissingPort,
+    /// An address followed by more input.
+    TrailingCharacters,
 }
 
+/// An error returned when parsing an address fails.
+///
+/// Errors of the parser also tell the byte offset where the input went wrong
+/// and, if known, what was expected there, i.e. `invalid Scion address
+/// syntax: expected ',' at offset 14`.
+///
+/// Two errors are equal if their kind, reason, offset and expectation are,
+/// so an error of the parser differs from [`AddrParseError::new`] of the same
+/// kind. Compare [`kind`](Self::kind) to only match the kind.
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct AddrParseError(pub AddrKind);
+#[non_exhaustive]
+pub struct AddrParseError {
+    kind: AddrKind,
+    reason: Option<AddrKind>,
+    offset: Option<usize>,
+    expected: Option<&'static str>,
+}
+
+impl AddrParseError {
+    /// Returns an error of `kind` without details.
+    #[must_use]
+    pub const fn new(kind: AddrKind) -> AddrParseError {
+        AddrParseError { kind, reason: None, offset: None, expected: None }
+    }
+
+    /// Returns an error of `kind` at `offset` of the input.
+    #[must_use]
+    pub const fn at(
+        kind: AddrKind,
+        offset: usize,
+        expected: Option<&'static str>,
+    ) -> AddrParseError {
+        AddrParseError { kind, reason: None, offset: Some(offset), expected }
+    }
+
+    /// Returns the error with the reason `reason`, see [`reason`](Self::reason).
+    #[must_use]
+    pub const fn with_reason(mut self, reason: AddrKind) -> AddrParseError {
+        self.reason = Some(reason);
+        self
+    }
+
+    /// Returns the kind of address that failed to parse, i.e.
+    /// [`AddrKind::Ipv4`] for [`Ipv4Addr`](crate::Ipv4Addr).
+    #[must_use]
+    pub const fn kind(&self) -> &AddrKind {
+        &self.kind
+    }
+
+    /// Returns why parsing failed, if more is known than that the input is
+    /// no address of the [`kind`](Self::kind), i.e. to tell a typo in the
+    /// port from a missing one:
+    ///
+    /// ```
+    /// use scionnet::{AddrKind, SocketAddrScion};
+    /// use std::str::FromStr;
+    ///
+    /// let err = SocketAddrScion::from_str("1-ff00:0:110,10.0.0.1").unwrap_err();
+    /// assert_eq!(err.kind(), &AddrKind::SocketScion);
+    /// assert_eq!(err.reason(), Some(&AddrKind::MissingPort));
+    /// let err = SocketAddrScion::from_str("1-ff00:0:110,10.0.0.1:https!").unwrap_err();
+    /// assert_eq!(err.reason(), Some(&AddrKind::TrailingCharacters));
+    /// let err = SocketAddrScion::from_str("1-ff00:0:110,10.0.0.1:x").unwrap_err();
+    /// assert_eq!(err.reason(), None);
+    /// ```
+    #[must_use]
+    pub const fn reason(&self) -> Option<&AddrKind> {
+        self.reason.as_ref()
+    }
+
+    /// Returns the byte offset of the input where parsing failed.
+    #[must_use]
+    pub const fn offset(&self) -> Option<usize> {
+        self.offset
+    }
+
+    /// Returns what was expected at [`offset`](Self::offset), i.e. `','` or
+    /// `a port number`.
+    #[must_use]
+    pub const fn expected(&self) -> Option<&'static str> {
+        self.expected
+    }
+}
 
 impl fmt::Display for AddrParseError {
     #[allow(deprecated, deprecated_in_future)]
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
-        fmt.write_str(self.description())
+        fmt.write_str(self.description())?;
+        if let Some(reason) = &self.reason {
+            write!(fmt, " ({})", AddrParseError::new(reason.clone()).description())?;
+        }
+        match (self.expected, self.offset) {
+            (Some(expected), Some(offset)) => {
+                write!(fmt, ": expected {} at offset {}", expected, offset)
+            }
+            (None, Some(offset)) => write!(fmt, " at offset {}", offset),
+            _ => Ok(()),
+        }
     }
 }
 
 impl Error for AddrParseError {
     #[allow(deprecated)]
     fn description(&self) -> &str {
-        match self.0 {
+        match self.kind {
             AddrKind::Ip => "invalid IP address syntax",
             AddrKind::Ipv4 => "invalid IPv4 address syntax",
             AddrKind::Ipv6 => "invalid IPv6 address syntax",
+            AddrKind::IpNet => "invalid IP prefix",
+            AddrKind::Ipv4Net => "invalid IPv4 prefix",
+            AddrKind::Ipv6Net => "invalid IPv6 prefix",
+            AddrKind::ScionNet => "invalid SCION prefix",
+            AddrKind::Isd => "invalid ISD number",
+            AddrKind::IsdRange => "invalid ISD range",
+            AddrKind::Asn => "invalid AS number",
+            AddrKind::AsnPrefix => "invalid AS prefix",
+            AddrKind::Ia => "invalid ISD-AS syntax",
             AddrKind::Scion => "invalid Scion address syntax",
+            AddrKind::Host => "invalid SCION host address syntax",
+            AddrKind::Svc => "invalid SCION service address",
             AddrKind::SocketScion => "invalid ScionSocket address syntax",
             AddrKind::L3Addr => "invalid L3Address syntax",
             AddrKind::Socket => "invalid socket address syntax",
             AddrKind::SocketV4 => "invalid IPv4 socket address syntax",
             AddrKind::SocketV6 => "invalid IPv6 socket address syntax",
+            AddrKind::IsdOutOfRange => "ISD number out of range",
+            AddrKind::AsnOutOfRange => "AS number out of range",
+            AddrKind::MissingPort => "missing port",
+            AddrKind::TrailingCharacters => "trailing characters after the address",
         }
     }
 }
@@ -105,8 +511,36 @@ impl<I: Into<IpAddr>> From<(I, u16)> for SocketAddr {
 }
 
 impl From<(ScionAddr, u16)> for SocketAddr {
+    /// Converts a tuple struct ([`ScionAddr`], `u16`) into a
+    /// [`SocketAddr::SCION`].
+    ///
+    /// This impl is not generic over `Into<ScionAddr>` like the one for IP
+    /// addresses, as the two would overlap.
     fn from(pieces: (ScionAddr, u16)) -> SocketAddr {
-        SocketAddr::new_scion(pieces.0.get_ia(),*pieces.0.get_host(), pieces.1)
+        SocketAddr::SCION(SocketAddrScion::from_scion_addr(pieces.0, pieces.1))
+    }
+}
+
+impl From<(IsdAsn, IpAddr, u16)> for SocketAddr {
+    /// Converts a tuple struct ([`IsdAsn`], [`IpAddr`], `u16`) into a
+    /// [`SocketAddr::SCION`].
+    ///
+    /// ```
+    /// use scionnet::{IpAddr, IsdAsn, Ipv4Addr, SocketAddr};
+    ///
+    /// let ia: IsdAsn = "19-ffaa:1:1067".parse().unwrap();
+    /// let addr = SocketAddr::from((ia, IpAddr::V4(Ipv4Addr::LOCALHOST), 53));
+    /// assert_eq!(addr.to_string(), "19-ffaa:1:1067,127.0.0.1:53");
+    /// ```
+    fn from(pieces: (IsdAsn, IpAddr, u16)) -> SocketAddr {
+        SocketAddr::new_scion(pieces.0.to_u64(), pieces.1, pieces.2)
+    }
+}
+
+impl Default for SocketAddr {
+    /// Returns `0.0.0.0:0`, the unspecified IPv4 address with port 0.
+    fn default() -> Self {
+        SocketAddr::V4(SocketAddrV4::default())
     }
 }
 
@@ -126,18 +560,18 @@ impl SocketAddr {
         }
     }
 
-    pub fn new_scion(ia: u64, ip: IpAddr, port: u16) -> SocketAddr {
-        SocketAddr::SCION(SocketAddrScion::new(ia, ip, port))
+    pub fn new_scion<H: Into<HostAddr>>(ia: u64, ip: H, port: u16) -> SocketAddr {
+        SocketAddr::SCION(SocketAddrScion::from_ia(IsdAsn::from(ia), ip, port))
     }
 
     #[must_use]
     #[inline]
-    pub fn host(&self) -> IpAddr {
+    pub fn host(&self) -> HostAddr {
         match &self {
             SocketAddr::SCION(addr) => *addr.host(),
-            SocketAddr::V4(ref a) => IpAddr::V4(*a.ip()),
+            SocketAddr::V4(ref a) => HostAddr::from(*a.ip()),
 
-            SocketAddr::V6(ref a) => IpAddr::V6(*a.ip()),
+            SocketAddr::V6(ref a) => HostAddr::from(*a.ip()),
         }
     }
 
@@ -155,16 +589,16 @@ impl SocketAddr {
 
     pub fn set_host(&mut self, new_host: L3Addr) {
         match new_host {
-            L3Addr::SCION(ScionAddr { ia, host }) => match (self) {
+            L3Addr::SCION(addr) => match (self) {
                 &mut SocketAddr::SCION(ref mut a) => {
-                    a.set_host(host);
+                    a.set_host(*addr.get_host());
                 }
-                (&mut SocketAddr::V4(ref mut a)) => match host {
-                    IpAddr::V4(h) => a.set_ip(h),
+                (&mut SocketAddr::V4(ref mut a)) => match *addr.get_host() {
+                    HostAddr::Ip(IpAddr::V4(h)) => a.set_ip(h),
                     _ => {}
                 },
-                (&mut SocketAddr::V6(ref mut a)) => match host {
-                    IpAddr::V6(h) => a.set_ip(h),
+                (&mut SocketAddr::V6(ref mut a)) => match *addr.get_host() {
+                    HostAddr::Ip(IpAddr::V6(h)) => a.set_ip(h),
                     _ => {}
                 },
             },
@@ -178,6 +612,35 @@ impl SocketAddr {
         }
     }
 
+    /// Returns the IA of a SCION address, or `None` for IP addresses.
+    #[must_use]
+    #[inline]
+    pub fn ia(&self) -> Option<u64> {
+        match *self {
+            SocketAddr::SCION(ref a) => Some(a.ia()),
+            SocketAddr::V4(_) | SocketAddr::V6(_) => None,
+        }
+    }
+
+    /// Sets the IA of a SCION address. An IP address becomes a SCION address
+    /// in the AS `ia` with the same host and port; the scope and flow
+    /// information of an IPv6 address are dropped.
+    ///
+    /// ```
+    /// use scionnet::SocketAddr;
+    ///
+    /// let mut addr: SocketAddr = "10.0.0.1:53".parse().unwrap();
+    /// addr.set_ia(0x13_ffaa_0001_1067);
+    /// assert_eq!(addr, "19-ffaa:1:1067,10.0.0.1:53".parse().unwrap());
+    /// ```
+    pub fn set_ia(&mut self, ia: u64) {
+        match *self {
+            SocketAddr::SCION(ref mut a) => a.set_ia(ia),
+            SocketAddr::V4(a) => *self = SocketAddr::new_scion(ia, *a.ip(), a.port()),
+            SocketAddr::V6(a) => *self = SocketAddr::new_scion(ia, *a.ip(), a.port()),
+        }
+    }
+
     #[must_use]
     #[inline]
     pub const fn port(&self) -> u16 {
@@ -188,6 +651,14 @@ impl SocketAddr {
         }
     }
 
+    /// Returns `true` if the port is 0, which binds an ephemeral port and
+    /// cannot be sent to.
+    #[must_use]
+    #[inline]
+    pub const fn is_unspecified_port(&self) -> bool {
+        self.port() == 0
+    }
+
     #[inline]
     pub fn set_port(&mut self, new_port: u16) {
         match *self {
@@ -197,6 +668,25 @@ impl SocketAddr {
         }
     }
 
+    #[must_use]
+    #[inline]
+    pub const fn family(&self) -> AddrFamily {
+        match *self {
+            SocketAddr::V4(_) => AddrFamily::Ipv4,
+            SocketAddr::V6(_) => AddrFamily::Ipv6,
+            SocketAddr::SCION(_) => AddrFamily::Scion,
+        }
+    }
+
+    /// Calls the method of `visitor` for the family of this address.
+    pub fn visit<V: SocketAddrVisitor>(&self, visitor: V) -> V::Output {
+        match *self {
+            SocketAddr::V4(ref a) => visitor.visit_v4(a),
+            SocketAddr::V6(ref a) => visitor.visit_v6(a),
+            SocketAddr::SCION(ref a) => visitor.visit_scion(a),
+        }
+    }
+
     #[must_use]
     #[inline]
     pub fn is_ipv4(&self) -> bool {
@@ -208,6 +698,96 @@ impl SocketAddr {
     pub fn is_ipv6(&self) -> bool {
         matches!(*self, SocketAddr::V6(_))
     }
+
+    #[must_use]
+    #[inline]
+    pub const fn is_scion(&self) -> bool {
+        matches!(*self, SocketAddr::SCION(_))
+    }
+
+    /// Returns the IPv4 socket address, or `None` for other families.
+    #[must_use]
+    #[inline]
+    pub const fn as_v4(&self) -> Option<&SocketAddrV4> {
+        match *self {
+            SocketAddr::V4(ref a) => Some(a),
+            _ => None,
+        }
+    }
+
+    /// Returns the IPv6 socket address, or `None` for other families.
+    #[must_use]
+    #[inline]
+    pub const fn as_v6(&self) -> Option<&SocketAddrV6> {
+        match *self {
+            SocketAddr::V6(ref a) => Some(a),
+            _ => None,
+        }
+    }
+
+    /// Returns the SCION socket address, or `None` for IP addresses.
+    ///
+    /// ```
+    /// use scionnet::SocketAddr;
+    ///
+    /// let addr: SocketAddr = "19-ffaa:1:1067,127.0.0.1:53".parse().unwrap();
+    /// assert_eq!(addr.as_scion().map(|a| a.host().to_string()), Some("127.0.0.1".into()));
+    /// assert!(addr.as_v4().is_none());
+    /// ```
+    #[must_use]
+    #[inline]
+    pub const fn as_scion(&self) -> Option<&SocketAddrScion> {
+        match *self {
+            SocketAddr::SCION(ref a) => Some(a),
+            _ => None,
+        }
+    }
+
+    /// Returns the canonical form of this socket address.
+    ///
+    /// IPv4-mapped IPv6 addresses are converted to [`SocketAddr::V4`], the
+    /// IPv6 flow information is cleared and the host of a SCION address is
+    /// canonicalized as well, so that two addresses naming the same endpoint
+    /// compare and hash equal.
+    #[must_use = "this returns the result of the operation, \
+                  without modifying the original"]
+    pub fn to_canonical(&self) -> SocketAddr {
+        match *self {
+            SocketAddr::V4(_) => *self,
+            SocketAddr::V6(ref a) => match a.ip().to_ipv4_mapped() {
+                Some(v4) => SocketAddr::V4(SocketAddrV4::new(v4, a.port())),
+                None => SocketAddr::V6(SocketAddrV6::new(*a.ip(), a.port(), 0, a.scope_id())),
+            },
+            SocketAddr::SCION(ref a) => {
+                SocketAddr::new_scion(a.ia(), a.host().to_canonical(), a.port())
+            }
+        }
+    }
+}
+
+macro_rules! try_from_socket_addr {
+    ($($variant:ident => $t:ty, $family:ident;)*) => {$(
+        impl TryFrom<SocketAddr> for $t {
+            type Error = AddrFamilyError;
+
+            /// Fails if the address is of another family.
+            fn try_from(addr: SocketAddr) -> Result<$t, AddrFamilyError> {
+                match addr {
+                    SocketAddr::$variant(a) => Ok(a),
+                    _ => Err(AddrFamilyError {
+                        expected: AddrFamily::$family,
+                        found: addr.family(),
+                    }),
+                }
+            }
+        }
+    )*};
+}
+
+try_from_socket_addr! {
+    V4 => SocketAddrV4, Ipv4;
+    V6 => SocketAddrV6, Ipv6;
+    SCION => SocketAddrScion, Scion;
 }
 
 impl From<SocketAddrScion> for SocketAddr {
@@ -218,6 +798,40 @@ impl From<SocketAddrScion> for SocketAddr {
     }
 }
 
+impl SocketAddr {
+    /// The length of the longest textual form of an address, a SCION one,
+    /// see [`SocketAddrScion::MAX_DISPLAY_LEN`].
+    pub const MAX_DISPLAY_LEN: usize = SocketAddrScion::MAX_DISPLAY_LEN;
+
+    /// Writes the textual form of the address to `w`, without allocating.
+    ///
+    /// ```
+    /// use scionnet::SocketAddr;
+    /// use std::fmt::Write;
+    ///
+    /// let mut line = String::from("peer ");
+    /// let addr: SocketAddr = "1-ff00:0:110,10.0.0.1:53".parse().unwrap();
+    /// addr.write_to(&mut line).unwrap();
+    /// assert_eq!(line, "peer 1-ff00:0:110,10.0.0.1:53");
+    /// ```
+    pub fn write_to<W: Write>(&self, w: &mut W) -> fmt::Result {
+        match self {
+            SocketAddr::V4(a) => write!(w, "{}", a),
+            SocketAddr::V6(a) => write!(w, "{}", a),
+            SocketAddr::SCION(a) => a.write_to(w),
+        }
+    }
+
+    /// Returns the textual form of the address in a stack buffer.
+    #[must_use]
+    pub fn to_display_buffer(&self) -> DisplayBuffer<{ SocketAddr::MAX_DISPLAY_LEN }> {
+        let mut buf = DisplayBuffer::new();
+        // the buffer is long enough for the longest address
+        self.write_to(&mut buf).unwrap();
+        buf
+    }
+}
+
 impl fmt::Display for SocketAddr {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match *self {
@@ -228,18 +842,29 @@ impl fmt::Display for SocketAddr {
     }
 }
 
-impl From<std::net::SocketAddr> for SocketAddr{
-    fn from(sock6: std::net::SocketAddr) -> SocketAddr
-    {
-        SocketAddr::from_str(&sock6.to_string() ).unwrap()
+impl From<std::net::SocketAddr> for SocketAddr {
+    #[inline]
+    fn from(addr: std::net::SocketAddr) -> SocketAddr {
+        match addr {
+            std::net::SocketAddr::V4(a) => SocketAddr::V4(a.into()),
+            std::net::SocketAddr::V6(a) => SocketAddr::V6(a.into()),
+        }
     }
 }
 
-impl Into<std::net::SocketAddr> for SocketAddr
-{
-    /// might Err when self is a Scion variant
-    fn into(self) -> std::net::SocketAddr
-    {
-        std::net::SocketAddr::from_str( &self.to_string() ).unwrap()
+impl TryFrom<SocketAddr> for std::net::SocketAddr {
+    type Error = std::io::Error;
+
+    /// Fails for SCION addresses, which std cannot represent.
+    #[inline]
+    fn try_from(addr: SocketAddr) -> std::io::Result<std::net::SocketAddr> {
+        match addr {
+            SocketAddr::V4(a) => Ok(std::net::SocketAddr::V4(a.into())),
+            SocketAddr::V6(a) => Ok(std::net::SocketAddr::V6(a.into())),
+            SocketAddr::SCION(_) => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "a SCION socket address has no std::net counterpart",
+            )),
+        }
     }
 }
\ No newline at end of file
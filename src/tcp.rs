@@ -0,0 +1,373 @@
+use crate::sock_addr_traits::ToScionSocketAddrs;
+use crate::udp_socket::ip_socket_addr;
+use crate::{IsdAsn, RemoteEndpoint, SocketAddr, SocketAddrScion};
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+/// A reliable, ordered byte stream between two SCION endpoints,
+/// as provided by a [`StreamTransport`].
+pub trait TransportStream: Read + Write + Send {
+    fn local_addr(&self) -> io::Result<SocketAddrScion>;
+    fn peer_addr(&self) -> io::Result<SocketAddrScion>;
+    fn shutdown(&self, how: Shutdown) -> io::Result<()>;
+    fn set_read_timeout(&self, dur: Option<Duration>) -> io::Result<()>;
+    fn set_write_timeout(&self, dur: Option<Duration>) -> io::Result<()>;
+}
+
+/// The accepting side of a [`StreamTransport`].
+pub trait TransportListener: Send + Sync {
+    fn accept(&self) -> io::Result<Box<dyn TransportStream>>;
+    fn local_addr(&self) -> io::Result<SocketAddrScion>;
+}
+
+/// Something that carries [`ScionTcpStream`]s, i.e. QUIC over SCION.
+pub trait StreamTransport: Send + Sync {
+    /// Opens a stream to `addr`, giving up after `timeout` if one is set.
+    fn connect(
+        &self,
+        addr: &SocketAddrScion,
+        timeout: Option<Duration>,
+    ) -> io::Result<Box<dyn TransportStream>>;
+
+    /// Listens for incoming streams on `addr`.
+    fn bind(&self, addr: &SocketAddrScion) -> io::Result<Box<dyn TransportListener>>;
+}
+
+/// How long [`IpTcpTransport`] listeners wait for the preamble of a new
+/// connection by default.
+pub const DEFAULT_PREAMBLE_TIMEOUT: Duration = Duration::from_secs(5);
+
+static DEFAULT_TRANSPORT: OnceLock<Arc<dyn StreamTransport>> = OnceLock::new();
+
+/// Sets the transport used by [`ScionTcpStream::connect`] and
+/// [`ScionTcpListener::bind`].
+///
+/// The default transport can only be set once; the transport is handed back
+/// if one is set already.
+pub fn set_default_transport(
+    transport: Arc<dyn StreamTransport>,
+) -> Result<(), Arc<dyn StreamTransport>> {
+    DEFAULT_TRANSPORT.set(transport)
+}
+
+fn default_transport() -> io::Result<&'static dyn StreamTransport> {
+    DEFAULT_TRANSPORT
+        .get()
+        .map(|t| &**t)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::Unsupported, "no default stream transport set"))
+}
+
+/// Calls `f` for each address `addr` resolves to,
+/// returning the first success or the last error.
+fn each_addr<A, T, F>(addr: A, mut f: F) -> io::Result<T>
+where
+    A: ToScionSocketAddrs,
+    F: FnMut(&SocketAddrScion) -> io::Result<T>,
+{
+    let mut last_err = None;
+    for addr in addr.to_scion_socket_addrs()? {
+        match f(&addr) {
+            Ok(v) => return Ok(v),
+            Err(e) => last_err = Some(e),
+        }
+    }
+    Err(last_err.unwrap_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "could not resolve to any addresses")
+    }))
+}
+
+/// A stream between a local and a remote SCION socket, the SCION counterpart of
+/// [`std::net::TcpStream`].
+///
+/// The bytes are carried by a [`StreamTransport`], either the default one set
+/// with [`set_default_transport`] or one given to [`connect_with`](Self::connect_with).
+pub struct ScionTcpStream {
+    inner: Box<dyn TransportStream>,
+}
+
+impl ScionTcpStream {
+    /// Opens a stream to `addr` over the default transport.
+    ///
+    /// If `addr` yields multiple addresses, a connection is attempted with each
+    /// of the addresses until one succeeds.
+    pub fn connect<A: ToScionSocketAddrs>(addr: A) -> io::Result<ScionTcpStream> {
+        Self::connect_with(default_transport()?, addr)
+    }
+
+    /// Opens a stream to `addr` over `transport`.
+    pub fn connect_with<A: ToScionSocketAddrs>(
+        transport: &dyn StreamTransport,
+        addr: A,
+    ) -> io::Result<ScionTcpStream> {
+        each_addr(addr, |a| transport.connect(a, None)).map(ScionTcpStream::from_transport)
+    }
+
+    /// Opens a stream to the first reachable SCION candidate of `remote` over
+    /// the default transport, updating its health; see
+    /// [`RemoteEndpoint::connect_with`].
+    pub fn connect_endpoint(remote: &mut RemoteEndpoint) -> io::Result<ScionTcpStream> {
+        Self::connect_endpoint_with(default_transport()?, remote)
+    }
+
+    /// Opens a stream to the first reachable SCION candidate of `remote`
+    /// over `transport`.
+    pub fn connect_endpoint_with(
+        transport: &dyn StreamTransport,
+        remote: &mut RemoteEndpoint,
+    ) -> io::Result<ScionTcpStream> {
+        remote
+            .connect_with(|addr| each_addr(addr, |a| transport.connect(a, None)))
+            .map(ScionTcpStream::from_transport)
+    }
+
+    /// Opens a stream to `addr` over the default transport with a timeout.
+    pub fn connect_timeout(addr: &SocketAddrScion, timeout: Duration) -> io::Result<ScionTcpStream> {
+        default_transport()?
+            .connect(addr, Some(timeout))
+            .map(ScionTcpStream::from_transport)
+    }
+
+    /// Wraps a stream of a custom transport.
+    pub fn from_transport(inner: Box<dyn TransportStream>) -> ScionTcpStream {
+        ScionTcpStream { inner }
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddrScion> {
+        self.inner.local_addr()
+    }
+
+    pub fn peer_addr(&self) -> io::Result<SocketAddrScion> {
+        self.inner.peer_addr()
+    }
+
+    pub fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        self.inner.shutdown(how)
+    }
+
+    pub fn set_read_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        self.inner.set_read_timeout(dur)
+    }
+
+    pub fn set_write_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        self.inner.set_write_timeout(dur)
+    }
+}
+
+impl Read for ScionTcpStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl Write for ScionTcpStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl fmt::Debug for ScionTcpStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_struct("ScionTcpStream");
+        if let Ok(addr) = self.local_addr() {
+            d.field("addr", &format_args!("{}", addr));
+        }
+        if let Ok(peer) = self.peer_addr() {
+            d.field("peer", &format_args!("{}", peer));
+        }
+        d.finish()
+    }
+}
+
+/// A SCION socket server listening for streams, the SCION counterpart of
+/// [`std::net::TcpListener`].
+pub struct ScionTcpListener {
+    inner: Box<dyn TransportListener>,
+}
+
+impl ScionTcpListener {
+    /// Listens on `addr` with the default transport.
+    pub fn bind<A: ToScionSocketAddrs>(addr: A) -> io::Result<ScionTcpListener> {
+        Self::bind_with(default_transport()?, addr)
+    }
+
+    /// Listens on `addr` with `transport`.
+    pub fn bind_with<A: ToScionSocketAddrs>(
+        transport: &dyn StreamTransport,
+        addr: A,
+    ) -> io::Result<ScionTcpListener> {
+        each_addr(addr, |a| transport.bind(a)).map(|inner| ScionTcpListener { inner })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddrScion> {
+        self.inner.local_addr()
+    }
+
+    /// Accepts a new incoming stream, returning it with the remote address.
+    pub fn accept(&self) -> io::Result<(ScionTcpStream, SocketAddr)> {
+        let stream = ScionTcpStream::from_transport(self.inner.accept()?);
+        let peer = stream.peer_addr()?;
+        Ok((stream, SocketAddr::SCION(peer)))
+    }
+
+    /// Returns an iterator over the incoming streams, which never returns `None`.
+    pub fn incoming(&self) -> Incoming<'_> {
+        Incoming { listener: self }
+    }
+}
+
+impl fmt::Debug for ScionTcpListener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_struct("ScionTcpListener");
+        if let Ok(addr) = self.local_addr() {
+            d.field("addr", &format_args!("{}", addr));
+        }
+        d.finish()
+    }
+}
+
+/// An iterator that infinitely [`accept`]s streams on a [`ScionTcpListener`].
+///
+/// [`accept`]: ScionTcpListener::accept
+pub struct Incoming<'a> {
+    listener: &'a ScionTcpListener,
+}
+
+impl Iterator for Incoming<'_> {
+    type Item = io::Result<ScionTcpStream>;
+
+    fn next(&mut self) -> Option<io::Result<ScionTcpStream>> {
+        Some(self.listener.accept().map(|p| p.0))
+    }
+}
+
+/// A [`StreamTransport`] that carries the streams in plain TCP connections
+/// to the host address, for SCION hosts that are also reachable over IP.
+///
+/// The connecting side announces its ISD-AS in an 8 byte preamble, so that
+/// both ends know the full SCION address of their peer. Nothing checks the
+/// announcement: the IA of an accepted stream's peer is only what the client
+/// claims, and must not be trusted for access control.
+///
+/// Accepting a connection fails if its preamble does not arrive within the
+/// [preamble timeout](Self::preamble_timeout), so clients that send nothing
+/// cannot stall the listener for longer.
+pub struct IpTcpTransport {
+    local_ia: IsdAsn,
+    preamble_timeout: Duration,
+}
+
+impl IpTcpTransport {
+    /// Creates a transport for a host in `local_ia`.
+    pub fn new(local_ia: IsdAsn) -> IpTcpTransport {
+        IpTcpTransport { local_ia, preamble_timeout: DEFAULT_PREAMBLE_TIMEOUT }
+    }
+
+    /// Sets how long listeners wait for the preamble of a new connection,
+    /// [`DEFAULT_PREAMBLE_TIMEOUT`] by default.
+    #[must_use]
+    pub fn preamble_timeout(mut self, timeout: Duration) -> IpTcpTransport {
+        self.preamble_timeout = timeout;
+        self
+    }
+}
+
+impl StreamTransport for IpTcpTransport {
+    fn connect(
+        &self,
+        addr: &SocketAddrScion,
+        timeout: Option<Duration>,
+    ) -> io::Result<Box<dyn TransportStream>> {
+        let target = ip_socket_addr(addr)?;
+        let mut stream = match timeout {
+            Some(timeout) => TcpStream::connect_timeout(&target, timeout)?,
+            None => TcpStream::connect(target)?,
+        };
+        stream.write_all(&self.local_ia.to_u64().to_be_bytes())?;
+        Ok(Box::new(IpTcpStream { stream, local_ia: self.local_ia, peer_ia: addr.ia() }))
+    }
+
+    fn bind(&self, addr: &SocketAddrScion) -> io::Result<Box<dyn TransportListener>> {
+        let listener = TcpListener::bind(ip_socket_addr(addr)?)?;
+        let preamble_timeout = self.preamble_timeout;
+        Ok(Box::new(IpTcpListener { listener, local_ia: self.local_ia, preamble_timeout }))
+    }
+}
+
+struct IpTcpStream {
+    stream: TcpStream,
+    local_ia: IsdAsn,
+    peer_ia: u64,
+}
+
+impl Read for IpTcpStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.stream.read(buf)
+    }
+}
+
+impl Write for IpTcpStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stream.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+impl TransportStream for IpTcpStream {
+    fn local_addr(&self) -> io::Result<SocketAddrScion> {
+        let a = self.stream.local_addr()?;
+        Ok(SocketAddrScion::from_ia(self.local_ia, a.ip(), a.port()))
+    }
+
+    fn peer_addr(&self) -> io::Result<SocketAddrScion> {
+        let a = self.stream.peer_addr()?;
+        Ok(SocketAddrScion::from_ia(IsdAsn::from(self.peer_ia), a.ip(), a.port()))
+    }
+
+    fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        self.stream.shutdown(how)
+    }
+
+    fn set_read_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        self.stream.set_read_timeout(dur)
+    }
+
+    fn set_write_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        self.stream.set_write_timeout(dur)
+    }
+}
+
+struct IpTcpListener {
+    listener: TcpListener,
+    local_ia: IsdAsn,
+    preamble_timeout: Duration,
+}
+
+impl TransportListener for IpTcpListener {
+    fn accept(&self) -> io::Result<Box<dyn TransportStream>> {
+        let (mut stream, _) = self.listener.accept()?;
+        let mut preamble = [0u8; 8];
+        stream.set_read_timeout(Some(self.preamble_timeout))?;
+        stream.read_exact(&mut preamble)?;
+        stream.set_read_timeout(None)?;
+        Ok(Box::new(IpTcpStream {
+            stream,
+            local_ia: self.local_ia,
+            peer_ia: u64::from_be_bytes(preamble),
+        }))
+    }
+
+    fn local_addr(&self) -> io::Result<SocketAddrScion> {
+        let a = self.listener.local_addr()?;
+        Ok(SocketAddrScion::from_ia(self.local_ia, a.ip(), a.port()))
+    }
+}
@@ -0,0 +1,291 @@
+//! Helpers for the tests of downstream crates: the proptest strategies of
+//! the `proptest` feature, and the conformance test vectors of the
+//! `test-vectors` feature.
+
+#[cfg(any(test, feature = "test-vectors"))]
+pub use self::vectors::{test_vectors, TestVector, VectorKind};
+
+#[cfg(feature = "proptest")]
+pub mod strategies {
+    //! [`proptest`] strategies for the address types.
+    //!
+    //! Besides uniformly random values, the strategies are biased towards
+    //! the edge cases parsers and formatters get wrong more often than chance
+    //! would find: wildcard ISDs and ASes, the largest AS number, BGP and
+    //! SCION-only AS numbers, IPv4-mapped IPv6 hosts, service addresses and
+    //! the ports 0 and 65535.
+    //!
+    //! Every generated value survives a round trip through `Display` and
+    //! `FromStr`, so the strategies fit property tests of anything that
+    //! reads or writes addresses:
+    //!
+    //! ```
+    //! use proptest::prelude::*;
+    //! use scionnet::testing::strategies::socket_addr;
+    //! use scionnet::SocketAddr;
+    //!
+    //! proptest!(|(addr in socket_addr())| {
+    //!     prop_assert_eq!(addr.to_string().parse::<SocketAddr>().unwrap(), addr);
+    //! });
+    //! ```
+
+    use crate::{
+        Asn, AsnPrefix, HostAddr, IpAddr, Ipv4Addr, Ipv6Addr, Isd, IsdAsn, IsdRange, IsdSet,
+        ScionAddr, SocketAddr, SocketAddrScion, SocketAddrV4, SocketAddrV6, Svc,
+    };
+    use proptest::prelude::*;
+    use std::convert::TryFrom;
+
+    /// The lowest and highest AS number of `2:0:0/16`, the public SCION-only
+    /// ASes.
+    const SCION_ONLY: (u64, u64) = (0x2_0000_0000, 0x2_ffff_ffff);
+
+    pub fn ipv4_addr() -> impl Strategy<Value = Ipv4Addr> {
+        prop_oneof![
+            1 => Just(Ipv4Addr::UNSPECIFIED),
+            1 => Just(Ipv4Addr::LOCALHOST),
+            1 => Just(Ipv4Addr::BROADCAST),
+            7 => any::<[u8; 4]>().prop_map(Ipv4Addr::from),
+        ]
+    }
+
+    /// IPv6 addresses, among them IPv4-mapped and link-local ones.
+    pub fn ipv6_addr() -> impl Strategy<Value = Ipv6Addr> {
+        prop_oneof![
+            1 => Just(Ipv6Addr::UNSPECIFIED),
+            1 => Just(Ipv6Addr::LOCALHOST),
+            2 => ipv4_addr().prop_map(|ip| ip.to_ipv6_mapped()),
+            1 => any::<u16>().prop_map(|id| Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, id)),
+            5 => any::<[u8; 16]>().prop_map(Ipv6Addr::from_octets),
+        ]
+    }
+
+    pub fn ip_addr() -> impl Strategy<Value = IpAddr> {
+        prop_oneof![ipv4_addr().prop_map(IpAddr::V4), ipv6_addr().prop_map(IpAddr::V6)]
+    }
+
+    pub fn isd() -> impl Strategy<Value = Isd> {
+        prop_oneof![
+            1 => Just(Isd::WILDCARD),
+            1 => Just(Isd::MAX),
+            6 => any::<u16>().prop_map(Isd::new),
+        ]
+    }
+
+    /// AS numbers, among them BGP and SCION-only ones, which are written in
+    /// different forms.
+    pub fn asn() -> impl Strategy<Value = Asn> {
+        let asn = |n: u64| Asn::try_from(n).expect("an AS number within 48 bits");
+        prop_oneof![
+            1 => Just(Asn::WILDCARD),
+            1 => Just(Asn::MAX),
+            2 => (1..=u64::from(u32::MAX)).prop_map(asn),
+            2 => (SCION_ONLY.0..=SCION_ONLY.1).prop_map(asn),
+            4 => (0..=Asn::MAX.to_u64()).prop_map(asn),
+        ]
+    }
+
+    pub fn isd_asn() -> impl Strategy<Value = IsdAsn> {
+        prop_oneof![
+            1 => Just(IsdAsn::WILDCARD),
+            9 => (isd(), asn()).prop_map(|(isd, asn)| IsdAsn::new(isd, asn)),
+        ]
+    }
+
+    pub fn asn_prefix() -> impl Strategy<Value = AsnPrefix> {
+        (asn(), 0..=Asn::BITS as u8)
+            .prop_map(|(asn, len)| AsnPrefix::new(asn, len).expect("a length of at most 48"))
+    }
+
+    pub fn isd_range() -> impl Strategy<Value = IsdRange> {
+        (isd(), isd())
+            .prop_map(|(a, b)| IsdRange::new(a.min(b), a.max(b)).expect("an ordered range"))
+    }
+
+    /// Sets of up to 4 ranges, which may overlap.
+    pub fn isd_set() -> impl Strategy<Value = IsdSet> {
+        proptest::collection::vec(isd_range(), 0..=4).prop_map(IsdSet::from_iter)
+    }
+
+    /// Service addresses, both the named and the anycast and multicast ones.
+    pub fn svc() -> impl Strategy<Value = Svc> {
+        let named = prop_oneof![Just(Svc::DS), Just(Svc::CS), Just(Svc::WILDCARD)];
+        prop_oneof![
+            2 => named.clone(),
+            1 => named.prop_map(Svc::multicast),
+            1 => any::<u16>().prop_map(Svc::new),
+        ]
+    }
+
+    pub fn host_addr() -> impl Strategy<Value = HostAddr> {
+        prop_oneof![
+            3 => ip_addr().prop_map(HostAddr::Ip),
+            1 => svc().prop_map(HostAddr::Svc),
+        ]
+    }
+
+    pub fn scion_addr() -> impl Strategy<Value = ScionAddr> {
+        (isd_asn(), host_addr()).prop_map(|(ia, host)| ScionAddr::from_ia_host(ia, host))
+    }
+
+    pub fn port() -> impl Strategy<Value = u16> {
+        prop_oneof![
+            1 => Just(0),
+            1 => Just(u16::MAX),
+            6 => any::<u16>(),
+        ]
+    }
+
+    pub fn socket_addr_v4() -> impl Strategy<Value = SocketAddrV4> {
+        (ipv4_addr(), port()).prop_map(|(ip, port)| SocketAddrV4::new(ip, port))
+    }
+
+    /// IPv6 socket addresses, with and without a scope id. The flow info is
+    /// always 0, as the textual form cannot carry it.
+    pub fn socket_addr_v6() -> impl Strategy<Value = SocketAddrV6> {
+        let scope_id = prop_oneof![3 => Just(0), 1 => any::<u32>()];
+        (ipv6_addr(), port(), scope_id)
+            .prop_map(|(ip, port, scope_id)| SocketAddrV6::new(ip, port, 0, scope_id))
+    }
+
+    pub fn socket_addr_scion() -> impl Strategy<Value = SocketAddrScion> {
+        (scion_addr(), port()).prop_map(|(addr, port)| SocketAddrScion::from_scion_addr(addr, port))
+    }
+
+    pub fn socket_addr() -> impl Strategy<Value = SocketAddr> {
+        prop_oneof![
+            socket_addr_v4().prop_map(SocketAddr::V4),
+            socket_addr_v6().prop_map(SocketAddr::V6),
+            socket_addr_scion().prop_map(SocketAddr::SCION),
+        ]
+    }
+}
+
+#[cfg(any(test, feature = "test-vectors"))]
+mod vectors {
+    use self::VectorKind::*;
+
+    /// What the input of a [`TestVector`] is parsed as.
+    #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+    #[non_exhaustive]
+    pub enum VectorKind {
+        Isd,
+        Asn,
+        IsdAsn,
+        ScionAddr,
+    }
+
+    /// A parsing and formatting test vector of the SCION reference
+    /// implementation.
+    #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+    pub struct TestVector {
+        pub kind: VectorKind,
+        pub input: &'static str,
+        /// The canonical text form of the parsed value, or `None` if the
+        /// input is invalid.
+        pub canonical: Option<&'static str>,
+        /// The parsed value as an integer, for valid ISDs, ASes and IAs. The
+        /// ISD of an IA takes the upper 16 bits, the AS the lower 48.
+        pub value: Option<u64>,
+    }
+
+    const fn valid(
+        kind: VectorKind,
+        input: &'static str,
+        canonical: &'static str,
+        value: u64,
+    ) -> TestVector {
+        TestVector { kind, input, canonical: Some(canonical), value: Some(value) }
+    }
+
+    const fn valid_addr(input: &'static str, canonical: &'static str) -> TestVector {
+        TestVector { kind: ScionAddr, input, canonical: Some(canonical), value: None }
+    }
+
+    const fn invalid(kind: VectorKind, input: &'static str) -> TestVector {
+        TestVector { kind, input, canonical: None, value: None }
+    }
+
+    // the cases of `pkg/addr` in scionproto/scion, the Go implementation
+    const VECTORS: &[TestVector] = &[
+        valid(Isd, "0", "0", 0),
+        valid(Isd, "1", "1", 1),
+        valid(Isd, "65535", "65535", 65535),
+        invalid(Isd, ""),
+        invalid(Isd, "65536"),
+        invalid(Isd, "-1"),
+        invalid(Isd, "a"),
+        invalid(Isd, "0x1"),
+        // decimal AS numbers are BGP AS numbers of 32 bits
+        valid(Asn, "0", "0", 0),
+        valid(Asn, "1", "1", 1),
+        valid(Asn, "42", "42", 42),
+        valid(Asn, "4294967295", "4294967295", 0xffff_ffff),
+        invalid(Asn, "4294967296"),
+        invalid(Asn, ""),
+        invalid(Asn, "0x0"),
+        invalid(Asn, "ff"),
+        // all others are three groups of hex digits, printed in decimal if
+        // they are in the BGP range
+        valid(Asn, "0:0:0", "0", 0),
+        valid(Asn, "0:0:1", "1", 1),
+        valid(Asn, "0:ffff:ffff", "4294967295", 0xffff_ffff),
+        valid(Asn, "1:0:0", "1:0:0", 0x1_0000_0000),
+        valid(Asn, "1:fcd1:1", "1:fcd1:1", 0x1_fcd1_0001),
+        valid(Asn, "ff00:0:110", "ff00:0:110", 0xff00_0000_0110),
+        valid(Asn, "FFFF:0:0", "ffff:0:0", 0xffff_0000_0000),
+        valid(Asn, "ffff:ffff:ffff", "ffff:ffff:ffff", 0xffff_ffff_ffff),
+        invalid(Asn, "0:0:0:"),
+        invalid(Asn, ":0:0:"),
+        invalid(Asn, "0:0"),
+        invalid(Asn, "0:0:0:0"),
+        invalid(Asn, "10000:0:0"),
+        invalid(Asn, "0:10000:0"),
+        invalid(Asn, "0:0:10000"),
+        invalid(Asn, "0:0x0:0"),
+        valid(IsdAsn, "0-0", "0-0", 0),
+        valid(IsdAsn, "1-1", "1-1", 0x0001_0000_0000_0001),
+        valid(IsdAsn, "65535-1", "65535-1", 0xffff_0000_0000_0001),
+        valid(IsdAsn, "1-4294967295", "1-4294967295", 0x0001_0000_ffff_ffff),
+        valid(IsdAsn, "1-1:0:0", "1-1:0:0", 0x0001_0001_0000_0000),
+        valid(IsdAsn, "1-1:fcd1:1", "1-1:fcd1:1", 0x0001_0001_fcd1_0001),
+        valid(IsdAsn, "1-ff00:0:110", "1-ff00:0:110", 0x0001_ff00_0000_0110),
+        valid(IsdAsn, "65535-ffff:ffff:ffff", "65535-ffff:ffff:ffff", u64::MAX),
+        invalid(IsdAsn, ""),
+        invalid(IsdAsn, "a"),
+        invalid(IsdAsn, "1a-2b"),
+        invalid(IsdAsn, "-"),
+        invalid(IsdAsn, "1-"),
+        invalid(IsdAsn, "-1"),
+        invalid(IsdAsn, "-1-"),
+        invalid(IsdAsn, "1--1"),
+        invalid(IsdAsn, "65536-1"),
+        invalid(IsdAsn, "1-4294967296"),
+        invalid(IsdAsn, "65536-ffff:ffff:ffff"),
+        valid_addr("1-ff00:0:110,192.0.2.1", "1-ff00:0:110,192.0.2.1"),
+        valid_addr("1-ff00:0:110,CS", "1-ff00:0:110,CS"),
+        valid_addr("1-ff00:0:110,DS_M", "1-ff00:0:110,DS_M"),
+        valid_addr("1-ff00:0:110,Wildcard", "1-ff00:0:110,Wildcard"),
+        invalid(ScionAddr, "1-ff00:0:110"),
+        invalid(ScionAddr, "1-ff00:0:110,"),
+        invalid(ScionAddr, "1-ff00:0:110,192.0.2.256"),
+        invalid(ScionAddr, "1-ff00:0,192.0.2.1"),
+    ];
+
+    /// Returns the parsing and formatting test vectors of the SCION reference
+    /// implementation, which this crate passes, for checking that other
+    /// implementations agree with it.
+    ///
+    /// ```
+    /// use scionnet::testing::{test_vectors, VectorKind};
+    /// use scionnet::IsdAsn;
+    ///
+    /// for v in test_vectors().filter(|v| v.kind == VectorKind::IsdAsn) {
+    ///     let parsed = v.input.parse::<IsdAsn>().ok();
+    ///     assert_eq!(parsed.map(|ia| ia.to_u64()), v.value);
+    /// }
+    /// ```
+    pub fn test_vectors() -> impl Iterator<Item = TestVector> {
+        VECTORS.iter().copied()
+    }
+}
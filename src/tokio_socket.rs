@@ -0,0 +1,135 @@
+//! Async SCION sockets for the tokio runtime, enabled by the `tokio` feature.
+
+use crate::sock_addr_traits::ToScionSocketAddrs;
+use crate::udp_socket::{bind_first, decode_packet, first_addr, SocketState, MAX_DATAGRAM};
+use crate::{IsdAsn, ScionRoute, ScionUdpSocket, SocketAddr, SocketAddrScion};
+use std::future::poll_fn;
+use std::io;
+use std::sync::Mutex;
+use std::task::{Context, Poll};
+use tokio::io::ReadBuf;
+use tokio::net::UdpSocket;
+
+/// The async counterpart of [`ScionUdpSocket`], driven by the tokio reactor.
+///
+/// Packets are sent and routed exactly like with [`ScionUdpSocket`].
+pub struct AsyncScionUdpSocket {
+    underlay: UdpSocket,
+    state: SocketState,
+    recv_buf: Mutex<Box<[u8]>>,
+}
+
+impl AsyncScionUdpSocket {
+    fn from_parts(underlay: UdpSocket, state: SocketState) -> AsyncScionUdpSocket {
+        AsyncScionUdpSocket {
+            underlay,
+            state,
+            recv_buf: Mutex::new(vec![0u8; MAX_DATAGRAM].into_boxed_slice()),
+        }
+    }
+
+    /// Creates a socket bound to `addr`, see [`ScionUdpSocket::bind`].
+    ///
+    /// Resolving names in `addr` blocks the current thread.
+    pub async fn bind<A: ToScionSocketAddrs>(addr: A) -> io::Result<AsyncScionUdpSocket> {
+        let (underlay, local) = bind_first(addr, |a| {
+            let socket = std::net::UdpSocket::bind(a)?;
+            let port = socket.local_addr()?.port();
+            Ok((socket, port))
+        })?;
+        Self::from_std(ScionUdpSocket::from_bound(underlay, local))
+    }
+
+    /// Registers a blocking socket with the tokio reactor, keeping its routes.
+    ///
+    /// Must be called from within a tokio runtime.
+    pub fn from_std(socket: ScionUdpSocket) -> io::Result<AsyncScionUdpSocket> {
+        let (underlay, state) = socket.into_parts();
+        underlay.set_nonblocking(true)?;
+        Ok(Self::from_parts(UdpSocket::from_std(underlay)?, state))
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddrScion> {
+        Ok(self.state.local())
+    }
+
+    /// Returns the underlying UDP socket.
+    pub fn underlay(&self) -> &UdpSocket {
+        &self.underlay
+    }
+
+    /// Sets the route used for packets to hosts in `ia`.
+    pub fn set_route(&self, ia: IsdAsn, route: ScionRoute) {
+        self.state.set_route(ia, route)
+    }
+
+    /// Removes the route for `ia` and returns it.
+    pub fn remove_route(&self, ia: IsdAsn) -> Option<ScionRoute> {
+        self.state.remove_route(ia)
+    }
+
+    /// Returns the route currently used for packets to `ia`.
+    pub fn route(&self, ia: IsdAsn) -> Option<ScionRoute> {
+        self.state.route(ia)
+    }
+
+    /// Sets the `dispatched_ports` range of the local AS, see
+    /// [`ScionUdpSocket::set_dispatched_ports`](crate::ScionUdpSocket::set_dispatched_ports).
+    pub fn set_dispatched_ports(&self, range: Option<(u16, u16)>) {
+        self.state.set_dispatched_ports(range)
+    }
+
+    pub fn dispatched_ports(&self) -> Option<(u16, u16)> {
+        self.state.dispatched_ports()
+    }
+
+    /// Attempts to send `buf` to `target`.
+    ///
+    /// Returns the number of payload bytes sent, or `Poll::Pending` after
+    /// registering the waker of `cx` if the underlay socket is not writable.
+    pub fn poll_send_to(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+        target: &SocketAddrScion,
+    ) -> Poll<io::Result<usize>> {
+        let (packet, next_hop) = self.state.encode(buf, target)?;
+        self.underlay.poll_send_to(cx, &packet, next_hop).map_ok(|_| buf.len())
+    }
+
+    /// Attempts to receive a single SCION/UDP datagram, skipping anything else.
+    ///
+    /// Returns the number of payload bytes read and the sender's address.
+    /// Payload bytes that do not fit into `buf` are discarded.
+    pub fn poll_recv_from(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<(usize, SocketAddr)>> {
+        let mut packet = self.recv_buf.lock().unwrap();
+        loop {
+            let mut read_buf = ReadBuf::new(&mut packet);
+            if let Err(e) = std::task::ready!(self.underlay.poll_recv_from(cx, &mut read_buf)) {
+                return Poll::Ready(Err(e));
+            }
+            if let Some((src, payload)) = decode_packet(read_buf.filled()) {
+                let n = payload.len().min(buf.len());
+                buf[..n].copy_from_slice(&payload[..n]);
+                return Poll::Ready(Ok((n, SocketAddr::SCION(src))));
+            }
+        }
+    }
+
+    /// Sends `buf` to the first SCION address `target` resolves to.
+    ///
+    /// Resolving names in `target` blocks the current thread.
+    pub async fn send_to<A: ToScionSocketAddrs>(&self, buf: &[u8], target: A) -> io::Result<usize> {
+        let target = first_addr(target)?;
+        poll_fn(|cx| self.poll_send_to(cx, buf, &target)).await
+    }
+
+    /// Receives a single SCION/UDP datagram, see [`poll_recv_from`](Self::poll_recv_from).
+    pub async fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        poll_fn(|cx| self.poll_recv_from(cx, buf)).await
+    }
+}
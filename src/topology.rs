@@ -0,0 +1,550 @@
+use crate::underlay::underlay_addr;
+use crate::{diff_topologies, HostAddr, IsdAsn, ScionAddr, SocketAddrScion, Svc, TopologyDiff};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// The relation of an AS to the neighbor behind a border router interface.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum LinkType {
+    Core,
+    Parent,
+    Child,
+    Peer,
+}
+
+impl LinkType {
+    /// Returns the `link_to` value of the link type in a topology.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            LinkType::Core => "core",
+            LinkType::Parent => "parent",
+            LinkType::Child => "child",
+            LinkType::Peer => "peer",
+        }
+    }
+}
+
+impl fmt::Display for LinkType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for LinkType {
+    type Err = io::Error;
+
+    /// Parses the `link_to` value of a topology, ignoring case.
+    fn from_str(s: &str) -> io::Result<LinkType> {
+        match s.to_ascii_lowercase().as_str() {
+            "core" => Ok(LinkType::Core),
+            "parent" => Ok(LinkType::Parent),
+            "child" => Ok(LinkType::Child),
+            "peer" => Ok(LinkType::Peer),
+            _ => Err(invalid_topology(format!("unknown link type '{}'", s))),
+        }
+    }
+}
+
+/// An inter-AS interface of a border router.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BrInterface {
+    /// The AS on the other side of the link.
+    pub neighbor: IsdAsn,
+    pub link_to: LinkType,
+    /// The MTU of the link.
+    pub mtu: u16,
+    /// The local underlay address of the link.
+    pub local: std::net::SocketAddr,
+    /// The underlay address of the neighbor's border router, unset for
+    /// links that only learn it at runtime.
+    pub remote: Option<std::net::SocketAddr>,
+}
+
+/// A border router of the local AS.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BorderRouter {
+    /// The address the border router accepts SCION packets from hosts of the
+    /// local AS on, i.e. the next hop of packets leaving through it.
+    pub internal_addr: std::net::SocketAddr,
+    /// The interfaces of the router, keyed by interface id.
+    pub interfaces: BTreeMap<u64, BrInterface>,
+}
+
+/// The topology of the local AS, as described by the `topology.json` the SCION
+/// services of an AS are configured with.
+///
+/// It contains everything needed to send packets without the SCION daemon:
+/// the local ISD-AS and the underlay addresses of the border routers.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Topology {
+    pub ia: IsdAsn,
+    /// Whether the local AS is a core AS.
+    pub core: bool,
+    /// The MTU within the AS.
+    pub mtu: u16,
+    /// The port range of the end hosts, `dispatched_ports` in the JSON.
+    pub dispatched_ports: Option<(u16, u16)>,
+    /// The border routers, keyed by name.
+    pub border_routers: BTreeMap<String, BorderRouter>,
+    /// The addresses of the control services, keyed by name.
+    pub control_services: BTreeMap<String, std::net::SocketAddr>,
+    /// The members not covered by the fields above, at the place they were
+    /// read from, i.e. the `bfd` settings of an interface or attributes
+    /// other than `core`. [`to_json`](Topology::to_json) writes them back.
+    pub extra: Map<String, Value>,
+}
+
+fn invalid_topology(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn field<'a>(obj: &'a Map<String, Value>, key: &str, ctx: &str) -> io::Result<&'a Value> {
+    obj.get(key)
+        .ok_or_else(|| invalid_topology(format!("{}: missing field '{}'", ctx, key)))
+}
+
+fn as_object<'a>(v: &'a Value, ctx: &str) -> io::Result<&'a Map<String, Value>> {
+    v.as_object()
+        .ok_or_else(|| invalid_topology(format!("{}: expected an object", ctx)))
+}
+
+fn as_str<'a>(v: &'a Value, ctx: &str) -> io::Result<&'a str> {
+    v.as_str()
+        .ok_or_else(|| invalid_topology(format!("{}: expected a string", ctx)))
+}
+
+fn as_u16(v: &Value, ctx: &str) -> io::Result<u16> {
+    v.as_u64()
+        .and_then(|n| u16::try_from(n).ok())
+        .ok_or_else(|| invalid_topology(format!("{}: expected an integer up to 65535", ctx)))
+}
+
+fn parse_value<T: FromStr>(v: &Value, ctx: &str) -> io::Result<T> {
+    let s = as_str(v, ctx)?;
+    s.parse()
+        .map_err(|_| invalid_topology(format!("{}: invalid value '{}'", ctx, s)))
+}
+
+fn parse_ports(v: &Value) -> io::Result<(u16, u16)> {
+    let s = as_str(v, "dispatched_ports")?;
+    let range = s.split_once('-').and_then(|(lo, hi)| Some((lo.parse().ok()?, hi.parse().ok()?)));
+    match range {
+        Some((lo, hi)) if lo <= hi => Ok((lo, hi)),
+        _ => Err(invalid_topology(format!("dispatched_ports: invalid port range '{}'", s))),
+    }
+}
+
+fn parse_interface(v: &Value, ctx: &str) -> io::Result<BrInterface> {
+    let obj = as_object(v, ctx)?;
+    let underlay = as_object(field(obj, "underlay", ctx)?, ctx)?;
+    // older topologies name the local underlay address "public"
+    let local = match underlay.get("local").or_else(|| underlay.get("public")) {
+        Some(v) => parse_value(v, ctx)?,
+        None => return Err(invalid_topology(format!("{}: missing local underlay address", ctx))),
+    };
+    let remote = match underlay.get("remote") {
+        Some(v) => Some(parse_value(v, ctx)?),
+        None => None,
+    };
+    Ok(BrInterface {
+        neighbor: parse_value(field(obj, "isd_as", ctx)?, ctx)?,
+        link_to: as_str(field(obj, "link_to", ctx)?, ctx)?.parse()?,
+        mtu: as_u16(field(obj, "mtu", ctx)?, ctx)?,
+        local,
+        remote,
+    })
+}
+
+fn parse_router(v: &Value, ctx: &str) -> io::Result<BorderRouter> {
+    let obj = as_object(v, ctx)?;
+    let mut interfaces = BTreeMap::new();
+    if let Some(intfs) = obj.get("interfaces") {
+        for (id, intf) in as_object(intfs, ctx)? {
+            let ctx = format!("{} interface {}", ctx, id);
+            let id: u64 = id
+                .parse()
+                .map_err(|_| invalid_topology(format!("{}: invalid interface id", ctx)))?;
+            interfaces.insert(id, parse_interface(intf, &ctx)?);
+        }
+    }
+    Ok(BorderRouter { internal_addr: parse_value(field(obj, "internal_addr", ctx)?, ctx)?, interfaces })
+}
+
+/// The members holding objects keyed by name, i.e. of border routers.
+const COLLECTIONS: [&str; 3] = ["border_routers", "control_service", "interfaces"];
+
+/// Returns the members of `obj` but `known`.
+fn without(obj: &Map<String, Value>, known: &[&str]) -> Map<String, Value> {
+    obj.iter()
+        .filter(|(key, _)| !known.contains(&key.as_str()))
+        .map(|(key, v)| (key.clone(), v.clone()))
+        .collect()
+}
+
+/// Returns the entries of the collection `v` reduced to their unknown
+/// members by `unknown`, leaving out those without any.
+fn unknown_entries(
+    v: Option<&Value>,
+    unknown: fn(&Map<String, Value>) -> Map<String, Value>,
+) -> Option<Value> {
+    let entries: Map<String, Value> = v
+        .and_then(Value::as_object)?
+        .iter()
+        .filter_map(|(name, entry)| Some((name.clone(), unknown(entry.as_object()?))))
+        .filter(|(_, members)| !members.is_empty())
+        .map(|(name, members)| (name, Value::Object(members)))
+        .collect();
+    (!entries.is_empty()).then_some(Value::Object(entries))
+}
+
+fn unknown_interface(intf: &Map<String, Value>) -> Map<String, Value> {
+    let mut unknown = without(intf, &["isd_as", "link_to", "mtu", "underlay"]);
+    if let Some(underlay) = intf.get("underlay").and_then(Value::as_object) {
+        let underlay = without(underlay, &["local", "public", "remote"]);
+        if !underlay.is_empty() {
+            unknown.insert("underlay".to_string(), Value::Object(underlay));
+        }
+    }
+    unknown
+}
+
+fn unknown_router(br: &Map<String, Value>) -> Map<String, Value> {
+    let mut unknown = without(br, &["internal_addr", "interfaces"]);
+    if let Some(interfaces) = unknown_entries(br.get("interfaces"), unknown_interface) {
+        unknown.insert("interfaces".to_string(), interfaces);
+    }
+    unknown
+}
+
+/// Returns the members of a topology the parser does not read.
+fn unknown_topology(root: &Map<String, Value>) -> Map<String, Value> {
+    let known = ["isd_as", "mtu", "dispatched_ports", "attributes"];
+    let mut unknown = without(root, &[&known[..], &["border_routers", "control_service"]].concat());
+    if let Some(Value::Array(attrs)) = root.get("attributes") {
+        let attrs: Vec<Value> =
+            attrs.iter().filter(|a| a.as_str() != Some("core")).cloned().collect();
+        if !attrs.is_empty() {
+            unknown.insert("attributes".to_string(), Value::Array(attrs));
+        }
+    }
+    if let Some(routers) = unknown_entries(root.get("border_routers"), unknown_router) {
+        unknown.insert("border_routers".to_string(), routers);
+    }
+    let unknown_service = |cs: &Map<String, Value>| without(cs, &["addr"]);
+    if let Some(services) = unknown_entries(root.get("control_service"), unknown_service) {
+        unknown.insert("control_service".to_string(), services);
+    }
+    unknown
+}
+
+/// Adds the members of `extra` to `obj` where it has none of its own.
+/// Arrays are appended to, and entries of collections `obj` does not have
+/// any more are left out.
+fn merge(obj: &mut Map<String, Value>, extra: &Map<String, Value>, named: bool) {
+    for (key, value) in extra {
+        let entries = !named && COLLECTIONS.contains(&key.as_str());
+        match (obj.get_mut(key), value) {
+            (Some(Value::Object(obj)), Value::Object(extra)) => merge(obj, extra, entries),
+            (Some(Value::Array(obj)), Value::Array(extra)) => obj.extend(extra.iter().cloned()),
+            (Some(_), _) => {}
+            (None, _) if named || entries => {}
+            (None, value) => {
+                obj.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+/// Returns `v` with the members of all its objects ordered by key.
+fn sorted(v: Value) -> Value {
+    match v {
+        Value::Object(members) => object(members.into_iter().map(|(k, v)| (k, sorted(v)))),
+        Value::Array(items) => Value::Array(items.into_iter().map(sorted).collect()),
+        v => v,
+    }
+}
+
+/// Returns an object with the given members, inserted in the order of
+/// their keys so the output does not depend on how `Map` orders them.
+fn object<K: Into<String>>(members: impl IntoIterator<Item = (K, Value)>) -> Value {
+    let members: BTreeMap<String, Value> = members.into_iter().map(|(k, v)| (k.into(), v)).collect();
+    Value::Object(members.into_iter().collect())
+}
+
+fn string<T: ToString>(v: T) -> Value {
+    Value::String(v.to_string())
+}
+
+fn write_interface(intf: &BrInterface) -> Value {
+    let mut underlay = vec![("local", string(intf.local))];
+    if let Some(remote) = intf.remote {
+        underlay.push(("remote", string(remote)));
+    }
+    object([
+        ("underlay", object(underlay)),
+        ("isd_as", string(intf.neighbor)),
+        ("link_to", string(intf.link_to)),
+        ("mtu", Value::from(intf.mtu)),
+    ])
+}
+
+fn write_router(br: &BorderRouter) -> Value {
+    let mut members = vec![("internal_addr", string(br.internal_addr))];
+    if !br.interfaces.is_empty() {
+        let interfaces = br.interfaces.iter().map(|(id, intf)| (id.to_string(), write_interface(intf)));
+        members.push(("interfaces", object(interfaces)));
+    }
+    object(members)
+}
+
+impl Topology {
+    /// Parses the contents of a `topology.json`.
+    pub fn from_json(json: &str) -> io::Result<Topology> {
+        let root: Value = serde_json::from_str(json).map_err(io::Error::from)?;
+        let root = as_object(&root, "topology")?;
+
+        let core = match root.get("attributes") {
+            Some(Value::Array(attrs)) => attrs.iter().any(|a| a.as_str() == Some("core")),
+            Some(_) => return Err(invalid_topology("attributes: expected an array".to_string())),
+            None => false,
+        };
+
+        let mut border_routers = BTreeMap::new();
+        if let Some(brs) = root.get("border_routers") {
+            for (name, br) in as_object(brs, "border_routers")? {
+                border_routers.insert(name.clone(), parse_router(br, name)?);
+            }
+        }
+
+        let mut control_services = BTreeMap::new();
+        if let Some(css) = root.get("control_service") {
+            for (name, cs) in as_object(css, "control_service")? {
+                let addr = field(as_object(cs, name)?, "addr", name)?;
+                control_services.insert(name.clone(), parse_value(addr, name)?);
+            }
+        }
+
+        Ok(Topology {
+            ia: parse_value(field(root, "isd_as", "topology")?, "isd_as")?,
+            core,
+            mtu: as_u16(field(root, "mtu", "topology")?, "mtu")?,
+            dispatched_ports: root.get("dispatched_ports").map(parse_ports).transpose()?,
+            border_routers,
+            control_services,
+            extra: unknown_topology(root),
+        })
+    }
+
+    /// Reads and parses the `topology.json` at `path`.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> io::Result<Topology> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+
+    /// Returns the topology as canonical JSON: members sorted by key, and so
+    /// are the attributes, two spaces of indentation and no trailing newline.
+    ///
+    /// The output parses back into an equal topology, and equal topologies
+    /// give identical output, so regenerated files can be diffed. Members the
+    /// parser does not read are written back from [`extra`](Topology::extra),
+    /// except for those of border routers, interfaces and control services
+    /// no longer in the topology. The `public` underlay address of older
+    /// topologies is written as `local`.
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        let mut root = vec![
+            ("isd_as", string(self.ia)),
+            ("mtu", Value::from(self.mtu)),
+        ];
+        if self.core {
+            root.push(("attributes", Value::Array(vec![string("core")])));
+        }
+        if let Some((lo, hi)) = self.dispatched_ports {
+            root.push(("dispatched_ports", Value::String(format!("{}-{}", lo, hi))));
+        }
+        if !self.border_routers.is_empty() {
+            let brs = self.border_routers.iter().map(|(name, br)| (name.clone(), write_router(br)));
+            root.push(("border_routers", object(brs)));
+        }
+        if !self.control_services.is_empty() {
+            let css = self
+                .control_services
+                .iter()
+                .map(|(name, addr)| (name.clone(), object([("addr", string(addr))])));
+            root.push(("control_service", object(css)));
+        }
+        let mut root: Map<String, Value> = root.into_iter().map(|(k, v)| (k.into(), v)).collect();
+        merge(&mut root, &self.extra, false);
+        if let Some(Value::Array(attrs)) = root.get_mut("attributes") {
+            attrs.sort_by(|a, b| a.as_str().cmp(&b.as_str()));
+        }
+        // serializing a `Value` cannot fail
+        serde_json::to_string_pretty(&sorted(Value::Object(root))).unwrap_or_default()
+    }
+
+    /// Writes the topology to `path` as canonical JSON, see [`to_json`](Self::to_json).
+    pub fn to_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut json = self.to_json();
+        json.push('\n');
+        std::fs::write(path, json)
+    }
+
+    /// Replaces the topology with `new`, i.e. the reloaded `topology.json`,
+    /// returning what changed, see [`diff_topologies`].
+    pub fn reload(&mut self, new: Topology) -> TopologyDiff {
+        let diff = diff_topologies(self, &new);
+        *self = new;
+        diff
+    }
+
+    /// Rereads the `topology.json` at `path` and [`reload`](Self::reload)s
+    /// it. The topology is left as it was if the file cannot be read.
+    pub fn reload_from_file<P: AsRef<Path>>(&mut self, path: P) -> io::Result<TopologyDiff> {
+        Ok(self.reload(Self::from_file(path)?))
+    }
+
+    /// Returns the interface with id `ifid` and the router it belongs to.
+    #[must_use]
+    pub fn interface(&self, ifid: u64) -> Option<(&BorderRouter, &BrInterface)> {
+        self.border_routers
+            .values()
+            .find_map(|br| br.interfaces.get(&ifid).map(|intf| (br, intf)))
+    }
+
+    /// Returns the internal address of the border router owning the interface
+    /// `ifid`, i.e. the underlay next hop of packets leaving the AS through it.
+    #[must_use]
+    pub fn next_hop(&self, ifid: u64) -> Option<std::net::SocketAddr> {
+        self.interface(ifid).map(|(br, _)| br.internal_addr)
+    }
+
+    /// Returns the SCION address of `host` within the local AS.
+    #[must_use]
+    pub fn scion_addr(&self, host: std::net::IpAddr) -> ScionAddr {
+        ScionAddr::from_ia(self.ia, host)
+    }
+
+    /// Returns the SCION address of this host, using the local address the
+    /// operating system picks to reach the first border router.
+    pub fn local_addr(&self) -> io::Result<ScionAddr> {
+        let br = self
+            .border_routers
+            .values()
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "topology has no border routers"))?;
+        let unspecified: std::net::IpAddr = match br.internal_addr {
+            std::net::SocketAddr::V4(_) => std::net::Ipv4Addr::UNSPECIFIED.into(),
+            std::net::SocketAddr::V6(_) => std::net::Ipv6Addr::UNSPECIFIED.into(),
+        };
+        // connecting a UDP socket sends nothing, it only selects the route
+        let probe = std::net::UdpSocket::bind((unspecified, 0))?;
+        probe.connect(br.internal_addr)?;
+        Ok(self.scion_addr(probe.local_addr()?.ip()))
+    }
+}
+
+/// Watches a `topology.json`, so that services pick up a reconfigured AS
+/// without restarting.
+///
+/// The file is reread on every [`poll`](Self::poll), which is cheap for the
+/// few kilobytes of a topology; it is only parsed again if its contents
+/// changed.
+#[derive(Clone, Debug)]
+pub struct TopologyWatcher {
+    path: PathBuf,
+    json: String,
+    topology: Topology,
+}
+
+impl TopologyWatcher {
+    /// Reads the topology at `path`.
+    pub fn new<P: Into<PathBuf>>(path: P) -> io::Result<TopologyWatcher> {
+        let path = path.into();
+        let json = std::fs::read_to_string(&path)?;
+        let topology = Topology::from_json(&json)?;
+        Ok(TopologyWatcher { path, json, topology })
+    }
+
+    /// Returns the topology as of the last successful read.
+    #[must_use]
+    pub fn topology(&self) -> &Topology {
+        &self.topology
+    }
+
+    /// Rereads the file, returning the changes of the topology if there are
+    /// any. If the file cannot be read or parsed, i.e. while it is being
+    /// rewritten, the topology is kept and the next poll tries again.
+    pub fn poll(&mut self) -> io::Result<Option<TopologyDiff>> {
+        let json = std::fs::read_to_string(&self.path)?;
+        if json == self.json {
+            return Ok(None);
+        }
+        let diff = self.topology.reload(Topology::from_json(&json)?);
+        self.json = json;
+        Ok(Some(diff).filter(|diff| !diff.is_empty()))
+    }
+}
+
+impl FromStr for Topology {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> io::Result<Topology> {
+        Topology::from_json(s)
+    }
+}
+
+impl SocketAddrScion {
+    /// Returns the underlay address a packet to this address along `path`
+    /// is sent to from the AS of `topology`: the internal address of the
+    /// border router owning the egress interface of the current hop field,
+    /// or the destination host itself if it is within the AS. The control
+    /// service address `CS` within the AS stands for the first control
+    /// service of `topology`.
+    ///
+    /// Fails if `path` points past its fields, its egress interface is not
+    /// in `topology` or no host within the AS provides the service.
+    pub fn underlay_next_hop(
+        &self,
+        topology: &Topology,
+        path: &crate::path::Path,
+    ) -> io::Result<std::net::SocketAddr> {
+        if self.ia() == topology.ia.to_u64() {
+            return match *self.host() {
+                HostAddr::Ip(ip) => Ok(underlay_addr(ip, self.port(), topology.dispatched_ports)),
+                HostAddr::Svc(svc) if svc.anycast() == Svc::CS => {
+                    topology.control_services.values().next().copied().ok_or_else(|| {
+                        let msg = "no control service in the topology";
+                        io::Error::new(io::ErrorKind::NotFound, msg)
+                    })
+                }
+                HostAddr::Svc(svc) => Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("no underlay address for the service {}", svc),
+                )),
+            };
+        }
+        let info = path.info_fields.get(usize::from(path.curr_inf));
+        let hop = path.hop_fields.get(usize::from(path.curr_hf));
+        let (info, hop) = match (info, hop) {
+            (Some(info), Some(hop)) => (info, hop),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "SCION path points past its last info or hop field",
+                ))
+            }
+        };
+        // against construction direction, packets leave through the ingress
+        let egress = if info.cons_dir { hop.cons_egress } else { hop.cons_ingress };
+        topology.next_hop(u64::from(egress)).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("interface {} is not in the topology of {}", egress, topology.ia),
+            )
+        })
+    }
+}
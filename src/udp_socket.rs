@@ -0,0 +1,541 @@
+use crate::sock_addr_traits::ToScionSocketAddrs;
+use crate::underlay::underlay_addr;
+use crate::wire::{
+    CommonHeader, UdpHeader, ADDR_TYPE_IPV4, ADDR_TYPE_IPV6, ADDR_TYPE_SVC, COMMON_HEADER_LEN,
+    MAX_HEADER_LEN, PROTO_UDP, UDP_HEADER_LEN as UDP_HDR_LEN,
+};
+use crate::{
+    HostAddr, IpAddr, Ipv4Addr, Ipv6Addr, IsdAsn, PathSelector, ScionAddr, ScionPath, SocketAddr,
+    SocketAddrScion, Svc,
+};
+use std::collections::HashMap;
+use std::io::{self, IoSlice, IoSliceMut};
+use std::net::UdpSocket;
+use std::sync::RwLock;
+use std::time::Duration;
+
+/// The largest underlay datagram we are prepared to receive.
+pub(crate) const MAX_DATAGRAM: usize = 65535;
+
+/// How packets to a remote ISD-AS leave the local AS.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScionRoute {
+    /// The underlay address of the border router to hand the packets to.
+    pub next_hop: std::net::SocketAddr,
+    /// The SCION path type of `raw_path`, i.e. 1 for the standard SCION path.
+    pub path_type: u8,
+    /// The encoded dataplane path.
+    pub raw_path: Vec<u8>,
+}
+
+/// A UDP socket that sends and receives SCION datagrams.
+///
+/// Packets are carried in UDP datagrams of the local underlay socket. Packets
+/// to hosts in the local AS go directly to the destination's underlay address,
+/// packets to other ASes go to the border router of the [route](ScionRoute)
+/// registered for the destination IA with [`set_route`](ScionUdpSocket::set_route).
+///
+/// The underlay port of a local destination is its SCION port, or the end host
+/// port 30041 if the port lies outside the range set with
+/// [`set_dispatched_ports`](ScionUdpSocket::set_dispatched_ports); see
+/// [`underlay`](crate::underlay).
+pub struct ScionUdpSocket {
+    underlay: UdpSocket,
+    state: SocketState,
+}
+
+/// The local address and routes of a SCION socket, shared by the blocking
+/// and the async sockets.
+pub(crate) struct SocketState {
+    local: SocketAddrScion,
+    routes: RwLock<HashMap<IsdAsn, ScionRoute>>,
+    dispatched_ports: RwLock<Option<(u16, u16)>>,
+}
+
+impl SocketState {
+    pub(crate) fn new(local: SocketAddrScion) -> SocketState {
+        SocketState { local, routes: RwLock::new(HashMap::new()), dispatched_ports: RwLock::new(None) }
+    }
+
+    pub(crate) fn set_dispatched_ports(&self, range: Option<(u16, u16)>) {
+        *self.dispatched_ports.write().unwrap() = range;
+    }
+
+    pub(crate) fn dispatched_ports(&self) -> Option<(u16, u16)> {
+        *self.dispatched_ports.read().unwrap()
+    }
+
+    pub(crate) fn local(&self) -> SocketAddrScion {
+        self.local
+    }
+
+    pub(crate) fn set_route(&self, ia: IsdAsn, route: ScionRoute) {
+        self.routes.write().unwrap().insert(ia, route);
+    }
+
+    pub(crate) fn remove_route(&self, ia: IsdAsn) -> Option<ScionRoute> {
+        self.routes.write().unwrap().remove(&ia)
+    }
+
+    pub(crate) fn route(&self, ia: IsdAsn) -> Option<ScionRoute> {
+        self.routes.read().unwrap().get(&ia).cloned()
+    }
+
+    /// Encodes `payload` for `dst`, returning the packet and the underlay
+    /// address to send it to.
+    pub(crate) fn encode(
+        &self,
+        payload: &[u8],
+        dst: &SocketAddrScion,
+    ) -> io::Result<(Vec<u8>, std::net::SocketAddr)> {
+        let (mut packet, next_hop) = self.encode_header(&[payload], dst)?;
+        packet.extend_from_slice(payload);
+        Ok((packet, next_hop))
+    }
+
+    /// Encodes the SCION and UDP header of a packet to `dst` carrying the
+    /// concatenation of `payload`, returning it and the underlay address to
+    /// send the packet to.
+    pub(crate) fn encode_header(
+        &self,
+        payload: &[&[u8]],
+        dst: &SocketAddrScion,
+    ) -> io::Result<(Vec<u8>, std::net::SocketAddr)> {
+        if dst.is_unspecified_port() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "cannot send to port 0"));
+        }
+        if dst.ia() == self.local.ia() {
+            let ip = ip_socket_addr(dst)?.ip().into();
+            let next_hop = underlay_addr(ip, dst.port(), self.dispatched_ports());
+            return Ok((encode_header(&self.local, dst, 0, &[], payload)?, next_hop));
+        }
+        let routes = self.routes.read().unwrap();
+        let route = routes.get(&IsdAsn::from(dst.ia())).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, format!("no route to {}", IsdAsn::from(dst.ia())))
+        })?;
+        let header = encode_header(&self.local, dst, route.path_type, &route.raw_path, payload)?;
+        Ok((header, route.next_hop))
+    }
+}
+
+/// Resolves `addr` and binds the first address that can be bound with `bind`.
+pub(crate) fn bind_first<A, T, F>(addr: A, mut bind: F) -> io::Result<(T, SocketAddrScion)>
+where
+    A: ToScionSocketAddrs,
+    F: FnMut(std::net::SocketAddr) -> io::Result<(T, u16)>,
+{
+    let mut last_err = None;
+    for addr in addr.to_scion_socket_addrs()? {
+        match ip_socket_addr(&addr).and_then(&mut bind) {
+            Ok((socket, port)) => {
+                let mut local = addr;
+                local.set_port(port);
+                return Ok((socket, local));
+            }
+            Err(e) => last_err = Some(e),
+        }
+    }
+    Err(last_err.unwrap_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "could not resolve to any addresses")
+    }))
+}
+
+/// Returns the first address `addr` resolves to.
+pub(crate) fn first_addr<A: ToScionSocketAddrs>(addr: A) -> io::Result<SocketAddrScion> {
+    addr.to_scion_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no addresses to send data to"))
+}
+
+impl ScionUdpSocket {
+    /// Creates a socket bound to `addr`. The host part of `addr` is used as
+    /// the underlay address; port 0 binds an ephemeral port.
+    ///
+    /// If `addr` yields multiple addresses, the first one that can be bound is used.
+    pub fn bind<A: ToScionSocketAddrs>(addr: A) -> io::Result<ScionUdpSocket> {
+        let (underlay, local) = bind_first(addr, |a| {
+            let socket = UdpSocket::bind(a)?;
+            let port = socket.local_addr()?.port();
+            Ok((socket, port))
+        })?;
+        Ok(Self::from_bound(underlay, local))
+    }
+
+    pub(crate) fn from_bound(underlay: UdpSocket, local: SocketAddrScion) -> ScionUdpSocket {
+        ScionUdpSocket { underlay, state: SocketState::new(local) }
+    }
+
+    /// Returns the SCION address this socket is bound to.
+    pub fn local_addr(&self) -> io::Result<SocketAddrScion> {
+        Ok(self.state.local())
+    }
+
+    /// Returns the underlying UDP socket.
+    pub fn underlay(&self) -> &UdpSocket {
+        &self.underlay
+    }
+
+    #[cfg(feature = "tokio")]
+    pub(crate) fn into_parts(self) -> (UdpSocket, SocketState) {
+        (self.underlay, self.state)
+    }
+
+    /// Sets the route used for packets to hosts in `ia`.
+    pub fn set_route(&self, ia: IsdAsn, route: ScionRoute) {
+        self.state.set_route(ia, route)
+    }
+
+    /// Removes the route for `ia` and returns it.
+    pub fn remove_route(&self, ia: IsdAsn) -> Option<ScionRoute> {
+        self.state.remove_route(ia)
+    }
+
+    /// Returns the route currently used for packets to `ia`.
+    pub fn route(&self, ia: IsdAsn) -> Option<ScionRoute> {
+        self.state.route(ia)
+    }
+
+    /// Sets the `dispatched_ports` range of the local AS, as found in its
+    /// topology. Packets to local hosts on ports outside the range go to
+    /// the end host port instead; `None`, the default, dispatches all ports.
+    pub fn set_dispatched_ports(&self, range: Option<(u16, u16)>) {
+        self.state.set_dispatched_ports(range)
+    }
+
+    pub fn dispatched_ports(&self) -> Option<(u16, u16)> {
+        self.state.dispatched_ports()
+    }
+
+    /// Picks one of `paths` with `selector` and uses it as the route to its
+    /// destination IA, returning the chosen path.
+    ///
+    /// Returns `None` if no path is chosen or the chosen path leads to another
+    /// AS but has no next hop. Empty paths within the local AS need no route.
+    pub fn select_path<'a>(
+        &self,
+        paths: &'a [ScionPath],
+        selector: &dyn PathSelector,
+    ) -> Option<&'a ScionPath> {
+        let path = paths.get(selector.select(paths)?)?;
+        if path.dst.to_u64() != self.state.local().ia() {
+            self.set_route(path.dst, path.route()?);
+        }
+        Some(path)
+    }
+
+    /// Sends `buf` to the first SCION address `addr` resolves to.
+    ///
+    /// Returns the number of payload bytes sent.
+    pub fn send_to<A: ToScionSocketAddrs>(&self, buf: &[u8], addr: A) -> io::Result<usize> {
+        let (packet, next_hop) = self.state.encode(buf, &first_addr(addr)?)?;
+        self.underlay.send_to(&packet, next_hop)?;
+        Ok(buf.len())
+    }
+
+    /// Receives a single SCION/UDP datagram, skipping anything else.
+    ///
+    /// Returns the number of payload bytes read and the sender's address.
+    /// Payload bytes that do not fit into `buf` are discarded.
+    pub fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        let mut packet = vec![0u8; MAX_DATAGRAM];
+        loop {
+            let (len, _) = self.underlay.recv_from(&mut packet)?;
+            if let Some((src, payload)) = decode_packet(&packet[..len]) {
+                let n = payload.len().min(buf.len());
+                buf[..n].copy_from_slice(&payload[..n]);
+                return Ok((n, SocketAddr::SCION(src)));
+            }
+        }
+    }
+
+    /// Sends the concatenation of `bufs` as the payload of a single datagram
+    /// to the first SCION address `addr` resolves to.
+    ///
+    /// The SCION and UDP headers are encoded into a separate buffer that is
+    /// handed to the kernel together with `bufs`, so the payload is not copied.
+    /// Returns the number of payload bytes sent.
+    pub fn send_vectored_to<A: ToScionSocketAddrs>(
+        &self,
+        bufs: &[IoSlice<'_>],
+        addr: A,
+    ) -> io::Result<usize> {
+        let payload: Vec<&[u8]> = bufs.iter().map(|b| &**b).collect();
+        let payload_len = payload.iter().map(|b| b.len()).sum();
+        let (header, next_hop) = self.state.encode_header(&payload, &first_addr(addr)?)?;
+        sys::send_vectored(&self.underlay, &header, bufs, next_hop)?;
+        Ok(payload_len)
+    }
+
+    /// Receives a single SCION/UDP datagram into `bufs`, filling them in
+    /// order and skipping anything that is not SCION/UDP.
+    ///
+    /// Returns the number of payload bytes read and the sender's address.
+    /// Payload bytes that do not fit into `bufs` are discarded.
+    ///
+    /// The headers are peeked before the datagram is read, so that the payload
+    /// lands in `bufs` without being copied. A datagram that another thread
+    /// reads from the socket in between is dropped.
+    pub fn recv_vectored_from(&self, bufs: &mut [IoSliceMut<'_>]) -> io::Result<(usize, SocketAddr)> {
+        let mut header = [0u8; MAX_HEADER_LEN + UDP_HDR_LEN];
+        loop {
+            let (peeked, _) = self.underlay.peek_from(&mut header)?;
+            let offset = match decode_header(&header[..peeked]) {
+                Some((_, offset, _)) => offset,
+                None => {
+                    // not for us, drop it
+                    self.underlay.recv_from(&mut header)?;
+                    continue;
+                }
+            };
+            let received = sys::recv_vectored(&self.underlay, &mut header[..offset], bufs)?;
+            match decode_header(&header[..offset.min(received)]) {
+                Some((src, o, len)) if o == offset => {
+                    return Ok(((received - offset).min(len), SocketAddr::SCION(src)));
+                }
+                _ => continue,
+            }
+        }
+    }
+
+    pub fn set_read_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        self.underlay.set_read_timeout(dur)
+    }
+
+    pub fn set_write_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        self.underlay.set_write_timeout(dur)
+    }
+
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        self.underlay.set_nonblocking(nonblocking)
+    }
+}
+
+#[cfg(unix)]
+mod sys {
+    use std::io::{self, IoSlice, IoSliceMut};
+    use std::mem;
+    use std::net::UdpSocket;
+    use std::os::unix::io::AsRawFd;
+
+    fn sockaddr(addr: std::net::SocketAddr) -> (libc::sockaddr_storage, libc::socklen_t) {
+        crate::SocketAddr::from(addr).to_sockaddr_storage().expect("an IP socket address")
+    }
+
+    /// Sends `header` followed by `bufs` as one datagram to `to`.
+    pub(super) fn send_vectored(
+        socket: &UdpSocket,
+        header: &[u8],
+        bufs: &[IoSlice<'_>],
+        to: std::net::SocketAddr,
+    ) -> io::Result<usize> {
+        let mut iov = Vec::with_capacity(bufs.len() + 1);
+        iov.push(libc::iovec { iov_base: header.as_ptr() as *mut libc::c_void, iov_len: header.len() });
+        iov.extend(bufs.iter().map(|b| libc::iovec {
+            iov_base: b.as_ptr() as *mut libc::c_void,
+            iov_len: b.len(),
+        }));
+        let (mut name, name_len) = sockaddr(to);
+        // SAFETY: all-zero is a valid `msghdr`
+        let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+        msg.msg_name = &mut name as *mut _ as *mut libc::c_void;
+        msg.msg_namelen = name_len;
+        msg.msg_iov = iov.as_mut_ptr();
+        msg.msg_iovlen = iov.len() as _;
+        // SAFETY: `msg` points to valid buffers that outlive the call, which
+        // the kernel only reads from
+        let ret = unsafe { libc::sendmsg(socket.as_raw_fd(), &msg, 0) };
+        if ret < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(ret as usize)
+    }
+
+    /// Receives one datagram, filling `header` and then `bufs`.
+    pub(super) fn recv_vectored(
+        socket: &UdpSocket,
+        header: &mut [u8],
+        bufs: &mut [IoSliceMut<'_>],
+    ) -> io::Result<usize> {
+        let mut iov = Vec::with_capacity(bufs.len() + 1);
+        iov.push(libc::iovec {
+            iov_base: header.as_mut_ptr() as *mut libc::c_void,
+            iov_len: header.len(),
+        });
+        iov.extend(bufs.iter_mut().map(|b| libc::iovec {
+            iov_base: b.as_mut_ptr() as *mut libc::c_void,
+            iov_len: b.len(),
+        }));
+        // SAFETY: all-zero is a valid `msghdr`
+        let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+        msg.msg_iov = iov.as_mut_ptr();
+        msg.msg_iovlen = iov.len() as _;
+        // SAFETY: `msg` points to valid, exclusively borrowed buffers that
+        // outlive the call
+        let ret = unsafe { libc::recvmsg(socket.as_raw_fd(), &mut msg, 0) };
+        if ret < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(ret as usize)
+    }
+}
+
+#[cfg(not(unix))]
+mod sys {
+    use std::io::{self, IoSlice, IoSliceMut};
+    use std::net::UdpSocket;
+
+    pub(super) fn send_vectored(
+        socket: &UdpSocket,
+        header: &[u8],
+        bufs: &[IoSlice<'_>],
+        to: std::net::SocketAddr,
+    ) -> io::Result<usize> {
+        let mut packet = header.to_vec();
+        for buf in bufs {
+            packet.extend_from_slice(buf);
+        }
+        socket.send_to(&packet, to)
+    }
+
+    pub(super) fn recv_vectored(
+        socket: &UdpSocket,
+        header: &mut [u8],
+        bufs: &mut [IoSliceMut<'_>],
+    ) -> io::Result<usize> {
+        let mut packet = vec![0u8; super::MAX_DATAGRAM];
+        let (len, _) = socket.recv_from(&mut packet)?;
+        let mut rest = &packet[..len];
+        for buf in std::iter::once(header).chain(bufs.iter_mut().map(|b| &mut **b)) {
+            let n = rest.len().min(buf.len());
+            buf[..n].copy_from_slice(&rest[..n]);
+            rest = &rest[n..];
+        }
+        Ok(len - rest.len())
+    }
+}
+
+/// Returns the address type/length bits and the raw bytes of a host address.
+pub(crate) fn encode_host(host: &HostAddr) -> (u8, Vec<u8>) {
+    match *host {
+        HostAddr::Ip(IpAddr::V4(ip)) => (ADDR_TYPE_IPV4, ip.octets().to_vec()),
+        HostAddr::Ip(IpAddr::V6(ip)) => (ADDR_TYPE_IPV6, ip.octets().to_vec()),
+        HostAddr::Svc(svc) => {
+            let [hi, lo] = svc.to_u16().to_be_bytes();
+            (ADDR_TYPE_SVC, vec![hi, lo, 0, 0])
+        }
+    }
+}
+
+/// Returns the IP socket address of `addr`, failing for service addresses,
+/// which are resolved by routers rather than bound or sent to directly.
+pub(crate) fn ip_socket_addr(addr: &SocketAddrScion) -> io::Result<std::net::SocketAddr> {
+    let ip = addr.ip().ok_or_else(|| {
+        let msg = format!("{} is a service address, not an IP address", addr.host());
+        io::Error::new(io::ErrorKind::InvalidInput, msg)
+    })?;
+    Ok(std::net::SocketAddr::new(ip.into(), addr.port()))
+}
+
+/// Encodes the SCION header of a packet with the given path, carrying an
+/// upper-layer packet of protocol `next_hdr` and `l4_len` bytes.
+pub(crate) fn encode_scion_header(
+    src: &ScionAddr,
+    dst: &ScionAddr,
+    next_hdr: u8,
+    path_type: u8,
+    raw_path: &[u8],
+    l4_len: usize,
+) -> io::Result<Vec<u8>> {
+    let addr_len = 16 + dst.wire_host_len() + src.wire_host_len();
+    let hdr_len = COMMON_HEADER_LEN + addr_len + raw_path.len();
+    if hdr_len % 4 != 0 || hdr_len > MAX_HEADER_LEN || l4_len > u16::MAX as usize {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "SCION packet too large"));
+    }
+    let common = CommonHeader {
+        next_header: next_hdr,
+        header_len: hdr_len,
+        payload_len: l4_len as u16,
+        path_type,
+        dst_addr_type: dst.wire_type(),
+        src_addr_type: src.wire_type(),
+        ..CommonHeader::default()
+    };
+
+    let mut packet = Vec::with_capacity(hdr_len + l4_len);
+    packet.extend_from_slice(&common.to_bytes());
+    packet.resize(COMMON_HEADER_LEN + addr_len, 0);
+    ScionAddr::write_wire(dst, src, &mut packet[COMMON_HEADER_LEN..])?;
+    packet.extend_from_slice(raw_path);
+    Ok(packet)
+}
+
+/// Encodes the headers of a SCION/UDP packet with the given path, up to the
+/// payload, the concatenation of `payload`.
+fn encode_header(
+    src: &SocketAddrScion,
+    dst: &SocketAddrScion,
+    path_type: u8,
+    raw_path: &[u8],
+    payload: &[&[u8]],
+) -> io::Result<Vec<u8>> {
+    let payload_len = payload.iter().map(|p| p.len()).sum();
+    let mut udp = UdpHeader::new(src.port(), dst.port(), payload_len)?;
+    let (src, dst) = (ScionAddr::from(*src), ScionAddr::from(*dst));
+    udp.checksum = udp.compute_checksum_vectored(&src, &dst, payload);
+    let mut packet =
+        encode_scion_header(&src, &dst, PROTO_UDP, path_type, raw_path, usize::from(udp.length))?;
+    packet.extend_from_slice(&udp.encode());
+    Ok(packet)
+}
+
+/// Decodes a host address of the given type/length bits.
+pub(crate) fn decode_host(type_len: u8, raw: &[u8]) -> Option<(HostAddr, usize)> {
+    match type_len {
+        ADDR_TYPE_IPV4 => {
+            let octets: [u8; 4] = raw.get(..4)?.try_into().ok()?;
+            Some((Ipv4Addr::from(octets).into(), 4))
+        }
+        ADDR_TYPE_IPV6 => {
+            let octets: [u8; 16] = raw.get(..16)?.try_into().ok()?;
+            Some((Ipv6Addr::from(octets).into(), 16))
+        }
+        ADDR_TYPE_SVC => {
+            let value = u16::from_be_bytes(raw.get(..2)?.try_into().ok()?);
+            raw.get(..4)?;
+            Some((Svc::new(value).into(), 4))
+        }
+        _ => None,
+    }
+}
+
+/// Decodes the SCION header at the start of a packet into the source
+/// address, the upper-layer protocol, the header length and the length of
+/// the upper-layer packet.
+pub(crate) fn decode_scion_header(packet: &[u8]) -> Option<(ScionAddr, u8, usize, usize)> {
+    let common = CommonHeader::decode_from(packet).ok()?;
+    let addr_header = packet.get(COMMON_HEADER_LEN..)?;
+    let (_, src, _) =
+        ScionAddr::read_wire(common.dst_addr_type, common.src_addr_type, addr_header).ok()?;
+    let l4_len = usize::from(common.payload_len);
+    Some((src, common.next_header, common.header_len, l4_len))
+}
+
+/// Decodes the headers at the start of a SCION/UDP packet into the source
+/// address, the offset of the payload and its length.
+///
+/// Only the headers need to be present in `packet`.
+fn decode_header(packet: &[u8]) -> Option<(SocketAddrScion, usize, usize)> {
+    let (src, next_hdr, hdr_len, l4_len) = decode_scion_header(packet)?;
+    if next_hdr != PROTO_UDP || l4_len < UDP_HDR_LEN {
+        return None;
+    }
+    let udp = UdpHeader::decode(packet.get(hdr_len..)?).ok()?;
+    let src = SocketAddrScion::from_scion_addr(src, udp.src_port);
+    Some((src, hdr_len + UDP_HDR_LEN, l4_len - UDP_HDR_LEN))
+}
+
+/// Decodes a SCION/UDP packet into the source address and the payload.
+pub(crate) fn decode_packet(packet: &[u8]) -> Option<(SocketAddrScion, &[u8])> {
+    let (src, offset, len) = decode_header(packet)?;
+    Some((src, packet.get(offset..offset + len)?))
+}
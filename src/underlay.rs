@@ -0,0 +1,190 @@
+//! Helpers for the dispatcher-less end host model of SCION.
+//!
+//! Hosts send SCION packets in UDP datagrams straight to the internal address
+//! of a border router. Border routers, and hosts of the same AS, deliver
+//! packets to the underlay port equal to the destination L4 port if it lies
+//! within the AS's `dispatched_ports` range, and to [`ENDHOST_PORT`]
+//! otherwise. A [`ShimDispatcher`] listening on that port forwards such
+//! packets to the socket bound to their L4 port, so sockets of this crate
+//! receive them either way.
+
+use crate::scmp::{
+    ScmpResponder, PROTO_SCMP, TYPE_ECHO_REPLY, TYPE_ECHO_REQUEST, TYPE_EXTERNAL_INTERFACE_DOWN,
+    TYPE_INTERNAL_CONNECTIVITY_DOWN, TYPE_TRACEROUTE_REPLY, TYPE_TRACEROUTE_REQUEST,
+};
+use crate::udp_socket::{decode_scion_header, MAX_DATAGRAM};
+use crate::wire::{PROTO_UDP, UDP_HEADER_LEN};
+use crate::IpAddr;
+use std::io;
+use std::net::UdpSocket;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// The underlay port packets outside the dispatched port range are delivered to.
+pub const ENDHOST_PORT: u16 = 30041;
+
+/// Returns the underlay port a packet to the L4 port `port` is delivered to,
+/// given the `dispatched_ports` range of the destination AS. Without a range,
+/// all ports are dispatched.
+#[must_use]
+pub fn underlay_port(port: u16, dispatched_ports: Option<(u16, u16)>) -> u16 {
+    match dispatched_ports {
+        Some((lo, hi)) if port < lo || port > hi => ENDHOST_PORT,
+        _ => port,
+    }
+}
+
+/// Returns the underlay address a packet to `host` and the L4 port `port`
+/// within the local AS is sent to, see [`underlay_port`].
+#[must_use]
+pub fn underlay_addr(
+    host: IpAddr,
+    port: u16,
+    dispatched_ports: Option<(u16, u16)>,
+) -> std::net::SocketAddr {
+    std::net::SocketAddr::new(host.into(), underlay_port(port, dispatched_ports))
+}
+
+fn be_u16(buf: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(buf.get(at..at + 2)?.try_into().ok()?))
+}
+
+/// Returns the port of the receiving socket, `source` selecting the source
+/// rather than the destination port, of the SCION packet at the start of
+/// `packet`.
+fn l4_port(packet: &[u8], source: bool) -> Option<u16> {
+    let (_, next_hdr, hdr_len, _) = decode_scion_header(packet)?;
+    let l4 = packet.get(hdr_len..)?;
+    match next_hdr {
+        PROTO_UDP if l4.len() >= UDP_HEADER_LEN => be_u16(l4, if source { 0 } else { 2 }),
+        PROTO_SCMP => match *l4.first()? {
+            // informational messages carry the port as their identifier
+            TYPE_ECHO_REQUEST | TYPE_ECHO_REPLY | TYPE_TRACEROUTE_REQUEST | TYPE_TRACEROUTE_REPLY => {
+                be_u16(l4, 4)
+            }
+            // error messages quote the offending packet, sent by the receiver
+            kind if kind < 128 => {
+                let offset = match kind {
+                    TYPE_EXTERNAL_INTERFACE_DOWN => 20,
+                    TYPE_INTERNAL_CONNECTIVITY_DOWN => 28,
+                    _ => 8,
+                };
+                l4_port(l4.get(offset..)?, !source)
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Returns the L4 port a received SCION packet is meant for: the destination
+/// port of UDP, the identifier of SCMP informational messages and the source
+/// port of the quoted packet of SCMP errors.
+///
+/// Returns `None` for packets that are malformed or name no port.
+#[must_use]
+pub fn dst_port(packet: &[u8]) -> Option<u16> {
+    l4_port(packet, false)
+}
+
+/// Forwards the packets arriving at [`ENDHOST_PORT`] to the underlay port
+/// equal to their L4 port, see [`dst_port`], on the same host.
+///
+/// It stands in for the dispatcher on hosts of ASes whose `dispatched_ports`
+/// do not cover all ports, and is only needed once per host. With a
+/// [`ScmpResponder`] attached, it answers the SCMP echo requests to the host.
+///
+/// Packets for the shim's own port are dropped, as forwarding them would
+/// loop.
+pub struct ShimDispatcher {
+    socket: UdpSocket,
+    responder: Option<ScmpResponder>,
+    send_errors: AtomicU64,
+}
+
+impl ShimDispatcher {
+    /// Binds the shim to [`ENDHOST_PORT`] of `host`.
+    pub fn bind(host: IpAddr) -> io::Result<ShimDispatcher> {
+        Self::bind_addr(std::net::SocketAddr::new(host.into(), ENDHOST_PORT))
+    }
+
+    /// Binds the shim to `addr`, i.e. to another port for testing.
+    pub fn bind_addr(addr: std::net::SocketAddr) -> io::Result<ShimDispatcher> {
+        let socket = UdpSocket::bind(addr)?;
+        Ok(ShimDispatcher { socket, responder: None, send_errors: AtomicU64::new(0) })
+    }
+
+    /// Answers the requests `responder` answers instead of forwarding them.
+    #[must_use]
+    pub fn with_responder(mut self, responder: ScmpResponder) -> ShimDispatcher {
+        self.responder = Some(responder);
+        self
+    }
+
+    pub fn local_addr(&self) -> io::Result<std::net::SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    /// Returns the underlying UDP socket, i.e. to set timeouts.
+    pub fn underlay(&self) -> &UdpSocket {
+        &self.socket
+    }
+
+    /// Receives one packet and forwards it, returning the port it was
+    /// forwarded to or `None` if it was answered or dropped.
+    pub fn forward_one(&self) -> io::Result<Option<u16>> {
+        let mut packet = vec![0u8; MAX_DATAGRAM];
+        self.forward_with(&mut packet)
+    }
+
+    /// Returns the number of packets [`run`](Self::run) failed to forward or
+    /// answer.
+    #[must_use]
+    pub fn send_errors(&self) -> u64 {
+        self.send_errors.load(Ordering::Relaxed)
+    }
+
+    fn forward_with(&self, packet: &mut [u8]) -> io::Result<Option<u16>> {
+        let (len, from) = self.socket.recv_from(packet)?;
+        self.forward(&packet[..len], from)
+    }
+
+    fn forward(&self, packet: &[u8], from: std::net::SocketAddr) -> io::Result<Option<u16>> {
+        if let Some(reply) = self.responder.as_ref().and_then(|r| r.reply(packet)) {
+            self.socket.send_to(&reply, from)?;
+            return Ok(None);
+        }
+        let mut to = self.socket.local_addr()?;
+        let port = match dst_port(packet) {
+            Some(port) if port != 0 && port != to.port() => port,
+            _ => return Ok(None),
+        };
+        if to.ip().is_unspecified() {
+            to.set_ip(match to {
+                std::net::SocketAddr::V4(_) => std::net::Ipv4Addr::LOCALHOST.into(),
+                std::net::SocketAddr::V6(_) => std::net::Ipv6Addr::LOCALHOST.into(),
+            });
+        }
+        to.set_port(port);
+        match self.socket.send_to(packet, to) {
+            Ok(_) => Ok(Some(port)),
+            // nobody listens on the port
+            Err(e) if e.kind() == io::ErrorKind::ConnectionRefused => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Forwards packets until receiving fails. Failing to forward or answer
+    /// a packet only drops it, and is counted in
+    /// [`send_errors`](Self::send_errors).
+    pub fn run(&self) -> io::Result<()> {
+        let mut packet = vec![0u8; MAX_DATAGRAM];
+        loop {
+            let (len, from) = self.socket.recv_from(&mut packet)?;
+            if let Err(_e) = self.forward(&packet[..len], from) {
+                self.send_errors.fetch_add(1, Ordering::Relaxed);
+                #[cfg(feature = "tracing")]
+                tracing::debug!(target: "scionnet::underlay", error = %_e, "dropped packet");
+            }
+        }
+    }
+}
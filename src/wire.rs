@@ -0,0 +1,546 @@
+//! Encoding and decoding of the headers of SCION packets.
+//!
+//! The common header starts every SCION packet:
+//!
+//! ```text
+//!  0                   1                   2                   3
+//!  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! |Version| TrafficClass  |                FlowID                 |
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! |    NextHdr    |    HdrLen     |          PayloadLen           |
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! |    PathType   |DT |DL |ST |SL |              RSV              |
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! ```
+//!
+//! The UDP header follows the SCION header of SCION/UDP packets, see [`UdpHeader`].
+//! [`PacketBuilder`] puts the headers together into whole datagrams and
+//! [`ParsedPacket`] takes received ones apart.
+
+use crate::checksum::scion_checksum_vectored;
+use crate::path::Path;
+use crate::udp_socket::{decode_host, encode_host};
+use crate::{HostAddr, IpAddr, IsdAsn, ScionAddr, SocketAddrScion, PATH_TYPE_SCION};
+use std::io;
+
+/// The encoded length of a [`CommonHeader`].
+pub const COMMON_HEADER_LEN: usize = 12;
+/// The longest SCION header, as its length is counted in 4-byte units in a single byte.
+pub const MAX_HEADER_LEN: usize = 255 * 4;
+/// The encoded length of a [`UdpHeader`].
+pub const UDP_HEADER_LEN: usize = 8;
+/// The protocol number of UDP in the SCION next header field.
+pub const PROTO_UDP: u8 = 17;
+/// The SCION version this crate speaks.
+pub const SCION_VERSION: u8 = 0;
+/// The path type of the empty path, used within an AS.
+pub const PATH_TYPE_EMPTY: u8 = 0;
+
+/// The address type/length bits of an IPv4 host.
+pub const ADDR_TYPE_IPV4: u8 = 0b0000;
+/// The address type/length bits of an IPv6 host.
+pub const ADDR_TYPE_IPV6: u8 = 0b0011;
+/// The address type/length bits of a service address, padded to 4 bytes.
+pub const ADDR_TYPE_SVC: u8 = 0b0100;
+
+fn invalid_header(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn invalid_field(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+/// The common header of a SCION packet.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CommonHeader {
+    /// The 4-bit version, [`SCION_VERSION`].
+    pub version: u8,
+    pub traffic_class: u8,
+    /// The 20-bit flow label.
+    pub flow_id: u32,
+    /// The protocol of the header following the SCION header, i.e. 17 for UDP.
+    pub next_header: u8,
+    /// The length of the SCION header in bytes, including this header, the
+    /// address header and the path. A multiple of 4, at most [`MAX_HEADER_LEN`].
+    pub header_len: usize,
+    /// The length of everything after the SCION header.
+    pub payload_len: u16,
+    pub path_type: u8,
+    /// The 4 type/length bits of the destination host address.
+    pub dst_addr_type: u8,
+    /// The 4 type/length bits of the source host address.
+    pub src_addr_type: u8,
+}
+
+/// Returns the length of a host address with the given type/length bits.
+#[must_use]
+pub fn addr_len(addr_type: u8) -> usize {
+    4 * (usize::from(addr_type & 0b11) + 1)
+}
+
+impl CommonHeader {
+    /// Returns the length of the address header: both IAs and both hosts.
+    #[must_use]
+    pub fn address_header_len(&self) -> usize {
+        16 + addr_len(self.dst_addr_type) + addr_len(self.src_addr_type)
+    }
+
+    /// Returns the length of the path, the rest of the SCION header.
+    #[must_use]
+    pub fn path_len(&self) -> usize {
+        self.header_len.saturating_sub(COMMON_HEADER_LEN + self.address_header_len())
+    }
+
+    /// Writes the header to the start of `buf`, returning the number of
+    /// bytes written.
+    ///
+    /// Fails if a field does not fit its bits or `buf` is shorter than
+    /// [`COMMON_HEADER_LEN`].
+    pub fn encode_to(&self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.version > 0xf || self.flow_id > 0xf_ffff {
+            return Err(invalid_field("SCION version or flow id out of range"));
+        }
+        if self.dst_addr_type > 0xf || self.src_addr_type > 0xf {
+            return Err(invalid_field("SCION address type out of range"));
+        }
+        if self.header_len % 4 != 0
+            || self.header_len > MAX_HEADER_LEN
+            || self.header_len < COMMON_HEADER_LEN + self.address_header_len()
+        {
+            return Err(invalid_field("SCION header length out of range"));
+        }
+        let buf = buf
+            .get_mut(..COMMON_HEADER_LEN)
+            .ok_or_else(|| invalid_field("buffer too short for the SCION common header"))?;
+        let first = u32::from(self.version) << 28 | u32::from(self.traffic_class) << 20 | self.flow_id;
+        buf[0..4].copy_from_slice(&first.to_be_bytes());
+        buf[4] = self.next_header;
+        buf[5] = (self.header_len / 4) as u8;
+        buf[6..8].copy_from_slice(&self.payload_len.to_be_bytes());
+        buf[8] = self.path_type;
+        buf[9] = self.dst_addr_type << 4 | self.src_addr_type;
+        buf[10..12].copy_from_slice(&[0, 0]);
+        Ok(COMMON_HEADER_LEN)
+    }
+
+    /// Returns the encoded header.
+    #[must_use = "use encode_to to check for errors"]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = vec![0u8; COMMON_HEADER_LEN];
+        match self.encode_to(&mut buf) {
+            Ok(_) => buf,
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Reads the header from the start of `buf`.
+    ///
+    /// Fails on other versions than [`SCION_VERSION`] and header lengths too
+    /// short for the address header.
+    pub fn decode_from(buf: &[u8]) -> io::Result<CommonHeader> {
+        let buf = buf
+            .get(..COMMON_HEADER_LEN)
+            .ok_or_else(|| invalid_header("truncated SCION common header"))?;
+        let first = u32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]);
+        let hdr = CommonHeader {
+            version: (first >> 28) as u8,
+            traffic_class: (first >> 20) as u8,
+            flow_id: first & 0xf_ffff,
+            next_header: buf[4],
+            header_len: usize::from(buf[5]) * 4,
+            payload_len: u16::from_be_bytes([buf[6], buf[7]]),
+            path_type: buf[8],
+            dst_addr_type: buf[9] >> 4,
+            src_addr_type: buf[9] & 0xf,
+        };
+        if hdr.version != SCION_VERSION {
+            return Err(invalid_header("unsupported SCION version"));
+        }
+        if hdr.header_len < COMMON_HEADER_LEN + hdr.address_header_len() {
+            return Err(invalid_header("SCION header length shorter than its address header"));
+        }
+        Ok(hdr)
+    }
+}
+
+/// The UDP header of a SCION/UDP packet.
+///
+/// Unlike UDP over IPv4, the checksum is mandatory. It covers a pseudo header
+/// made of the IAs and hosts of both endpoints, see
+/// [`compute_checksum`](Self::compute_checksum).
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct UdpHeader {
+    pub src_port: u16,
+    pub dst_port: u16,
+    /// The length of the header and the payload.
+    pub length: u16,
+    pub checksum: u16,
+}
+
+impl UdpHeader {
+    /// Returns the header of a datagram with `payload_len` bytes of payload
+    /// and no checksum yet.
+    ///
+    /// Fails if the datagram would be longer than 65535 bytes.
+    pub fn new(src_port: u16, dst_port: u16, payload_len: usize) -> io::Result<UdpHeader> {
+        let length = u16::try_from(UDP_HEADER_LEN + payload_len)
+            .map_err(|_| invalid_field("UDP datagram too large"))?;
+        Ok(UdpHeader { src_port, dst_port, length, checksum: 0 })
+    }
+
+    /// Returns the encoded header.
+    #[must_use]
+    pub fn encode(&self) -> [u8; UDP_HEADER_LEN] {
+        let mut buf = [0u8; UDP_HEADER_LEN];
+        buf[0..2].copy_from_slice(&self.src_port.to_be_bytes());
+        buf[2..4].copy_from_slice(&self.dst_port.to_be_bytes());
+        buf[4..6].copy_from_slice(&self.length.to_be_bytes());
+        buf[6..8].copy_from_slice(&self.checksum.to_be_bytes());
+        buf
+    }
+
+    /// Writes the header to the start of `buf`, returning the number of bytes written.
+    pub fn encode_to(&self, buf: &mut [u8]) -> io::Result<usize> {
+        buf.get_mut(..UDP_HEADER_LEN)
+            .ok_or_else(|| invalid_field("buffer too short for the UDP header"))?
+            .copy_from_slice(&self.encode());
+        Ok(UDP_HEADER_LEN)
+    }
+
+    /// Reads the header from the start of `buf`.
+    pub fn decode(buf: &[u8]) -> io::Result<UdpHeader> {
+        let buf = buf
+            .get(..UDP_HEADER_LEN)
+            .ok_or_else(|| invalid_header("truncated UDP header"))?;
+        let hdr = UdpHeader {
+            src_port: u16::from_be_bytes([buf[0], buf[1]]),
+            dst_port: u16::from_be_bytes([buf[2], buf[3]]),
+            length: u16::from_be_bytes([buf[4], buf[5]]),
+            checksum: u16::from_be_bytes([buf[6], buf[7]]),
+        };
+        if usize::from(hdr.length) < UDP_HEADER_LEN {
+            return Err(invalid_header("UDP length shorter than its header"));
+        }
+        Ok(hdr)
+    }
+
+    /// Computes the checksum of a datagram with this header and `payload`
+    /// sent from `src` to `dst`. The `checksum` field is ignored.
+    #[must_use]
+    pub fn compute_checksum(&self, src: &ScionAddr, dst: &ScionAddr, payload: &[u8]) -> u16 {
+        self.compute_checksum_vectored(src, dst, &[payload])
+    }
+
+    /// Computes the checksum of a datagram whose payload is the
+    /// concatenation of `payload`.
+    #[must_use]
+    pub fn compute_checksum_vectored(&self, src: &ScionAddr, dst: &ScionAddr, payload: &[&[u8]]) -> u16 {
+        let header = UdpHeader { checksum: 0, ..*self }.encode();
+        let mut parts = Vec::with_capacity(payload.len() + 1);
+        parts.push(&header[..]);
+        parts.extend_from_slice(payload);
+        scion_checksum_vectored(src, dst, PROTO_UDP, &parts)
+    }
+
+    /// Sets the checksum of a datagram with this header and `payload`.
+    pub fn set_checksum(&mut self, src: &ScionAddr, dst: &ScionAddr, payload: &[u8]) {
+        self.checksum = self.compute_checksum(src, dst, payload);
+    }
+
+    /// Returns `true` if the checksum of a datagram with this header and
+    /// `payload` is correct.
+    #[must_use]
+    pub fn verify_checksum(&self, src: &ScionAddr, dst: &ScionAddr, payload: &[u8]) -> bool {
+        self.checksum == self.compute_checksum(src, dst, payload)
+    }
+}
+
+/// Builds SCION/UDP datagrams ready to be sent to the first hop of `path`.
+///
+/// ```
+/// use scionnet::path::{HopField, InfoField, Path};
+/// use scionnet::wire::PacketBuilder;
+///
+/// let path = Path {
+///     seg_len: [2, 0, 0],
+///     info_fields: vec![InfoField { cons_dir: true, ..InfoField::default() }],
+///     hop_fields: vec![
+///         HopField { cons_egress: 1, ..HopField::default() },
+///         HopField { cons_ingress: 2, ..HopField::default() },
+///     ],
+///     ..Path::default()
+/// };
+/// let packet = PacketBuilder::new(
+///     "1-ff00:0:110,10.0.0.1:5000".parse().unwrap(),
+///     "1-ff00:0:111,10.0.0.2:6000".parse().unwrap(),
+///     path,
+/// )
+/// .traffic_class(0xb8)
+/// .build(b"hello")
+/// .unwrap();
+/// assert_eq!(packet.len(), 12 + 24 + 36 + 8 + 5);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PacketBuilder {
+    src: SocketAddrScion,
+    dst: SocketAddrScion,
+    path: Option<Path>,
+    traffic_class: u8,
+    flow_id: u32,
+}
+
+impl PacketBuilder {
+    /// Creates a builder of datagrams from `src` to `dst` along `path`.
+    #[must_use]
+    pub fn new(src: SocketAddrScion, dst: SocketAddrScion, path: Path) -> PacketBuilder {
+        PacketBuilder { src, dst, path: Some(path), traffic_class: 0, flow_id: 0 }
+    }
+
+    /// Creates a builder of datagrams with the empty path, from `src` to
+    /// `dst` within the same AS.
+    #[must_use]
+    pub fn empty_path(src: SocketAddrScion, dst: SocketAddrScion) -> PacketBuilder {
+        PacketBuilder { src, dst, path: None, traffic_class: 0, flow_id: 0 }
+    }
+
+    #[must_use]
+    pub fn traffic_class(mut self, traffic_class: u8) -> PacketBuilder {
+        self.traffic_class = traffic_class;
+        self
+    }
+
+    /// Sets the flow id; only the low 20 bits are sent, building fails otherwise.
+    #[must_use]
+    pub fn flow_id(mut self, flow_id: u32) -> PacketBuilder {
+        self.flow_id = flow_id;
+        self
+    }
+
+    /// Returns the length of a datagram with `payload_len` bytes of payload.
+    #[must_use]
+    pub fn packet_len(&self, payload_len: usize) -> usize {
+        let path_len = self.path.as_ref().map_or(0, Path::encoded_len);
+        COMMON_HEADER_LEN
+            + 16
+            + self.dst.addr().wire_host_len()
+            + self.src.addr().wire_host_len()
+            + path_len
+            + UDP_HEADER_LEN
+            + payload_len
+    }
+
+    /// Writes the datagram carrying `payload` to the start of `buf`,
+    /// returning its length.
+    ///
+    /// Fails if the path is malformed, the headers or the payload are too
+    /// long, or `buf` is shorter than [`packet_len`](Self::packet_len).
+    pub fn build_into(&self, payload: &[u8], buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.packet_len(payload.len());
+        let buf = buf
+            .get_mut(..len)
+            .ok_or_else(|| invalid_field("buffer too short for the SCION packet"))?;
+        let (src, dst) = (ScionAddr::from(self.src), ScionAddr::from(self.dst));
+        let mut udp = UdpHeader::new(self.src.port(), self.dst.port(), payload.len())?;
+        udp.set_checksum(&src, &dst, payload);
+        let header_len = len - usize::from(udp.length);
+        let common = CommonHeader {
+            version: SCION_VERSION,
+            traffic_class: self.traffic_class,
+            flow_id: self.flow_id,
+            next_header: PROTO_UDP,
+            header_len,
+            payload_len: udp.length,
+            path_type: if self.path.is_some() { PATH_TYPE_SCION } else { PATH_TYPE_EMPTY },
+            dst_addr_type: dst.wire_type(),
+            src_addr_type: src.wire_type(),
+        };
+        let mut pos = common.encode_to(buf)?;
+        pos += ScionAddr::write_wire(&dst, &src, &mut buf[pos..])?;
+        if let Some(path) = &self.path {
+            pos += path.encode_to(&mut buf[pos..])?;
+        }
+        pos += udp.encode_to(&mut buf[pos..])?;
+        buf[pos..].copy_from_slice(payload);
+        Ok(len)
+    }
+
+    /// Returns the datagram carrying `payload`, see [`build_into`](Self::build_into).
+    pub fn build(&self, payload: &[u8]) -> io::Result<Vec<u8>> {
+        let mut buf = vec![0u8; self.packet_len(payload.len())];
+        self.build_into(payload, &mut buf)?;
+        Ok(buf)
+    }
+}
+
+/// The address header, which follows the common header:
+///
+/// ```text
+/// +---------------+---------------+--------------------+--------------------+
+/// | dst ISD-AS (8)| src ISD-AS (8)| dst host (4 or 16) | src host (4 or 16) |
+/// +---------------+---------------+--------------------+--------------------+
+/// ```
+///
+/// The type and length of the hosts are given by the DT/DL and ST/SL fields
+/// of the common header. Service addresses take 4 bytes, the service number
+/// padded with zeros.
+impl ScionAddr {
+    /// Returns the address type and length bits of the host, the DT/DL or
+    /// ST/SL field of the common header.
+    #[must_use]
+    pub fn wire_type(&self) -> u8 {
+        match *self.get_host() {
+            HostAddr::Ip(IpAddr::V4(_)) => ADDR_TYPE_IPV4,
+            HostAddr::Ip(IpAddr::V6(_)) => ADDR_TYPE_IPV6,
+            HostAddr::Svc(_) => ADDR_TYPE_SVC,
+        }
+    }
+
+    /// Returns the length of the host in the address header.
+    #[must_use]
+    pub fn wire_host_len(&self) -> usize {
+        addr_len(self.wire_type())
+    }
+
+    /// Writes the address header of a packet from `src` to `dst` to the
+    /// start of `buf`, returning its length. The common header must carry
+    /// the [`wire_type`](Self::wire_type) of both.
+    ///
+    /// Fails if `buf` is too short.
+    pub fn write_wire(dst: &ScionAddr, src: &ScionAddr, buf: &mut [u8]) -> io::Result<usize> {
+        let len = 16 + dst.wire_host_len() + src.wire_host_len();
+        let buf = buf
+            .get_mut(..len)
+            .ok_or_else(|| invalid_field("buffer too short for the SCION address header"))?;
+        let (_, dst_host) = encode_host(dst.get_host());
+        let (_, src_host) = encode_host(src.get_host());
+        let (dst_ia, src_ia) = (dst.get_ia().to_be_bytes(), src.get_ia().to_be_bytes());
+        let mut pos = 0;
+        for part in [&dst_ia[..], &src_ia, &dst_host, &src_host] {
+            buf[pos..pos + part.len()].copy_from_slice(part);
+            pos += part.len();
+        }
+        Ok(len)
+    }
+
+    /// Reads the address header at the start of `buf`, given the DT/DL and
+    /// ST/SL fields of the common header, returning the destination, the
+    /// source and the length of the header.
+    ///
+    /// Fails with [`io::ErrorKind::InvalidData`] if `buf` is too short or
+    /// an address type is unknown.
+    pub fn read_wire(
+        dst_type: u8,
+        src_type: u8,
+        buf: &[u8],
+    ) -> io::Result<(ScionAddr, ScionAddr, usize)> {
+        if buf.len() < 16 {
+            return Err(invalid_header("SCION address header truncated"));
+        }
+        let mut pos = 16;
+        let (dst_host, len) = decode_host(dst_type, &buf[pos..])
+            .ok_or_else(|| invalid_header("unsupported SCION destination address type"))?;
+        pos += len;
+        let (src_host, len) = decode_host(src_type, &buf[pos..])
+            .ok_or_else(|| invalid_header("unsupported SCION source address type"))?;
+        pos += len;
+        let dst = ScionAddr::from_ia(IsdAsn::from(read_ia(buf, 0)), dst_host);
+        Ok((dst, ScionAddr::from_ia(IsdAsn::from(read_ia(buf, 8)), src_host), pos))
+    }
+}
+
+/// A SCION/UDP datagram parsed by [`ParsedPacket::parse`], borrowing the
+/// path and the payload from the received bytes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParsedPacket<'a> {
+    pub common: CommonHeader,
+    pub src: SocketAddrScion,
+    pub dst: SocketAddrScion,
+    /// The encoded path, of type `common.path_type`.
+    pub raw_path: &'a [u8],
+    pub udp: UdpHeader,
+    pub payload: &'a [u8],
+}
+
+fn read_ia(buf: &[u8], at: usize) -> u64 {
+    let mut ia = [0u8; 8];
+    ia.copy_from_slice(&buf[at..at + 8]);
+    u64::from_be_bytes(ia)
+}
+
+impl<'a> ParsedPacket<'a> {
+    /// Parses the datagram at the start of `buf`, which may be followed by
+    /// padding. The inverse of [`PacketBuilder::build`].
+    ///
+    /// Fails with [`io::ErrorKind::InvalidData`] if `buf` is truncated, the
+    /// lengths in the headers disagree, a host address type is unknown or the
+    /// packet does not carry UDP. The checksum is not verified, see
+    /// [`verify_checksum`](Self::verify_checksum).
+    pub fn parse(buf: &'a [u8]) -> io::Result<ParsedPacket<'a>> {
+        let common = CommonHeader::decode_from(buf)?;
+        if common.next_header != PROTO_UDP {
+            return Err(invalid_header("SCION packet does not carry UDP"));
+        }
+        let header = buf
+            .get(..common.header_len)
+            .ok_or_else(|| invalid_header("SCION header longer than the packet"))?;
+        let end = common.header_len + usize::from(common.payload_len);
+        let l4 = buf
+            .get(common.header_len..end)
+            .ok_or_else(|| invalid_header("SCION payload length longer than the packet"))?;
+
+        let (dst, src, len) = ScionAddr::read_wire(
+            common.dst_addr_type,
+            common.src_addr_type,
+            &header[COMMON_HEADER_LEN..],
+        )?;
+        let pos = COMMON_HEADER_LEN + len;
+
+        let udp = UdpHeader::decode(l4)?;
+        if usize::from(udp.length) != l4.len() {
+            return Err(invalid_header("UDP length disagrees with the SCION payload length"));
+        }
+        Ok(ParsedPacket {
+            common,
+            src: SocketAddrScion::from_scion_addr(src, udp.src_port),
+            dst: SocketAddrScion::from_scion_addr(dst, udp.dst_port),
+            raw_path: &header[pos..],
+            udp,
+            payload: &l4[UDP_HEADER_LEN..],
+        })
+    }
+
+    /// Returns the length of the whole datagram, headers included, which
+    /// can be shorter than the parsed bytes.
+    #[must_use]
+    pub fn packet_len(&self) -> usize {
+        self.common.header_len + usize::from(self.common.payload_len)
+    }
+
+    /// Decodes the path, or returns `None` for the empty path.
+    ///
+    /// Fails if the path is not a standard SCION path or is malformed.
+    pub fn path(&self) -> io::Result<Option<Path>> {
+        match self.common.path_type {
+            PATH_TYPE_EMPTY if self.raw_path.is_empty() => Ok(None),
+            PATH_TYPE_SCION => Path::decode_from(self.raw_path).map(Some),
+            _ => Err(invalid_header("unsupported SCION path type")),
+        }
+    }
+
+    /// Returns a builder of replies to the sender, along the reversed path.
+    pub fn reply(&self) -> io::Result<PacketBuilder> {
+        let builder = match self.path()? {
+            Some(path) => PacketBuilder::new(self.dst, self.src, path.reversed()?),
+            None => PacketBuilder::empty_path(self.dst, self.src),
+        };
+        Ok(builder.traffic_class(self.common.traffic_class).flow_id(self.common.flow_id))
+    }
+
+    /// Returns `true` if the UDP checksum is correct.
+    #[must_use]
+    pub fn verify_checksum(&self) -> bool {
+        self.udp.verify_checksum(&self.src.into(), &self.dst.into(), self.payload)
+    }
+}
@@ -0,0 +1,36 @@
+//! Conversions between IPv6 zone (scope) ids and interface names,
+//! enabled by the `os-zone` feature.
+//!
+//! With the feature enabled, `SocketAddrV6` parses zones such as
+//! `[fe80::1%eth0]:443` and formats known scope ids by interface name.
+
+use std::ffi::{CStr, CString};
+
+/// The maximum length of an interface name, including the terminating NUL.
+pub const IF_NAMESIZE: usize = libc::IF_NAMESIZE;
+
+/// Returns the index of the interface called `name`,
+/// or `None` if there is no such interface.
+pub fn if_name_to_index(name: &str) -> Option<u32> {
+    let name = CString::new(name).ok()?;
+    // SAFETY: `name` is a valid NUL-terminated string.
+    match unsafe { libc::if_nametoindex(name.as_ptr()) } {
+        0 => None,
+        index => Some(index),
+    }
+}
+
+/// Writes the name of the interface with index `index` into `buf`.
+///
+/// Returns `None` if there is no such interface.
+pub fn if_index_to_name(index: u32, buf: &mut [u8; IF_NAMESIZE]) -> Option<&str> {
+    if index == 0 {
+        return None;
+    }
+    // SAFETY: `buf` is IF_NAMESIZE bytes long, as required by if_indextoname.
+    let ret = unsafe { libc::if_indextoname(index, buf.as_mut_ptr() as *mut libc::c_char) };
+    if ret.is_null() {
+        return None;
+    }
+    CStr::from_bytes_until_nul(&buf[..]).ok()?.to_str().ok()
+}